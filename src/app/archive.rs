@@ -0,0 +1,1221 @@
+//! Zip and tar family archives browsed like folders.
+//!
+//! A path inside an archive is the path of the archive file followed by the
+//! path of the entry, e.g. `/home/me/photos.zip/2024/beach.jpg`. Listings are
+//! read once and cached for as long as the archive on disk is unchanged.
+//! Files are opened by extracting them to the user's cache directory.
+//!
+//! New zip, `.tar.gz` and `.tar.zst` archives can be created from a selection
+//! and archives can be extracted, both as background file jobs.
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs::{self, File, OpenOptions},
+    hash::{DefaultHasher, Hash, Hasher},
+    io::{self, BufReader, BufWriter, Read, Write},
+    path::{Component, Path, PathBuf},
+    sync::{Arc, LazyLock, Mutex},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::{Context, Result, bail};
+use directories::ProjectDirs;
+use lru::LruCache;
+
+use crate::app::file_ops::{Cancelled, JobProgress};
+use crate::data::files::{DirEntry, DirEntryMetaData, EntryType, SortKey};
+use crate::data::time::TimestampSeconds;
+
+/// How many archive listings are kept in memory.
+const LISTING_CACHE_CAPACITY: usize = 8;
+
+type Listing = Arc<Vec<ArchiveEntry>>;
+
+/// A listing with the modification time and length of the archive it was
+/// read from.
+struct CachedListing {
+    modified: Option<SystemTime>,
+    len: u64,
+    entries: Listing,
+}
+
+/// Listings keyed by archive path.
+static LISTINGS: LazyLock<Mutex<LruCache<PathBuf, CachedListing>>> = LazyLock::new(|| {
+    Mutex::new(LruCache::new(
+        std::num::NonZero::new(LISTING_CACHE_CAPACITY).expect("LISTING_CACHE_CAPACITY > 0"),
+    ))
+});
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArchiveKind {
+    Zip,
+    Tar,
+    TarGz,
+    TarZst,
+}
+
+impl ArchiveKind {
+    /// File name suffixes, longest first so `.tar.gz` wins over `.tar`.
+    const SUFFIXES: [(&'static str, Self); 6] = [
+        (".tar.zst", Self::TarZst),
+        (".tar.gz", Self::TarGz),
+        (".tzst", Self::TarZst),
+        (".tgz", Self::TarGz),
+        (".tar", Self::Tar),
+        (".zip", Self::Zip),
+    ];
+
+    /// Formats new archives can be written in.
+    pub const WRITABLE: [Self; 3] = [Self::Zip, Self::TarGz, Self::TarZst];
+
+    /// The suffix of `name` that names an archive, with the kind it names.
+    fn suffix_of(name: &str) -> Option<(&'static str, Self)> {
+        let name = name.to_ascii_lowercase();
+        Self::SUFFIXES
+            .into_iter()
+            .find(|(suffix, _)| name.len() > suffix.len() && name.ends_with(suffix))
+    }
+
+    /// The kind of archive `path` names, judging by its extension.
+    pub fn from_path(path: &Path) -> Option<Self> {
+        Self::suffix_of(&path.file_name()?.to_string_lossy()).map(|(_, kind)| kind)
+    }
+
+    pub const fn extension(self) -> &'static str {
+        match self {
+            Self::Zip => ".zip",
+            Self::Tar => ".tar",
+            Self::TarGz => ".tar.gz",
+            Self::TarZst => ".tar.zst",
+        }
+    }
+
+    /// Compression levels the format accepts, `0` meaning none.
+    pub const fn levels(self) -> std::ops::RangeInclusive<u32> {
+        match self {
+            Self::Zip | Self::TarGz => 0..=9,
+            Self::TarZst => 1..=19,
+            Self::Tar => 0..=0,
+        }
+    }
+
+    pub const fn default_level(self) -> u32 {
+        match self {
+            Self::Zip | Self::TarGz => 6,
+            Self::TarZst => 3,
+            Self::Tar => 0,
+        }
+    }
+}
+
+impl std::fmt::Display for ArchiveKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Zip => write!(f, "Zip"),
+            Self::Tar => write!(f, "Tar"),
+            Self::TarGz => write!(f, "Tar (gzip)"),
+            Self::TarZst => write!(f, "Tar (zstd)"),
+        }
+    }
+}
+
+/// One file or folder stored in an archive.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ArchiveEntry {
+    /// Path inside the archive, components joined with `/`.
+    pub path: String,
+    pub is_dir: bool,
+    pub size: u64,
+    pub modified: TimestampSeconds,
+}
+
+impl ArchiveEntry {
+    pub fn name(&self) -> &str {
+        self.path.rsplit('/').next().unwrap_or(&self.path)
+    }
+}
+
+/// The file name of `archive` without its archive extension, e.g. `src` for
+/// `src.tar.gz`.
+pub fn stem(archive: &Path) -> Option<String> {
+    let name = archive.file_name()?.to_string_lossy();
+    let (suffix, _) = ArchiveKind::suffix_of(&name)?;
+    Some(name[..name.len() - suffix.len()].to_string())
+}
+
+/// Whether `path` is an archive file that can be browsed.
+pub fn is_archive(path: &Path) -> bool {
+    ArchiveKind::from_path(path).is_some() && path.is_file()
+}
+
+/// Splits a path that points into an archive into the archive file and the
+/// path inside it. Real directories and files give `None`.
+pub fn split(path: &Path) -> Option<(PathBuf, PathBuf)> {
+    for archive in path.ancestors() {
+        match fs::metadata(archive) {
+            Ok(meta) if meta.is_file() && ArchiveKind::from_path(archive).is_some() => {
+                let inner = path.strip_prefix(archive).ok()?;
+                let archive = fs::canonicalize(archive).unwrap_or_else(|_| archive.to_path_buf());
+                return Some((archive, inner.to_path_buf()));
+            }
+            Ok(_) => return None,
+            Err(_) => {}
+        }
+    }
+    None
+}
+
+/// The path `inner` inside `archive` is displayed under.
+pub fn virtual_path(archive: &Path, inner: &Path) -> PathBuf {
+    if inner.as_os_str().is_empty() {
+        archive.to_path_buf()
+    } else {
+        archive.join(inner)
+    }
+}
+
+/// Normalizes an entry name to `/` separated components. Names that climb
+/// out of the archive with `..` give `None`.
+pub fn clean_name(name: &str) -> Option<String> {
+    let mut parts = Vec::new();
+    for part in name.split(['/', '\\']) {
+        match part {
+            "" | "." => {}
+            ".." => return None,
+            part => parts.push(part),
+        }
+    }
+    (!parts.is_empty()).then(|| parts.join("/"))
+}
+
+fn inner_key(inner: &Path) -> String {
+    inner
+        .components()
+        .filter_map(|component| match component {
+            Component::Normal(part) => Some(part.to_string_lossy()),
+            _ => None,
+        })
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// The entries directly inside the folder `inner` (`""` for the root).
+/// Folders that are only implied by deeper entries are included.
+pub fn children(entries: &[ArchiveEntry], inner: &str) -> Vec<ArchiveEntry> {
+    let prefix = if inner.is_empty() {
+        String::new()
+    } else {
+        format!("{inner}/")
+    };
+    let mut children: BTreeMap<&str, ArchiveEntry> = BTreeMap::new();
+    for entry in entries {
+        let Some(rest) = entry.path.strip_prefix(&prefix) else {
+            continue;
+        };
+        match rest.split_once('/') {
+            Some((folder, _)) => {
+                children.entry(folder).or_insert_with(|| ArchiveEntry {
+                    path: format!("{prefix}{folder}"),
+                    is_dir: true,
+                    size: 0,
+                    modified: entry.modified,
+                });
+            }
+            None if !rest.is_empty() => {
+                children.insert(rest, entry.clone());
+            }
+            None => {}
+        }
+    }
+    children.into_values().collect()
+}
+
+/// All entries of `archive`, from the cache while the file is unchanged.
+pub fn entries(archive: &Path) -> Result<Listing> {
+    let meta = fs::metadata(archive)
+        .with_context(|| format!("Failed to read metadata of {}", archive.display()))?;
+    let (modified, len) = (meta.modified().ok(), meta.len());
+    if let Ok(mut listings) = LISTINGS.lock()
+        && let Some(cached) = listings.get(archive)
+        && (cached.modified, cached.len) == (modified, len)
+    {
+        return Ok(Arc::clone(&cached.entries));
+    }
+    let Some(kind) = ArchiveKind::from_path(archive) else {
+        bail!("{} is not a supported archive", archive.display());
+    };
+    let listing = Arc::new(
+        read_entries(archive, kind)
+            .with_context(|| format!("Failed to read {}", archive.display()))?,
+    );
+    if let Ok(mut listings) = LISTINGS.lock() {
+        let entries = Arc::clone(&listing);
+        listings.put(
+            archive.to_path_buf(),
+            CachedListing {
+                modified,
+                len,
+                entries,
+            },
+        );
+    }
+    Ok(listing)
+}
+
+/// The folder `inner` of `archive` as directory entries, which carry the
+/// virtual path of every entry.
+pub fn read_dir(archive: &Path, inner: &Path) -> Result<Vec<DirEntry>> {
+    let entries = entries(archive)?;
+    let dir: Arc<str> = Arc::from(virtual_path(archive, inner).to_string_lossy().as_ref());
+    Ok(children(&entries, &inner_key(inner))
+        .into_iter()
+        .map(|entry| {
+            let entry_type = if entry.is_dir {
+                EntryType::Directory
+            } else {
+                EntryType::File
+            };
+            let file_name = entry.name().to_string();
+            DirEntry {
+                meta: DirEntryMetaData {
+                    entry_type,
+                    created_at: entry.modified,
+                    modified_at: entry.modified,
+                    since_modified: entry.modified.elapsed(),
+                    size: entry.size,
+                },
+                sort_key: SortKey::new_path(&file_name, !entry.is_dir),
+                dir: Arc::clone(&dir),
+                file_name,
+            }
+        })
+        .collect())
+}
+
+pub fn open_zip(archive: &Path) -> Result<zip::ZipArchive<BufReader<File>>> {
+    let file =
+        File::open(archive).with_context(|| format!("Failed to open {}", archive.display()))?;
+    zip::ZipArchive::new(BufReader::new(file))
+        .with_context(|| format!("{} is not a valid zip archive", archive.display()))
+}
+
+pub fn open_tar(archive: &Path, kind: ArchiveKind) -> Result<tar::Archive<Box<dyn Read>>> {
+    let file = BufReader::new(
+        File::open(archive).with_context(|| format!("Failed to open {}", archive.display()))?,
+    );
+    let reader: Box<dyn Read> = match kind {
+        ArchiveKind::TarGz => Box::new(flate2::read::MultiGzDecoder::new(file)),
+        ArchiveKind::TarZst => Box::new(zstd::stream::read::Decoder::with_buffer(file)?),
+        ArchiveKind::Tar | ArchiveKind::Zip => Box::new(file),
+    };
+    Ok(tar::Archive::new(reader))
+}
+
+fn zip_timestamp(time: Option<zip::DateTime>) -> TimestampSeconds {
+    time.and_then(|time| {
+        TimestampSeconds::from_utc_parts(
+            time.year().into(),
+            time.month().into(),
+            time.day().into(),
+            time.hour().into(),
+            time.minute().into(),
+            time.second().into(),
+        )
+    })
+    .unwrap_or_default()
+}
+
+fn read_entries(archive: &Path, kind: ArchiveKind) -> Result<Vec<ArchiveEntry>> {
+    let mut entries = Vec::new();
+    if kind == ArchiveKind::Zip {
+        let mut zip = open_zip(archive)?;
+        for index in 0..zip.len() {
+            let file = zip.by_index_raw(index)?;
+            let Some(path) = clean_name(file.name()) else {
+                continue;
+            };
+            entries.push(ArchiveEntry {
+                path,
+                is_dir: file.is_dir(),
+                size: file.size(),
+                modified: zip_timestamp(file.last_modified()),
+            });
+        }
+    } else {
+        let mut tar = open_tar(archive, kind)?;
+        for entry in tar.entries()? {
+            let entry = entry?;
+            let header = entry.header();
+            if header.entry_type().is_pax_global_extensions() {
+                continue;
+            }
+            let Some(path) = clean_name(&entry.path()?.to_string_lossy()) else {
+                continue;
+            };
+            let modified = header
+                .mtime()
+                .map(|seconds| (UNIX_EPOCH + Duration::from_secs(seconds)).into())
+                .unwrap_or_default();
+            entries.push(ArchiveEntry {
+                path,
+                is_dir: header.entry_type().is_dir(),
+                size: entry.size(),
+                modified,
+            });
+        }
+    }
+    Ok(entries)
+}
+
+/// Extracts the file `inner` of `archive` to the user's cache directory and
+/// returns where it was written.
+pub fn extract_file(archive: &Path, inner: &Path) -> Result<PathBuf> {
+    let dirs = ProjectDirs::from("io", "github.leinnan", "dirfleet")
+        .context("There is no cache directory to extract to")?;
+    extract_file_into(archive, inner, &dirs.cache_dir().join("archives"))
+}
+
+/// Extracts the file `inner` of `archive` below `dir`. Folders are created
+/// private to the user and the file is always written anew, never through
+/// whatever is already at its path.
+fn extract_file_into(archive: &Path, inner: &Path, dir: &Path) -> Result<PathBuf> {
+    let name = inner_key(inner);
+    let Some(kind) = ArchiveKind::from_path(archive) else {
+        bail!("{} is not a supported archive", archive.display());
+    };
+    let mut hasher = DefaultHasher::new();
+    archive.hash(&mut hasher);
+    let target = dir.join(format!("{:016x}", hasher.finish())).join(&name);
+    if let Some(parent) = target.parent() {
+        let mut builder = fs::DirBuilder::new();
+        builder.recursive(true);
+        #[cfg(unix)]
+        std::os::unix::fs::DirBuilderExt::mode(&mut builder, 0o700);
+        builder
+            .create(parent)
+            .with_context(|| format!("Failed to create {}", parent.display()))?;
+    }
+    let write = |reader: &mut dyn Read| -> Result<()> {
+        match fs::remove_file(&target) {
+            Err(err) if err.kind() != io::ErrorKind::NotFound => {
+                return Err(err).context(format!("Failed to replace {}", target.display()));
+            }
+            _ => {}
+        }
+        let mut file = OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&target)
+            .with_context(|| format!("Failed to create {}", target.display()))?;
+        io::copy(reader, &mut file)
+            .with_context(|| format!("Failed to extract {name} from {}", archive.display()))?;
+        Ok(())
+    };
+    if kind == ArchiveKind::Zip {
+        let mut zip = open_zip(archive)?;
+        let index = (0..zip.len())
+            .find(|index| {
+                zip.name_for_index(*index)
+                    .and_then(clean_name)
+                    .is_some_and(|path| path == name)
+            })
+            .with_context(|| format!("{name} is not in {}", archive.display()))?;
+        write(&mut zip.by_index(index)?)?;
+    } else {
+        let mut tar = open_tar(archive, kind)?;
+        let mut found = false;
+        for entry in tar.entries()? {
+            let mut entry = entry?;
+            if clean_name(&entry.path()?.to_string_lossy()).is_some_and(|path| path == name) {
+                write(&mut entry)?;
+                found = true;
+                break;
+            }
+        }
+        if !found {
+            bail!("{name} is not in {}", archive.display());
+        }
+    }
+    Ok(target)
+}
+
+/// How many files `archive` holds and their total size, `(0, 0)` when it
+/// can't be read.
+pub fn measure(archive: &Path) -> (u64, u64) {
+    entries(archive).map_or((0, 0), |entries| {
+        entries
+            .iter()
+            .filter(|entry| !entry.is_dir)
+            .fold((0, 0), |(files, bytes), entry| {
+                (files + 1, bytes + entry.size)
+            })
+    })
+}
+
+/// Picks where the top-level item `name` of an archive is extracted to, given
+/// when it was modified. `None` skips it with everything inside.
+pub type ResolveDestination<'a> =
+    dyn FnMut(&str, Option<SystemTime>) -> Result<Option<PathBuf>> + 'a;
+
+/// What an archive entry unpacks to.
+enum Contents<'a> {
+    Dir,
+    File(&'a mut dyn Read, u64),
+    Link(PathBuf),
+    /// A hard link to an entry extracted earlier, written as a copy of it.
+    HardLink(String),
+}
+
+/// Writes archive entries below one folder.
+struct Unpacker<'a, 'b> {
+    /// The canonical destination folder, nothing is written outside of it.
+    root: PathBuf,
+    resolve: &'a mut ResolveDestination<'b>,
+    progress: &'a JobProgress,
+    /// Destinations of the top-level items picked so far.
+    destinations: HashMap<String, Option<PathBuf>>,
+    /// Entries left out because their name would leave the destination.
+    unsafe_entries: usize,
+}
+
+impl Unpacker<'_, '_> {
+    /// Where the entry `name` goes, `None` when it is skipped.
+    fn destination(&mut self, name: &str, modified: Option<SystemTime>) -> Result<Option<PathBuf>> {
+        let mut parts = name.split('/');
+        let top = parts.next().unwrap_or_default();
+        let is_plain = |part: &str| {
+            matches!(
+                Path::new(part).components().collect::<Vec<_>>().as_slice(),
+                [Component::Normal(_)]
+            )
+        };
+        if !name.split('/').all(is_plain) {
+            self.unsafe_entries += 1;
+            return Ok(None);
+        }
+        let destination = if let Some(destination) = self.destinations.get(top) {
+            destination.clone()
+        } else {
+            let destination = (self.resolve)(top, modified)?;
+            self.destinations
+                .insert(top.to_string(), destination.clone());
+            destination
+        };
+        Ok(destination.map(|mut path| {
+            path.extend(parts);
+            path
+        }))
+    }
+
+    /// Fails when `dir` resolves to somewhere outside the destination, which
+    /// links extracted earlier could lead to.
+    fn check_inside(&self, dir: &Path) -> Result<()> {
+        let resolved = fs::canonicalize(dir)
+            .with_context(|| format!("Failed to resolve {}", dir.display()))?;
+        if !resolved.starts_with(&self.root) {
+            bail!(
+                "{} would be written outside of {}",
+                dir.display(),
+                self.root.display()
+            );
+        }
+        Ok(())
+    }
+
+    fn unpack(
+        &mut self,
+        name: &str,
+        modified: Option<SystemTime>,
+        mode: Option<u32>,
+        contents: Contents<'_>,
+    ) -> Result<()> {
+        self.progress.checkpoint()?;
+        let Some(path) = self.destination(name, modified)? else {
+            if let Contents::File(_, size) = contents {
+                self.progress.add_files(1);
+                self.progress.add_bytes(size);
+            } else if !matches!(contents, Contents::Dir) {
+                self.progress.add_files(1);
+            }
+            return Ok(());
+        };
+        if matches!(contents, Contents::Dir) {
+            fs::create_dir_all(&path)
+                .with_context(|| format!("Failed to create {}", path.display()))?;
+            return self.check_inside(&path);
+        }
+        let parent = path.parent().unwrap_or(&self.root);
+        fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create {}", parent.display()))?;
+        self.check_inside(parent)?;
+        // A link left by an earlier entry is replaced rather than written
+        // through.
+        if path.symlink_metadata().is_ok_and(|meta| meta.is_symlink()) {
+            fs::remove_file(&path)
+                .with_context(|| format!("Failed to replace {}", path.display()))?;
+        }
+        self.progress.set_current(&path);
+        match contents {
+            Contents::File(reader, _) => {
+                let mut file = File::create(&path)
+                    .with_context(|| format!("Failed to create {}", path.display()))?;
+                let mut reader = ProgressReader {
+                    inner: reader,
+                    progress: self.progress,
+                };
+                if let Err(err) = io::copy(&mut reader, &mut file) {
+                    drop(file);
+                    let _ = fs::remove_file(&path);
+                    if self.progress.is_cancelled() {
+                        return Err(Cancelled.into());
+                    }
+                    return Err(err).with_context(|| format!("Failed to write {}", path.display()));
+                }
+                if let Some(modified) = modified {
+                    let _ = file.set_modified(modified);
+                }
+                #[cfg(unix)]
+                if let Some(mode) = mode {
+                    use std::os::unix::fs::PermissionsExt;
+                    let _ = file.set_permissions(fs::Permissions::from_mode(mode & 0o777));
+                }
+            }
+            #[cfg(unix)]
+            Contents::Link(target) => std::os::unix::fs::symlink(&target, &path)
+                .with_context(|| format!("Failed to create link {}", path.display()))?,
+            Contents::HardLink(original) => {
+                if let Some(original) = self.destination(&original, None)? {
+                    self.check_inside(original.parent().unwrap_or(&self.root))?;
+                    fs::copy(&original, &path)
+                        .with_context(|| format!("Failed to create {}", path.display()))?;
+                }
+            }
+            // Folders are created above. Creating links needs extra
+            // privileges on Windows, they are left out.
+            _ => {}
+        }
+        #[cfg(not(unix))]
+        let _ = mode;
+        self.progress.add_files(1);
+        Ok(())
+    }
+}
+
+/// Whether a raw entry name that [`clean_name`] rejected tried to climb out of
+/// the archive, rather than being empty.
+fn climbs_out(name: &str) -> bool {
+    name.split(['/', '\\']).any(|part| part == "..")
+}
+
+/// Extracts everything in `archive` into `target`. Entry names are kept
+/// inside it: absolute names are taken as relative and names with `..` are
+/// left out, which fails the extraction once the rest is written.
+pub fn extract(
+    archive: &Path,
+    target: &Path,
+    progress: &JobProgress,
+    resolve: &mut ResolveDestination<'_>,
+) -> Result<()> {
+    let Some(kind) = ArchiveKind::from_path(archive) else {
+        bail!("{} is not a supported archive", archive.display());
+    };
+    fs::create_dir_all(target).with_context(|| format!("Failed to create {}", target.display()))?;
+    let mut unpacker = Unpacker {
+        root: fs::canonicalize(target)
+            .with_context(|| format!("Failed to resolve {}", target.display()))?,
+        resolve,
+        progress,
+        destinations: HashMap::new(),
+        unsafe_entries: 0,
+    };
+    if kind == ArchiveKind::Zip {
+        let mut zip = open_zip(archive)?;
+        for index in 0..zip.len() {
+            let mut file = zip.by_index(index)?;
+            let Some(name) = clean_name(file.name()) else {
+                unpacker.unsafe_entries += usize::from(climbs_out(file.name()));
+                continue;
+            };
+            let modified = file
+                .last_modified()
+                .map(|time| zip_timestamp(Some(time)).system_time());
+            let mode = file.unix_mode();
+            let contents = if file.is_dir() {
+                Contents::Dir
+            } else if file.is_symlink() {
+                let mut link = String::new();
+                file.read_to_string(&mut link)?;
+                Contents::Link(link.into())
+            } else {
+                let size = file.size();
+                Contents::File(&mut file, size)
+            };
+            unpacker.unpack(&name, modified, mode, contents)?;
+        }
+    } else {
+        let mut tar = open_tar(archive, kind)?;
+        for entry in tar.entries()? {
+            let mut entry = entry?;
+            let raw_name = entry.path()?.to_string_lossy().to_string();
+            let Some(name) = clean_name(&raw_name) else {
+                unpacker.unsafe_entries += usize::from(climbs_out(&raw_name));
+                continue;
+            };
+            let header = entry.header();
+            let entry_type = header.entry_type();
+            let modified = header
+                .mtime()
+                .ok()
+                .map(|seconds| UNIX_EPOCH + Duration::from_secs(seconds));
+            let mode = header.mode().ok();
+            let link = entry.link_name()?.map(std::borrow::Cow::into_owned);
+            let contents = if entry_type.is_dir() {
+                Contents::Dir
+            } else if entry_type.is_symlink() {
+                Contents::Link(link.unwrap_or_default())
+            } else if entry_type.is_hard_link() {
+                match link.and_then(|link| clean_name(&link.to_string_lossy())) {
+                    Some(original) => Contents::HardLink(original),
+                    None => continue,
+                }
+            } else if entry_type.is_file() || entry_type.is_contiguous() {
+                let size = entry.size();
+                Contents::File(&mut entry, size)
+            } else {
+                // Devices, fifos and extension headers.
+                continue;
+            };
+            unpacker.unpack(&name, modified, mode, contents)?;
+        }
+    }
+    if unpacker.unsafe_entries > 0 {
+        bail!(
+            "Left out {} entries of {} that point outside the destination",
+            unpacker.unsafe_entries,
+            archive.display()
+        );
+    }
+    Ok(())
+}
+
+/// A file, folder or link to store, with its name inside the archive.
+struct SourceEntry {
+    path: PathBuf,
+    name: String,
+    meta: fs::Metadata,
+}
+
+/// Everything under `sources`, named relative to the folder each source is
+/// in. Links are stored as links.
+fn source_entries(sources: &[PathBuf]) -> Result<Vec<SourceEntry>> {
+    let mut entries = Vec::new();
+    for source in sources {
+        let base = source.parent().unwrap_or(source);
+        for entry in walkdir::WalkDir::new(source).follow_links(false) {
+            let entry = entry.with_context(|| format!("Failed to read {}", source.display()))?;
+            let name = entry
+                .path()
+                .strip_prefix(base)
+                .unwrap_or_else(|_| entry.path())
+                .components()
+                .map(|component| component.as_os_str().to_string_lossy())
+                .collect::<Vec<_>>()
+                .join("/");
+            let meta = entry.metadata().with_context(|| {
+                format!("Failed to read metadata of {}", entry.path().display())
+            })?;
+            entries.push(SourceEntry {
+                path: entry.into_path(),
+                name,
+                meta,
+            });
+        }
+    }
+    Ok(entries)
+}
+
+/// Reads a file while reporting progress to a file job, failing once the job
+/// is cancelled.
+struct ProgressReader<'a, R> {
+    inner: R,
+    progress: &'a JobProgress,
+}
+
+impl<R: Read> Read for ProgressReader<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.progress.checkpoint().map_err(io::Error::other)?;
+        let read = self.inner.read(buf)?;
+        self.progress.add_bytes(read as u64);
+        Ok(read)
+    }
+}
+
+fn open_source<'a>(
+    entry: &SourceEntry,
+    progress: &'a JobProgress,
+) -> Result<ProgressReader<'a, File>> {
+    progress.set_current(&entry.path);
+    let file = File::open(&entry.path)
+        .with_context(|| format!("Failed to open {}", entry.path.display()))?;
+    Ok(ProgressReader {
+        inner: file,
+        progress,
+    })
+}
+
+fn read_link(entry: &SourceEntry) -> Result<PathBuf> {
+    fs::read_link(&entry.path)
+        .with_context(|| format!("Failed to read link {}", entry.path.display()))
+}
+
+fn write_zip(
+    file: File,
+    entries: &[SourceEntry],
+    level: u32,
+    progress: &JobProgress,
+) -> Result<()> {
+    use zip::{CompressionMethod, write::SimpleFileOptions};
+
+    let mut zip = zip::ZipWriter::new(BufWriter::new(file));
+    for entry in entries {
+        progress.checkpoint()?;
+        let mut options = SimpleFileOptions::default()
+            .compression_method(if level == 0 {
+                CompressionMethod::Stored
+            } else {
+                CompressionMethod::Deflated
+            })
+            .compression_level((level > 0).then_some(level.into()))
+            .large_file(entry.meta.len() > u64::from(u32::MAX));
+        if let Some(modified) = entry
+            .meta
+            .modified()
+            .ok()
+            .and_then(|modified| zip_date_time(modified.into()))
+        {
+            options = options.last_modified_time(modified);
+        }
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            options = options.unix_permissions(entry.meta.permissions().mode() & 0o7777);
+        }
+        if entry.meta.is_symlink() {
+            let target = read_link(entry)?;
+            zip.add_symlink(&entry.name, target.to_string_lossy(), options)?;
+            progress.add_files(1);
+        } else if entry.meta.is_dir() {
+            zip.add_directory(&entry.name, options)?;
+        } else {
+            zip.start_file(&entry.name, options)?;
+            io::copy(&mut open_source(entry, progress)?, &mut zip)?;
+            progress.add_files(1);
+        }
+    }
+    zip.finish()?.flush()?;
+    Ok(())
+}
+
+/// Zip timestamps only cover the years 1980 to 2107.
+fn zip_date_time(timestamp: TimestampSeconds) -> Option<zip::DateTime> {
+    let (year, month, day, hour, minute, second) = timestamp.to_utc_parts();
+    zip::DateTime::from_date_and_time(
+        u16::try_from(year).ok()?,
+        u8::try_from(month).ok()?,
+        u8::try_from(day).ok()?,
+        u8::try_from(hour).ok()?,
+        u8::try_from(minute).ok()?,
+        u8::try_from(second).ok()?,
+    )
+    .ok()
+}
+
+fn write_tar<W: Write>(
+    builder: &mut tar::Builder<W>,
+    entries: &[SourceEntry],
+    progress: &JobProgress,
+) -> Result<()> {
+    for entry in entries {
+        progress.checkpoint()?;
+        let mut header = tar::Header::new_gnu();
+        header.set_metadata(&entry.meta);
+        if entry.meta.is_symlink() {
+            builder.append_link(&mut header, &entry.name, read_link(entry)?)?;
+            progress.add_files(1);
+        } else if entry.meta.is_dir() {
+            builder.append_data(&mut header, &entry.name, io::empty())?;
+        } else {
+            // The header already holds the length, a file that grew meanwhile
+            // is cut off at it.
+            let reader = open_source(entry, progress)?.take(entry.meta.len());
+            builder.append_data(&mut header, &entry.name, reader)?;
+            progress.add_files(1);
+        }
+    }
+    Ok(())
+}
+
+/// Writes `sources` with everything inside them to a new archive at
+/// `archive`, in the format its extension names. A partly written archive is
+/// removed when this fails or the job is cancelled.
+pub fn compress(
+    sources: &[PathBuf],
+    archive: &Path,
+    level: u32,
+    progress: &JobProgress,
+) -> Result<()> {
+    let Some(kind) = ArchiveKind::from_path(archive) else {
+        bail!("{} is not a supported archive", archive.display());
+    };
+    let entries = source_entries(sources)?;
+    let file = OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(archive)
+        .with_context(|| format!("Failed to create {}", archive.display()))?;
+    let result = match kind {
+        ArchiveKind::Zip => write_zip(file, &entries, level, progress),
+        ArchiveKind::Tar => {
+            let mut builder = tar::Builder::new(BufWriter::new(file));
+            write_tar(&mut builder, &entries, progress)
+                .and_then(|()| Ok(builder.into_inner()?.flush()?))
+        }
+        ArchiveKind::TarGz => {
+            let encoder = flate2::write::GzEncoder::new(
+                BufWriter::new(file),
+                flate2::Compression::new(level),
+            );
+            let mut builder = tar::Builder::new(encoder);
+            write_tar(&mut builder, &entries, progress)
+                .and_then(|()| Ok(builder.into_inner()?.finish()?.flush()?))
+        }
+        ArchiveKind::TarZst => {
+            let level = i32::try_from(level).unwrap_or(zstd::DEFAULT_COMPRESSION_LEVEL);
+            let encoder = zstd::stream::write::Encoder::new(BufWriter::new(file), level)?;
+            let mut builder = tar::Builder::new(encoder);
+            write_tar(&mut builder, &entries, progress)
+                .and_then(|()| Ok(builder.into_inner()?.finish()?.flush()?))
+        }
+    };
+    if let Err(err) = result {
+        let _ = fs::remove_file(archive);
+        if progress.is_cancelled() {
+            return Err(Cancelled.into());
+        }
+        return Err(err.context(format!("Failed to write {}", archive.display())));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, path::PathBuf};
+
+    use super::{
+        ArchiveEntry, ArchiveKind, children, clean_name, compress, entries, extract,
+        extract_file_into, read_dir, split, stem,
+    };
+    use crate::app::file_ops::{Cancelled, JobProgress};
+    use crate::app::test_support::unique_test_dir;
+    use crate::data::time::TimestampSeconds;
+
+    fn entry(path: &str, is_dir: bool) -> ArchiveEntry {
+        ArchiveEntry {
+            path: path.to_string(),
+            is_dir,
+            size: 1,
+            modified: TimestampSeconds::default(),
+        }
+    }
+
+    #[test]
+    fn archive_kind_follows_the_extension() {
+        let kind = |name: &str| ArchiveKind::from_path(std::path::Path::new(name));
+        assert_eq!(kind("photos.ZIP"), Some(ArchiveKind::Zip));
+        assert_eq!(kind("src.tar.gz"), Some(ArchiveKind::TarGz));
+        assert_eq!(kind("src.tgz"), Some(ArchiveKind::TarGz));
+        assert_eq!(kind("src.tar.zst"), Some(ArchiveKind::TarZst));
+        assert_eq!(kind("src.tar"), Some(ArchiveKind::Tar));
+        assert_eq!(kind("notes.txt"), None);
+        assert_eq!(kind(".zip"), None);
+    }
+
+    #[test]
+    fn stem_drops_the_archive_extension() {
+        let stem = |name: &str| stem(std::path::Path::new(name));
+        assert_eq!(stem("src.tar.gz").as_deref(), Some("src"));
+        assert_eq!(stem("Photos.ZIP").as_deref(), Some("Photos"));
+        assert_eq!(stem("notes.txt"), None);
+    }
+
+    #[test]
+    fn entry_names_are_normalized() {
+        assert_eq!(
+            clean_name("./dir//file.txt").as_deref(),
+            Some("dir/file.txt")
+        );
+        assert_eq!(clean_name("dir\\file.txt").as_deref(), Some("dir/file.txt"));
+        assert_eq!(clean_name("/abs/file").as_deref(), Some("abs/file"));
+        assert_eq!(clean_name("dir/"), Some("dir".to_string()));
+        assert_eq!(clean_name("../evil"), None);
+        assert_eq!(clean_name("./"), None);
+    }
+
+    #[test]
+    fn children_include_implied_folders() {
+        let entries = [
+            entry("readme.md", false),
+            entry("src/main.rs", false),
+            entry("src/app/mod.rs", false),
+            entry("docs", true),
+        ];
+        let names = |inner: &str| {
+            children(&entries, inner)
+                .into_iter()
+                .map(|entry| (entry.name().to_string(), entry.is_dir))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            names(""),
+            vec![
+                ("docs".to_string(), true),
+                ("readme.md".to_string(), false),
+                ("src".to_string(), true),
+            ]
+        );
+        assert_eq!(
+            names("src"),
+            vec![("app".to_string(), true), ("main.rs".to_string(), false)]
+        );
+        assert!(names("docs").is_empty());
+    }
+
+    #[test]
+    fn tar_archives_are_listed_and_extracted() {
+        let dir = unique_test_dir("archive_tar");
+        let archive = dir.join("bundle.tar");
+        let mut builder = tar::Builder::new(Vec::new());
+        let mut header = tar::Header::new_gnu();
+        header.set_size(5);
+        header.set_mode(0o644);
+        header.set_mtime(951_827_696);
+        header.set_cksum();
+        builder
+            .append_data(&mut header, "nested/hello.txt", &b"hello"[..])
+            .expect("append file");
+        fs::write(&archive, builder.into_inner().expect("finish tar")).expect("write tar");
+
+        let (found, inner) = split(&archive.join("nested")).expect("inside the archive");
+        assert_eq!(found, fs::canonicalize(&archive).expect("canonicalize"));
+        assert_eq!(inner, PathBuf::from("nested"));
+        assert!(split(&dir).is_none());
+
+        let root = read_dir(&found, &PathBuf::new()).expect("list root");
+        assert_eq!(root.len(), 1);
+        assert!(!root[0].is_file());
+        let nested = read_dir(&found, &inner).expect("list folder");
+        assert_eq!(nested[0].file_name, "hello.txt");
+        assert_eq!(nested[0].meta.size, 5);
+        assert_eq!(*nested[0].meta.modified_at, 951_827_696);
+        assert_eq!(nested[0].get_path(), found.join("nested/hello.txt"));
+
+        let cache = dir.join("cache");
+        let inner = PathBuf::from("nested/hello.txt");
+        let extracted = extract_file_into(&found, &inner, &cache).expect("extract");
+        assert_eq!(fs::read(&extracted).expect("read extracted"), b"hello");
+
+        // A link planted where the file goes is replaced, not written through.
+        #[cfg(unix)]
+        {
+            let victim = dir.join("victim.txt");
+            fs::write(&victim, b"mine").expect("write victim");
+            fs::remove_file(&extracted).expect("remove extracted");
+            std::os::unix::fs::symlink(&victim, &extracted).expect("plant link");
+            let again = extract_file_into(&found, &inner, &cache).expect("extract again");
+            assert!(!again.symlink_metadata().expect("meta").is_symlink());
+            assert_eq!(fs::read(&again).expect("read extracted"), b"hello");
+            assert_eq!(fs::read(&victim).expect("read victim"), b"mine");
+        }
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn selections_are_compressed_in_every_writable_format() {
+        let dir = unique_test_dir("archive_compress");
+        let source = dir.join("project");
+        fs::create_dir_all(source.join("src")).expect("create dirs");
+        fs::write(source.join("src/main.rs"), b"fn main() {}").expect("write file");
+        fs::write(dir.join("notes.txt"), b"notes").expect("write file");
+        let sources = [source, dir.join("notes.txt")];
+
+        for kind in ArchiveKind::WRITABLE {
+            let archive = dir.join(format!("bundle{}", kind.extension()));
+            let progress = JobProgress::default();
+            compress(&sources, &archive, kind.default_level(), &progress).expect("compress");
+            assert_eq!(progress.files().0, 2);
+            assert_eq!(progress.bytes().0, 17);
+
+            let mut names = entries(&archive)
+                .expect("list archive")
+                .iter()
+                .map(|entry| (entry.path.clone(), entry.is_dir))
+                .collect::<Vec<_>>();
+            names.sort();
+            assert_eq!(
+                names,
+                vec![
+                    ("notes.txt".to_string(), false),
+                    ("project".to_string(), true),
+                    ("project/src".to_string(), true),
+                    ("project/src/main.rs".to_string(), false),
+                ],
+                "{kind}"
+            );
+            let inner = PathBuf::from("project/src/main.rs");
+            let extracted =
+                extract_file_into(&archive, &inner, &dir.join("cache")).expect("extract");
+            assert_eq!(fs::read(extracted).expect("read"), b"fn main() {}");
+            assert!(
+                compress(&sources, &archive, 1, &JobProgress::default()).is_err(),
+                "existing archives are not overwritten"
+            );
+        }
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    /// A tar entry with a raw name, which `tar::Builder` would refuse.
+    fn append_raw(
+        builder: &mut tar::Builder<Vec<u8>>,
+        name: &str,
+        kind: tar::EntryType,
+        data: &[u8],
+    ) {
+        let mut header = tar::Header::new_old();
+        header.as_old_mut().name[..name.len()].copy_from_slice(name.as_bytes());
+        header.set_entry_type(kind);
+        header.set_size(data.len() as u64);
+        header.set_mode(0o644);
+        header.set_cksum();
+        builder.append(&header, data).expect("append entry");
+    }
+
+    #[test]
+    fn extraction_stays_inside_the_destination() {
+        let dir = unique_test_dir("archive_extract");
+        let archive = dir.join("bundle.tar");
+        let mut builder = tar::Builder::new(Vec::new());
+        append_raw(
+            &mut builder,
+            "docs/readme.md",
+            tar::EntryType::Regular,
+            b"read me",
+        );
+        append_raw(
+            &mut builder,
+            "../evil.txt",
+            tar::EntryType::Regular,
+            b"evil",
+        );
+        append_raw(
+            &mut builder,
+            "/absolute.txt",
+            tar::EntryType::Regular,
+            b"abs",
+        );
+        append_raw(
+            &mut builder,
+            "skipped.txt",
+            tar::EntryType::Regular,
+            b"skip",
+        );
+        fs::write(&archive, builder.into_inner().expect("finish tar")).expect("write tar");
+
+        let target = dir.join("out");
+        let progress = JobProgress::default();
+        let mut asked = Vec::new();
+        let result = extract(&archive, &target, &progress, &mut |name, _| {
+            asked.push(name.to_string());
+            Ok((name != "skipped.txt").then(|| target.join(name)))
+        });
+
+        let error = result.expect_err("the climbing entry is reported");
+        assert!(format!("{error:#}").contains("Left out 1 entries"));
+        assert_eq!(asked, ["docs", "absolute.txt", "skipped.txt"]);
+        assert_eq!(
+            fs::read(target.join("docs/readme.md")).expect("read"),
+            b"read me"
+        );
+        assert_eq!(fs::read(target.join("absolute.txt")).expect("read"), b"abs");
+        assert!(!target.join("skipped.txt").exists());
+        assert!(!dir.join("evil.txt").exists());
+        assert_eq!(progress.files().0, 3);
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn cancelled_extraction_leaves_no_partial_file() {
+        let dir = unique_test_dir("archive_extract_cancel");
+        let archive = dir.join("bundle.tar");
+        let mut builder = tar::Builder::new(Vec::new());
+        append_raw(&mut builder, "first.txt", tar::EntryType::Regular, b"first");
+        append_raw(
+            &mut builder,
+            "big.bin",
+            tar::EntryType::Regular,
+            &[7; 64 * 1024],
+        );
+        fs::write(&archive, builder.into_inner().expect("finish tar")).expect("write tar");
+
+        let target = dir.join("out");
+        let progress = JobProgress::default();
+        // Cancelling once the second file is picked stops the job while it
+        // is being written.
+        let result = extract(&archive, &target, &progress, &mut |name, _| {
+            if name == "big.bin" {
+                progress.cancel();
+            }
+            Ok(Some(target.join(name)))
+        });
+
+        let error = result.expect_err("the extraction is cancelled");
+        assert!(error.is::<Cancelled>(), "{error:#}");
+        assert_eq!(fs::read(target.join("first.txt")).expect("read"), b"first");
+        assert!(!target.join("big.bin").exists());
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn extraction_does_not_write_through_links() {
+        let dir = unique_test_dir("archive_extract_link");
+        let archive = dir.join("bundle.tar");
+        let mut builder = tar::Builder::new(Vec::new());
+        let mut header = tar::Header::new_gnu();
+        header.set_entry_type(tar::EntryType::Symlink);
+        header.set_size(0);
+        header.set_mode(0o777);
+        builder
+            .append_link(&mut header, "link", "..")
+            .expect("append link");
+        append_raw(
+            &mut builder,
+            "link/escaped.txt",
+            tar::EntryType::Regular,
+            b"x",
+        );
+        fs::write(&archive, builder.into_inner().expect("finish tar")).expect("write tar");
+
+        let target = dir.join("out");
+        let result = extract(
+            &archive,
+            &target,
+            &JobProgress::default(),
+            &mut |name, _| Ok(Some(target.join(name))),
+        );
+
+        assert!(result.is_err());
+        assert!(target.join("link").symlink_metadata().is_ok());
+        assert!(!dir.join("escaped.txt").exists());
+        let _ = fs::remove_dir_all(dir);
+    }
+}
@@ -0,0 +1,571 @@
+//! Renaming many entries at once.
+//!
+//! A [`RenamePattern`] turns every selected name into a new one: an optional
+//! find/replace (plain text, `*`/`?` wildcards or regex), a case change of the
+//! name and a template with tokens such as a counter or the modification date.
+//! [`preview`] flags invalid and colliding names before [`rename_all`] applies
+//! the whole batch.
+
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::Display,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result};
+use regex::{NoExpand, Regex, RegexBuilder};
+
+use crate::app::SearchTermType;
+use crate::data::time::TimestampSeconds;
+
+/// Tokens understood by [`RenamePattern::template`].
+pub const TEMPLATE_TOKENS: &[(&str, &str)] = &[
+    ("{name}", "Name without extension, after find/replace"),
+    ("{ext}", "Extension including the dot"),
+    ("{n}", "Counter"),
+    ("{date}", "Modification date in UTC, YYYY-MM-DD"),
+    ("{time}", "Modification time in UTC, HH-MM-SS"),
+    ("{year}", "Modification year in UTC"),
+    ("{month}", "Modification month in UTC"),
+    ("{day}", "Modification day in UTC"),
+];
+
+/// Tokens filled in from the modification time, which is taken in UTC.
+const TIME_TOKENS: &[&str] = &["{date}", "{time}", "{year}", "{month}", "{day}"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CaseChange {
+    #[default]
+    Keep,
+    Lower,
+    Upper,
+    Title,
+}
+
+impl CaseChange {
+    pub const ALL: [Self; 4] = [Self::Keep, Self::Lower, Self::Upper, Self::Title];
+
+    fn apply(self, text: &str) -> String {
+        match self {
+            Self::Keep => text.to_string(),
+            Self::Lower => text.to_lowercase(),
+            Self::Upper => text.to_uppercase(),
+            Self::Title => {
+                let mut result = String::with_capacity(text.len());
+                let mut word_start = true;
+                for c in text.chars() {
+                    if word_start {
+                        result.extend(c.to_uppercase());
+                    } else {
+                        result.extend(c.to_lowercase());
+                    }
+                    word_start = c.is_whitespace() || c == '_' || c == '-';
+                }
+                result
+            }
+        }
+    }
+}
+
+impl Display for CaseChange {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Keep => write!(f, "Keep case"),
+            Self::Lower => write!(f, "lowercase"),
+            Self::Upper => write!(f, "UPPERCASE"),
+            Self::Title => write!(f, "Title Case"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RenamePattern {
+    pub find: String,
+    pub replace: String,
+    pub term_type: SearchTermType,
+    pub case_sensitive: bool,
+    /// Case change applied to `{name}`.
+    pub case: CaseChange,
+    pub template: String,
+    pub counter_start: u32,
+    pub counter_step: u32,
+    /// Minimal number of digits of `{n}`, padded with zeros.
+    pub counter_padding: usize,
+}
+
+impl Default for RenamePattern {
+    fn default() -> Self {
+        Self {
+            find: String::new(),
+            replace: String::new(),
+            term_type: SearchTermType::Plain,
+            case_sensitive: true,
+            case: CaseChange::Keep,
+            template: "{name}{ext}".to_string(),
+            counter_start: 1,
+            counter_step: 1,
+            counter_padding: 0,
+        }
+    }
+}
+
+impl RenamePattern {
+    /// Whether the template names entries after their modification time.
+    pub fn uses_modification_time(&self) -> bool {
+        TIME_TOKENS
+            .iter()
+            .any(|token| self.template.contains(token))
+    }
+
+    fn compile_find(&self) -> Result<Option<Regex>, regex::Error> {
+        if self.find.is_empty() {
+            return Ok(None);
+        }
+        let pattern = match self.term_type {
+            // Metadata and fuzzy terms only filter searches, names are found as text.
+            SearchTermType::Plain | SearchTermType::Metadata | SearchTermType::Fuzzy => {
+                regex::escape(&self.find)
+            }
+            SearchTermType::Glob => regex::escape(&self.find)
+                .replace(r"\*", ".*")
+                .replace(r"\?", "."),
+            SearchTermType::Regex => self.find.clone(),
+        };
+        RegexBuilder::new(&pattern)
+            .case_insensitive(!self.case_sensitive)
+            .build()
+            .map(Some)
+    }
+
+    fn new_name(
+        &self,
+        find: Option<&Regex>,
+        name: &str,
+        is_dir: bool,
+        modified_at: TimestampSeconds,
+        index: usize,
+    ) -> String {
+        let replaced = match find {
+            Some(find) if self.term_type == SearchTermType::Regex => {
+                find.replace_all(name, self.replace.as_str())
+            }
+            Some(find) => find.replace_all(name, NoExpand(&self.replace)),
+            None => name.into(),
+        };
+        let (stem, ext) = split_extension(&replaced, is_dir);
+        let counter = u64::from(self.counter_start)
+            .saturating_add(u64::from(self.counter_step).saturating_mul(index as u64));
+        let (year, month, day, hour, minute, second) = modified_at.to_utc_parts();
+        expand_template(&self.template, |token| {
+            Some(match token {
+                "name" => self.case.apply(stem),
+                "ext" => ext.to_string(),
+                "n" => format!("{counter:0width$}", width = self.counter_padding),
+                "date" => format!("{year:04}-{month:02}-{day:02}"),
+                "time" => format!("{hour:02}-{minute:02}-{second:02}"),
+                "year" => format!("{year:04}"),
+                "month" => format!("{month:02}"),
+                "day" => format!("{day:02}"),
+                _ => return None,
+            })
+        })
+        .trim()
+        .to_string()
+    }
+}
+
+/// Replaces every `{token}` in `template` in a single pass. Unknown tokens are
+/// kept as they are.
+fn expand_template(template: &str, value: impl Fn(&str) -> Option<String>) -> String {
+    let mut expanded = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        expanded.push_str(&rest[..start]);
+        let after = &rest[start + 1..];
+        if let Some((end, value)) = after
+            .find('}')
+            .and_then(|end| value(&after[..end]).map(|value| (end, value)))
+        {
+            expanded.push_str(&value);
+            rest = &after[end + 1..];
+        } else {
+            expanded.push('{');
+            rest = after;
+        }
+    }
+    expanded.push_str(rest);
+    expanded
+}
+
+/// Splits `name` into stem and extension (with the dot). Directories and
+/// dotfiles such as `.bashrc` have no extension.
+fn split_extension(name: &str, is_dir: bool) -> (&str, &str) {
+    match name.rfind('.') {
+        Some(dot) if dot > 0 && !is_dir => name.split_at(dot),
+        _ => (name, ""),
+    }
+}
+
+/// An entry to rename, with the data its new name can be built from.
+#[derive(Debug, Clone)]
+pub struct RenameSource {
+    pub path: PathBuf,
+    pub is_dir: bool,
+    pub modified_at: TimestampSeconds,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RenameProblem {
+    Empty,
+    Reserved,
+    InvalidCharacter(char),
+    /// Several entries would get the same name.
+    Duplicate,
+    /// Another entry with that name already exists.
+    Exists,
+}
+
+impl Display for RenameProblem {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Empty => write!(f, "Name is empty"),
+            Self::Reserved => write!(f, "Name is reserved"),
+            Self::InvalidCharacter(c) => write!(f, "Contains invalid character {c:?}"),
+            Self::Duplicate => write!(f, "Same name as another renamed item"),
+            Self::Exists => write!(f, "Already exists"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RenamePreview {
+    pub from: PathBuf,
+    pub to: PathBuf,
+    pub problem: Option<RenameProblem>,
+}
+
+impl RenamePreview {
+    pub fn is_unchanged(&self) -> bool {
+        self.from == self.to
+    }
+}
+
+#[cfg(windows)]
+const INVALID_CHARACTERS: &[char] = &['<', '>', ':', '"', '/', '\\', '|', '?', '*', '\0'];
+#[cfg(not(windows))]
+const INVALID_CHARACTERS: &[char] = &['/', '\0'];
+
+pub fn validate_name(name: &str) -> Option<RenameProblem> {
+    if name.is_empty() {
+        return Some(RenameProblem::Empty);
+    }
+    if name == "." || name == ".." {
+        return Some(RenameProblem::Reserved);
+    }
+    name.chars()
+        .find(|c| INVALID_CHARACTERS.contains(c) || c.is_control())
+        .map(RenameProblem::InvalidCharacter)
+}
+
+/// Key under which two paths name the same file. Windows and macOS file
+/// systems are case insensitive by default.
+fn path_key(path: &Path) -> String {
+    let path = path.to_string_lossy();
+    if cfg!(any(windows, target_os = "macos")) {
+        path.to_lowercase()
+    } else {
+        path.into_owned()
+    }
+}
+
+/// Computes the new name of every source, in order. Fails only when the find
+/// pattern is not a valid regex.
+pub fn preview(
+    sources: &[RenameSource],
+    pattern: &RenamePattern,
+) -> Result<Vec<RenamePreview>, regex::Error> {
+    let find = pattern.compile_find()?;
+    let mut previews: Vec<RenamePreview> = sources
+        .iter()
+        .enumerate()
+        .map(|(index, source)| {
+            let name = source
+                .path
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_default();
+            let new_name = pattern.new_name(
+                find.as_ref(),
+                &name,
+                source.is_dir,
+                source.modified_at,
+                index,
+            );
+            let problem = validate_name(&new_name);
+            RenamePreview {
+                from: source.path.clone(),
+                to: source.path.with_file_name(&new_name),
+                problem,
+            }
+        })
+        .collect();
+
+    let renamed: HashSet<String> = previews.iter().map(|p| path_key(&p.from)).collect();
+    let mut targets: HashMap<String, usize> = HashMap::new();
+    for preview in &previews {
+        *targets.entry(path_key(&preview.to)).or_default() += 1;
+    }
+    for preview in previews.iter_mut().filter(|p| p.problem.is_none()) {
+        let key = path_key(&preview.to);
+        if targets.get(&key).is_some_and(|count| *count > 1) {
+            preview.problem = Some(RenameProblem::Duplicate);
+        } else if !preview.is_unchanged()
+            && !renamed.contains(&key)
+            && preview.to.symlink_metadata().is_ok()
+        {
+            preview.problem = Some(RenameProblem::Exists);
+        }
+    }
+    Ok(previews)
+}
+
+/// The `(from, to)` pairs to rename, or `None` while any name has a problem.
+pub fn planned_renames(previews: &[RenamePreview]) -> Option<Vec<(PathBuf, PathBuf)>> {
+    if previews.iter().any(|p| p.problem.is_some()) {
+        return None;
+    }
+    Some(
+        previews
+            .iter()
+            .filter(|p| !p.is_unchanged())
+            .map(|p| (p.from.clone(), p.to.clone()))
+            .collect(),
+    )
+}
+
+fn temporary_path(path: &Path, index: usize) -> PathBuf {
+    let name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    path.with_file_name(format!(
+        ".{name}.lwa_fm_rename_{}_{index}",
+        std::process::id()
+    ))
+}
+
+/// Renames every `(from, to)` pair as one step. Entries are first moved to
+/// temporary names, so swapped names and case-only changes work. When any
+/// rename fails, the already renamed entries get their old names back.
+pub fn rename_all(renames: &[(PathBuf, PathBuf)]) -> Result<()> {
+    let temporary: Vec<PathBuf> = renames
+        .iter()
+        .enumerate()
+        .map(|(index, (from, _))| temporary_path(from, index))
+        .collect();
+
+    for (index, ((from, _), temp)) in renames.iter().zip(&temporary).enumerate() {
+        if let Err(err) = fs::rename(from, temp) {
+            roll_back(renames, &temporary, index, 0);
+            return Err(err).with_context(|| format!("Failed to rename {}", from.display()));
+        }
+    }
+    for (index, ((_, to), temp)) in renames.iter().zip(&temporary).enumerate() {
+        let result = if to.symlink_metadata().is_ok() {
+            Err(anyhow::anyhow!("{} already exists", to.display()))
+        } else {
+            fs::rename(temp, to).with_context(|| format!("Failed to rename to {}", to.display()))
+        };
+        if let Err(err) = result {
+            roll_back(renames, &temporary, renames.len(), index);
+            return Err(err);
+        }
+    }
+    Ok(())
+}
+
+/// Undoes a partially applied [`rename_all`]: the first `finished` entries
+/// already have their new names and the first `moved` sit at temporary ones.
+fn roll_back(renames: &[(PathBuf, PathBuf)], temporary: &[PathBuf], moved: usize, finished: usize) {
+    for ((_, to), temp) in renames.iter().zip(temporary).take(finished) {
+        if let Err(err) = fs::rename(to, temp) {
+            log::error!("Failed to roll back rename of {}: {err}", to.display());
+        }
+    }
+    for ((from, _), temp) in renames.iter().zip(temporary).take(moved) {
+        if let Err(err) = fs::rename(temp, from) {
+            log::error!("Failed to roll back rename of {}: {err}", from.display());
+        }
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::literal_string_with_formatting_args)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::{
+        CaseChange, RenamePattern, RenameProblem, RenameSource, planned_renames, preview,
+        rename_all,
+    };
+    use crate::app::SearchTermType;
+    use crate::app::test_support::unique_test_dir;
+    use crate::data::time::TimestampSeconds;
+
+    fn source(path: PathBuf) -> RenameSource {
+        RenameSource {
+            is_dir: path.is_dir(),
+            path,
+            // 2024-03-05 06:07:08 UTC
+            modified_at: TimestampSeconds::from(
+                std::time::UNIX_EPOCH + std::time::Duration::from_secs(1_709_618_828),
+            ),
+        }
+    }
+
+    #[test]
+    fn template_tokens_counter_and_case() {
+        let dir = unique_test_dir("rename_tokens");
+        let sources = [
+            source(dir.join("holiday photo.JPG")),
+            source(dir.join("other {n}.png")),
+        ];
+        let pattern = RenamePattern {
+            find: "photo".to_string(),
+            replace: "pic".to_string(),
+            case: CaseChange::Title,
+            template: "{date}_{n}_{name}{ext}".to_string(),
+            counter_start: 9,
+            counter_padding: 3,
+            ..RenamePattern::default()
+        };
+        let names: Vec<PathBuf> = preview(&sources, &pattern)
+            .expect("valid pattern")
+            .into_iter()
+            .map(|p| p.to)
+            .collect();
+        assert_eq!(
+            names,
+            vec![
+                dir.join("2024-03-05_009_Holiday Pic.JPG"),
+                dir.join("2024-03-05_010_Other {n}.png")
+            ]
+        );
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn regex_replacement_expands_groups() {
+        let dir = unique_test_dir("rename_regex");
+        let sources = [source(dir.join("IMG_1234.jpg"))];
+        let pattern = RenamePattern {
+            find: r"img_(\d+)".to_string(),
+            replace: "photo-$1".to_string(),
+            term_type: SearchTermType::Regex,
+            case_sensitive: false,
+            ..RenamePattern::default()
+        };
+        let previews = preview(&sources, &pattern).expect("valid pattern");
+        assert_eq!(previews[0].to, dir.join("photo-1234.jpg"));
+
+        let glob = RenamePattern {
+            find: "?MG".to_string(),
+            replace: "$pic".to_string(),
+            term_type: SearchTermType::Glob,
+            ..RenamePattern::default()
+        };
+        let previews = preview(&sources, &glob).expect("valid pattern");
+        assert_eq!(previews[0].to, dir.join("$pic_1234.jpg"));
+
+        let invalid = RenamePattern {
+            find: "(".to_string(),
+            term_type: SearchTermType::Regex,
+            ..RenamePattern::default()
+        };
+        assert!(preview(&sources, &invalid).is_err());
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn preview_flags_duplicates_existing_and_invalid_names() {
+        let dir = unique_test_dir("rename_problems");
+        for name in ["a.txt", "b.txt", "taken.txt"] {
+            std::fs::write(dir.join(name), name).expect("write file");
+        }
+        let sources = [source(dir.join("a.txt")), source(dir.join("b.txt"))];
+        let same = RenamePattern {
+            template: "same{ext}".to_string(),
+            ..RenamePattern::default()
+        };
+        let previews = preview(&sources, &same).expect("valid pattern");
+        assert!(
+            previews
+                .iter()
+                .all(|p| p.problem == Some(RenameProblem::Duplicate))
+        );
+        assert_eq!(planned_renames(&previews), None);
+
+        let taken = RenamePattern {
+            find: "a".to_string(),
+            replace: "taken".to_string(),
+            ..RenamePattern::default()
+        };
+        let previews = preview(&sources, &taken).expect("valid pattern");
+        assert_eq!(previews[0].problem, Some(RenameProblem::Exists));
+        assert_eq!(previews[1].problem, None);
+
+        let slash = RenamePattern {
+            template: "{name}/x".to_string(),
+            ..RenamePattern::default()
+        };
+        let previews = preview(&sources, &slash).expect("valid pattern");
+        assert_eq!(
+            previews[0].problem,
+            Some(RenameProblem::InvalidCharacter('/'))
+        );
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn swapping_names_is_applied_as_one_batch() {
+        let dir = unique_test_dir("rename_swap");
+        std::fs::write(dir.join("a.txt"), "a").expect("write file");
+        std::fs::write(dir.join("b.txt"), "b").expect("write file");
+        let sources = [source(dir.join("a.txt")), source(dir.join("b.txt"))];
+        let swap = RenamePattern {
+            template: "{n}{ext}".to_string(),
+            ..RenamePattern::default()
+        };
+        let previews = preview(&sources, &swap).expect("valid pattern");
+        let renames = planned_renames(&previews).expect("no problems");
+        rename_all(&renames).expect("renamed");
+        assert_eq!(
+            std::fs::read_to_string(dir.join("1.txt")).expect("read"),
+            "a"
+        );
+        assert_eq!(
+            std::fs::read_to_string(dir.join("2.txt")).expect("read"),
+            "b"
+        );
+
+        let swapped = vec![
+            (dir.join("1.txt"), dir.join("2.txt")),
+            (dir.join("2.txt"), dir.join("1.txt")),
+        ];
+        rename_all(&swapped).expect("swapped");
+        assert_eq!(
+            std::fs::read_to_string(dir.join("1.txt")).expect("read"),
+            "b"
+        );
+
+        let failing = vec![
+            (dir.join("1.txt"), dir.join("3.txt")),
+            (dir.join("missing.txt"), dir.join("4.txt")),
+        ];
+        assert!(rename_all(&failing).is_err());
+        assert!(dir.join("1.txt").exists());
+        assert!(!dir.join("3.txt").exists());
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
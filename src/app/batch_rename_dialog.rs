@@ -0,0 +1,243 @@
+use std::path::PathBuf;
+
+use egui::{Context, Id, RichText, Ui};
+
+use super::batch_rename::{
+    self, CaseChange, RenamePattern, RenamePreview, RenameSource, TEMPLATE_TOKENS,
+};
+use super::commands::{ActionToPerform, ModalWindow};
+use super::journal::{self, JournalOperation};
+use super::{App, SearchTermType};
+use crate::data::files::DirEntry;
+use crate::toast;
+
+#[derive(Debug, Clone, Default)]
+struct BatchRenameState {
+    sources: Vec<RenameSource>,
+    pattern: RenamePattern,
+    /// Pattern the `previews` were computed for.
+    previewed: Option<RenamePattern>,
+    previews: Vec<RenamePreview>,
+    error: Option<String>,
+}
+
+impl BatchRenameState {
+    fn update_previews(&mut self) {
+        if self.previewed.as_ref() == Some(&self.pattern) {
+            return;
+        }
+        match batch_rename::preview(&self.sources, &self.pattern) {
+            Ok(previews) => {
+                self.previews = previews;
+                self.error = None;
+            }
+            Err(err) => {
+                self.previews.clear();
+                self.error = Some(err.to_string());
+            }
+        }
+        self.previewed = Some(self.pattern.clone());
+    }
+}
+
+fn state_id() -> Id {
+    Id::new(ModalWindow::BatchRename)
+}
+
+/// Opens the batch rename dialog for `paths`.
+pub fn open(ctx: &Context, paths: &[PathBuf]) {
+    let sources = paths
+        .iter()
+        .filter_map(|path| DirEntry::from_path(path))
+        .map(|entry| RenameSource {
+            path: entry.get_path(),
+            is_dir: !entry.is_file(),
+            modified_at: entry.meta.modified_at,
+        })
+        .collect();
+    let state = BatchRenameState {
+        sources,
+        ..Default::default()
+    };
+    ctx.data_mut(|d| d.insert_temp(state_id(), state));
+    ActionToPerform::ToggleModalWindow(ModalWindow::BatchRename).schedule();
+}
+
+impl App {
+    /// Renames every selected entry using one pattern, with a live preview.
+    pub(crate) fn batch_rename_modal(&mut self, ctx: &Context) {
+        #[cfg(feature = "profiling")]
+        puffin::profile_scope!("lwa_fm::batch_rename_modal");
+        let Some(mut state) = ctx.data(|d| d.get_temp::<BatchRenameState>(state_id())) else {
+            self.display_modal = None;
+            return;
+        };
+        let mut apply = None;
+        let modal = egui::Modal::new(state_id()).show(ctx, |ui| {
+            ui.set_width(620.0);
+            ui.heading(format!("Rename {} items", state.sources.len()));
+            ui.separator();
+            pattern_ui(ui, &mut state.pattern);
+            state.update_previews();
+            ui.separator();
+            if let Some(error) = &state.error {
+                ui.colored_label(ui.visuals().error_fg_color, error);
+            } else {
+                previews_ui(ui, &state.previews);
+                if state.pattern.uses_modification_time() {
+                    ui.weak("Dates and times are taken in UTC");
+                }
+            }
+            ui.separator();
+            let renames = batch_rename::planned_renames(&state.previews)
+                .filter(|renames| !renames.is_empty() && state.error.is_none());
+            ui.horizontal(|ui| {
+                let label = renames.as_ref().map_or_else(
+                    || "Rename".to_string(),
+                    |renames| format!("Rename {}", renames.len()),
+                );
+                if ui
+                    .add_enabled(renames.is_some(), egui::Button::new(label))
+                    .clicked()
+                {
+                    apply = renames;
+                }
+                if ui.button("Cancel").clicked() {
+                    ui.close();
+                }
+            });
+        });
+        ctx.data_mut(|d| d.insert_temp(state_id(), state));
+
+        if let Some(renames) = apply {
+            match batch_rename::rename_all(&renames) {
+                Ok(()) => {
+                    let operation = JournalOperation::BatchRename(renames);
+                    self.refresh_changed_dirs(operation.affected_dirs());
+                    journal::record(&operation);
+                }
+                Err(err) => {
+                    log::error!("Batch rename failed: {err:#}");
+                    toast!(Error, "Rename failed: {err}");
+                }
+            }
+            self.close_batch_rename(ctx);
+        } else if modal.should_close() {
+            self.close_batch_rename(ctx);
+        }
+    }
+
+    fn close_batch_rename(&mut self, ctx: &Context) {
+        ctx.data_mut(|d| d.remove_temp::<BatchRenameState>(state_id()));
+        self.display_modal = None;
+    }
+}
+
+fn pattern_ui(ui: &mut Ui, pattern: &mut RenamePattern) {
+    egui::Grid::new("batch_rename_pattern")
+        .num_columns(2)
+        .spacing([12.0, 6.0])
+        .show(ui, |ui| {
+            ui.label("Find");
+            ui.horizontal(|ui| {
+                ui.text_edit_singleline(&mut pattern.find);
+                egui::ComboBox::from_id_salt("batch_rename_term_type")
+                    .selected_text(match pattern.term_type {
+                        SearchTermType::Plain
+                        | SearchTermType::Metadata
+                        | SearchTermType::Fuzzy => "Plain",
+                        SearchTermType::Glob => "Glob",
+                        SearchTermType::Regex => "Regex",
+                    })
+                    .show_ui(ui, |ui| {
+                        ui.selectable_value(&mut pattern.term_type, SearchTermType::Plain, "Plain");
+                        ui.selectable_value(&mut pattern.term_type, SearchTermType::Glob, "Glob");
+                        ui.selectable_value(&mut pattern.term_type, SearchTermType::Regex, "Regex");
+                    });
+                ui.checkbox(&mut pattern.case_sensitive, "Case sensitive");
+            });
+            ui.end_row();
+
+            ui.label("Replace");
+            ui.text_edit_singleline(&mut pattern.replace)
+                .on_hover_text("Regex mode supports groups like $1");
+            ui.end_row();
+
+            ui.label("Name");
+            ui.text_edit_singleline(&mut pattern.template)
+                .on_hover_ui(|ui| {
+                    egui::Grid::new("batch_rename_tokens").show(ui, |ui| {
+                        for (token, description) in TEMPLATE_TOKENS {
+                            ui.monospace(*token);
+                            ui.label(*description);
+                            ui.end_row();
+                        }
+                    });
+                });
+            ui.end_row();
+
+            ui.label("Counter");
+            ui.horizontal(|ui| {
+                ui.add(egui::DragValue::new(&mut pattern.counter_start).prefix("start "));
+                ui.add(
+                    egui::DragValue::new(&mut pattern.counter_step)
+                        .range(1..=1000)
+                        .prefix("step "),
+                );
+                ui.add(
+                    egui::DragValue::new(&mut pattern.counter_padding)
+                        .range(0..=10)
+                        .prefix("digits "),
+                );
+            });
+            ui.end_row();
+
+            ui.label("Case");
+            egui::ComboBox::from_id_salt("batch_rename_case")
+                .selected_text(pattern.case.to_string())
+                .show_ui(ui, |ui| {
+                    for case in CaseChange::ALL {
+                        ui.selectable_value(&mut pattern.case, case, case.to_string());
+                    }
+                });
+            ui.end_row();
+        });
+}
+
+fn previews_ui(ui: &mut Ui, previews: &[RenamePreview]) {
+    let file_name = |path: &PathBuf| {
+        path.file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default()
+    };
+    egui::ScrollArea::vertical()
+        .max_height(320.0)
+        .auto_shrink([false, true])
+        .show(ui, |ui| {
+            egui::Grid::new("batch_rename_preview")
+                .num_columns(3)
+                .striped(true)
+                .show(ui, |ui| {
+                    ui.strong("Current name");
+                    ui.strong("New name");
+                    ui.strong("");
+                    ui.end_row();
+                    for preview in previews {
+                        ui.label(file_name(&preview.from));
+                        let new_name = RichText::new(file_name(&preview.to));
+                        if let Some(problem) = preview.problem {
+                            let color = ui.visuals().error_fg_color;
+                            ui.label(new_name.color(color));
+                            ui.colored_label(color, problem.to_string());
+                        } else if preview.is_unchanged() {
+                            ui.label(new_name.weak());
+                            ui.weak("Unchanged");
+                        } else {
+                            ui.label(new_name);
+                            ui.label("");
+                        }
+                        ui.end_row();
+                    }
+                });
+        });
+}
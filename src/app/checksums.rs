@@ -0,0 +1,685 @@
+//! File checksums: computing them, writing and verifying `SHA256SUMS` style
+//! manifests, and the optional checksum column of the file lists.
+//!
+//! Checksums shown in the column are kept in memory and in a sled tree,
+//! together with the size, modification time and inode of the file they were
+//! taken from, so an edited or replaced file is hashed again.
+
+use std::{
+    collections::HashMap,
+    fmt::Display,
+    fs::{self, File},
+    io::{self, Read},
+    path::{Path, PathBuf},
+    sync::{
+        LazyLock, Mutex,
+        atomic::{AtomicBool, Ordering},
+    },
+    time::UNIX_EPOCH,
+};
+
+use anyhow::{Context as _, Result, bail};
+use bincode::{Decode, Encode, config};
+use egui::Context;
+use md5::Digest as _;
+use rayon::ThreadPoolBuilder;
+use serde::{Deserialize, Serialize};
+
+use super::database::{SLED_DIRS, cache_key};
+
+const TREE_NAME: &[u8] = b"checksums_v2";
+const CHUNK_SIZE: usize = 256 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+pub enum ChecksumKind {
+    Md5,
+    Sha1,
+    #[default]
+    Sha256,
+    Blake3,
+}
+
+impl ChecksumKind {
+    pub const ALL: [Self; 4] = [Self::Md5, Self::Sha1, Self::Sha256, Self::Blake3];
+
+    /// Name of the manifest file `*sum` tools write for this kind.
+    pub const fn manifest_name(self) -> &'static str {
+        match self {
+            Self::Md5 => "MD5SUMS",
+            Self::Sha1 => "SHA1SUMS",
+            Self::Sha256 => "SHA256SUMS",
+            Self::Blake3 => "B3SUMS",
+        }
+    }
+
+    const fn extension(self) -> &'static str {
+        match self {
+            Self::Md5 => "md5",
+            Self::Sha1 => "sha1",
+            Self::Sha256 => "sha256",
+            Self::Blake3 => "b3",
+        }
+    }
+
+    const fn hex_len(self) -> usize {
+        match self {
+            Self::Md5 => 32,
+            Self::Sha1 => 40,
+            Self::Sha256 | Self::Blake3 => 64,
+        }
+    }
+
+    /// The kind of manifest `path` is, judging by its name.
+    pub fn of_manifest(path: &Path) -> Option<Self> {
+        let name = path.file_name()?.to_string_lossy();
+        let extension = path
+            .extension()
+            .map(|extension| extension.to_string_lossy().to_ascii_lowercase());
+        Self::ALL.into_iter().find(|kind| {
+            name.eq_ignore_ascii_case(kind.manifest_name())
+                || extension.as_deref() == Some(kind.extension())
+        })
+    }
+
+    fn hasher(self) -> Hasher {
+        match self {
+            Self::Md5 => Hasher::Md5(md5::Md5::new()),
+            Self::Sha1 => Hasher::Sha1(sha1::Sha1::new()),
+            Self::Sha256 => Hasher::Sha256(sha2::Sha256::new()),
+            Self::Blake3 => Hasher::Blake3(Box::new(blake3::Hasher::new())),
+        }
+    }
+}
+
+impl Display for ChecksumKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Md5 => write!(f, "MD5"),
+            Self::Sha1 => write!(f, "SHA-1"),
+            Self::Sha256 => write!(f, "SHA-256"),
+            Self::Blake3 => write!(f, "BLAKE3"),
+        }
+    }
+}
+
+enum Hasher {
+    Md5(md5::Md5),
+    Sha1(sha1::Sha1),
+    Sha256(sha2::Sha256),
+    Blake3(Box<blake3::Hasher>),
+}
+
+impl Hasher {
+    fn update(&mut self, data: &[u8]) {
+        match self {
+            Self::Md5(hasher) => hasher.update(data),
+            Self::Sha1(hasher) => hasher.update(data),
+            Self::Sha256(hasher) => hasher.update(data),
+            Self::Blake3(hasher) => {
+                hasher.update(data);
+            }
+        }
+    }
+
+    fn finish(self) -> String {
+        match self {
+            Self::Md5(hasher) => format!("{:x}", hasher.finalize()),
+            Self::Sha1(hasher) => format!("{:x}", hasher.finalize()),
+            Self::Sha256(hasher) => format!("{:x}", hasher.finalize()),
+            Self::Blake3(hasher) => hasher.finalize().to_hex().to_string(),
+        }
+    }
+}
+
+/// Hex checksums of `path`, one per kind in `kinds`, reading the file once.
+pub fn compute(
+    path: &Path,
+    kinds: &[ChecksumKind],
+    cancel: &AtomicBool,
+) -> io::Result<Vec<String>> {
+    let mut file = File::open(path)?;
+    let mut hashers = kinds.iter().map(|kind| kind.hasher()).collect::<Vec<_>>();
+    let mut buffer = vec![0; CHUNK_SIZE];
+    loop {
+        if cancel.load(Ordering::Relaxed) {
+            return Err(io::ErrorKind::Interrupted.into());
+        }
+        let read = match file.read(&mut buffer) {
+            Ok(0) => break,
+            Ok(read) => read,
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+            Err(err) => return Err(err),
+        };
+        for hasher in &mut hashers {
+            hasher.update(&buffer[..read]);
+        }
+    }
+    Ok(hashers.into_iter().map(Hasher::finish).collect())
+}
+
+/// Checksums of one of the files asked for.
+#[derive(Debug, Clone)]
+pub struct FileChecksums {
+    pub path: PathBuf,
+    pub size: u64,
+    /// One per kind asked for, in the same order, or why the file couldn't
+    /// be read.
+    pub digests: Result<Vec<String>, String>,
+}
+
+impl FileChecksums {
+    pub fn digest(&self, kinds: &[ChecksumKind], kind: ChecksumKind) -> Option<&str> {
+        let index = kinds.iter().position(|k| *k == kind)?;
+        self.digests.as_ref().ok()?.get(index).map(String::as_str)
+    }
+}
+
+/// Computes `kinds` for every file in `paths`, remembering them for the
+/// checksum column. Returns `None` when cancelled.
+pub fn compute_all(
+    paths: &[PathBuf],
+    kinds: &[ChecksumKind],
+    cancel: &AtomicBool,
+    progress: &dyn Fn(String),
+) -> Option<Vec<FileChecksums>> {
+    let mut results = Vec::with_capacity(paths.len());
+    for (index, path) in paths.iter().enumerate() {
+        progress(format!("Hashing {} of {}…", index + 1, paths.len()));
+        let meta = fs::metadata(path);
+        let stamp = meta.as_ref().ok().and_then(Stamp::of);
+        let digests = compute(path, kinds, cancel);
+        if cancel.load(Ordering::Relaxed) {
+            return None;
+        }
+        if let (Ok(digests), Some(stamp)) = (&digests, stamp) {
+            for (kind, hex) in kinds.iter().zip(digests) {
+                remember(path, *kind, stamp, hex);
+            }
+        }
+        results.push(FileChecksums {
+            path: path.clone(),
+            size: meta.map_or(0, |meta| meta.len()),
+            digests: digests.map_err(|err| err.to_string()),
+        });
+    }
+    Some(results)
+}
+
+/// Name under which `path` is listed in a manifest kept in `dir`.
+fn listed_name(dir: &Path, path: &Path) -> String {
+    path.strip_prefix(dir).map_or_else(
+        |_| path.display().to_string(),
+        |relative| {
+            relative
+                .components()
+                .map(|part| part.as_os_str().to_string_lossy())
+                .collect::<Vec<_>>()
+                .join("/")
+        },
+    )
+}
+
+/// Manifest text for the `kind` checksums of `files`, as `sha256sum` and
+/// friends write it, with paths relative to `dir` where possible.
+pub fn format_manifest(
+    files: &[FileChecksums],
+    kinds: &[ChecksumKind],
+    kind: ChecksumKind,
+    dir: &Path,
+) -> String {
+    files
+        .iter()
+        .filter_map(|file| {
+            let digest = file.digest(kinds, kind)?;
+            let name = listed_name(dir, &file.path);
+            // GNU tools flag lines whose name holds a backslash or newline
+            // with a leading backslash and escape those characters.
+            Some(if name.contains(['\\', '\n']) {
+                let name = name.replace('\\', "\\\\").replace('\n', "\\n");
+                format!("\\{digest}  {name}\n")
+            } else {
+                format!("{digest}  {name}\n")
+            })
+        })
+        .collect()
+}
+
+/// Undoes the escaping of a manifest line flagged with a leading backslash.
+fn unescape_name(name: &str) -> String {
+    let mut unescaped = String::with_capacity(name.len());
+    let mut chars = name.chars();
+    while let Some(c) = chars.next() {
+        match (c, chars.clone().next()) {
+            ('\\', Some('n')) => {
+                chars.next();
+                unescaped.push('\n');
+            }
+            ('\\', Some('\\')) => {
+                chars.next();
+                unescaped.push('\\');
+            }
+            _ => unescaped.push(c),
+        }
+    }
+    unescaped
+}
+
+/// `(checksum, name)` pairs of a manifest, skipping blank and comment lines.
+pub fn parse_manifest(text: &str) -> Result<Vec<(String, String)>> {
+    text.lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty() && !line.starts_with('#'))
+        .map(|(index, line)| {
+            // GNU tools prefix lines whose name had to be escaped with a backslash.
+            let (line, escaped) = line
+                .strip_prefix('\\')
+                .map_or((line, false), |line| (line, true));
+            let Some((digest, name)) = line.split_once(' ') else {
+                bail!("line {} isn't a checksum line", index + 1);
+            };
+            // The second separator is a space in text mode and `*` in binary mode.
+            let name = name
+                .strip_prefix(' ')
+                .or_else(|| name.strip_prefix('*'))
+                .unwrap_or(name);
+            if digest.is_empty() || !digest.chars().all(|c| c.is_ascii_hexdigit()) {
+                bail!("line {} has no checksum", index + 1);
+            }
+            let name = if escaped {
+                unescape_name(name)
+            } else {
+                name.to_string()
+            };
+            Ok((digest.to_ascii_lowercase(), name))
+        })
+        .collect()
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Verdict {
+    Pass,
+    Fail,
+    Missing,
+    Unreadable(String),
+}
+
+impl Display for Verdict {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Pass => write!(f, "OK"),
+            Self::Fail => write!(f, "FAILED"),
+            Self::Missing => write!(f, "Missing"),
+            Self::Unreadable(err) => write!(f, "Unreadable: {err}"),
+        }
+    }
+}
+
+/// A file listed in a manifest and whether it still matches.
+#[derive(Debug, Clone)]
+pub struct Verified {
+    pub name: String,
+    pub path: PathBuf,
+    pub verdict: Verdict,
+}
+
+/// Outcome of checking every file listed in a manifest.
+#[derive(Debug, Clone)]
+pub struct Verification {
+    pub kind: ChecksumKind,
+    pub entries: Vec<Verified>,
+}
+
+impl Verification {
+    pub fn passed(&self) -> usize {
+        self.entries
+            .iter()
+            .filter(|entry| entry.verdict == Verdict::Pass)
+            .count()
+    }
+}
+
+/// Checks the files listed in `manifest` against their checksums. The kind
+/// comes from the manifest's name, or from the checksum length when the name
+/// doesn't tell and only one kind has that length.
+pub fn verify(
+    manifest: &Path,
+    cancel: &AtomicBool,
+    progress: &dyn Fn(String),
+) -> Result<Verification> {
+    let text = fs::read_to_string(manifest)
+        .with_context(|| format!("failed to read {}", manifest.display()))?;
+    let listed = parse_manifest(&text)?;
+    let kind = match ChecksumKind::of_manifest(manifest) {
+        Some(kind) => kind,
+        None => kind_of_digests(&listed)?,
+    };
+    let dir = manifest.parent().unwrap_or_else(|| Path::new(""));
+    let total = listed.len();
+    let mut entries = Vec::with_capacity(total);
+    for (index, (expected, name)) in listed.into_iter().enumerate() {
+        if cancel.load(Ordering::Relaxed) {
+            bail!("Cancelled");
+        }
+        progress(format!("Checking {} of {total}…", index + 1));
+        let path = dir.join(&name);
+        let verdict = match compute(&path, &[kind], cancel) {
+            Ok(digests) if digests.first() == Some(&expected) => Verdict::Pass,
+            Ok(_) => Verdict::Fail,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Verdict::Missing,
+            Err(err) => Verdict::Unreadable(err.to_string()),
+        };
+        entries.push(Verified {
+            name,
+            path,
+            verdict,
+        });
+    }
+    Ok(Verification { kind, entries })
+}
+
+/// The only kind whose checksums are as long as the ones `listed`.
+fn kind_of_digests(listed: &[(String, String)]) -> Result<ChecksumKind> {
+    let len = listed.first().map_or(0, |(digest, _)| digest.len());
+    let mut kinds = ChecksumKind::ALL
+        .into_iter()
+        .filter(|kind| kind.hex_len() == len);
+    match (kinds.next(), kinds.next()) {
+        (Some(kind), None) => Ok(kind),
+        (Some(first), Some(second)) => bail!(
+            "can't tell whether the manifest lists {first} or {second} checksums, \
+             name it after the kind (e.g. `{}` or `.{}`)",
+            first.manifest_name(),
+            second.extension()
+        ),
+        _ => bail!("can't tell which checksum the manifest lists"),
+    }
+}
+
+/// Size, modification time and inode of the file a checksum was taken from.
+/// The time is kept in nanoseconds, so a same size rewrite within a second
+/// still counts as a change.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Encode, Decode)]
+struct Stamp {
+    size: u64,
+    modified_nanos: u128,
+    /// Always 0 where there are no inodes.
+    inode: u64,
+}
+
+impl Stamp {
+    fn of(meta: &fs::Metadata) -> Option<Self> {
+        #[cfg(unix)]
+        let inode = std::os::unix::fs::MetadataExt::ino(meta);
+        #[cfg(not(unix))]
+        let inode = 0;
+        Some(Self {
+            size: meta.len(),
+            modified_nanos: meta
+                .modified()
+                .ok()?
+                .duration_since(UNIX_EPOCH)
+                .ok()?
+                .as_nanos(),
+            inode,
+        })
+    }
+
+    fn read(path: &Path) -> Option<Self> {
+        fs::metadata(path).ok().as_ref().and_then(Self::of)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Encode, Decode)]
+struct Stored {
+    stamp: Stamp,
+    hex: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Hashed {
+    Pending(Stamp),
+    Failed(Stamp),
+    Done(Stored),
+}
+
+impl Hashed {
+    const fn stamp(&self) -> Stamp {
+        match self {
+            Self::Pending(stamp) | Self::Failed(stamp) => *stamp,
+            Self::Done(stored) => stored.stamp,
+        }
+    }
+}
+
+/// A single thread, so the column never competes with itself for the disk.
+static HASH_POOL: LazyLock<rayon::ThreadPool> = LazyLock::new(|| {
+    ThreadPoolBuilder::new()
+        .num_threads(1)
+        .thread_name(|i| format!("lwa_fm_checksums_{i}"))
+        .build()
+        .expect("Failed to create checksum thread pool")
+});
+
+static STORED: LazyLock<Option<sled::Tree>> = LazyLock::new(|| {
+    SLED_DIRS
+        .open_tree(TREE_NAME)
+        .map_err(|err| log::warn!("failed to open checksum tree: {err}"))
+        .ok()
+});
+
+/// Checksums asked for this session.
+static HASHED: LazyLock<Mutex<HashMap<(PathBuf, ChecksumKind), Hashed>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+fn stored_key(path: &Path, kind: ChecksumKind) -> Vec<u8> {
+    let mut key = cache_key(path);
+    key.push(0);
+    key.extend_from_slice(kind.extension().as_bytes());
+    key
+}
+
+fn stored(path: &Path, kind: ChecksumKind) -> Option<Stored> {
+    let data = STORED.as_ref()?.get(stored_key(path, kind)).ok()??;
+    bincode::decode_from_slice(&data, config::standard())
+        .ok()
+        .map(|(stored, _)| stored)
+}
+
+fn remember(path: &Path, kind: ChecksumKind, stamp: Stamp, hex: &str) {
+    let stored = Stored {
+        stamp,
+        hex: hex.to_string(),
+    };
+    if let Some(tree) = STORED.as_ref()
+        && let Ok(data) = bincode::encode_to_vec(&stored, config::standard())
+        && let Err(err) = tree.insert(stored_key(path, kind), data)
+    {
+        log::warn!("failed to store checksum of {}: {err}", path.display());
+    }
+    if let Ok(mut hashed) = HASHED.lock() {
+        hashed.insert((path.to_path_buf(), kind), Hashed::Done(stored));
+    }
+}
+
+/// The `kind` checksum of the file at `path` as it is now, hashing it in the
+/// background and repainting `ctx` once done when it isn't known.
+pub fn get(path: &Path, kind: ChecksumKind, ctx: &Context) -> Option<String> {
+    let stamp = Stamp::read(path)?;
+    let key = (path.to_path_buf(), kind);
+    let Ok(mut hashed) = HASHED.lock() else {
+        return None;
+    };
+    if let Some(known) = hashed.get(&key)
+        && known.stamp() == stamp
+    {
+        return match known {
+            Hashed::Done(stored) => Some(stored.hex.clone()),
+            Hashed::Pending(_) | Hashed::Failed(_) => None,
+        };
+    }
+    if let Some(stored) = stored(path, kind)
+        && stored.stamp == stamp
+    {
+        let hex = stored.hex.clone();
+        hashed.insert(key, Hashed::Done(stored));
+        return Some(hex);
+    }
+    hashed.insert(key.clone(), Hashed::Pending(stamp));
+    drop(hashed);
+
+    let ctx = ctx.clone();
+    HASH_POOL.spawn(move || {
+        let (path, kind) = key;
+        let result = compute(&path, &[kind], &AtomicBool::new(false));
+        let still_wanted = Stamp::read(&path) == Some(stamp)
+            && HASHED.lock().is_ok_and(|hashed| {
+                hashed.get(&(path.clone(), kind)) == Some(&Hashed::Pending(stamp))
+            });
+        // The file changed while being hashed, the result may be stale.
+        if !still_wanted {
+            return;
+        }
+        match result.ok().and_then(|digests| digests.into_iter().next()) {
+            Some(hex) => remember(&path, kind, stamp, &hex),
+            None => {
+                if let Ok(mut hashed) = HASHED.lock() {
+                    hashed.insert((path, kind), Hashed::Failed(stamp));
+                }
+            }
+        }
+        ctx.request_repaint();
+    });
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::test_support::unique_test_dir;
+
+    #[test]
+    fn computes_known_digests_in_one_pass() {
+        let dir = unique_test_dir("checksums");
+        let file = dir.join("abc.txt");
+        fs::write(&file, "abc").expect("write file");
+
+        let kinds = [ChecksumKind::Md5, ChecksumKind::Sha1, ChecksumKind::Sha256];
+        let digests = compute(&file, &kinds, &AtomicBool::new(false)).expect("hash file");
+        assert_eq!(
+            digests,
+            [
+                "900150983cd24fb0d6963f7d28e17f72",
+                "a9993e364706816aba3e25717850c26c9cd0d89d",
+                "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad",
+            ]
+        );
+
+        let files = compute_all(
+            std::slice::from_ref(&file),
+            &kinds,
+            &AtomicBool::new(false),
+            &|_| {},
+        )
+        .expect("not cancelled");
+        let manifest = dir.join("SHA256SUMS");
+        fs::write(
+            &manifest,
+            format_manifest(&files, &kinds, ChecksumKind::Sha256, &dir),
+        )
+        .expect("write manifest");
+        let verification = verify(&manifest, &AtomicBool::new(false), &|_| {}).expect("verify");
+        assert_eq!(verification.kind, ChecksumKind::Sha256);
+        assert_eq!(verification.passed(), 1);
+
+        fs::write(&file, "abd").expect("change file");
+        fs::write(
+            &manifest,
+            format!(
+                "{}\n{}  gone.txt\n",
+                fs::read_to_string(&manifest).expect("read"),
+                digests[2]
+            ),
+        )
+        .expect("append to manifest");
+        let verification = verify(&manifest, &AtomicBool::new(false), &|_| {}).expect("verify");
+        let verdicts = verification
+            .entries
+            .iter()
+            .map(|entry| entry.verdict.clone())
+            .collect::<Vec<_>>();
+        assert_eq!(verdicts, [Verdict::Fail, Verdict::Missing]);
+
+        fs::remove_dir_all(&dir).expect("cleanup");
+    }
+
+    #[test]
+    fn parses_text_and_binary_manifest_lines() {
+        let listed = parse_manifest(
+            "# comment\nABCDEF  a file.txt\n0123 *bin/b.dat\n\n\\ff  new\\nline\\\\x\n",
+        )
+        .expect("parse");
+        assert_eq!(
+            listed,
+            [
+                ("abcdef".to_string(), "a file.txt".to_string()),
+                ("0123".to_string(), "bin/b.dat".to_string()),
+                ("ff".to_string(), "new\nline\\x".to_string()),
+            ]
+        );
+        assert!(parse_manifest("not a checksum\n").is_err());
+        assert_eq!(
+            ChecksumKind::of_manifest(Path::new("/x/release.sha256")),
+            Some(ChecksumKind::Sha256)
+        );
+        assert_eq!(
+            ChecksumKind::of_manifest(Path::new("/x/md5sums")),
+            Some(ChecksumKind::Md5)
+        );
+        assert_eq!(ChecksumKind::of_manifest(Path::new("/x/notes.txt")), None);
+    }
+    #[test]
+    fn escaped_names_round_trip_and_ambiguous_lengths_are_refused() {
+        let dir = Path::new("/x");
+        let files = ["a\\n", "b\nc", "plain"].map(|name| FileChecksums {
+            path: dir.join(name),
+            size: 0,
+            digests: Ok(vec!["ab".to_string()]),
+        });
+        let manifest = format_manifest(&files, &[ChecksumKind::Md5], ChecksumKind::Md5, dir);
+        assert_eq!(manifest, "\\ab  a\\\\n\n\\ab  b\\nc\nab  plain\n");
+        let names = parse_manifest(&manifest)
+            .expect("parse")
+            .into_iter()
+            .map(|(_, name)| name)
+            .collect::<Vec<_>>();
+        assert_eq!(names, ["a\\n", "b\nc", "plain"]);
+
+        let listed = |len| vec![("0".repeat(len), "file".to_string())];
+        assert_eq!(kind_of_digests(&listed(40)).ok(), Some(ChecksumKind::Sha1));
+        assert!(kind_of_digests(&listed(64)).is_err());
+    }
+
+    #[test]
+    fn stamp_tells_apart_rewrites_within_a_second() {
+        let dir = unique_test_dir("checksums_stamp");
+        let path = dir.join("file.txt");
+        fs::write(&path, "abc").expect("write file");
+        let set_modified = |nanos| {
+            File::options()
+                .write(true)
+                .open(&path)
+                .and_then(|file| {
+                    file.set_modified(UNIX_EPOCH + std::time::Duration::new(1_000_000, nanos))
+                })
+                .expect("set modification time");
+        };
+        set_modified(1);
+        let first = Stamp::read(&path).expect("stamp");
+        set_modified(2);
+        let second = Stamp::read(&path).expect("stamp");
+        assert_eq!(first.size, second.size);
+        assert_ne!(first, second);
+
+        fs::remove_dir_all(&dir).expect("cleanup");
+    }
+}
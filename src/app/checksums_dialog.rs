@@ -0,0 +1,350 @@
+use std::{
+    path::{Path, PathBuf},
+    sync::{
+        Arc, Mutex, OnceLock,
+        atomic::{AtomicBool, Ordering},
+    },
+};
+
+use egui::{Color32, Context, Id, RichText, Ui};
+
+use super::App;
+use super::checksums::{self, ChecksumKind, FileChecksums, Verdict, Verification};
+use super::commands::{ActionToPerform, ModalWindow};
+use super::file_ops;
+use crate::helper::format_bytes_simple;
+use crate::toast;
+
+const ROW_HEIGHT: f32 = 20.0;
+
+#[derive(Debug, Clone)]
+enum Job {
+    /// Checksums of the selected files, all kinds at once.
+    Compute {
+        /// Folder a manifest is written to.
+        dir: PathBuf,
+        result: Arc<OnceLock<Option<Vec<FileChecksums>>>>,
+        /// Which kind a written manifest lists.
+        manifest_kind: ChecksumKind,
+    },
+    /// Files listed in a manifest, checked against it.
+    Verify {
+        manifest: PathBuf,
+        result: Arc<OnceLock<Result<Verification, String>>>,
+    },
+}
+
+#[derive(Debug, Clone)]
+struct ChecksumsState {
+    job: Job,
+    /// Latest progress message.
+    status: Arc<Mutex<String>>,
+    cancel: Arc<AtomicBool>,
+}
+
+fn state_id() -> Id {
+    Id::new(ModalWindow::Checksums)
+}
+
+/// Runs `work` in the background with a progress callback, then shows the
+/// modal with `job`.
+fn start(
+    ctx: &Context,
+    job: Job,
+    work: impl FnOnce(&AtomicBool, &dyn Fn(String)) + Send + 'static,
+) {
+    let status = Arc::new(Mutex::new(String::new()));
+    let cancel = Arc::new(AtomicBool::new(false));
+    {
+        let status = Arc::clone(&status);
+        let cancel = Arc::clone(&cancel);
+        let ctx = ctx.clone();
+        file_ops::spawn_task(move || {
+            let progress = |message: String| {
+                if let Ok(mut status) = status.lock() {
+                    *status = message;
+                }
+                ctx.request_repaint();
+            };
+            work(&cancel, &progress);
+            ctx.request_repaint();
+        });
+    }
+    let state = ChecksumsState {
+        job,
+        status,
+        cancel,
+    };
+    ctx.data_mut(|d| d.insert_temp(state_id(), state));
+    ActionToPerform::ToggleModalWindow(ModalWindow::Checksums).schedule();
+}
+
+/// Computes every kind of checksum for the files in `paths` in the
+/// background and shows them once done.
+pub fn open(ctx: &Context, paths: Vec<PathBuf>) {
+    let dir = paths
+        .first()
+        .and_then(|path| path.parent())
+        .map(Path::to_path_buf)
+        .unwrap_or_default();
+    let result = Arc::new(OnceLock::new());
+    let job = Job::Compute {
+        dir,
+        result: Arc::clone(&result),
+        manifest_kind: ChecksumKind::default(),
+    };
+    start(ctx, job, move |cancel, progress| {
+        let _ = result.set(checksums::compute_all(
+            &paths,
+            &ChecksumKind::ALL,
+            cancel,
+            progress,
+        ));
+    });
+}
+
+/// Checks every file listed in `manifest` in the background and shows which
+/// ones still match.
+pub fn open_verify(ctx: &Context, manifest: PathBuf) {
+    let result = Arc::new(OnceLock::new());
+    let job = Job::Verify {
+        manifest: manifest.clone(),
+        result: Arc::clone(&result),
+    };
+    start(ctx, job, move |cancel, progress| {
+        let verification = checksums::verify(&manifest, cancel, progress);
+        let _ = result.set(verification.map_err(|err| format!("{err:#}")));
+    });
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name().map_or_else(
+        || path.display().to_string(),
+        |name| name.to_string_lossy().to_string(),
+    )
+}
+
+fn copy_to_clipboard(text: String) {
+    let Ok(mut clipboard) = arboard::Clipboard::new() else {
+        toast!(Error, "Failed to read the clipboard.");
+        return;
+    };
+    clipboard.set_text(text).unwrap_or_else(|_| {
+        toast!(Error, "Failed to update the clipboard.");
+    });
+}
+
+impl App {
+    /// Checksums of the selected files, or the result of checking a manifest.
+    pub(crate) fn checksums_modal(&mut self, ctx: &Context) {
+        #[cfg(feature = "profiling")]
+        puffin::profile_scope!("lwa_fm::checksums_modal");
+        let Some(mut state) = ctx.data(|d| d.get_temp::<ChecksumsState>(state_id())) else {
+            self.display_modal = None;
+            return;
+        };
+
+        let mut close = false;
+        let mut written = None;
+        let modal = egui::Modal::new(state_id()).show(ctx, |ui| {
+            ui.set_width(760.0);
+            match &mut state.job {
+                Job::Compute {
+                    dir,
+                    result,
+                    manifest_kind,
+                } => {
+                    ui.heading("Checksums");
+                    ui.weak(dir.display().to_string());
+                    ui.separator();
+                    match result.get() {
+                        Some(Some(files)) => {
+                            files_ui(ui, files);
+                            ui.separator();
+                            written = compute_buttons_ui(ui, files, dir, manifest_kind, &mut close);
+                        }
+                        Some(None) => close = true,
+                        None => progress_ui(ui, &state.status, &mut close),
+                    }
+                }
+                Job::Verify { manifest, result } => {
+                    ui.heading("Verify checksums");
+                    ui.weak(manifest.display().to_string());
+                    ui.separator();
+                    match result.get() {
+                        Some(Ok(verification)) => {
+                            verification_ui(ui, verification);
+                            ui.separator();
+                            if ui.button("Close").clicked() {
+                                close = true;
+                            }
+                        }
+                        Some(Err(err)) => {
+                            ui.colored_label(ui.visuals().error_fg_color, err);
+                            if ui.button("Close").clicked() {
+                                close = true;
+                            }
+                        }
+                        None => progress_ui(ui, &state.status, &mut close),
+                    }
+                }
+            }
+        });
+        if let Some(manifest) = written
+            && let Some(dir) = manifest.parent()
+        {
+            self.refresh_changed_dirs(vec![dir.to_path_buf()]);
+        }
+        if close || modal.should_close() {
+            state.cancel.store(true, Ordering::Relaxed);
+            ctx.data_mut(|d| d.remove::<ChecksumsState>(state_id()));
+            self.display_modal = None;
+        } else {
+            ctx.data_mut(|d| d.insert_temp(state_id(), state));
+        }
+    }
+}
+
+fn progress_ui(ui: &mut Ui, status: &Mutex<String>, close: &mut bool) {
+    ui.horizontal(|ui| {
+        ui.spinner();
+        ui.label(status.lock().map(|s| s.clone()).unwrap_or_default());
+    });
+    if ui.button("Cancel").clicked() {
+        *close = true;
+    }
+}
+
+fn files_ui(ui: &mut Ui, files: &[FileChecksums]) {
+    egui::ScrollArea::vertical()
+        .max_height(420.0)
+        .auto_shrink([false, true])
+        .show(ui, |ui| {
+            for (index, file) in files.iter().enumerate() {
+                let title = format!(
+                    "{} ({})",
+                    file_name(&file.path),
+                    format_bytes_simple(file.size)
+                );
+                egui::CollapsingHeader::new(title)
+                    .id_salt(("checksums_file", index))
+                    .default_open(true)
+                    .show(ui, |ui| match &file.digests {
+                        Ok(digests) => {
+                            egui::Grid::new(("checksums_grid", index))
+                                .num_columns(3)
+                                .show(ui, |ui| {
+                                    for (kind, digest) in ChecksumKind::ALL.iter().zip(digests) {
+                                        ui.label(kind.to_string());
+                                        ui.add(
+                                            egui::Label::new(RichText::new(digest).monospace())
+                                                .selectable(true),
+                                        );
+                                        if ui.small_button("Copy").clicked() {
+                                            copy_to_clipboard(digest.clone());
+                                        }
+                                        ui.end_row();
+                                    }
+                                });
+                        }
+                        Err(err) => {
+                            ui.colored_label(ui.visuals().error_fg_color, err);
+                        }
+                    });
+            }
+        });
+}
+
+/// Shows the copy and manifest buttons, returning the manifest written.
+fn compute_buttons_ui(
+    ui: &mut Ui,
+    files: &[FileChecksums],
+    dir: &Path,
+    manifest_kind: &mut ChecksumKind,
+    close: &mut bool,
+) -> Option<PathBuf> {
+    let mut written = None;
+    ui.horizontal(|ui| {
+        egui::ComboBox::from_id_salt("checksums_manifest_kind")
+            .selected_text(manifest_kind.to_string())
+            .show_ui(ui, |ui| {
+                for kind in ChecksumKind::ALL {
+                    ui.selectable_value(manifest_kind, kind, kind.to_string());
+                }
+            });
+        let manifest = checksums::format_manifest(files, &ChecksumKind::ALL, *manifest_kind, dir);
+        if ui
+            .add_enabled(!manifest.is_empty(), egui::Button::new("Copy"))
+            .on_hover_text("Copy the checksums in manifest format")
+            .clicked()
+        {
+            copy_to_clipboard(manifest.clone());
+        }
+        let path = dir.join(manifest_kind.manifest_name());
+        let exists = path.exists();
+        let label = if exists {
+            format!("Overwrite {}", manifest_kind.manifest_name())
+        } else {
+            format!("Write {}", manifest_kind.manifest_name())
+        };
+        let button = ui
+            .add_enabled(!manifest.is_empty(), egui::Button::new(label))
+            .on_hover_text(path.display().to_string());
+        if button.clicked() {
+            match std::fs::write(&path, manifest) {
+                Ok(()) => {
+                    toast!(Success, "Wrote {}", path.display());
+                    written = Some(path);
+                }
+                Err(err) => {
+                    toast!(Error, "Failed to write {}: {err}", path.display());
+                }
+            }
+        }
+        if ui.button("Close").clicked() {
+            *close = true;
+        }
+    });
+    written
+}
+
+fn verdict_color(ui: &Ui, verdict: &Verdict) -> Color32 {
+    match verdict {
+        Verdict::Pass => Color32::from_rgb(90, 170, 90),
+        Verdict::Fail | Verdict::Unreadable(_) => ui.visuals().error_fg_color,
+        Verdict::Missing => ui.visuals().warn_fg_color,
+    }
+}
+
+fn verification_ui(ui: &mut Ui, verification: &Verification) {
+    let total = verification.entries.len();
+    let passed = verification.passed();
+    let summary = format!(
+        "{passed} of {total} file(s) match their {} checksum",
+        verification.kind
+    );
+    if passed == total {
+        ui.label(summary);
+    } else {
+        ui.colored_label(ui.visuals().error_fg_color, summary);
+    }
+    egui::ScrollArea::vertical()
+        .max_height(420.0)
+        .auto_shrink([false, true])
+        .show_rows(ui, ROW_HEIGHT, total, |ui, range| {
+            for entry in &verification.entries[range] {
+                ui.horizontal(|ui| {
+                    ui.add_sized(
+                        [140.0, ROW_HEIGHT],
+                        egui::Label::new(
+                            RichText::new(entry.verdict.to_string())
+                                .color(verdict_color(ui, &entry.verdict)),
+                        )
+                        .truncate(),
+                    );
+                    ui.add(egui::Label::new(&entry.name).truncate())
+                        .on_hover_text(entry.path.display().to_string());
+                });
+            }
+        });
+}
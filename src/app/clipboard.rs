@@ -0,0 +1,299 @@
+//! Cut/copy/paste of files.
+//!
+//! Copied files are kept in an in-app clipboard (which remembers whether they
+//! were cut) and mirrored to the system clipboard as a URI list, so other file
+//! managers can paste them. Pasting accepts file lists from other apps too.
+
+use std::{
+    path::{Path, PathBuf},
+    sync::{LazyLock, Mutex},
+    time::{Duration, Instant},
+};
+
+use egui::{Context, Event, Key};
+
+use crate::app::dock::{Selected, TabData};
+use crate::app::file_ops::{FileOperation, FileOperationKind, JobReport};
+use crate::helper::{DataHolder, shift_delete_pressed, text_edit_focused};
+use crate::toast;
+
+/// How long a paste triggered by `Event::Paste` suppresses the key release
+/// fallback, see [`handle_shortcuts`].
+const PASTE_DEBOUNCE: Duration = Duration::from_millis(1000);
+
+static FILE_CLIPBOARD: LazyLock<Mutex<FileClipboard>> =
+    LazyLock::new(|| Mutex::new(FileClipboard::default()));
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ClipboardMode {
+    #[default]
+    Copy,
+    Cut,
+}
+
+#[derive(Debug, Default)]
+struct FileClipboard {
+    mode: ClipboardMode,
+    paths: Vec<PathBuf>,
+    last_paste: Option<Instant>,
+}
+
+/// Puts `paths` on both the in-app and the system clipboard.
+pub fn set_files(paths: Vec<PathBuf>, mode: ClipboardMode) {
+    if paths.is_empty() {
+        return;
+    }
+    match arboard::Clipboard::new() {
+        Ok(mut clipboard) => {
+            if clipboard.set().file_list(&paths).is_err() {
+                toast!(Error, "Failed to update the clipboard.");
+            }
+        }
+        Err(_) => {
+            toast!(Error, "Failed to read the clipboard.");
+        }
+    }
+    let count = paths.len();
+    if let Ok(mut state) = FILE_CLIPBOARD.lock() {
+        state.mode = mode;
+        state.paths = paths;
+    }
+    match mode {
+        ClipboardMode::Copy => toast!(Info, "Copied {count} item(s)"),
+        ClipboardMode::Cut => toast!(Info, "Cut {count} item(s)"),
+    }
+}
+
+/// Files to paste and whether they should be moved. The system clipboard wins
+/// over the in-app one, unless it still holds what was cut here.
+fn files_to_paste(fallback_text: Option<&str>) -> Option<(Vec<PathBuf>, ClipboardMode)> {
+    let mut clipboard = arboard::Clipboard::new().ok();
+    let mut system_files = clipboard
+        .as_mut()
+        .and_then(|clipboard| clipboard.get().file_list().ok())
+        .unwrap_or_default();
+    if system_files.is_empty() {
+        let text = fallback_text.map(ToOwned::to_owned).or_else(|| {
+            clipboard
+                .as_mut()
+                .and_then(|clipboard| clipboard.get_text().ok())
+        });
+        system_files = text.as_deref().map(parse_file_list).unwrap_or_default();
+    }
+    system_files.retain(|path| path.exists());
+
+    let state = FILE_CLIPBOARD.lock().ok()?;
+    if system_files.is_empty() || same_files(&system_files, &state.paths) {
+        if state.paths.is_empty() {
+            return None;
+        }
+        // Cut files stay on the clipboard until a move takes them, see
+        // [`move_finished`].
+        return Some((state.paths.clone(), state.mode));
+    }
+    Some((system_files, ClipboardMode::Copy))
+}
+
+fn same_files(a: &[PathBuf], b: &[PathBuf]) -> bool {
+    let canonical = |path: &PathBuf| path.canonicalize().unwrap_or_else(|_| path.clone());
+    a.len() == b.len() && a.iter().map(canonical).eq(b.iter().map(canonical))
+}
+
+/// Parses clipboard text into paths. Understands `text/uri-list` and the
+/// `x-special/gnome-copied-files` layout (an optional `copy`/`cut` first
+/// line). Any other text, a copied path included, isn't a file list. Only
+/// existing paths are returned.
+pub fn parse_file_list(text: &str) -> Vec<PathBuf> {
+    let mut lines = text
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .peekable();
+    lines.next_if(|line| *line == "copy" || *line == "cut");
+    lines
+        .map(file_uri_to_path)
+        .collect::<Option<Vec<_>>>()
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|path| path.is_absolute() && path.exists())
+        .collect()
+}
+
+fn file_uri_to_path(uri: &str) -> Option<PathBuf> {
+    let uri = uri.strip_prefix("file://")?;
+    // Drop an optional `localhost` (or any) authority.
+    let path = &uri[uri.find('/')?..];
+    percent_decode(path).map(|path| PathBuf::from(uri_path_to_native(&path)))
+}
+
+#[cfg(windows)]
+fn uri_path_to_native(path: &str) -> String {
+    // `file:///C:/dir` decodes to `/C:/dir`.
+    path.strip_prefix('/').unwrap_or(path).replace('/', "\\")
+}
+
+#[cfg(not(windows))]
+fn uri_path_to_native(path: &str) -> String {
+    path.to_string()
+}
+
+fn percent_decode(input: &str) -> Option<String> {
+    let bytes = input.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            let hex = input.get(i + 1..i + 3)?;
+            decoded.push(u8::from_str_radix(hex, 16).ok()?);
+            i += 3;
+        } else {
+            decoded.push(bytes[i]);
+            i += 1;
+        }
+    }
+    String::from_utf8(decoded).ok()
+}
+
+/// Takes the items a finished move brought over off the cut files. Once
+/// nothing is left the system clipboard is cleared too, so items that were
+/// skipped, failed or cancelled can still be pasted.
+pub fn move_finished(report: &JobReport) {
+    if !matches!(report.operation.kind, FileOperationKind::Move) || report.transferred.is_empty() {
+        return;
+    }
+    let Ok(mut state) = FILE_CLIPBOARD.lock() else {
+        return;
+    };
+    if state.mode != ClipboardMode::Cut || state.paths.is_empty() {
+        return;
+    }
+    state
+        .paths
+        .retain(|path| !report.transferred.iter().any(|(source, _)| source == path));
+    if state.paths.is_empty()
+        && let Ok(mut clipboard) = arboard::Clipboard::new()
+    {
+        let _ = clipboard.clear();
+    }
+}
+
+/// Pastes the clipboard into `target`, moving the files when they were cut.
+pub fn paste_into(target: &Path, fallback_text: Option<&str>) {
+    let Some((paths, mode)) = files_to_paste(fallback_text) else {
+        toast!(Info, "Nothing to paste");
+        return;
+    };
+    let kind = match mode {
+        ClipboardMode::Copy => FileOperationKind::Copy,
+        ClipboardMode::Cut => FileOperationKind::Move,
+    };
+    FileOperation::new(kind, paths, Some(target.to_path_buf())).schedule();
+}
+
+pub fn selected_paths(ctx: &Context, tab: &TabData) -> Vec<PathBuf> {
+    ctx.data_get_path::<Selected>(&tab.current_path)
+        .map(|selected| selected.paths(tab))
+        .unwrap_or_default()
+}
+
+/// Handles Ctrl+C/Ctrl+X/Ctrl+V for the active tab.
+///
+/// The windowing backend turns these shortcuts into `Event::Copy`, `Event::Cut`
+/// and `Event::Paste`. The latter is only sent when the system clipboard holds
+/// text, which is not the case for a plain file list, so a released V with the
+/// command modifier is used as a fallback.
+pub fn handle_shortcuts(ctx: &Context, tab: &TabData) {
+    if text_edit_focused(ctx) {
+        return;
+    }
+    let mut copy = None;
+    let mut paste = None;
+    let mut paste_key_released = false;
+    ctx.input(|i| {
+        for event in &i.events {
+            match event {
+                Event::Copy => copy = Some(ClipboardMode::Copy),
+                Event::Cut => copy = Some(ClipboardMode::Cut),
+                Event::Paste(text) => paste = Some(text.clone()),
+                Event::Key {
+                    key: Key::V,
+                    pressed: false,
+                    modifiers,
+                    ..
+                } if modifiers.command => paste_key_released = true,
+                _ => {}
+            }
+        }
+    });
+
+    // Shift+Delete arrives as a cut on Windows, it deletes instead.
+    if copy == Some(ClipboardMode::Cut) && ctx.input(shift_delete_pressed) {
+        copy = None;
+    }
+    if let Some(mode) = copy {
+        set_files(selected_paths(ctx, tab), mode);
+    }
+    if paste.is_none() && !paste_key_released {
+        return;
+    }
+    let now = Instant::now();
+    {
+        let Ok(mut state) = FILE_CLIPBOARD.lock() else {
+            return;
+        };
+        if paste.is_none()
+            && state
+                .last_paste
+                .is_some_and(|last| now.duration_since(last) < PASTE_DEBOUNCE)
+        {
+            return;
+        }
+        state.last_paste = paste.is_some().then_some(now);
+    }
+    let Some(target) = tab.current_path.single_path() else {
+        toast!(Error, "Can't paste while showing multiple directories");
+        return;
+    };
+    paste_into(&target, paste.as_deref());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_file_list, percent_decode};
+    use crate::app::test_support::unique_test_dir;
+
+    #[test]
+    fn percent_decode_handles_escapes_and_utf8() {
+        assert_eq!(percent_decode("/tmp/a%20b").as_deref(), Some("/tmp/a b"));
+        assert_eq!(
+            percent_decode("/tmp/%C5%BC%C3%B3%C5%82w").as_deref(),
+            Some("/tmp/żółw")
+        );
+        assert_eq!(percent_decode("/tmp/%2"), None);
+        assert_eq!(percent_decode("/tmp/%zz"), None);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn parse_file_list_accepts_only_file_uris() {
+        let dir = unique_test_dir("clipboard");
+        let spaced = dir.join("with space.txt");
+        let plain = dir.join("plain.txt");
+        std::fs::write(&spaced, b"a").expect("write file");
+        std::fs::write(&plain, b"b").expect("write file");
+
+        let text = format!(
+            "copy\n# comment\nfile://{}/with%20space.txt\r\nfile://localhost{}\nfile:///does/not/exist\n",
+            dir.display(),
+            plain.display()
+        );
+        assert_eq!(parse_file_list(&text), vec![spaced.clone(), plain.clone()]);
+        // A copied path is text, not a file.
+        assert!(parse_file_list(&plain.display().to_string()).is_empty());
+        let mixed = format!("file://{}\n{}", spaced.display(), plain.display());
+        assert!(parse_file_list(&mixed).is_empty());
+        assert!(parse_file_list("cut").is_empty());
+        assert!(parse_file_list("just some words").is_empty());
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
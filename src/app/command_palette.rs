@@ -5,6 +5,8 @@ use egui::{Modal, TextBuffer};
 use crate::{app::dock::CurrentPath, locations::Locations};
 
 use super::commands::ActionToPerform;
+use super::new_entry::{self, NewEntryKind};
+use super::smart_folders;
 
 #[derive(Default, Debug, Clone)]
 pub struct CommandPalette {
@@ -37,6 +39,23 @@ pub fn build_for_path(
                     name: "Go Up".into(),
                 });
             }
+            commands.push(ActionToPerform::CreateEntry(NewEntryKind::Directory).into());
+            commands.push(ActionToPerform::CreateEntry(NewEntryKind::File).into());
+            commands.push(ActionToPerform::FindDuplicates.into());
+            commands.push(ActionToPerform::AnalyzeDiskUsage(path.to_path_buf()).into());
+            commands.push(ActionToPerform::CompareFolders.into());
+            commands.push(ActionToPerform::ManageSyncJobs.into());
+            commands.push(ActionToPerform::ExportSmartFolders.into());
+            if path.join(smart_folders::EXPORT_FILE_NAME).is_file() {
+                commands.push(ActionToPerform::ImportSmartFolders.into());
+            }
+            for template in new_entry::templates() {
+                let kind = NewEntryKind::Template(template);
+                commands.push(ValidAction {
+                    name: format!("{} (template)", kind.label()).into(),
+                    action: ActionToPerform::CreateEntry(kind),
+                });
+            }
         } else {
             commands.push(ValidAction {
                 action: ActionToPerform::TabAction(
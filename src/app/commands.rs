@@ -1,4 +1,7 @@
-use crate::app::{DataSource, MatchMode, SearchTerm, dock::CurrentPath};
+use crate::app::{
+    DataSource, MatchMode, SearchTerm, content_search::ContentHit, dock::CurrentPath,
+    file_ops::JobReport, journal::JournalReport, new_entry::NewEntryKind, trash_bin::TrashReport,
+};
 use crate::data::files::{DirEntry, DirList};
 use crossbeam::queue::SegQueue;
 use std::{borrow::Cow, fmt::Display, path::PathBuf, str::FromStr};
@@ -8,10 +11,20 @@ pub static COMMANDS_QUEUE: std::sync::LazyLock<SegQueue<ActionToPerform>> =
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum ModalWindow {
-    // NewDirectory,
     Settings,
     Commands,
     Rename,
+    BatchRename,
+    Conflict,
+    ConfirmDelete,
+    Properties,
+    Compress,
+    Extract,
+    Duplicates,
+    DiskUsage,
+    Compare,
+    Sync,
+    Checksums,
 }
 impl Display for ModalWindow {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
@@ -19,6 +32,17 @@ impl Display for ModalWindow {
             Self::Settings => write!(f, "Settings"),
             Self::Commands => write!(f, "Commands"),
             Self::Rename => write!(f, "Rename"),
+            Self::BatchRename => write!(f, "Batch rename"),
+            Self::Conflict => write!(f, "Conflict"),
+            Self::ConfirmDelete => write!(f, "Delete permanently"),
+            Self::Properties => write!(f, "Properties"),
+            Self::Compress => write!(f, "Compress"),
+            Self::Extract => write!(f, "Extract"),
+            Self::Duplicates => write!(f, "Duplicates"),
+            Self::DiskUsage => write!(f, "Disk usage"),
+            Self::Compare => write!(f, "Compare folders"),
+            Self::Sync => write!(f, "Sync jobs"),
+            Self::Checksums => write!(f, "Checksums"),
         }
     }
 }
@@ -65,6 +89,15 @@ pub enum TabAction {
         progress: String,
         generation: u64,
     },
+    /// A content search found these files, more may follow until
+    /// `FilesLoaded` arrives.
+    ContentMatches {
+        hits: Vec<(DirEntry, ContentHit)>,
+        generation: u64,
+    },
+    /// Select the entry at `path` once it is listed, optionally opening the
+    /// rename dialog for it.
+    RevealEntry { path: PathBuf, rename: bool },
 }
 
 impl TabAction {
@@ -122,6 +155,52 @@ pub enum ActionToPerform {
     ViewSettingsChanged(DataSource),
     /// Open the specified path in the system's default application.
     SystemOpen(Cow<'static, str>),
+    /// A background file operation has finished.
+    FileJobFinished(JobReport),
+    /// Revert the last journaled file operation.
+    UndoFileOperation,
+    /// Apply the last reverted file operation again.
+    RedoFileOperation,
+    /// A journal undo/redo has finished in the background.
+    JournalApplied(JournalReport),
+    /// Create a new entry in the active tab's directory.
+    CreateEntry(NewEntryKind),
+    /// A trash restore or permanent delete has finished in the background.
+    TrashApplied(TrashReport),
+    /// Look for duplicate files in the active tab's directories.
+    FindDuplicates,
+    /// Show what takes up space below the specified folder.
+    AnalyzeDiskUsage(PathBuf),
+    /// Compare the active tab's directory with another folder.
+    CompareFolders,
+    /// Show the saved sync jobs, offering the active tab's directory as the
+    /// source of a new one.
+    ManageSyncJobs,
+    /// Compute the checksums of the specified files.
+    ComputeChecksums(Vec<PathBuf>),
+    /// Check the files listed in the specified checksum manifest.
+    VerifyChecksums(PathBuf),
+    /// Open the smart folder with the specified name in a new tab.
+    OpenSmartFolder(String),
+    /// Show or hide a saved search in the sidebar.
+    PinSavedSearch {
+        name: String,
+        pinned: bool,
+    },
+    /// Rename a smart folder.
+    RenameSmartFolder {
+        from: String,
+        to: String,
+    },
+    /// Move a smart folder up or down the sidebar.
+    MoveSmartFolder {
+        name: String,
+        up: bool,
+    },
+    /// Write the smart folders to a file in the active tab's directory.
+    ExportSmartFolders,
+    /// Read smart folders exported to the active tab's directory.
+    ImportSmartFolders,
 }
 
 impl ActionToPerform {
@@ -156,6 +235,8 @@ impl From<&ActionToPerform> for Cow<'static, str> {
                 TabAction::DeleteSavedSearch(_) => Cow::Borrowed("Delete search"),
                 TabAction::FilesLoaded { .. } => Cow::Borrowed("Files loaded"),
                 TabAction::FilesProgress { .. } => Cow::Borrowed("Files loading progress"),
+                TabAction::ContentMatches { .. } => Cow::Borrowed("Content matches"),
+                TabAction::RevealEntry { .. } => Cow::Borrowed("Reveal entry"),
             },
             ActionToPerform::AddToFavorites(_) => Cow::Borrowed("Add to favorites"),
             ActionToPerform::RemoveFromFavorites(_) => Cow::Borrowed("Remove from favorites"),
@@ -174,6 +255,30 @@ impl From<&ActionToPerform> for Cow<'static, str> {
                 }
             }
             ActionToPerform::SystemOpen(path) => Cow::Owned(format!("Open {path}")),
+            ActionToPerform::FileJobFinished(_) => Cow::Borrowed("File operation finished"),
+            ActionToPerform::UndoFileOperation => Cow::Borrowed("Undo file operation"),
+            ActionToPerform::RedoFileOperation => Cow::Borrowed("Redo file operation"),
+            ActionToPerform::JournalApplied(_) => Cow::Borrowed("File operation reverted"),
+            ActionToPerform::CreateEntry(kind) => Cow::Owned(kind.label()),
+            ActionToPerform::TrashApplied(_) => Cow::Borrowed("Trash operation finished"),
+            ActionToPerform::FindDuplicates => Cow::Borrowed("Find duplicates"),
+            ActionToPerform::AnalyzeDiskUsage(_) => Cow::Borrowed("Analyze disk usage"),
+            ActionToPerform::CompareFolders => Cow::Borrowed("Compare folders"),
+            ActionToPerform::ManageSyncJobs => Cow::Borrowed("Sync jobs"),
+            ActionToPerform::ComputeChecksums(_) => Cow::Borrowed("Checksums"),
+            ActionToPerform::VerifyChecksums(_) => Cow::Borrowed("Verify checksums"),
+            ActionToPerform::OpenSmartFolder(name) => Cow::Owned(format!("Open {name}")),
+            ActionToPerform::PinSavedSearch { pinned, .. } => {
+                if *pinned {
+                    Cow::Borrowed("Pin to sidebar")
+                } else {
+                    Cow::Borrowed("Unpin from sidebar")
+                }
+            }
+            ActionToPerform::RenameSmartFolder { .. } => Cow::Borrowed("Rename smart folder"),
+            ActionToPerform::MoveSmartFolder { .. } => Cow::Borrowed("Move smart folder"),
+            ActionToPerform::ExportSmartFolders => Cow::Borrowed("Export smart folders"),
+            ActionToPerform::ImportSmartFolders => Cow::Borrowed("Import smart folders"),
         }
     }
 }
@@ -0,0 +1,401 @@
+//! Comparing the contents of two folders.
+//!
+//! Both sides are listed with [`read_directory`] and matched up by their
+//! path relative to the folder compared. Files are judged by size and
+//! modification time, or by the hash of their contents.
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt::Display,
+    path::{Component, Path, PathBuf},
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use super::dir_handling::{WalkFilter, read_directory};
+use super::duplicates::hash_file;
+use crate::data::files::EntryType;
+use crate::data::time::TimestampSeconds;
+
+/// Deep enough for any real folder tree.
+const RECURSIVE_DEPTH: usize = usize::MAX - 1;
+/// How many hashed files pass between progress updates.
+const PROGRESS_INTERVAL: usize = 32;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CompareStatus {
+    OnlyLeft,
+    OnlyRight,
+    Identical,
+    Different,
+}
+
+impl CompareStatus {
+    pub const ALL: [Self; 4] = [
+        Self::OnlyLeft,
+        Self::OnlyRight,
+        Self::Different,
+        Self::Identical,
+    ];
+}
+
+impl Display for CompareStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::OnlyLeft => write!(f, "Only left"),
+            Self::OnlyRight => write!(f, "Only right"),
+            Self::Identical => write!(f, "Identical"),
+            Self::Different => write!(f, "Different"),
+        }
+    }
+}
+
+/// How two files with the same relative path are told apart.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CompareBy {
+    SizeAndModified,
+    Contents,
+}
+
+impl Display for CompareBy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::SizeAndModified => write!(f, "Size and modification time"),
+            Self::Contents => write!(f, "Contents"),
+        }
+    }
+}
+
+/// One side of a compared entry.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Side {
+    pub is_dir: bool,
+    pub size: u64,
+    pub modified: TimestampSeconds,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ComparedEntry {
+    /// Path below both of the folders compared.
+    pub relative: PathBuf,
+    pub left: Option<Side>,
+    pub right: Option<Side>,
+    pub status: CompareStatus,
+}
+
+impl ComparedEntry {
+    /// Whether the entry can be copied from the left to the right, or the
+    /// other way around with `to_left`. Folders found on both sides are
+    /// compared entry by entry instead.
+    pub fn can_copy(&self, to_left: bool) -> bool {
+        let (from, to) = if to_left {
+            (self.right, self.left)
+        } else {
+            (self.left, self.right)
+        };
+        match (from, to) {
+            (Some(_), None) => true,
+            (Some(from), Some(to)) => {
+                self.status == CompareStatus::Different && !from.is_dir && !to.is_dir
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Whether `name` is hidden, by the same rule as the directory listings.
+fn is_hidden(name: &str) -> bool {
+    name.starts_with('.') || name.starts_with('$')
+}
+
+fn list(
+    root: &Path,
+    recursive: bool,
+    show_hidden: bool,
+    cancel: &AtomicBool,
+) -> BTreeMap<PathBuf, Side> {
+    let depth = if recursive { RECURSIVE_DEPTH } else { 1 };
+    read_directory(
+        &[root.to_path_buf()],
+        depth,
+        show_hidden,
+        &WalkFilter::default(),
+        cancel,
+    )
+    .into_iter()
+    .filter_map(|entry| {
+        let relative = entry.get_path().strip_prefix(root).ok()?.to_path_buf();
+        let hidden = relative.components().any(|component| match component {
+            Component::Normal(name) => is_hidden(&name.to_string_lossy()),
+            _ => false,
+        });
+        if relative.as_os_str().is_empty() || (hidden && !show_hidden) {
+            return None;
+        }
+        let side = Side {
+            is_dir: entry.meta.entry_type == EntryType::Directory,
+            size: entry.meta.size,
+            modified: entry.meta.modified_at,
+        };
+        Some((relative, side))
+    })
+    .collect()
+}
+
+/// Whether the two files behind `relative` hold the same contents. Files
+/// that can't be read count as different.
+fn same_contents(left: &Path, right: &Path) -> bool {
+    match (hash_file(left, None), hash_file(right, None)) {
+        (Ok(left), Ok(right)) => left == right,
+        (Err(err), _) | (_, Err(err)) => {
+            log::warn!(
+                "Failed to compare {} with {}: {err}",
+                left.display(),
+                right.display()
+            );
+            false
+        }
+    }
+}
+
+/// Entries below `left` and `right` by relative path, or `None` once
+/// `cancel` is set. Folders on both sides count as identical, unless
+/// `recursive` compares what is inside them and finds a difference.
+pub fn compare(
+    left: &Path,
+    right: &Path,
+    recursive: bool,
+    by: CompareBy,
+    show_hidden: bool,
+    cancel: &AtomicBool,
+    progress: &dyn Fn(String),
+) -> Option<Vec<ComparedEntry>> {
+    progress(format!("Listing {}…", left.display()));
+    let left_entries = list(left, recursive, show_hidden, cancel);
+    progress(format!("Listing {}…", right.display()));
+    let right_entries = list(right, recursive, show_hidden, cancel);
+    if cancel.load(Ordering::Relaxed) {
+        return None;
+    }
+
+    let paths = left_entries
+        .keys()
+        .chain(right_entries.keys())
+        .cloned()
+        .collect::<BTreeSet<_>>();
+    let to_hash = paths
+        .iter()
+        .filter(|path| {
+            by == CompareBy::Contents
+                && matches!(
+                    (left_entries.get(*path), right_entries.get(*path)),
+                    (Some(l), Some(r)) if !l.is_dir && !r.is_dir && l.size == r.size
+                )
+        })
+        .count();
+    let mut hashed = 0;
+    let mut entries = Vec::with_capacity(paths.len());
+    for relative in paths {
+        let left_side = left_entries.get(&relative).copied();
+        let right_side = right_entries.get(&relative).copied();
+        let status = match (left_side, right_side) {
+            (Some(_), None) => CompareStatus::OnlyLeft,
+            (None, Some(_)) => CompareStatus::OnlyRight,
+            (Some(l), Some(r)) if l.is_dir != r.is_dir => CompareStatus::Different,
+            (Some(l), Some(_)) if l.is_dir => CompareStatus::Identical,
+            (Some(l), Some(r)) if l.size != r.size => CompareStatus::Different,
+            (Some(l), Some(r)) => {
+                let same = match by {
+                    CompareBy::SizeAndModified => l.modified == r.modified,
+                    CompareBy::Contents => {
+                        if cancel.load(Ordering::Relaxed) {
+                            return None;
+                        }
+                        if hashed % PROGRESS_INTERVAL == 0 {
+                            progress(format!(
+                                "Comparing contents of {hashed} of {to_hash} files…"
+                            ));
+                        }
+                        hashed += 1;
+                        same_contents(&left.join(&relative), &right.join(&relative))
+                    }
+                };
+                if same {
+                    CompareStatus::Identical
+                } else {
+                    CompareStatus::Different
+                }
+            }
+            (None, None) => continue,
+        };
+        entries.push(ComparedEntry {
+            relative,
+            left: left_side,
+            right: right_side,
+            status,
+        });
+    }
+
+    if recursive {
+        // A folder on both sides differs once anything below it does.
+        let changed = entries
+            .iter()
+            .filter(|entry| entry.status != CompareStatus::Identical)
+            .flat_map(|entry| entry.relative.ancestors().skip(1))
+            .map(Path::to_path_buf)
+            .collect::<BTreeSet<_>>();
+        for entry in &mut entries {
+            if entry.status == CompareStatus::Identical && changed.contains(&entry.relative) {
+                entry.status = CompareStatus::Different;
+            }
+        }
+    }
+    Some(entries)
+}
+
+/// Groups `selected` entries by the folder below the target root they are
+/// copied into. Entries below another selected entry come along with it and
+/// are left out.
+pub fn copy_groups(selected: &[&ComparedEntry]) -> BTreeMap<PathBuf, Vec<PathBuf>> {
+    let chosen = selected
+        .iter()
+        .map(|entry| entry.relative.as_path())
+        .collect::<BTreeSet<_>>();
+    let mut groups = BTreeMap::<PathBuf, Vec<PathBuf>>::new();
+    for entry in selected {
+        if entry
+            .relative
+            .ancestors()
+            .skip(1)
+            .any(|ancestor| chosen.contains(ancestor))
+        {
+            continue;
+        }
+        let parent = entry
+            .relative
+            .parent()
+            .map(Path::to_path_buf)
+            .unwrap_or_default();
+        groups
+            .entry(parent)
+            .or_default()
+            .push(entry.relative.clone());
+    }
+    groups
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        fs::{self, File},
+        path::{Path, PathBuf},
+        sync::atomic::AtomicBool,
+        time::{Duration, SystemTime},
+    };
+
+    use super::{CompareBy, CompareStatus, ComparedEntry, compare, copy_groups};
+    use crate::app::test_support::unique_test_dir;
+
+    fn write(path: &Path, contents: &[u8], modified: u64) {
+        fs::create_dir_all(path.parent().expect("parent")).expect("create dirs");
+        fs::write(path, contents).expect("write");
+        File::options()
+            .write(true)
+            .open(path)
+            .expect("open")
+            .set_modified(SystemTime::UNIX_EPOCH + Duration::from_secs(modified))
+            .expect("set modified");
+    }
+
+    fn statuses(
+        left: &Path,
+        right: &Path,
+        recursive: bool,
+        by: CompareBy,
+    ) -> Vec<(String, CompareStatus)> {
+        compare(
+            left,
+            right,
+            recursive,
+            by,
+            false,
+            &AtomicBool::new(false),
+            &|_| {},
+        )
+        .expect("compare")
+        .into_iter()
+        .map(|entry| {
+            (
+                entry.relative.to_string_lossy().replace('\\', "/"),
+                entry.status,
+            )
+        })
+        .collect()
+    }
+
+    #[test]
+    fn entries_are_matched_by_relative_path() {
+        use CompareStatus::{Different, Identical, OnlyLeft, OnlyRight};
+        let dir = unique_test_dir("compare");
+        let (left, right) = (dir.join("left"), dir.join("right"));
+        write(&left.join("same"), b"one", 1_000);
+        write(&right.join("same"), b"one", 1_000);
+        // Same size and time, different contents.
+        write(&left.join("sub/touched"), b"abc", 2_000);
+        write(&right.join("sub/touched"), b"xyz", 2_000);
+        write(&left.join("newer"), b"two", 1_000);
+        write(&right.join("newer"), b"two", 3_000);
+        write(&left.join("left_only"), b"", 1_000);
+        write(&right.join("right_only"), b"", 1_000);
+        write(&right.join(".hidden"), b"", 1_000);
+
+        let flat = statuses(&left, &right, false, CompareBy::SizeAndModified);
+        assert_eq!(
+            flat,
+            [
+                ("left_only".to_string(), OnlyLeft),
+                ("newer".to_string(), Different),
+                ("right_only".to_string(), OnlyRight),
+                ("same".to_string(), Identical),
+                ("sub".to_string(), Identical),
+            ]
+        );
+
+        let deep = statuses(&left, &right, true, CompareBy::SizeAndModified);
+        assert!(deep.contains(&("sub".to_string(), Identical)));
+        assert!(deep.contains(&("sub/touched".to_string(), Identical)));
+
+        let by_contents = statuses(&left, &right, true, CompareBy::Contents);
+        assert!(by_contents.contains(&("newer".to_string(), Identical)));
+        assert!(by_contents.contains(&("sub".to_string(), Different)));
+        assert!(by_contents.contains(&("sub/touched".to_string(), Different)));
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn copies_are_grouped_by_target_folder() {
+        let entry = |relative: &str| ComparedEntry {
+            relative: PathBuf::from(relative),
+            left: None,
+            right: None,
+            status: CompareStatus::OnlyLeft,
+        };
+        let entries = [
+            entry("a"),
+            entry("a/inner"),
+            entry("b/c"),
+            entry("b/d"),
+            entry("e"),
+        ];
+        let groups = copy_groups(&entries.iter().collect::<Vec<_>>());
+        assert_eq!(
+            groups.into_iter().collect::<Vec<_>>(),
+            [
+                (PathBuf::new(), vec![PathBuf::from("a"), PathBuf::from("e")]),
+                (
+                    PathBuf::from("b"),
+                    vec![PathBuf::from("b/c"), PathBuf::from("b/d")]
+                ),
+            ]
+        );
+    }
+}
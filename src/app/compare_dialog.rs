@@ -0,0 +1,420 @@
+use std::{
+    collections::HashSet,
+    path::PathBuf,
+    sync::{
+        Arc, Mutex, OnceLock,
+        atomic::{AtomicBool, Ordering},
+    },
+};
+
+use egui::{Color32, Context, Id, Ui};
+
+use super::App;
+use super::commands::{ActionToPerform, ModalWindow};
+use super::compare::{self, CompareBy, CompareStatus, ComparedEntry, Side};
+use super::file_ops::{self, FileOperation};
+use crate::helper::format_bytes_simple;
+use crate::toast;
+
+/// Height of a row in the results list.
+const ROW_HEIGHT: f32 = 20.0;
+
+/// A comparison running or done in the background.
+#[derive(Debug, Clone)]
+struct CompareRun {
+    left: PathBuf,
+    right: PathBuf,
+    /// Latest progress message of the comparison.
+    status: Arc<Mutex<String>>,
+    cancel: Arc<AtomicBool>,
+    /// Filled in once the comparison is done.
+    result: Arc<OnceLock<Arc<Vec<ComparedEntry>>>>,
+    entries: Option<Arc<Vec<ComparedEntry>>>,
+}
+
+impl CompareRun {
+    fn start(ctx: &Context, left: PathBuf, right: PathBuf, options: &CompareState) -> Self {
+        let status = Arc::new(Mutex::new(String::new()));
+        let cancel = Arc::new(AtomicBool::new(false));
+        let result = Arc::new(OnceLock::new());
+        {
+            let status = Arc::clone(&status);
+            let cancel = Arc::clone(&cancel);
+            let result = Arc::clone(&result);
+            let (left, right) = (left.clone(), right.clone());
+            let (recursive, by, show_hidden) = (options.recursive, options.by, options.show_hidden);
+            let ctx = ctx.clone();
+            file_ops::spawn_task(move || {
+                let progress = |message: String| {
+                    if let Ok(mut status) = status.lock() {
+                        *status = message;
+                    }
+                    ctx.request_repaint();
+                };
+                if let Some(entries) = compare::compare(
+                    &left,
+                    &right,
+                    recursive,
+                    by,
+                    show_hidden,
+                    &cancel,
+                    &progress,
+                ) {
+                    let _ = result.set(Arc::new(entries));
+                }
+                ctx.request_repaint();
+            });
+        }
+        Self {
+            left,
+            right,
+            status,
+            cancel,
+            result,
+            entries: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct CompareState {
+    left: String,
+    right: String,
+    /// Folders open in other tabs, offered for either side.
+    suggestions: Vec<PathBuf>,
+    by: CompareBy,
+    recursive: bool,
+    show_hidden: bool,
+    run: Option<CompareRun>,
+    /// Statuses listed in the results.
+    shown: HashSet<CompareStatus>,
+    /// Relative paths picked for copying.
+    selected: HashSet<PathBuf>,
+}
+
+impl CompareState {
+    fn start(&mut self, ctx: &Context) {
+        let (left, right) = (PathBuf::from(&self.left), PathBuf::from(&self.right));
+        if !left.is_dir() || !right.is_dir() {
+            toast!(Error, "Pick two existing folders to compare");
+            return;
+        }
+        if let Some(run) = &self.run {
+            run.cancel.store(true, Ordering::Relaxed);
+        }
+        self.selected.clear();
+        self.run = Some(CompareRun::start(ctx, left, right, self));
+    }
+
+    /// Copies the selected entries across and gives back how many were
+    /// scheduled. Folders missing on the target side are created first.
+    fn copy_selected(&mut self, to_left: bool) -> usize {
+        let Some(run) = &self.run else {
+            return 0;
+        };
+        let Some(entries) = &run.entries else {
+            return 0;
+        };
+        let (from, to) = if to_left {
+            (&run.right, &run.left)
+        } else {
+            (&run.left, &run.right)
+        };
+        let selected = entries
+            .iter()
+            .filter(|entry| self.selected.contains(&entry.relative) && entry.can_copy(to_left))
+            .collect::<Vec<_>>();
+        let mut scheduled = 0;
+        for (parent, relatives) in compare::copy_groups(&selected) {
+            let target = to.join(&parent);
+            if let Err(err) = std::fs::create_dir_all(&target) {
+                toast!(Error, "Could not create {}: {err}", target.display());
+                continue;
+            }
+            scheduled += relatives.len();
+            let sources = relatives
+                .iter()
+                .map(|relative| from.join(relative))
+                .collect();
+            FileOperation::copy(sources, target).schedule();
+        }
+        self.selected.clear();
+        scheduled
+    }
+}
+
+fn state_id() -> Id {
+    Id::new(ModalWindow::Compare)
+}
+
+/// Shows the comparison setup with `left` filled in and `suggestions`
+/// offered for the other side.
+pub fn open(ctx: &Context, left: PathBuf, suggestions: Vec<PathBuf>, show_hidden: bool) {
+    let right = suggestions
+        .iter()
+        .find(|path| **path != left)
+        .map(|path| path.display().to_string())
+        .unwrap_or_default();
+    let state = CompareState {
+        left: left.display().to_string(),
+        right,
+        suggestions,
+        by: CompareBy::SizeAndModified,
+        recursive: true,
+        show_hidden,
+        run: None,
+        shown: [
+            CompareStatus::OnlyLeft,
+            CompareStatus::OnlyRight,
+            CompareStatus::Different,
+        ]
+        .into_iter()
+        .collect(),
+        selected: HashSet::new(),
+    };
+    ctx.data_mut(|d| d.insert_temp(state_id(), state));
+    ActionToPerform::ToggleModalWindow(ModalWindow::Compare).schedule();
+}
+
+fn status_color(ui: &Ui, status: CompareStatus) -> Color32 {
+    match status {
+        CompareStatus::OnlyLeft | CompareStatus::OnlyRight => ui.visuals().warn_fg_color,
+        CompareStatus::Different => ui.visuals().error_fg_color,
+        CompareStatus::Identical => ui.visuals().weak_text_color(),
+    }
+}
+
+fn describe(side: Option<Side>) -> String {
+    match side {
+        None => "—".to_string(),
+        Some(side) if side.is_dir => format!("folder, {}", side.modified.format_utc()),
+        Some(side) => format!(
+            "{}, {}",
+            format_bytes_simple(side.size),
+            side.modified.format_utc()
+        ),
+    }
+}
+
+impl App {
+    /// Two folders side by side, entry by entry.
+    pub(crate) fn compare_modal(&mut self, ctx: &Context) {
+        #[cfg(feature = "profiling")]
+        puffin::profile_scope!("lwa_fm::compare_modal");
+        let Some(mut state) = ctx.data(|d| d.get_temp::<CompareState>(state_id())) else {
+            self.display_modal = None;
+            return;
+        };
+        if let Some(run) = &mut state.run
+            && run.entries.is_none()
+        {
+            run.entries = run.result.get().cloned();
+        }
+
+        let mut close = false;
+        let modal = egui::Modal::new(state_id()).show(ctx, |ui| {
+            ui.set_width(880.0);
+            ui.heading("Compare folders");
+            setup_ui(ui, &mut state);
+            ui.separator();
+            match state.run.clone() {
+                None => {}
+                Some(CompareRun {
+                    entries: None,
+                    status,
+                    ..
+                }) => {
+                    ui.horizontal(|ui| {
+                        ui.spinner();
+                        ui.label(status.lock().map(|s| s.clone()).unwrap_or_default());
+                    });
+                    if ui.button("Cancel").clicked()
+                        && let Some(run) = state.run.take()
+                    {
+                        run.cancel.store(true, Ordering::Relaxed);
+                    }
+                }
+                Some(CompareRun {
+                    entries: Some(entries),
+                    ..
+                }) => {
+                    filters_ui(ui, &mut state, &entries);
+                    results_ui(ui, &mut state, &entries);
+                }
+            }
+            ui.separator();
+            actions_ui(ui, &mut state, &mut close);
+        });
+        if close || modal.should_close() {
+            if let Some(run) = &state.run {
+                run.cancel.store(true, Ordering::Relaxed);
+            }
+            ctx.data_mut(|d| d.remove::<CompareState>(state_id()));
+            self.display_modal = None;
+        } else {
+            ctx.data_mut(|d| d.insert_temp(state_id(), state));
+        }
+    }
+}
+
+fn path_row(ui: &mut Ui, label: &str, path: &mut String, suggestions: &[PathBuf]) {
+    ui.label(label);
+    ui.horizontal(|ui| {
+        ui.add(egui::TextEdit::singleline(path).desired_width(560.0));
+        egui::ComboBox::from_id_salt(("compare_tabs", label))
+            .selected_text("Tabs")
+            .show_ui(ui, |ui| {
+                if suggestions.is_empty() {
+                    ui.weak("No other tabs open");
+                }
+                for suggestion in suggestions {
+                    let text = suggestion.display().to_string();
+                    if ui.selectable_label(*path == text, &text).clicked() {
+                        *path = text;
+                    }
+                }
+            });
+    });
+    ui.end_row();
+}
+
+fn setup_ui(ui: &mut Ui, state: &mut CompareState) {
+    egui::Grid::new("compare_setup")
+        .num_columns(2)
+        .show(ui, |ui| {
+            path_row(ui, "Left", &mut state.left, &state.suggestions);
+            path_row(ui, "Right", &mut state.right, &state.suggestions);
+            ui.label("Compare by");
+            egui::ComboBox::from_id_salt("compare_by")
+                .selected_text(state.by.to_string())
+                .show_ui(ui, |ui| {
+                    for by in [CompareBy::SizeAndModified, CompareBy::Contents] {
+                        ui.selectable_value(&mut state.by, by, by.to_string());
+                    }
+                });
+            ui.end_row();
+        });
+    ui.horizontal(|ui| {
+        ui.checkbox(&mut state.recursive, "Include subfolders");
+        ui.checkbox(&mut state.show_hidden, "Include hidden files");
+        let ready = !state.left.trim().is_empty() && !state.right.trim().is_empty();
+        if ui
+            .add_enabled(ready, egui::Button::new("Compare"))
+            .clicked()
+        {
+            state.start(ui.ctx());
+        }
+    });
+}
+
+fn filters_ui(ui: &mut Ui, state: &mut CompareState, entries: &[ComparedEntry]) {
+    ui.horizontal(|ui| {
+        for status in CompareStatus::ALL {
+            let count = entries
+                .iter()
+                .filter(|entry| entry.status == status)
+                .count();
+            let mut shown = state.shown.contains(&status);
+            let text =
+                egui::RichText::new(format!("{status} ({count})")).color(status_color(ui, status));
+            if ui.checkbox(&mut shown, text).changed() {
+                if shown {
+                    state.shown.insert(status);
+                } else {
+                    state.shown.remove(&status);
+                }
+            }
+        }
+    });
+}
+
+fn results_ui(ui: &mut Ui, state: &mut CompareState, entries: &[ComparedEntry]) {
+    let shown = entries
+        .iter()
+        .filter(|entry| state.shown.contains(&entry.status))
+        .collect::<Vec<_>>();
+    if shown.is_empty() {
+        ui.weak("Nothing to show");
+        return;
+    }
+    egui::ScrollArea::vertical()
+        .max_height(420.0)
+        .auto_shrink([false, true])
+        .show_rows(ui, ROW_HEIGHT, shown.len(), |ui, range| {
+            for entry in &shown[range] {
+                ui.horizontal(|ui| {
+                    let mut selected = state.selected.contains(&entry.relative);
+                    let copyable = entry.can_copy(false) || entry.can_copy(true);
+                    if ui
+                        .add_enabled(copyable, egui::Checkbox::without_text(&mut selected))
+                        .changed()
+                    {
+                        if selected {
+                            state.selected.insert(entry.relative.clone());
+                        } else {
+                            state.selected.remove(&entry.relative);
+                        }
+                    }
+                    ui.add_sized(
+                        [90.0, ROW_HEIGHT],
+                        egui::Label::new(
+                            egui::RichText::new(entry.status.to_string())
+                                .color(status_color(ui, entry.status)),
+                        ),
+                    );
+                    ui.add_sized(
+                        [180.0, ROW_HEIGHT],
+                        egui::Label::new(describe(entry.left)).truncate(),
+                    );
+                    ui.add_sized(
+                        [180.0, ROW_HEIGHT],
+                        egui::Label::new(describe(entry.right)).truncate(),
+                    );
+                    ui.add(egui::Label::new(entry.relative.display().to_string()).truncate());
+                });
+            }
+        });
+}
+
+fn actions_ui(ui: &mut Ui, state: &mut CompareState, close: &mut bool) {
+    ui.horizontal(|ui| {
+        let entries = state.run.as_ref().and_then(|run| run.entries.clone());
+        let copyable = |to_left: bool| {
+            entries.as_ref().is_some_and(|entries| {
+                entries.iter().any(|entry| {
+                    state.selected.contains(&entry.relative) && entry.can_copy(to_left)
+                })
+            })
+        };
+        let (to_right, to_left) = (copyable(false), copyable(true));
+        for (enabled, to_left, text) in [
+            (to_right, false, "Copy to right →"),
+            (to_left, true, "← Copy to left"),
+        ] {
+            if ui.add_enabled(enabled, egui::Button::new(text)).clicked() {
+                let scheduled = state.copy_selected(to_left);
+                if scheduled > 0 {
+                    toast!(Info, "Copying {scheduled} item(s), compare again once done");
+                }
+            }
+        }
+        if !state.selected.is_empty() {
+            ui.weak(format!("{} selected", state.selected.len()));
+        }
+        ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+            if ui.button("Close").clicked() {
+                *close = true;
+            }
+            if ui
+                .button("Swap sides")
+                .on_hover_text("Put the right folder on the left")
+                .clicked()
+            {
+                std::mem::swap(&mut state.left, &mut state.right);
+                if state.run.is_some() {
+                    state.start(ui.ctx());
+                }
+            }
+        });
+    });
+}
@@ -0,0 +1,160 @@
+use std::path::{Path, PathBuf};
+
+use egui::{Context, Id};
+
+use super::App;
+use super::archive::ArchiveKind;
+use super::commands::{ActionToPerform, ModalWindow};
+use super::file_ops::FileOperation;
+
+#[derive(Debug, Clone)]
+struct CompressState {
+    sources: Vec<PathBuf>,
+    /// Folder the archive is written to, the one the sources are in.
+    dir: PathBuf,
+    /// Archive name without the extension.
+    name: String,
+    kind: ArchiveKind,
+    level: u32,
+}
+
+impl CompressState {
+    fn target(&self) -> PathBuf {
+        self.dir
+            .join(format!("{}{}", self.name.trim(), self.kind.extension()))
+    }
+
+    /// Why the archive can't be created under the current name.
+    fn error(&self) -> Option<String> {
+        let name = self.name.trim();
+        if name.is_empty() {
+            return Some("The name can't be empty".to_string());
+        }
+        if name.contains(['/', '\\']) {
+            return Some("The name can't contain path separators".to_string());
+        }
+        let target = self.target();
+        target.symlink_metadata().is_ok().then(|| {
+            format!(
+                "{} already exists",
+                target.file_name().unwrap_or_default().to_string_lossy()
+            )
+        })
+    }
+}
+
+/// The name a single entry is stored under without its extension, or the
+/// name of the folder holding several.
+fn default_name(sources: &[PathBuf], dir: &Path) -> String {
+    let name = match sources {
+        [single] if single.is_dir() => single.file_name(),
+        [single] => single.file_stem(),
+        _ => dir.file_name(),
+    };
+    name.map_or_else(
+        || "Archive".to_string(),
+        |name| name.to_string_lossy().to_string(),
+    )
+}
+
+fn state_id() -> Id {
+    Id::new(ModalWindow::Compress)
+}
+
+/// Asks for the name and format of a new archive holding `sources`.
+pub fn open(ctx: &Context, sources: Vec<PathBuf>) {
+    let Some(dir) = sources
+        .first()
+        .and_then(|source| source.parent())
+        .map(Path::to_path_buf)
+    else {
+        return;
+    };
+    let kind = ArchiveKind::Zip;
+    let state = CompressState {
+        name: default_name(&sources, &dir),
+        sources,
+        dir,
+        kind,
+        level: kind.default_level(),
+    };
+    ctx.data_mut(|d| d.insert_temp(state_id(), state));
+    ActionToPerform::ToggleModalWindow(ModalWindow::Compress).schedule();
+}
+
+impl App {
+    /// Creates an archive from the selection as a background file job.
+    pub(crate) fn compress_modal(&mut self, ctx: &Context) {
+        #[cfg(feature = "profiling")]
+        puffin::profile_scope!("lwa_fm::compress_modal");
+        let Some(mut state) = ctx.data(|d| d.get_temp::<CompressState>(state_id())) else {
+            self.display_modal = None;
+            return;
+        };
+        let mut confirmed = false;
+        let modal = egui::Modal::new(state_id()).show(ctx, |ui| {
+            ui.set_width(360.0);
+            ui.heading(match state.sources.len() {
+                1 => "Compress 1 item".to_string(),
+                count => format!("Compress {count} items"),
+            });
+            ui.separator();
+            egui::Grid::new("compress_options")
+                .num_columns(2)
+                .show(ui, |ui| {
+                    ui.label("Name");
+                    ui.horizontal(|ui| {
+                        let response = ui.text_edit_singleline(&mut state.name);
+                        if response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter)) {
+                            confirmed = true;
+                        }
+                        ui.label(state.kind.extension());
+                    });
+                    ui.end_row();
+                    ui.label("Format");
+                    egui::ComboBox::from_id_salt("compress_format")
+                        .selected_text(state.kind.to_string())
+                        .show_ui(ui, |ui| {
+                            for kind in ArchiveKind::WRITABLE {
+                                if ui
+                                    .selectable_value(&mut state.kind, kind, kind.to_string())
+                                    .changed()
+                                {
+                                    state.level = kind.default_level();
+                                }
+                            }
+                        });
+                    ui.end_row();
+                    ui.label("Level");
+                    ui.add(egui::Slider::new(&mut state.level, state.kind.levels()));
+                    ui.end_row();
+                });
+            ui.separator();
+            let error = state.error();
+            if let Some(error) = &error {
+                ui.colored_label(ui.visuals().error_fg_color, error);
+            }
+            ui.horizontal(|ui| {
+                if ui
+                    .add_enabled(error.is_none(), egui::Button::new("Create"))
+                    .clicked()
+                {
+                    confirmed = true;
+                }
+                if ui.button("Cancel").clicked() {
+                    ui.close();
+                }
+            });
+            confirmed &= error.is_none();
+        });
+        if confirmed {
+            FileOperation::compress(state.sources.clone(), state.target(), state.level).schedule();
+        }
+        if confirmed || modal.should_close() {
+            ctx.data_mut(|d| d.remove::<CompressState>(state_id()));
+            self.display_modal = None;
+        } else {
+            ctx.data_mut(|d| d.insert_temp(state_id(), state));
+        }
+    }
+}
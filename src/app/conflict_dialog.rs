@@ -0,0 +1,110 @@
+use std::path::Path;
+
+use egui::{Context, Ui, Vec2};
+
+use super::App;
+use super::commands::ModalWindow;
+use super::file_ops::{self, ConflictResolution, FileConflict};
+use crate::data::files::{DirEntry, EntryType};
+use crate::helper::format_bytes_simple;
+
+const PREVIEW_SIZE: f32 = 96.0;
+
+impl App {
+    /// Asks how to resolve the oldest pending copy/move conflict.
+    pub(crate) fn conflict_modal(&mut self, ctx: &Context) {
+        #[cfg(feature = "profiling")]
+        puffin::profile_scope!("lwa_fm::conflict_modal");
+        let Some(conflict) = file_ops::next_conflict() else {
+            self.display_modal = None;
+            return;
+        };
+        let apply_to_all_id = egui::Id::new(ModalWindow::Conflict).with("apply_to_all");
+        let mut answer = None;
+        let modal = egui::Modal::new(egui::Id::new(ModalWindow::Conflict)).show(ctx, |ui| {
+            let name = conflict
+                .destination
+                .file_name()
+                .map(|name| name.to_string_lossy().to_string())
+                .unwrap_or_default();
+            ui.heading(format!("\"{name}\" already exists"));
+            ui.label(format!(
+                "in {}",
+                conflict
+                    .destination
+                    .parent()
+                    .map(|parent| parent.display().to_string())
+                    .unwrap_or_default()
+            ));
+            ui.separator();
+            ui.horizontal_top(|ui| {
+                self.conflict_side(ui, "Incoming", &conflict.source);
+                ui.separator();
+                self.conflict_side(ui, "Existing", &conflict.destination);
+            });
+            ui.separator();
+            let mut apply_to_all =
+                ui.data(|d| d.get_temp::<bool>(apply_to_all_id).unwrap_or_default());
+            if ui.checkbox(&mut apply_to_all, "Apply to all").changed() {
+                ui.data_mut(|d| d.insert_temp(apply_to_all_id, apply_to_all));
+            }
+            ui.horizontal(|ui| {
+                for resolution in ConflictResolution::ALL {
+                    if ui.button(resolution.to_string()).clicked() {
+                        answer = Some((resolution, apply_to_all));
+                    }
+                }
+                if ui.button("Cancel operation").clicked() {
+                    cancel_job(&conflict);
+                }
+            });
+        });
+
+        if modal.should_close() && answer.is_none() {
+            answer = Some((ConflictResolution::Skip, false));
+        }
+        if let Some((resolution, apply_to_all)) = answer {
+            file_ops::resolve_conflict(conflict.job_id, resolution, apply_to_all);
+            if file_ops::next_conflict().is_none() {
+                ctx.data_mut(|d| d.remove_temp::<bool>(apply_to_all_id));
+                self.display_modal = None;
+            }
+        }
+    }
+
+    fn conflict_side(&mut self, ui: &mut Ui, title: &str, path: &Path) {
+        ui.vertical(|ui| {
+            ui.set_width(PREVIEW_SIZE * 2.0);
+            ui.strong(title);
+            let Some(entry) = DirEntry::from_path(path) else {
+                ui.label("Not readable");
+                return;
+            };
+            if let Some(texture) = self.assets.request_entry_texture(&entry) {
+                ui.add(
+                    egui::Image::new(&texture)
+                        .maintain_aspect_ratio(true)
+                        .fit_to_exact_size(Vec2::splat(PREVIEW_SIZE)),
+                );
+            } else {
+                ui.allocate_space(Vec2::splat(PREVIEW_SIZE));
+            }
+            if entry.meta.entry_type == EntryType::Directory {
+                ui.label("Folder");
+            } else {
+                ui.label(format_bytes_simple(entry.meta.size));
+            }
+            ui.label(entry.meta.modified_at.format_utc())
+                .on_hover_text(entry.meta.since_modified.to_string());
+        });
+    }
+}
+
+fn cancel_job(conflict: &FileConflict) {
+    if let Some(job) = file_ops::active_jobs()
+        .into_iter()
+        .find(|job| job.id == conflict.job_id)
+    {
+        job.progress.cancel();
+    }
+}
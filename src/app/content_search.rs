@@ -0,0 +1,275 @@
+//! Looking for text inside files, for searches with "search in contents" on.
+//!
+//! Files over the size limit and files that look binary (a NUL byte near the
+//! start) are skipped. Hits are handed over in batches so the list fills up
+//! while the scan goes on.
+
+use std::{
+    fs, io,
+    path::Path,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use rayon::prelude::*;
+use regex::{Regex, RegexBuilder};
+
+use super::{Search, SearchTermType};
+use crate::data::files::DirEntry;
+
+/// Default limit for the size of files searched, in megabytes.
+pub const DEFAULT_MAX_FILE_MB: u64 = 16;
+/// Matching lines kept per file.
+pub const MAX_SNIPPETS: usize = 5;
+/// Longest snippet shown, in characters.
+const SNIPPET_LEN: usize = 160;
+/// How much of a file is looked at to tell whether it's binary.
+const BINARY_SNIFF_LEN: usize = 8 * 1024;
+/// Files searched between two batches of hits.
+const BATCH_SIZE: usize = 64;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MatchedLine {
+    /// 1-based line number.
+    pub number: usize,
+    pub text: String,
+}
+
+/// Where a file matched the search.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ContentHit {
+    /// The first [`MAX_SNIPPETS`] matching lines.
+    pub lines: Vec<MatchedLine>,
+    /// Number of matching lines, including the ones not kept.
+    pub total: usize,
+}
+
+pub enum ContentMatcher {
+    /// Lowercased already when the search ignores case.
+    Plain {
+        pattern: String,
+        case_sensitive: bool,
+    },
+    Regex(Regex),
+}
+
+impl ContentMatcher {
+    /// What to look for in files, if `search` is a content search with
+    /// something to look for.
+    pub fn new(search: &Search) -> Option<Self> {
+        if !search.searches_contents() || search.value.is_empty() {
+            return None;
+        }
+        match search.term_type {
+            SearchTermType::Regex => RegexBuilder::new(&search.value)
+                .case_insensitive(!search.case_sensitive)
+                .build()
+                .ok()
+                .map(Self::Regex),
+            // Globs and fuzzy patterns describe names, so in contents they
+            // are plain text.
+            SearchTermType::Plain | SearchTermType::Glob | SearchTermType::Fuzzy => {
+                Some(Self::Plain {
+                    pattern: if search.case_sensitive {
+                        search.value.clone()
+                    } else {
+                        search.value.to_lowercase()
+                    },
+                    case_sensitive: search.case_sensitive,
+                })
+            }
+            SearchTermType::Metadata => None,
+        }
+    }
+
+    fn is_match(&self, line: &str) -> bool {
+        match self {
+            Self::Plain {
+                pattern,
+                case_sensitive: true,
+            } => line.contains(pattern.as_str()),
+            Self::Plain { pattern, .. } => line.to_lowercase().contains(pattern.as_str()),
+            Self::Regex(re) => re.is_match(line),
+        }
+    }
+}
+
+fn snippet(line: &str) -> String {
+    let line = line.trim();
+    match line.char_indices().nth(SNIPPET_LEN) {
+        Some((end, _)) => format!("{}…", &line[..end]),
+        None => line.to_string(),
+    }
+}
+
+/// Where `path` matches, or `None` when it doesn't, is too large or looks
+/// binary.
+pub fn search_file(
+    path: &Path,
+    matcher: &ContentMatcher,
+    max_size: u64,
+) -> io::Result<Option<ContentHit>> {
+    if fs::metadata(path)?.len() > max_size {
+        return Ok(None);
+    }
+    let data = fs::read(path)?;
+    if data[..data.len().min(BINARY_SNIFF_LEN)].contains(&0) {
+        return Ok(None);
+    }
+    let text = String::from_utf8_lossy(&data);
+    let mut hit = ContentHit::default();
+    for (index, line) in text.lines().enumerate() {
+        if !matcher.is_match(line) {
+            continue;
+        }
+        hit.total += 1;
+        if hit.lines.len() < MAX_SNIPPETS {
+            hit.lines.push(MatchedLine {
+                number: index + 1,
+                text: snippet(line),
+            });
+        }
+    }
+    Ok((hit.total > 0).then_some(hit))
+}
+
+/// Searches the files among `entries`, passing each batch of hits to `found`
+/// as soon as it's ready. Returns every matching entry, or `None` when
+/// cancelled.
+pub fn scan(
+    entries: &[DirEntry],
+    matcher: &ContentMatcher,
+    max_size: u64,
+    cancel: &AtomicBool,
+    progress: &dyn Fn(String),
+    found: &dyn Fn(Vec<(DirEntry, ContentHit)>),
+) -> Option<Vec<DirEntry>> {
+    let files = entries
+        .iter()
+        .filter(|entry| entry.is_file())
+        .collect::<Vec<_>>();
+    let mut entries_hit = Vec::new();
+    for (index, batch) in files.chunks(BATCH_SIZE).enumerate() {
+        if cancel.load(Ordering::SeqCst) {
+            return None;
+        }
+        progress(format!(
+            "Searching {} of {} files…",
+            (index * BATCH_SIZE + batch.len()).min(files.len()),
+            files.len()
+        ));
+        let hits = batch
+            .par_iter()
+            .filter_map(|entry| {
+                if cancel.load(Ordering::SeqCst) {
+                    return None;
+                }
+                let hit = search_file(&entry.get_path(), matcher, max_size).ok()??;
+                Some(((*entry).clone(), hit))
+            })
+            .collect::<Vec<_>>();
+        if hits.is_empty() {
+            continue;
+        }
+        entries_hit.extend(hits.iter().map(|(entry, _)| entry.clone()));
+        found(hits);
+    }
+    (!cancel.load(Ordering::SeqCst)).then_some(entries_hit)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::test_support::unique_test_dir;
+    use std::sync::Mutex;
+
+    fn content_search(value: &str, term_type: SearchTermType, case_sensitive: bool) -> Search {
+        Search {
+            value: value.to_string(),
+            term_type,
+            case_sensitive,
+            in_contents: true,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn finds_matching_lines_and_skips_binary_and_large_files() {
+        let dir = unique_test_dir("content");
+        let text = dir.join("notes.txt");
+        fs::write(
+            &text,
+            "first line\n  TODO: fix the parser  \nnothing\ntodo again\n",
+        )
+        .expect("write text");
+        let binary = dir.join("blob.bin");
+        fs::write(&binary, b"TODO\0\x01\x02").expect("write binary");
+
+        let plain = ContentMatcher::new(&content_search("todo", SearchTermType::Plain, false))
+            .expect("plain matcher");
+        let hit = search_file(&text, &plain, u64::MAX)
+            .expect("read")
+            .expect("match");
+        assert_eq!(hit.total, 2);
+        assert_eq!(
+            hit.lines[0],
+            MatchedLine {
+                number: 2,
+                text: "TODO: fix the parser".to_string(),
+            }
+        );
+        assert_eq!(search_file(&binary, &plain, u64::MAX).expect("read"), None);
+        assert_eq!(search_file(&text, &plain, 8).expect("read"), None);
+
+        let regex = ContentMatcher::new(&content_search(
+            r"^\s*TODO:\s+\w+",
+            SearchTermType::Regex,
+            true,
+        ))
+        .expect("regex matcher");
+        let hit = search_file(&text, &regex, u64::MAX)
+            .expect("read")
+            .expect("match");
+        assert_eq!(hit.total, 1);
+
+        let entries = [text.as_path(), binary.as_path(), dir.as_path()]
+            .map(|path| DirEntry::test_new(&path.to_string_lossy()));
+        let batches = Mutex::new(Vec::new());
+        let matched = scan(
+            &entries,
+            &plain,
+            u64::MAX,
+            &AtomicBool::new(false),
+            &|_| {},
+            &|hits| batches.lock().expect("lock").push(hits.len()),
+        )
+        .expect("not cancelled");
+        assert_eq!(matched.len(), 1);
+        assert_eq!(*batches.lock().expect("lock"), [1]);
+
+        let cancelled = scan(
+            &entries,
+            &plain,
+            u64::MAX,
+            &AtomicBool::new(true),
+            &|_| {},
+            &|_| {},
+        );
+        assert!(cancelled.is_none());
+
+        fs::remove_dir_all(&dir).expect("cleanup");
+    }
+
+    #[test]
+    fn only_content_searches_with_a_value_get_a_matcher() {
+        let mut search = content_search("", SearchTermType::Plain, false);
+        assert!(ContentMatcher::new(&search).is_none());
+        search.value = "x".to_string();
+        search.in_contents = false;
+        assert!(ContentMatcher::new(&search).is_none());
+        assert!(
+            ContentMatcher::new(&content_search("(", SearchTermType::Regex, false)).is_none(),
+            "invalid regex"
+        );
+        assert_eq!(snippet(&"a".repeat(200)).chars().count(), SNIPPET_LEN + 1);
+    }
+}
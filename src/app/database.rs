@@ -92,7 +92,7 @@ fn cache_writer_sender() -> mpsc::Sender<CacheWrite> {
 }
 
 // All callers pass already-normalized paths (from user navigation or file system watchers).
-fn cache_key(dir: &Path) -> Vec<u8> {
+pub(crate) fn cache_key(dir: &Path) -> Vec<u8> {
     #[cfg(windows)]
     {
         // Windows paths are case-insensitive; lowercase the string representation
@@ -105,6 +105,17 @@ fn cache_key(dir: &Path) -> Vec<u8> {
     }
 }
 
+/// Keys of `tree` made by [`cache_key`] for `dir` and everything below it.
+pub(crate) fn keys_below(tree: &sled::Tree, dir: &Path) -> Vec<sled::IVec> {
+    let prefix = cache_key(dir);
+    let root = String::from_utf8_lossy(&prefix).into_owned();
+    tree.scan_prefix(&prefix)
+        .keys()
+        .filter_map(Result::ok)
+        .filter(|key| Path::new(String::from_utf8_lossy(key).as_ref()).starts_with(&root))
+        .collect()
+}
+
 fn current_generation(path: &[u8]) -> u64 {
     CACHE_GENERATIONS
         .lock()
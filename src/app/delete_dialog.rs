@@ -0,0 +1,142 @@
+use std::{
+    path::PathBuf,
+    sync::{
+        Arc, OnceLock,
+        atomic::{AtomicBool, Ordering},
+    },
+};
+
+use egui::{Context, Id, RichText};
+
+use super::App;
+use super::commands::{ActionToPerform, ModalWindow};
+use super::file_ops::{self, FileOperation, TreeSummary};
+use crate::helper::format_bytes_simple;
+
+/// How many of the entries to delete are listed by name.
+const LISTED_ENTRIES: usize = 8;
+
+#[derive(Debug, Clone)]
+struct DeleteConfirmation {
+    paths: Vec<PathBuf>,
+    /// Why moving the entries to the trash failed, when permanent deletion is
+    /// offered as a fallback.
+    trash_error: Option<String>,
+    /// Filled in by a background walk of `paths`.
+    summary: Arc<OnceLock<TreeSummary>>,
+    /// Stops the walk once the dialog is answered or closed, so it doesn't
+    /// hold up the delete itself.
+    cancel: Arc<AtomicBool>,
+}
+
+fn state_id() -> Id {
+    Id::new(ModalWindow::ConfirmDelete)
+}
+
+/// Asks whether to permanently delete `paths`, counting what's inside them
+/// in the background meanwhile.
+pub fn open(ctx: &Context, paths: Vec<PathBuf>, trash_error: Option<String>) {
+    if paths.is_empty() {
+        return;
+    }
+    let summary = Arc::new(OnceLock::new());
+    let cancel = Arc::new(AtomicBool::new(false));
+    {
+        let summary = Arc::clone(&summary);
+        let cancel = Arc::clone(&cancel);
+        let paths = paths.clone();
+        let ctx = ctx.clone();
+        file_ops::spawn_task(move || {
+            if let Some(counted) = file_ops::summarize(&paths, &cancel) {
+                let _ = summary.set(counted);
+                ctx.request_repaint();
+            }
+        });
+    }
+    let confirmation = DeleteConfirmation {
+        paths,
+        trash_error,
+        summary,
+        cancel,
+    };
+    ctx.data_mut(|d| d.insert_temp(state_id(), confirmation));
+    ActionToPerform::ToggleModalWindow(ModalWindow::ConfirmDelete).schedule();
+}
+
+fn count(count: u64, noun: &str) -> String {
+    if count == 1 {
+        format!("1 {noun}")
+    } else {
+        format!("{count} {noun}s")
+    }
+}
+
+impl App {
+    /// Confirms a permanent delete, which then runs as a background file job.
+    pub(crate) fn delete_modal(&mut self, ctx: &Context) {
+        #[cfg(feature = "profiling")]
+        puffin::profile_scope!("lwa_fm::delete_modal");
+        let Some(confirmation) = ctx.data(|d| d.get_temp::<DeleteConfirmation>(state_id())) else {
+            self.display_modal = None;
+            return;
+        };
+        let mut confirmed = false;
+        let modal = egui::Modal::new(state_id()).show(ctx, |ui| {
+            ui.set_max_width(460.0);
+            ui.heading("Delete permanently?");
+            if let Some(error) = &confirmation.trash_error {
+                ui.colored_label(
+                    ui.visuals().warn_fg_color,
+                    format!("Moving to the trash failed: {error}"),
+                );
+            }
+            ui.separator();
+            for path in confirmation.paths.iter().take(LISTED_ENTRIES) {
+                let name = path.file_name().map_or_else(
+                    || path.display().to_string(),
+                    |name| name.to_string_lossy().to_string(),
+                );
+                ui.label(name).on_hover_text(path.display().to_string());
+            }
+            if confirmation.paths.len() > LISTED_ENTRIES {
+                ui.weak(format!(
+                    "and {} more",
+                    confirmation.paths.len() - LISTED_ENTRIES
+                ));
+            }
+            ui.separator();
+            if let Some(summary) = confirmation.summary.get() {
+                ui.label(format!(
+                    "{}, {} ({})",
+                    count(summary.files, "file"),
+                    count(summary.dirs, "folder"),
+                    format_bytes_simple(summary.bytes)
+                ));
+            } else {
+                ui.horizontal(|ui| {
+                    ui.spinner();
+                    ui.label("Counting…");
+                });
+            }
+            ui.label("This can't be undone.");
+            ui.horizontal(|ui| {
+                let delete =
+                    egui::Button::new(RichText::new("Delete").color(ui.visuals().error_fg_color));
+                if ui.add(delete).clicked() {
+                    confirmed = true;
+                }
+                if ui.button("Cancel").clicked() {
+                    ui.close();
+                }
+            });
+        });
+        if confirmed {
+            FileOperation::delete(confirmation.paths).schedule();
+        }
+        if confirmed || modal.should_close() {
+            confirmation.cancel.store(true, Ordering::Relaxed);
+            ctx.data_mut(|d| d.remove::<DeleteConfirmation>(state_id()));
+            self.display_modal = None;
+        }
+    }
+}
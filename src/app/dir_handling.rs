@@ -8,6 +8,7 @@ use std::{
 
 use notify::RecursiveMode;
 
+use fuzzy_matcher::{FuzzyMatcher, skim::SkimMatcherV2};
 use icu::collator::CollatorBorrowed;
 use rayon::{
     iter::{IntoParallelIterator, IntoParallelRefIterator, ParallelIterator},
@@ -16,11 +17,14 @@ use rayon::{
 
 use crate::{
     app::{
-        Data, MatchMode, Search, SearchTermType, Sort, database,
+        Data, MatchMode, Search, SearchTermType, Sort, database, dir_sizes,
         directory_view_settings::{DirectoryShowHidden, DirectoryViewSettings},
         dock::{CurrentPath, build_collator},
+        meta_query::MetaQuery,
+        name_index,
+        top_bottom::TopDisplayPath,
     },
-    data::files::{DirEntry, DirEntryData, DirEntryMetaData, DirList},
+    data::files::{DirEntry, DirEntryData, DirEntryMetaData, DirList, EntryType},
     helper::{DataHolder, normalize_path},
 };
 pub static COLLATER: std::sync::LazyLock<CollatorBorrowed<'static>> =
@@ -54,6 +58,9 @@ where
             }
             extract(data_source.data_get_path_or_persisted::<T>(path))
         }
+        CurrentPath::Archive { .. } | CurrentPath::Trash => {
+            extract(data_source.data_get_path_or_persisted::<T>(path))
+        }
         CurrentPath::None => extract(Data::default()),
     }
 }
@@ -77,13 +84,19 @@ impl TabData {
             }
         };
         match &self.current_path {
-            CurrentPath::None => {}
+            CurrentPath::None | CurrentPath::Trash => {}
             CurrentPath::One(path_buf) => add_root(path_buf),
             CurrentPath::Multiple(path_bufs) => {
                 for root in path_bufs {
                     add_root(root);
                 }
             }
+            // Rewriting the archive shows up as a change of its folder.
+            CurrentPath::Archive { archive, .. } => {
+                if let Some(parent) = archive.parent() {
+                    add_root(&parent.to_path_buf());
+                }
+            }
         }
         if let Some(search) = &self.search {
             for extra in &search.extra_dirs {
@@ -111,6 +124,8 @@ impl TabData {
         self.current_path = path;
         if let Some(path) = self.current_path.get_path() {
             self.top_display_path.build(&path, self.show_hidden);
+        } else {
+            self.top_display_path = TopDisplayPath::default();
         }
         &self.current_path
     }
@@ -121,11 +136,12 @@ impl TabData {
             data_source,
             |d: Data<DirectoryShowHidden>| d.data.0,
         );
-        self.display_type = resolve_path_setting::<DirectoryViewSettings, _, _>(
-            &self.current_path,
-            data_source,
-            |d: Data<DirectoryViewSettings>| d.data.display_type,
-        );
+        (self.display_type, self.checksum_column) =
+            resolve_path_setting::<DirectoryViewSettings, _, _>(
+                &self.current_path,
+                data_source,
+                |d: Data<DirectoryViewSettings>| (d.data.display_type, d.data.checksum_column),
+            );
         if let Some(path) = self.current_path.get_path() {
             self.top_display_path.build(&path, self.show_hidden);
         }
@@ -148,13 +164,46 @@ impl TabData {
         #[cfg(feature = "profiling")]
         puffin::profile_scope!("lwa_fm::dir_handling::sort_entries");
         self.display_type = sort_settings.display_type;
+        self.checksum_column = sort_settings.checksum_column;
         if let Some(dir_list) = &mut self.dir_list {
-            sort_dir_entry_data_slice(std::sync::Arc::make_mut(&mut dir_list.entries), sort_settings);
+            let dir = PathBuf::from(dir_list.dir.as_ref());
+            let entries = std::sync::Arc::make_mut(&mut dir_list.entries);
+            if sort_settings.sorting == Sort::Size {
+                for entry in entries
+                    .iter_mut()
+                    .filter(|entry| entry.meta.entry_type == EntryType::Directory)
+                {
+                    entry.meta.size = dir_size(&dir.join(&entry.file_name));
+                }
+            }
+            sort_dir_entry_data_slice(entries, sort_settings);
         } else {
             sort_entries_vec(&mut self.list, sort_settings);
         }
     }
 
+    /// Asks for the sizes of every folder listed, so sorting by size can
+    /// place them once measured.
+    pub fn measure_dir_sizes(&self, ctx: &egui::Context) {
+        if matches!(self.current_path, CurrentPath::Archive { .. }) {
+            return;
+        }
+        if let Some(dir_list) = &self.dir_list {
+            let dir = Path::new(dir_list.dir.as_ref());
+            for entry in dir_list
+                .entries
+                .iter()
+                .filter(|entry| entry.meta.entry_type == EntryType::Directory)
+            {
+                dir_sizes::get(&dir.join(&entry.file_name), ctx);
+            }
+        } else {
+            for entry in self.list.iter().filter(|entry| !entry.is_file()) {
+                dir_sizes::get(&entry.get_path(), ctx);
+            }
+        }
+    }
+
     pub fn update_file_metadata(
         &mut self,
         path: &Path,
@@ -212,6 +261,109 @@ impl TabData {
     }
 }
 
+/// What a walk leaves out besides hidden entries. Left out folders are not
+/// descended into.
+#[derive(Default, Clone)]
+pub struct WalkFilter {
+    /// Honour `.gitignore`, `.ignore` and the global git excludes.
+    ignore_files: bool,
+    excludes: Vec<glob::Pattern>,
+    /// Answer deep walks from the name index where it covers the root. It
+    /// can lag behind the disk, so only listings shown to the user use it.
+    use_index: bool,
+}
+
+impl WalkFilter {
+    pub fn new(search: Option<&Search>) -> Self {
+        search.map_or_else(Self::default, |search| Self {
+            ignore_files: search.respect_ignore_files,
+            excludes: search
+                .exclude_globs
+                .iter()
+                .map(|glob| glob.trim())
+                .filter(|glob| !glob.is_empty())
+                .filter_map(|glob| glob::Pattern::new(glob).ok())
+                .collect(),
+            use_index: false,
+        })
+    }
+
+    /// Lets deep walks be answered from the name index.
+    #[must_use]
+    pub const fn with_index(mut self) -> Self {
+        self.use_index = true;
+        self
+    }
+
+    /// Whether `path`, found below `root`, matches an exclude glob by name
+    /// or by its path relative to `root`.
+    fn excludes(&self, root: &Path, path: &Path) -> bool {
+        let name = path.file_name().map(|name| name.to_string_lossy());
+        let relative = path.strip_prefix(root).ok();
+        self.excludes.iter().any(|pattern| {
+            name.as_ref().is_some_and(|name| pattern.matches(name))
+                || relative.is_some_and(|relative| pattern.matches_path(relative))
+        })
+    }
+
+    /// Like [`Self::excludes`], but also checks every folder between `root`
+    /// and `path`, for entries that did not come from a pruned walk.
+    fn excludes_below(&self, root: &Path, path: &Path) -> bool {
+        path.ancestors()
+            .take_while(|ancestor| *ancestor != root && ancestor.starts_with(root))
+            .any(|ancestor| self.excludes(root, ancestor))
+    }
+}
+
+/// Walks `root` with the ignore files in effect, pruning ignored and
+/// excluded folders.
+fn walk_respecting_ignores(
+    root: &Path,
+    max_depth: usize,
+    show_hidden: bool,
+    filter: &WalkFilter,
+    cancel: &AtomicBool,
+) -> Vec<DirEntry> {
+    #[cfg(feature = "profiling")]
+    puffin::profile_scope!(
+        "lwa_fm::dir_handling::walk_respecting_ignores",
+        root.to_string_lossy().as_ref()
+    );
+    let filter = filter.clone();
+    let walk_root = root.to_path_buf();
+    let walk_entries: Vec<ignore::DirEntry> = ignore::WalkBuilder::new(root)
+        .follow_links(true)
+        .max_depth(Some(max_depth))
+        .hidden(false)
+        .filter_entry(move |e| {
+            if e.depth() == 0 {
+                return true;
+            }
+            let name = e.file_name().to_string_lossy();
+            // Never tracked, so never interesting when ignore files are honoured.
+            if name == ".git" {
+                return false;
+            }
+            if !show_hidden && (name.starts_with('.') || name.starts_with('$')) {
+                return false;
+            }
+            !filter.excludes(&walk_root, e.path())
+        })
+        .build()
+        .filter_map(Result::ok)
+        .filter(|e| e.depth() > 0)
+        .take_while(|_| !cancel.load(AtomicOrdering::SeqCst))
+        .collect();
+
+    if cancel.load(AtomicOrdering::SeqCst) {
+        return Vec::new();
+    }
+    walk_entries
+        .into_par_iter()
+        .filter_map(|e| e.try_into().ok())
+        .collect()
+}
+
 /// Walk a single root path and return its entries.
 /// Shared helper used by [`read_directory`] for each path in the parallelized
 /// per-root walk.
@@ -219,12 +371,27 @@ fn walk_single_root(
     root: &PathBuf,
     depth: usize,
     show_hidden: bool,
+    filter: &WalkFilter,
     cancel: &AtomicBool,
 ) -> Vec<DirEntry> {
     if cancel.load(AtomicOrdering::SeqCst) {
         return Vec::new();
     }
+    if filter.ignore_files {
+        let max_depth = if depth > 1 { depth + 1 } else { 1 };
+        return walk_respecting_ignores(root, max_depth, show_hidden, filter, cancel);
+    }
     if depth > 1 {
+        // The index can't apply ignore files, but excluded folders can
+        // still be dropped from what it returns.
+        if filter.use_index
+            && let Some(mut entries) = name_index::lookup(root, depth + 1, show_hidden, cancel)
+        {
+            if !filter.excludes.is_empty() {
+                entries.retain(|entry| !filter.excludes_below(root, &entry.get_path()));
+            }
+            return entries;
+        }
         #[cfg(feature = "profiling")]
         puffin::profile_scope!(
             "lwa_fm::dir_handling::walk_single_root::walkdir",
@@ -239,11 +406,13 @@ fn walk_single_root(
                 if e.depth() == 0 {
                     return true;
                 }
-                if show_hidden {
-                    return true;
+                if !show_hidden {
+                    let name = e.file_name().to_string_lossy();
+                    if name.starts_with('.') || name.starts_with('$') {
+                        return false;
+                    }
                 }
-                let name = e.file_name().to_string_lossy();
-                !name.starts_with('.') && !name.starts_with('$')
+                !filter.excludes(root, e.path())
             })
             .filter_map(Result::ok)
             .take_while(|_| !cancel.load(AtomicOrdering::SeqCst))
@@ -265,6 +434,9 @@ fn walk_single_root(
     } else {
         let mut entries = Vec::new();
         database::read_dir(root, &mut entries);
+        if !filter.excludes.is_empty() {
+            entries.retain(|entry| !filter.excludes(root, &entry.get_path()));
+        }
         entries
     }
 }
@@ -273,6 +445,7 @@ pub fn read_directory(
     paths: &[PathBuf],
     depth: usize,
     show_hidden: bool,
+    filter: &WalkFilter,
     cancel: &AtomicBool,
 ) -> Vec<DirEntry> {
     #[cfg(feature = "profiling")]
@@ -282,7 +455,7 @@ pub fn read_directory(
     // For a single path the overhead of rayon dispatch is negligible.
     let lists: Vec<Vec<DirEntry>> = paths
         .par_iter()
-        .map(|d| walk_single_root(d, depth, show_hidden, cancel))
+        .map(|d| walk_single_root(d, depth, show_hidden, filter, cancel))
         .collect();
 
     let mut list: Vec<DirEntry> = lists.into_iter().flatten().collect();
@@ -361,7 +534,17 @@ fn sort_dir_entry_data_slice(entries: &mut [DirEntryData], settings: &DirectoryV
     }
 }
 
+/// Size a folder is sorted by, 0 until it has been measured.
+fn dir_size(dir: &Path) -> u64 {
+    dir_sizes::cached(dir).map_or(0, |size| size.size)
+}
+
 pub fn sort_entries_vec(entries: &mut [DirEntry], settings: &DirectoryViewSettings) {
+    if settings.sorting == Sort::Size {
+        for entry in entries.iter_mut().filter(|entry| !entry.is_file()) {
+            entry.meta.size = dir_size(&entry.get_path());
+        }
+    }
     match settings.sorting {
         Sort::Modified => {
             if settings.invert_sort {
@@ -422,10 +605,36 @@ enum CompiledTerm {
     Plain(String),
     Glob(glob::Pattern),
     Regex(regex::Regex),
+    Metadata(MetaQuery),
+    Fuzzy(Box<FuzzyTerm>),
+}
+
+/// A fuzzy pattern together with a skim matcher set up for its case handling.
+struct FuzzyTerm {
+    pattern: String,
+    matcher: SkimMatcherV2,
+}
+
+impl FuzzyTerm {
+    fn new(pattern: &str, case_sensitive: bool) -> Self {
+        let matcher = SkimMatcherV2::default();
+        Self {
+            pattern: pattern.to_string(),
+            matcher: if case_sensitive {
+                matcher.respect_case()
+            } else {
+                matcher.ignore_case()
+            },
+        }
+    }
+
+    fn score(&self, name: &str) -> Option<i64> {
+        self.matcher.fuzzy_match(name, &self.pattern)
+    }
 }
 
 impl CompiledTerm {
-    fn matches(&self, name: &str, case_sensitive: bool) -> bool {
+    fn matches(&self, name: &str, meta: &DirEntryMetaData, case_sensitive: bool) -> bool {
         match self {
             Self::Plain(pattern) => {
                 if case_sensitive {
@@ -436,6 +645,8 @@ impl CompiledTerm {
             }
             Self::Glob(pattern) => pattern.matches(name),
             Self::Regex(re) => re.is_match(name),
+            Self::Metadata(query) => query.matches(name, meta),
+            Self::Fuzzy(term) => term.score(name).is_some(),
         }
     }
 }
@@ -455,6 +666,13 @@ fn compile_term(
         }
         SearchTermType::Glob => glob::Pattern::new(pattern).ok().map(CompiledTerm::Glob),
         SearchTermType::Regex => regex::Regex::new(pattern).ok().map(CompiledTerm::Regex),
+        SearchTermType::Metadata => MetaQuery::parse(pattern, std::time::SystemTime::now().into())
+            .ok()
+            .map(CompiledTerm::Metadata),
+        SearchTermType::Fuzzy => Some(CompiledTerm::Fuzzy(Box::new(FuzzyTerm::new(
+            pattern,
+            case_sensitive,
+        )))),
     }
 }
 
@@ -468,6 +686,7 @@ impl CompiledSearch {
     fn matches(
         &self,
         name: &str,
+        meta: &DirEntryMetaData,
         case_sensitive: bool,
         collator: Option<&CollatorBorrowed<'_>>,
     ) -> bool {
@@ -476,6 +695,8 @@ impl CompiledSearch {
                 CompiledTerm::Plain(pattern) => plain_matches(name, pattern, case_sensitive, collator),
                 CompiledTerm::Glob(glob) => glob.matches(name),
                 CompiledTerm::Regex(re) => re.is_match(name),
+                CompiledTerm::Metadata(query) => query.matches(name, meta),
+                CompiledTerm::Fuzzy(term) => term.score(name).is_some(),
             }
         };
         match self.mode {
@@ -483,6 +704,72 @@ impl CompiledSearch {
             MatchMode::Any => self.terms.iter().any(matches_one),
         }
     }
+
+    /// Sum of the fuzzy scores of `name`, zero when no fuzzy term matches.
+    fn relevance(&self, name: &str) -> i64 {
+        self.terms
+            .iter()
+            .filter_map(|term| match term {
+                CompiledTerm::Fuzzy(term) => term.score(name),
+                _ => None,
+            })
+            .sum()
+    }
+}
+
+/// Reorders `visible` by descending relevance when the search asks for it.
+/// The sort is stable, so equally relevant entries keep the `Sort` order.
+fn rank_by_relevance<'a>(
+    visible: &mut [usize],
+    search: Option<&Search>,
+    compiled_search: Option<&CompiledSearch>,
+    name_of: impl Fn(usize) -> &'a str,
+) {
+    if let Some(cs) = compiled_search
+        && search.is_some_and(|s| s.sort_by_relevance)
+        && cs
+            .terms
+            .iter()
+            .any(|term| matches!(term, CompiledTerm::Fuzzy(_)))
+    {
+        visible.sort_by_cached_key(|&i| std::cmp::Reverse(cs.relevance(name_of(i))));
+    }
+}
+
+/// Whether the search has fuzzy terms, so relevance ordering means something.
+pub fn uses_fuzzy(search: &Search) -> bool {
+    fuzzy_patterns(search).next().is_some()
+}
+
+fn fuzzy_patterns(search: &Search) -> impl Iterator<Item = &str> {
+    let value = (search.terms.is_empty()
+        && !search.searches_contents()
+        && search.term_type == SearchTermType::Fuzzy
+        && !search.value.is_empty())
+    .then_some(search.value.as_str());
+    search
+        .terms
+        .iter()
+        .filter(|term| term.term_type == SearchTermType::Fuzzy)
+        .map(|term| term.pattern.as_str())
+        .chain(value)
+}
+
+/// Char indices of `name` matched by the fuzzy terms of `search`, sorted and
+/// without duplicates, for highlighting.
+pub fn fuzzy_highlights(search: &Search, name: &str) -> Vec<usize> {
+    let mut indices: Vec<usize> = fuzzy_patterns(search)
+        .flat_map(|pattern| {
+            let term = FuzzyTerm::new(pattern, search.case_sensitive);
+            term.matcher
+                .fuzzy_indices(name, &term.pattern)
+                .map(|(_, indices)| indices)
+                .unwrap_or_default()
+        })
+        .collect();
+    indices.sort_unstable();
+    indices.dedup();
+    indices
 }
 
 fn contains_ignore_ascii_case(name: &str, pattern: &str) -> bool {
@@ -548,7 +835,7 @@ fn compile_search(search: Option<&Search>) -> (bool, Option<CompiledSearch>) {
                 .iter()
                 .filter_map(|st| compile_term(&st.pattern, st.term_type, case_sensitive))
                 .collect()
-        } else if !s.value.is_empty() {
+        } else if !s.value.is_empty() && !s.searches_contents() {
             compile_term(&s.value, s.term_type, case_sensitive)
                 .into_iter()
                 .collect()
@@ -562,7 +849,7 @@ fn compile_search(search: Option<&Search>) -> (bool, Option<CompiledSearch>) {
             .terms
             .iter()
             .any(|st| st.term_type == SearchTermType::Plain)
-            || (s.terms.is_empty() && s.term_type == SearchTermType::Plain);
+            || (s.terms.is_empty() && !s.searches_contents() && s.term_type == SearchTermType::Plain);
         Some(CompiledSearch {
             terms,
             mode: s.match_mode,
@@ -590,12 +877,15 @@ pub fn filter_visible_entries(
             continue;
         }
         if let Some(ref cs) = compiled_search
-            && !cs.matches(name, case_sensitive, collator.as_ref())
+            && !cs.matches(name, &entry.meta, case_sensitive, collator.as_ref())
         {
             continue;
         }
         visible.push(i);
     }
+    rank_by_relevance(&mut visible, search, compiled_search.as_ref(), |i| {
+        entries[i].get_splitted_path().1
+    });
     visible
 }
 
@@ -617,12 +907,15 @@ pub fn filter_visible_dir_list(
             continue;
         }
         if let Some(ref cs) = compiled_search
-            && !cs.matches(name, case_sensitive, collator.as_ref())
+            && !cs.matches(name, &entry.meta, case_sensitive, collator.as_ref())
         {
             continue;
         }
         visible.push(i);
     }
+    rank_by_relevance(&mut visible, search, compiled_search.as_ref(), |i| {
+        dir_list.entries[i].file_name.as_str()
+    });
     visible
 }
 
@@ -719,6 +1012,7 @@ use rayon::{
     use crate::data::files::{DirEntry, DirList};
 
     use super::{Search, filter_visible_dir_list, filter_visible_entries};
+    use crate::app::test_support::unique_test_dir;
 
     #[test]
     fn filter_visible_entries_includes_all_entries_uniformly() {
@@ -766,6 +1060,85 @@ use rayon::{
         assert!(visible.contains(&2), "root-level notes.txt should match");
     }
 
+    #[test]
+    fn metadata_terms_combine_with_match_mode() {
+        use crate::app::{MatchMode, SearchTerm, SearchTermType};
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .expect("clock after epoch")
+            .as_secs() as u32;
+        let entries = vec![
+            entry_with("big.png", 20 << 20, now - 3600, now - 3600),
+            entry_with("old.png", 100, now - 400 * 86_400, now - 400 * 86_400),
+            entry_with("recent.txt", 100, now - 60, now - 60),
+        ];
+        let term = |pattern: &str| SearchTerm {
+            pattern: pattern.to_string(),
+            term_type: SearchTermType::Metadata,
+        };
+        let mut search = Search {
+            terms: vec![term("size>10MB"), term("modified<1d")],
+            ..Default::default()
+        };
+        let visible = filter_visible_entries(&entries, true, Some(&search));
+        assert_eq!(visible, vec![0], "AND needs both predicates");
+
+        search.match_mode = MatchMode::Any;
+        let visible = filter_visible_entries(&entries, true, Some(&search));
+        assert_eq!(visible, vec![0, 2], "OR needs either predicate");
+
+        search.terms = vec![
+            term("ext:png"),
+            SearchTerm {
+                pattern: "old".to_string(),
+                term_type: SearchTermType::Plain,
+            },
+        ];
+        search.match_mode = MatchMode::All;
+        let visible = filter_visible_entries(&entries, true, Some(&search));
+        assert_eq!(visible, vec![1], "metadata and name terms mix");
+
+        let search = Search {
+            value: "modified>1y".to_string(),
+            term_type: SearchTermType::Metadata,
+            in_contents: true,
+            ..Default::default()
+        };
+        assert!(!search.searches_contents());
+        let visible = filter_visible_entries(&entries, true, Some(&search));
+        assert_eq!(visible, vec![1], "metadata values filter even in content mode");
+    }
+
+    #[test]
+    fn fuzzy_terms_rank_by_relevance_and_highlight() {
+        use crate::app::SearchTermType;
+        let entries = vec![
+            entry_with("my_project_notes.md", 1, 0, 0),
+            entry_with("report.txt", 1, 0, 0),
+            entry_with("mpn.md", 1, 0, 0),
+        ];
+        let mut search = Search {
+            value: "mpn".to_string(),
+            term_type: SearchTermType::Fuzzy,
+            ..Default::default()
+        };
+        let visible = filter_visible_entries(&entries, true, Some(&search));
+        assert_eq!(visible, vec![0, 2], "without relevance the order stays");
+
+        search.sort_by_relevance = true;
+        let visible = filter_visible_entries(&entries, true, Some(&search));
+        assert_eq!(visible, vec![2, 0], "the tighter match ranks first");
+
+        assert!(uses_fuzzy(&search));
+        assert_eq!(fuzzy_highlights(&search, "mpn.md"), vec![0, 1, 2]);
+        assert!(fuzzy_highlights(&search, "report.txt").is_empty());
+
+        search.case_sensitive = true;
+        search.value = "MPN".to_string();
+        let visible = filter_visible_entries(&entries, true, Some(&search));
+        assert!(visible.is_empty(), "case sensitive fuzzy respects case");
+    }
+
     #[test]
     fn filter_visible_entries_hides_hidden_files() {
         let entries = vec![
@@ -824,17 +1197,46 @@ use rayon::{
         assert_eq!(list.len(), 2, "should have 2 unique entries");
     }
 
-    fn unique_test_dir(name: &str) -> std::path::PathBuf {
-        let dir = std::env::temp_dir().join(format!(
-            "lwa_fm_{name}_{}_{}",
-            std::process::id(),
-            std::time::SystemTime::now()
-                .duration_since(std::time::UNIX_EPOCH)
-                .expect("system time before unix epoch")
-                .as_nanos()
-        ));
-        std::fs::create_dir_all(&dir).expect("create temp test dir");
-        dir
+    #[test]
+    fn walk_filter_prunes_ignored_and_excluded_folders() {
+        let dir = unique_test_dir("walk_filter");
+        for sub in ["src", "target/debug", "node_modules/pkg"] {
+            std::fs::create_dir_all(dir.join(sub)).expect("create sub dir");
+        }
+        std::fs::write(dir.join(".ignore"), "target/\n").expect("write ignore file");
+        std::fs::write(dir.join("src/main.rs"), b"").expect("write file");
+        std::fs::write(dir.join("src/run.log"), b"").expect("write file");
+        std::fs::write(dir.join("target/debug/app"), b"").expect("write file");
+        std::fs::write(dir.join("node_modules/pkg/index.js"), b"").expect("write file");
+
+        let names = |search: &Search| -> Vec<String> {
+            let cancel = AtomicBool::new(false);
+            let filter = WalkFilter::new(Some(search));
+            let mut names: Vec<String> =
+                read_directory(std::slice::from_ref(&dir), 3, false, &filter, &cancel)
+                    .into_iter()
+                    .map(|entry| entry.file_name)
+                    .collect();
+            names.sort();
+            names
+        };
+        let mut search = Search {
+            depth: 3,
+            ..Default::default()
+        };
+        assert!(names(&search).contains(&"app".to_string()));
+
+        search.respect_ignore_files = true;
+        search.exclude_globs = vec!["node_modules".to_string(), "*.log".to_string()];
+        assert_eq!(names(&search), vec!["main.rs", "src"]);
+
+        search.respect_ignore_files = false;
+        search.exclude_globs = vec!["src/*.rs".to_string()];
+        let found = names(&search);
+        assert!(!found.contains(&"main.rs".to_string()), "relative globs exclude");
+        assert!(found.contains(&"run.log".to_string()));
+
+        let _ = std::fs::remove_dir_all(dir);
     }
 
     #[test]
@@ -905,6 +1307,7 @@ use rayon::{
             sorting,
             display_type: super::super::DisplayType::default(),
             invert_sort: invert,
+            checksum_column: None,
         }
     }
 
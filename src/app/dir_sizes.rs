@@ -0,0 +1,179 @@
+//! Recursive sizes of the folders shown in the file lists.
+//!
+//! A folder is measured in the background the first time its size is asked
+//! for, from the folder listings [`disk_usage`] keeps in the sled dir
+//! database. Those are checked against the modification time of the folder
+//! and of each of its files, so only what changed since is read again, also
+//! across runs. The result is kept in memory until a watcher event or a file
+//! operation touches the folder or anything below it, which also drops the
+//! stored listings of the folders touched.
+
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::{
+        LazyLock, Mutex,
+        atomic::{AtomicBool, AtomicU64, Ordering},
+    },
+};
+
+use egui::Context;
+use rayon::ThreadPoolBuilder;
+
+use super::disk_usage::{self, DirSize};
+
+/// Kept apart from the other pools so measuring a large tree never stalls
+/// listings or file operations.
+static MEASURE_POOL: LazyLock<rayon::ThreadPool> = LazyLock::new(|| {
+    ThreadPoolBuilder::new()
+        .num_threads(2)
+        .thread_name(|i| format!("lwa_fm_sizes_{i}"))
+        .build()
+        .expect("Failed to create folder size thread pool")
+});
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Measured {
+    /// Being measured by the request with this number.
+    Pending(u64),
+    Done(DirSize),
+}
+
+impl Measured {
+    const fn size(self) -> Option<DirSize> {
+        match self {
+            Self::Pending(_) => None,
+            Self::Done(size) => Some(size),
+        }
+    }
+}
+
+/// Sizes asked for this session.
+static SIZES: LazyLock<Mutex<HashMap<PathBuf, Measured>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+static NEXT_REQUEST: AtomicU64 = AtomicU64::new(1);
+
+/// Whether a size came in since [`take_changed`] was last called.
+static CHANGED: AtomicBool = AtomicBool::new(false);
+
+/// The size of `dir` if known, without measuring it.
+pub fn cached(dir: &Path) -> Option<DirSize> {
+    SIZES
+        .lock()
+        .ok()
+        .and_then(|sizes| sizes.get(dir).copied())
+        .and_then(Measured::size)
+}
+
+/// The size of `dir`, measuring it in the background and repainting `ctx`
+/// once done when it isn't known yet.
+pub fn get(dir: &Path, ctx: &Context) -> Option<DirSize> {
+    let Ok(mut sizes) = SIZES.lock() else {
+        return None;
+    };
+    if let Some(known) = sizes.get(dir) {
+        return known.size();
+    }
+    let request = NEXT_REQUEST.fetch_add(1, Ordering::Relaxed);
+    sizes.insert(dir.to_path_buf(), Measured::Pending(request));
+    drop(sizes);
+
+    let dir = dir.to_path_buf();
+    let ctx = ctx.clone();
+    MEASURE_POOL.spawn(move || {
+        let Some(size) = disk_usage::measure(&dir, true, &AtomicBool::new(false)) else {
+            return;
+        };
+        let Ok(mut sizes) = SIZES.lock() else {
+            return;
+        };
+        // Invalidated while being measured, the result may be stale.
+        if sizes.get(&dir) != Some(&Measured::Pending(request)) {
+            return;
+        }
+        sizes.insert(dir, Measured::Done(size));
+        CHANGED.store(true, Ordering::Relaxed);
+        ctx.request_repaint();
+    });
+    None
+}
+
+/// Forgets the sizes of `dirs` and of every folder holding them, since
+/// those grow and shrink along.
+pub fn invalidate(dirs: impl IntoIterator<Item = PathBuf>) {
+    let dirs = dirs.into_iter().collect::<Vec<_>>();
+    disk_usage::forget(&dirs, false);
+    forget_ancestors(&dirs);
+}
+
+/// Like [`invalidate`], also forgetting every folder below `dirs` so the
+/// whole tree is measured again.
+pub fn invalidate_below(dirs: impl IntoIterator<Item = PathBuf>) {
+    let dirs = dirs.into_iter().collect::<Vec<_>>();
+    disk_usage::forget(&dirs, true);
+    if let Ok(mut sizes) = SIZES.lock() {
+        sizes.retain(|path, _| !dirs.iter().any(|dir| path.starts_with(dir)));
+    }
+    forget_ancestors(&dirs);
+}
+
+fn forget_ancestors(dirs: &[PathBuf]) {
+    let Ok(mut sizes) = SIZES.lock() else {
+        return;
+    };
+    for dir in dirs {
+        for ancestor in dir.ancestors() {
+            sizes.remove(ancestor);
+        }
+    }
+}
+
+/// Whether a folder size came in since the last call.
+pub fn take_changed() -> bool {
+    CHANGED.swap(false, Ordering::Relaxed)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, time::Duration};
+
+    use super::{cached, get, invalidate, invalidate_below, take_changed};
+    use crate::app::test_support::unique_test_dir;
+
+    fn wait_for(dir: &std::path::Path) -> u64 {
+        for _ in 0..500 {
+            if let Some(size) = cached(dir) {
+                return size.size;
+            }
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        panic!("{} was not measured", dir.display());
+    }
+
+    #[test]
+    fn sizes_are_measured_and_invalidated_with_their_ancestors() {
+        let dir = unique_test_dir("dir_sizes");
+        let inner = dir.join("a/b");
+        fs::create_dir_all(&inner).expect("create dirs");
+        fs::write(inner.join("file"), [0; 100]).expect("write");
+        let ctx = egui::Context::default();
+
+        assert_eq!(get(&dir, &ctx), None);
+        assert_eq!(wait_for(&dir), 100);
+        assert!(take_changed());
+        assert_eq!(get(&dir, &ctx).map(|size| size.items), Some(1));
+
+        fs::write(inner.join("file"), [0; 40]).expect("write");
+        invalidate([inner]);
+        assert_eq!(cached(&dir), None);
+        assert_eq!(get(&dir, &ctx), None);
+        assert_eq!(wait_for(&dir), 40);
+
+        assert_eq!(get(&dir.join("a"), &ctx), None);
+        assert_eq!(wait_for(&dir.join("a")), 40);
+        invalidate_below([dir.clone()]);
+        assert_eq!(cached(&dir.join("a")), None);
+        let _ = fs::remove_dir_all(dir);
+    }
+}
@@ -7,6 +7,8 @@ pub struct DirectoryViewSettings {
     pub sorting: super::Sort,
     pub display_type: super::DisplayType,
     pub invert_sort: bool,
+    /// Checksum shown in an extra column of the list view.
+    pub checksum_column: Option<super::checksums::ChecksumKind>,
 }
 
 impl DirectoryViewSettings {
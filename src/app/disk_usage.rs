@@ -0,0 +1,571 @@
+//! Measuring how much space a folder tree takes.
+//!
+//! The listing of every scanned folder, the sizes and modification times of
+//! its files and the names of its subfolders, is kept in a sled tree. It is
+//! reused for as long as the folder's modification time stays the same and
+//! none of its files changed size or modification time, which a file growing
+//! in place does without touching its folder. Scanning a tree again then
+//! only has to check its files instead of listing every folder.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::{
+        LazyLock,
+        atomic::{AtomicBool, Ordering},
+    },
+    time::UNIX_EPOCH,
+};
+
+use bincode::{Decode, Encode, config};
+use egui::{Rect, pos2, vec2};
+
+use super::database::{SLED_DIRS, cache_key, keys_below};
+use crate::helper::format_bytes_simple;
+
+const TREE_NAME: &[u8] = b"disk_usage_v1";
+/// How many scanned folders pass between progress updates.
+const PROGRESS_INTERVAL: usize = 256;
+
+static LISTINGS: LazyLock<Option<sled::Tree>> = LazyLock::new(|| {
+    SLED_DIRS
+        .open_tree(TREE_NAME)
+        .map_err(|err| log::warn!("failed to open disk usage tree: {err}"))
+        .ok()
+});
+
+/// A file, or a folder with everything below it.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct UsageNode {
+    pub name: String,
+    /// Size of the file, or of all the files below the folder.
+    pub size: u64,
+    /// Number of files below the folder, 1 for a file.
+    pub files: u64,
+    pub is_dir: bool,
+    /// Largest first.
+    pub children: Vec<Self>,
+}
+
+impl UsageNode {
+    fn file(name: String, size: u64) -> Self {
+        Self {
+            name,
+            size,
+            files: 1,
+            is_dir: false,
+            children: Vec::new(),
+        }
+    }
+
+    fn dir(name: String, mut children: Vec<Self>) -> Self {
+        children.sort_by(Self::largest_first);
+        Self {
+            name,
+            size: children.iter().map(|child| child.size).sum(),
+            files: children.iter().map(|child| child.files).sum(),
+            is_dir: true,
+            children,
+        }
+    }
+
+    fn largest_first(a: &Self, b: &Self) -> std::cmp::Ordering {
+        b.size.cmp(&a.size).then_with(|| a.name.cmp(&b.name))
+    }
+
+    /// The entry reached by following `names` down from this one.
+    pub fn descendant(&self, names: &[String]) -> Option<&Self> {
+        names.iter().try_fold(self, |node, name| {
+            node.children.iter().find(|child| &child.name == name)
+        })
+    }
+
+    /// Takes out the entry reached by following `names`, shrinking every
+    /// folder on the way to it.
+    pub fn remove(&mut self, names: &[String]) -> Option<Self> {
+        let (name, rest) = names.split_first()?;
+        let index = self.children.iter().position(|child| &child.name == name)?;
+        let removed = if rest.is_empty() {
+            self.children.remove(index)
+        } else {
+            self.children[index].remove(rest)?
+        };
+        self.size = self.size.saturating_sub(removed.size);
+        self.files = self.files.saturating_sub(removed.files);
+        self.children.sort_by(Self::largest_first);
+        Some(removed)
+    }
+}
+
+/// Totals of a folder, without the entries they were added up from.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Encode, Decode)]
+pub struct DirSize {
+    /// Size of all the files below the folder.
+    pub size: u64,
+    /// Number of files below the folder.
+    pub files: u64,
+    /// Number of files and folders right inside the folder.
+    pub items: u64,
+}
+
+/// What a folder holds directly.
+#[derive(Debug, Clone, Default, Encode, Decode)]
+struct DirListing {
+    /// Modification time of the folder when it was read, in nanoseconds.
+    modified: u128,
+    /// Name, size and modification time in nanoseconds of every file.
+    files: Vec<(String, u64, u128)>,
+    dirs: Vec<String>,
+}
+
+impl DirListing {
+    /// Symbolic links and special files are left out, they take no space of
+    /// their own.
+    fn read(dir: &Path, modified: u128) -> Option<Self> {
+        let mut listing = Self {
+            modified,
+            ..Self::default()
+        };
+        for entry in fs::read_dir(dir).ok()?.flatten() {
+            let Ok(file_type) = entry.file_type() else {
+                continue;
+            };
+            let name = entry.file_name().to_string_lossy().to_string();
+            if file_type.is_dir() {
+                listing.dirs.push(name);
+            } else if file_type.is_file() {
+                let meta = entry.metadata().ok();
+                let size = meta.as_ref().map_or(0, fs::Metadata::len);
+                let modified = meta.as_ref().and_then(metadata_nanos).unwrap_or_default();
+                listing.files.push((name, size, modified));
+            }
+        }
+        Some(listing)
+    }
+
+    /// Whether every file of the listing still has the size and
+    /// modification time it was read with.
+    fn files_unchanged(&self, dir: &Path) -> bool {
+        self.files.iter().all(|(name, size, modified)| {
+            fs::symlink_metadata(dir.join(name)).is_ok_and(|meta| {
+                meta.is_file() && meta.len() == *size && metadata_nanos(&meta) == Some(*modified)
+            })
+        })
+    }
+}
+
+fn metadata_nanos(meta: &fs::Metadata) -> Option<u128> {
+    let modified = meta.modified().ok()?;
+    Some(modified.duration_since(UNIX_EPOCH).ok()?.as_nanos())
+}
+
+fn modified_nanos(dir: &Path) -> Option<u128> {
+    metadata_nanos(&fs::symlink_metadata(dir).ok()?)
+}
+
+struct Scanner<'a> {
+    /// Whether listings stored by an earlier scan may be used, and new ones
+    /// stored.
+    reuse_cache: bool,
+    cancel: &'a AtomicBool,
+    progress: &'a dyn Fn(String),
+    dirs: usize,
+    bytes: u64,
+}
+
+impl Scanner<'_> {
+    fn listing(&self, dir: &Path) -> Option<DirListing> {
+        let modified = modified_nanos(dir)?;
+        let key = cache_key(dir);
+        let cache = LISTINGS.as_ref().filter(|_| self.reuse_cache);
+        if let Some(cache) = cache
+            && let Ok(Some(data)) = cache.get(&key)
+            && let Ok((listing, _)) =
+                bincode::decode_from_slice::<DirListing, _>(&data, config::standard())
+            && listing.modified == modified
+            && listing.files_unchanged(dir)
+        {
+            return Some(listing);
+        }
+        let listing = DirListing::read(dir, modified)?;
+        if let Some(cache) = cache
+            && let Ok(data) = bincode::encode_to_vec(&listing, config::standard())
+            && let Err(err) = cache.insert(key, data)
+        {
+            log::warn!("failed to store disk usage of {}: {err}", dir.display());
+        }
+        Some(listing)
+    }
+
+    /// `None` once the scan is cancelled.
+    fn dir(&mut self, dir: &Path, name: String) -> Option<UsageNode> {
+        if self.cancel.load(Ordering::Relaxed) {
+            return None;
+        }
+        if self.dirs.is_multiple_of(PROGRESS_INTERVAL) {
+            (self.progress)(format!(
+                "Scanned {} folders, {}…",
+                self.dirs,
+                format_bytes_simple(self.bytes)
+            ));
+        }
+        self.dirs += 1;
+        let Some(listing) = self.listing(dir) else {
+            log::warn!("Failed to read {}", dir.display());
+            return Some(UsageNode::dir(name, Vec::new()));
+        };
+        let mut children = Vec::with_capacity(listing.files.len() + listing.dirs.len());
+        for (name, size, _) in listing.files {
+            self.bytes += size;
+            children.push(UsageNode::file(name, size));
+        }
+        for name in listing.dirs {
+            children.push(self.dir(&dir.join(&name), name)?);
+        }
+        Some(UsageNode::dir(name, children))
+    }
+
+    /// Like [`Self::dir`], adding up the sizes instead of keeping them.
+    fn total(&mut self, dir: &Path) -> Option<DirSize> {
+        if self.cancel.load(Ordering::Relaxed) {
+            return None;
+        }
+        let Some(listing) = self.listing(dir) else {
+            return Some(DirSize::default());
+        };
+        let mut total = DirSize {
+            size: listing.files.iter().map(|(_, size, _)| size).sum(),
+            files: listing.files.len() as u64,
+            items: (listing.files.len() + listing.dirs.len()) as u64,
+        };
+        for name in &listing.dirs {
+            let below = self.total(&dir.join(name))?;
+            total.size += below.size;
+            total.files += below.files;
+        }
+        Some(total)
+    }
+}
+
+/// Stored listings of `dirs` are read again on the next scan, even when
+/// the folder looks unchanged. With `below` the same goes for every folder
+/// under them.
+pub fn forget(dirs: &[PathBuf], below: bool) {
+    let Some(cache) = LISTINGS.as_ref() else {
+        return;
+    };
+    for dir in dirs {
+        if below {
+            for key in keys_below(cache, dir) {
+                let _ = cache.remove(key);
+            }
+        } else {
+            let _ = cache.remove(cache_key(dir));
+        }
+    }
+}
+
+/// Everything below `root`, or `None` once `cancel` is set. With
+/// `reuse_cache` unset every folder is read again and nothing is stored.
+pub fn scan(
+    root: &Path,
+    reuse_cache: bool,
+    cancel: &AtomicBool,
+    progress: &dyn Fn(String),
+) -> Option<UsageNode> {
+    let mut scanner = Scanner {
+        reuse_cache,
+        cancel,
+        progress,
+        dirs: 0,
+        bytes: 0,
+    };
+    let name = root.file_name().map_or_else(
+        || root.display().to_string(),
+        |name| name.to_string_lossy().to_string(),
+    );
+    scanner.dir(root, name)
+}
+
+/// Totals of `dir`, or `None` once `cancel` is set. `reuse_cache` works as
+/// in [`scan`].
+pub fn measure(dir: &Path, reuse_cache: bool, cancel: &AtomicBool) -> Option<DirSize> {
+    Scanner {
+        reuse_cache,
+        cancel,
+        progress: &|_| {},
+        dirs: 0,
+        bytes: 0,
+    }
+    .total(dir)
+}
+
+/// Splits `rect` into one rectangle per size, each with an area in
+/// proportion to it and as close to a square as the rest allow. Sizes are
+/// expected largest first.
+pub fn treemap(sizes: &[u64], rect: Rect) -> Vec<Rect> {
+    let total = sizes.iter().sum::<u64>() as f64;
+    let mut rects = Vec::with_capacity(sizes.len());
+    if total <= 0.0 || rect.area() <= 0.0 {
+        rects.resize(sizes.len(), Rect::from_min_size(rect.min, vec2(0.0, 0.0)));
+        return rects;
+    }
+    let scale = f64::from(rect.area()) / total;
+    let areas = sizes
+        .iter()
+        .map(|&size| (size as f64 * scale) as f32)
+        .collect::<Vec<_>>();
+    let mut free = rect;
+    let mut start = 0;
+    while start < areas.len() {
+        let side = free.width().min(free.height());
+        let mut end = start + 1;
+        while end < areas.len()
+            && worst_ratio(&areas[start..=end], side) <= worst_ratio(&areas[start..end], side)
+        {
+            end += 1;
+        }
+        free = lay_out_row(&areas[start..end], free, &mut rects);
+        start = end;
+    }
+    rects
+}
+
+/// The most elongated aspect ratio among `row` laid out along `side`.
+fn worst_ratio(row: &[f32], side: f32) -> f32 {
+    let sum = row.iter().sum::<f32>();
+    let max = row.iter().copied().fold(0.0, f32::max);
+    let min = row.iter().copied().fold(f32::INFINITY, f32::min);
+    if sum <= 0.0 || min <= 0.0 {
+        return f32::INFINITY;
+    }
+    let side = side * side;
+    let sum = sum * sum;
+    (side * max / sum).max(sum / (side * min))
+}
+
+/// Lays `row` out along the shorter side of `free`, giving back what is
+/// left of it.
+fn lay_out_row(row: &[f32], free: Rect, rects: &mut Vec<Rect>) -> Rect {
+    let sum = row.iter().sum::<f32>();
+    if sum <= 0.0 {
+        rects.extend(
+            row.iter()
+                .map(|_| Rect::from_min_size(free.min, vec2(0.0, 0.0))),
+        );
+        return free;
+    }
+    if free.width() >= free.height() {
+        let width = (sum / free.height()).min(free.width());
+        let mut y = free.top();
+        for area in row {
+            let height = area / width;
+            rects.push(Rect::from_min_size(
+                pos2(free.left(), y),
+                vec2(width, height),
+            ));
+            y += height;
+        }
+        Rect::from_min_max(pos2(free.left() + width, free.top()), free.max)
+    } else {
+        let height = (sum / free.width()).min(free.height());
+        let mut x = free.left();
+        for area in row {
+            let width = area / height;
+            rects.push(Rect::from_min_size(
+                pos2(x, free.top()),
+                vec2(width, height),
+            ));
+            x += width;
+        }
+        Rect::from_min_max(pos2(free.left(), free.top() + height), free.max)
+    }
+}
+
+/// One ring segment of a sunburst chart.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SunburstSegment {
+    /// Names leading to the entry from the chart's center.
+    pub names: Vec<String>,
+    /// Ring the segment is on, 0 being the innermost.
+    pub depth: usize,
+    /// Where the segment starts and ends, as fractions of a full turn.
+    pub start: f32,
+    pub end: f32,
+    pub is_dir: bool,
+}
+
+/// Segments of the entries below `node`, `rings` levels deep. Segments
+/// narrower than `min_span` of a turn are left out along with what is
+/// below them.
+pub fn sunburst(node: &UsageNode, rings: usize, min_span: f32) -> Vec<SunburstSegment> {
+    fn walk(
+        node: &UsageNode,
+        names: &[String],
+        start: f32,
+        span: f32,
+        rings: usize,
+        min_span: f32,
+        segments: &mut Vec<SunburstSegment>,
+    ) {
+        if node.size == 0 || names.len() >= rings {
+            return;
+        }
+        let mut start = start;
+        for child in &node.children {
+            let child_span = span * (child.size as f64 / node.size as f64) as f32;
+            if child_span < min_span {
+                // Children are sorted, the rest are narrower still.
+                break;
+            }
+            let mut child_names = names.to_vec();
+            child_names.push(child.name.clone());
+            if child.is_dir {
+                walk(
+                    child,
+                    &child_names,
+                    start,
+                    child_span,
+                    rings,
+                    min_span,
+                    segments,
+                );
+            }
+            segments.push(SunburstSegment {
+                depth: names.len(),
+                names: child_names,
+                start,
+                end: start + child_span,
+                is_dir: child.is_dir,
+            });
+            start += child_span;
+        }
+    }
+
+    let mut segments = Vec::new();
+    walk(node, &[], 0.0, 1.0, rings, min_span, &mut segments);
+    segments
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, sync::atomic::AtomicBool};
+
+    use egui::{Rect, pos2};
+
+    use super::{UsageNode, forget, measure, scan, sunburst, treemap};
+    use crate::app::test_support::unique_test_dir;
+
+    fn names(node: &UsageNode) -> Vec<&str> {
+        node.children
+            .iter()
+            .map(|child| child.name.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn trees_are_measured_largest_first() {
+        let dir = unique_test_dir("disk_usage_scan");
+        fs::create_dir_all(dir.join("big/inner")).expect("create dirs");
+        fs::create_dir_all(dir.join("empty")).expect("create dirs");
+        fs::write(dir.join("big/inner/a"), [0; 300]).expect("write");
+        fs::write(dir.join("big/b"), [0; 100]).expect("write");
+        fs::write(dir.join("small"), [0; 50]).expect("write");
+
+        let cancel = AtomicBool::new(false);
+        let mut root = scan(&dir, true, &cancel, &|_| {}).expect("scan");
+        assert_eq!((root.size, root.files), (450, 3));
+        assert_eq!(names(&root), ["big", "small", "empty"]);
+        let big = &root.children[0];
+        assert_eq!((big.size, big.files, big.is_dir), (400, 2, true));
+        assert_eq!(names(big), ["inner", "b"]);
+
+        // A new file changes the folder, so the stored listing is not reused.
+        fs::write(dir.join("empty/c"), [0; 500]).expect("write");
+        let again = scan(&dir, true, &cancel, &|_| {}).expect("scan");
+        assert_eq!(again.size, 950);
+        assert_eq!(names(&again), ["empty", "big", "small"]);
+
+        let removed = root
+            .remove(&["big".to_string(), "inner".to_string()])
+            .expect("remove");
+        assert_eq!(removed.size, 300);
+        assert_eq!((root.size, root.files), (150, 2));
+        assert_eq!(names(&root), ["big", "small", "empty"]);
+        assert_eq!(
+            root.descendant(&["big".to_string()]).map(|n| n.size),
+            Some(100)
+        );
+
+        let total = measure(&dir, true, &cancel).expect("measure");
+        assert_eq!((total.size, total.files, total.items), (950, 4, 3));
+        // A file that grows leaves its folder alone, but not its own stamp.
+        fs::write(dir.join("small"), [0; 60]).expect("write");
+        assert_eq!(
+            measure(&dir, true, &cancel).map(|total| total.size),
+            Some(960)
+        );
+        assert_eq!(
+            measure(&dir, false, &cancel).map(|total| total.size),
+            Some(960)
+        );
+        fs::write(dir.join("small"), [0; 70]).expect("write");
+        forget(std::slice::from_ref(&dir), false);
+        assert_eq!(
+            measure(&dir, true, &cancel).map(|total| total.size),
+            Some(970)
+        );
+
+        assert!(scan(&dir, true, &AtomicBool::new(true), &|_| {}).is_none());
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn treemap_areas_follow_sizes() {
+        let rect = Rect::from_min_max(pos2(0.0, 0.0), pos2(200.0, 100.0));
+        let rects = treemap(&[100, 50, 25, 25], rect);
+        assert_eq!(rects.len(), 4);
+        for (rect_item, expected) in rects.iter().zip([10_000.0, 5_000.0, 2_500.0, 2_500.0]) {
+            assert!((rect_item.area() - expected).abs() < 1.0);
+            assert!(rect.expand(0.01).contains_rect(*rect_item));
+        }
+        // Equal sizes in a square come out as squares.
+        let square = Rect::from_min_max(pos2(0.0, 0.0), pos2(100.0, 100.0));
+        for rect in treemap(&[1, 1, 1, 1], square) {
+            assert!((rect.width() - rect.height()).abs() < 0.01);
+        }
+        assert!(treemap(&[0, 0], rect).iter().all(|rect| rect.area() == 0.0));
+    }
+
+    #[test]
+    fn sunburst_spans_follow_sizes() {
+        let file = |name: &str, size| UsageNode {
+            name: name.to_string(),
+            size,
+            files: 1,
+            ..UsageNode::default()
+        };
+        let root = UsageNode::dir(
+            "root".to_string(),
+            vec![
+                UsageNode::dir("dir".to_string(), vec![file("a", 60), file("b", 15)]),
+                file("c", 24),
+                file("tiny", 1),
+            ],
+        );
+        let segments = sunburst(&root, 2, 0.05);
+        let expected = [
+            ("dir/a", 1, 0.0, 0.6),
+            ("dir/b", 1, 0.6, 0.75),
+            ("dir", 0, 0.0, 0.75),
+            ("c", 0, 0.75, 0.99),
+        ];
+        assert_eq!(segments.len(), expected.len());
+        for (segment, (names, depth, start, end)) in segments.iter().zip(expected) {
+            assert_eq!(segment.names.join("/"), names);
+            assert_eq!(segment.depth, depth);
+            assert!((segment.start - start).abs() < 1e-6);
+            assert!((segment.end - end).abs() < 1e-6);
+        }
+    }
+}
@@ -0,0 +1,525 @@
+use std::{
+    f32::consts::{FRAC_PI_2, TAU},
+    path::PathBuf,
+    sync::{
+        Arc, Mutex, OnceLock,
+        atomic::{AtomicBool, Ordering},
+    },
+};
+
+use egui::{
+    Align2, Color32, Context, FontId, Id, Mesh, Pos2, Rect, Sense, Shape, Stroke, Ui, ecolor::Hsva,
+    vec2,
+};
+
+use super::App;
+use super::commands::{ActionToPerform, ModalWindow, TabAction};
+use super::disk_usage::{self, UsageNode};
+use super::file_ops;
+use super::journal::{self, JournalOperation};
+use crate::helper::format_bytes_simple;
+use crate::toast;
+
+/// Rings of the sunburst chart around its center.
+const RINGS: usize = 4;
+/// Entries narrower than this part of a turn are left out of the sunburst.
+const MIN_SPAN: f32 = 0.003;
+/// Rows of the largest items list.
+const MAX_ROWS: usize = 200;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Chart {
+    Treemap,
+    Sunburst,
+}
+
+#[derive(Debug, Clone)]
+struct DiskUsageState {
+    root: PathBuf,
+    /// Latest progress message of the scan.
+    status: Arc<Mutex<String>>,
+    cancel: Arc<AtomicBool>,
+    /// Filled in once the scan is done.
+    scan: Arc<OnceLock<Arc<UsageNode>>>,
+    /// The scan result, minus entries trashed since.
+    tree: Option<Arc<UsageNode>>,
+    /// Names leading from the root to the folder shown.
+    current: Vec<String>,
+    /// Names leading from the root to the entry picked for an action.
+    selected: Option<Vec<String>>,
+    chart: Chart,
+}
+
+impl DiskUsageState {
+    /// Starts scanning `root` in the background.
+    fn scan(ctx: &Context, root: PathBuf, reuse_cache: bool) -> Self {
+        let status = Arc::new(Mutex::new(String::new()));
+        let cancel = Arc::new(AtomicBool::new(false));
+        let scan = Arc::new(OnceLock::new());
+        {
+            let status = Arc::clone(&status);
+            let cancel = Arc::clone(&cancel);
+            let scan = Arc::clone(&scan);
+            let root = root.clone();
+            let ctx = ctx.clone();
+            file_ops::spawn_task(move || {
+                let progress = |message: String| {
+                    if let Ok(mut status) = status.lock() {
+                        *status = message;
+                    }
+                    ctx.request_repaint();
+                };
+                if let Some(tree) = disk_usage::scan(&root, reuse_cache, &cancel, &progress) {
+                    let _ = scan.set(Arc::new(tree));
+                }
+                ctx.request_repaint();
+            });
+        }
+        Self {
+            root,
+            status,
+            cancel,
+            scan,
+            tree: None,
+            current: Vec::new(),
+            selected: None,
+            chart: Chart::Treemap,
+        }
+    }
+
+    fn path_of(&self, names: &[String]) -> PathBuf {
+        names
+            .iter()
+            .fold(self.root.clone(), |path, name| path.join(name))
+    }
+
+    fn open_folder(&mut self, names: Vec<String>) {
+        self.current = names;
+        self.selected = None;
+    }
+
+    /// Moves the selected entry to the trash and drops it from the tree.
+    fn trash_selected(&mut self) -> Option<PathBuf> {
+        let names = self.selected.take()?;
+        let path = self.path_of(&names);
+        if let Err(err) = trash::delete(&path) {
+            log::error!("Could not move {} to trash: {err}", path.display());
+            toast!(Error, "Could not move {} to trash: {err}", path.display());
+            return None;
+        }
+        journal::record(&JournalOperation::Trash(vec![path.clone()]));
+        if let Some(tree) = &mut self.tree
+            && let Some(removed) = Arc::make_mut(tree).remove(&names)
+        {
+            toast!(
+                Success,
+                "Moved {} to Trash, {} freed",
+                removed.name,
+                format_bytes_simple(removed.size)
+            );
+        }
+        Some(path)
+    }
+}
+
+fn state_id() -> Id {
+    Id::new(ModalWindow::DiskUsage)
+}
+
+/// Measures everything below `root` in the background and shows it once
+/// done.
+pub fn open(ctx: &Context, root: PathBuf) {
+    let state = DiskUsageState::scan(ctx, root, true);
+    ctx.data_mut(|d| d.insert_temp(state_id(), state));
+    ActionToPerform::ToggleModalWindow(ModalWindow::DiskUsage).schedule();
+}
+
+/// Fill color of an entry, `hue` being where it sits among its neighbours.
+fn color(hue: f32, depth: usize, is_dir: bool) -> Color32 {
+    let saturation = if is_dir { 0.55 } else { 0.25 };
+    let value = (depth as f32).mul_add(-0.1, 0.85).max(0.4);
+    Hsva::new(hue.fract(), saturation, value, 1.0).into()
+}
+
+fn describe(node: &UsageNode, total: u64) -> String {
+    let share = if total == 0 {
+        0.0
+    } else {
+        node.size as f64 / total as f64 * 100.0
+    };
+    if node.is_dir {
+        format!(
+            "{}\n{} ({share:.1}%), {} files",
+            node.name,
+            format_bytes_simple(node.size),
+            node.files
+        )
+    } else {
+        format!(
+            "{}\n{} ({share:.1}%)",
+            node.name,
+            format_bytes_simple(node.size)
+        )
+    }
+}
+
+/// What happened to an entry on a chart, named relative to the folder shown.
+enum ChartClick {
+    /// Picked for an action.
+    Select(Vec<String>),
+    Open(Vec<String>),
+    Up,
+}
+
+impl App {
+    /// What takes up space below a folder, as a chart and a list.
+    pub(crate) fn disk_usage_modal(&mut self, ctx: &Context) {
+        #[cfg(feature = "profiling")]
+        puffin::profile_scope!("lwa_fm::disk_usage_modal");
+        let Some(mut state) = ctx.data(|d| d.get_temp::<DiskUsageState>(state_id())) else {
+            self.display_modal = None;
+            return;
+        };
+        if state.tree.is_none() {
+            state.tree = state.scan.get().cloned();
+        }
+
+        let mut close = false;
+        let mut rescan = false;
+        let mut trashed = None;
+        let modal = egui::Modal::new(state_id()).show(ctx, |ui| {
+            ui.set_width(880.0);
+            ui.heading("Disk usage");
+            breadcrumbs_ui(ui, &mut state);
+            ui.separator();
+            let Some(tree) = state.tree.clone() else {
+                ui.horizontal(|ui| {
+                    ui.spinner();
+                    ui.label(state.status.lock().map(|s| s.clone()).unwrap_or_default());
+                });
+                if ui.button("Cancel").clicked() {
+                    ui.close();
+                }
+                return;
+            };
+            let Some(node) = tree.descendant(&state.current) else {
+                state.open_folder(Vec::new());
+                return;
+            };
+            ui.horizontal(|ui| {
+                ui.selectable_value(&mut state.chart, Chart::Treemap, "Treemap");
+                ui.selectable_value(&mut state.chart, Chart::Sunburst, "Sunburst");
+                ui.separator();
+                ui.label(format!(
+                    "{} in {} files",
+                    format_bytes_simple(node.size),
+                    node.files
+                ));
+            });
+            ui.horizontal_top(|ui| {
+                let size = vec2(540.0, 420.0);
+                let click = match state.chart {
+                    Chart::Treemap => treemap_ui(ui, node, size),
+                    Chart::Sunburst => sunburst_ui(ui, node, size),
+                };
+                match click {
+                    Some(ChartClick::Select(names)) => {
+                        state.selected = Some([state.current.clone(), names].concat());
+                    }
+                    Some(ChartClick::Open(names)) => {
+                        state.open_folder([state.current.clone(), names].concat());
+                    }
+                    Some(ChartClick::Up) => {
+                        let mut current = state.current.clone();
+                        current.pop();
+                        state.open_folder(current);
+                    }
+                    None => {}
+                }
+                ui.vertical(|ui| largest_items_ui(ui, &mut state, node));
+            });
+            ui.separator();
+            trashed = actions_ui(ui, &mut state, &mut close, &mut rescan);
+        });
+        if let Some(parent) = trashed.as_ref().and_then(|path| path.parent()) {
+            self.refresh_changed_dirs(vec![parent.to_path_buf()]);
+        }
+        if rescan {
+            let current = std::mem::take(&mut state.current);
+            let chart = state.chart;
+            state.cancel.store(true, Ordering::Relaxed);
+            state = DiskUsageState::scan(ctx, state.root, false);
+            state.current = current;
+            state.chart = chart;
+        }
+        if close || modal.should_close() {
+            state.cancel.store(true, Ordering::Relaxed);
+            ctx.data_mut(|d| d.remove::<DiskUsageState>(state_id()));
+            self.display_modal = None;
+        } else {
+            ctx.data_mut(|d| d.insert_temp(state_id(), state));
+        }
+    }
+}
+
+/// Actions on the selected entry and the whole scan. Gives back the path
+/// of an entry moved to the trash.
+fn actions_ui(
+    ui: &mut Ui,
+    state: &mut DiskUsageState,
+    close: &mut bool,
+    rescan: &mut bool,
+) -> Option<PathBuf> {
+    let mut trashed = None;
+    ui.horizontal(|ui| {
+        if let Some(names) = state.selected.clone() {
+            let path = state.path_of(&names);
+            if ui.button("Show").clicked()
+                && let Some(parent) = path.parent()
+            {
+                TabAction::ChangePaths(parent.to_path_buf().into()).schedule_active_tab();
+                TabAction::RevealEntry {
+                    path: path.clone(),
+                    rename: false,
+                }
+                .schedule_active_tab();
+                *close = true;
+            }
+            if ui.button("Move to Trash").clicked() {
+                trashed = state.trash_selected();
+            }
+            ui.label(path.display().to_string());
+        } else {
+            ui.weak("Click a file or right-click a folder to pick it");
+        }
+        ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+            if ui.button("Close").clicked() {
+                ui.close();
+            }
+            if ui
+                .button("Rescan")
+                .on_hover_text("Read every folder again")
+                .clicked()
+            {
+                *rescan = true;
+            }
+        });
+    });
+    trashed
+}
+
+fn breadcrumbs_ui(ui: &mut Ui, state: &mut DiskUsageState) {
+    ui.horizontal_wrapped(|ui| {
+        let mut open = ui
+            .small_button(state.root.display().to_string())
+            .clicked()
+            .then_some(0);
+        for (index, name) in state.current.iter().enumerate() {
+            ui.weak("›");
+            if ui.small_button(name).clicked() {
+                open = Some(index + 1);
+            }
+        }
+        if let Some(depth) = open {
+            let mut current = state.current.clone();
+            current.truncate(depth);
+            state.open_folder(current);
+        }
+    });
+}
+
+fn chart_click(response: &egui::Response, names: Vec<String>, is_dir: bool) -> Option<ChartClick> {
+    if response.secondary_clicked() || (response.clicked() && !is_dir) {
+        Some(ChartClick::Select(names))
+    } else if response.clicked() {
+        Some(ChartClick::Open(names))
+    } else {
+        None
+    }
+}
+
+fn treemap_ui(ui: &mut Ui, node: &UsageNode, size: egui::Vec2) -> Option<ChartClick> {
+    let (response, painter) = ui.allocate_painter(size, Sense::click());
+    let children = node
+        .children
+        .iter()
+        .filter(|child| child.size > 0)
+        .collect::<Vec<_>>();
+    if children.is_empty() {
+        painter.text(
+            response.rect.center(),
+            Align2::CENTER_CENTER,
+            "Nothing takes up space here",
+            FontId::proportional(14.0),
+            ui.visuals().weak_text_color(),
+        );
+        return None;
+    }
+    let sizes = children.iter().map(|child| child.size).collect::<Vec<_>>();
+    let rects = disk_usage::treemap(&sizes, response.rect);
+    let hover = response.hover_pos();
+    let mut hovered = None;
+    for (index, (child, rect)) in children.iter().zip(&rects).enumerate() {
+        let fill = color(index as f32 * 0.618, 0, child.is_dir);
+        let is_hovered = hover.is_some_and(|pos| rect.contains(pos));
+        if is_hovered {
+            hovered = Some(*child);
+        }
+        painter.rect_filled(rect.shrink(1.0), 2.0, fill);
+        if is_hovered {
+            painter.rect_stroke(
+                rect.shrink(1.0),
+                2.0,
+                Stroke::new(2.0, ui.visuals().strong_text_color()),
+                egui::StrokeKind::Inside,
+            );
+        }
+        if rect.width() > 40.0 && rect.height() > 18.0 {
+            painter.with_clip_rect(rect.shrink(2.0)).text(
+                rect.left_top() + vec2(4.0, 3.0),
+                Align2::LEFT_TOP,
+                format!("{}\n{}", child.name, format_bytes_simple(child.size)),
+                FontId::proportional(12.0),
+                Color32::BLACK,
+            );
+        }
+    }
+    let child = hovered?;
+    let click = chart_click(&response, vec![child.name.clone()], child.is_dir);
+    response.on_hover_text_at_pointer(describe(child, node.size));
+    click
+}
+
+/// A ring between `radii`, from `start` to `end` of a turn.
+fn ring_segment(center: Pos2, radii: (f32, f32), start: f32, end: f32, fill: Color32) -> Shape {
+    let steps = ((end - start) * 128.0).ceil().max(1.0) as u32;
+    let mut mesh = Mesh::default();
+    for step in 0..=steps {
+        let angle = (start + (end - start) * step as f32 / steps as f32).mul_add(TAU, -FRAC_PI_2);
+        let direction = vec2(angle.cos(), angle.sin());
+        mesh.colored_vertex(center + direction * radii.0, fill);
+        mesh.colored_vertex(center + direction * radii.1, fill);
+        if step > 0 {
+            let index = step * 2;
+            mesh.add_triangle(index - 2, index - 1, index);
+            mesh.add_triangle(index - 1, index, index + 1);
+        }
+    }
+    Shape::mesh(mesh)
+}
+
+fn sunburst_ui(ui: &mut Ui, node: &UsageNode, size: egui::Vec2) -> Option<ChartClick> {
+    let (response, painter) = ui.allocate_painter(size, Sense::click());
+    let rect: Rect = response.rect;
+    let center = rect.center();
+    let radius = rect.width().min(rect.height()) / 2.0 - 4.0;
+    let hole = radius * 0.2;
+    let ring = (radius - hole) / RINGS as f32;
+    let segments = disk_usage::sunburst(node, RINGS, MIN_SPAN);
+
+    // The ring and the fraction of a turn under the pointer.
+    let hover = response.hover_pos().map(|pos| {
+        let offset = pos - center;
+        let turn = (offset.y.atan2(offset.x) + FRAC_PI_2) / TAU;
+        (offset.length(), turn.rem_euclid(1.0))
+    });
+    let hovered = hover.and_then(|(distance, turn)| {
+        if distance < hole {
+            return None;
+        }
+        let depth = ((distance - hole) / ring) as usize;
+        segments
+            .iter()
+            .find(|segment| segment.depth == depth && segment.start <= turn && turn < segment.end)
+    });
+
+    let background = ui.visuals().extreme_bg_color;
+    for segment in &segments {
+        let inner = (segment.depth as f32).mul_add(ring, hole);
+        let is_hovered = hovered.is_some_and(|hovered| std::ptr::eq(hovered, segment));
+        let mut fill = color(
+            f32::midpoint(segment.start, segment.end),
+            segment.depth,
+            segment.is_dir,
+        );
+        if is_hovered {
+            fill = fill.gamma_multiply(1.3);
+        }
+        painter.add(ring_segment(
+            center,
+            (inner, inner + ring),
+            segment.start,
+            segment.end,
+            fill,
+        ));
+        let angle = segment.start.mul_add(TAU, -FRAC_PI_2);
+        let direction = vec2(angle.cos(), angle.sin());
+        painter.line_segment(
+            [
+                center + direction * inner,
+                center + direction * (inner + ring),
+            ],
+            Stroke::new(1.0, background),
+        );
+    }
+    painter.circle_filled(center, hole, ui.visuals().faint_bg_color);
+    painter.text(
+        center,
+        Align2::CENTER_CENTER,
+        format_bytes_simple(node.size),
+        FontId::proportional(13.0),
+        ui.visuals().strong_text_color(),
+    );
+
+    if hover.is_some_and(|(distance, _)| distance < hole) {
+        let click = response.clicked().then_some(ChartClick::Up);
+        response.on_hover_text_at_pointer("Up one folder");
+        return click;
+    }
+    let segment = hovered?;
+    let child = node.descendant(&segment.names)?;
+    let click = chart_click(&response, segment.names.clone(), segment.is_dir);
+    response.on_hover_text_at_pointer(describe(child, node.size));
+    click
+}
+
+fn largest_items_ui(ui: &mut Ui, state: &mut DiskUsageState, node: &UsageNode) {
+    ui.strong("Largest items");
+    let mut open = None;
+    egui::ScrollArea::vertical()
+        .id_salt("disk_usage_largest")
+        .max_height(400.0)
+        .auto_shrink([false, true])
+        .show(ui, |ui| {
+            for child in node.children.iter().take(MAX_ROWS) {
+                let names = [state.current.clone(), vec![child.name.clone()]].concat();
+                let share = if node.size == 0 {
+                    0.0
+                } else {
+                    child.size as f32 / node.size as f32
+                };
+                ui.horizontal(|ui| {
+                    ui.add(egui::ProgressBar::new(share).desired_width(60.0));
+                    ui.label(format_bytes_simple(child.size));
+                    let label = if child.is_dir {
+                        format!("{}/", child.name)
+                    } else {
+                        child.name.clone()
+                    };
+                    let selected = state.selected.as_ref() == Some(&names);
+                    let response = ui.selectable_label(selected, label);
+                    if response.double_clicked() && child.is_dir {
+                        open = Some(names.clone());
+                    } else if response.clicked() {
+                        state.selected = Some(names);
+                    }
+                });
+            }
+            if node.children.len() > MAX_ROWS {
+                ui.weak(format!("…and {} more", node.children.len() - MAX_ROWS));
+            }
+        });
+    if let Some(names) = open {
+        state.open_folder(names);
+    }
+}
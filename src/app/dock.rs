@@ -12,7 +12,7 @@ use lru::LruCache;
 use mlua::{Function, UserData};
 use serde::{Deserialize, Serialize};
 use std::borrow::Cow;
-use std::fs;
+use std::collections::HashMap;
 use std::num::NonZeroUsize;
 use std::path::Path;
 use std::path::PathBuf;
@@ -29,10 +29,26 @@ use taffy::style_helpers;
 
 use super::assets::{entry_has_animated_preview, AssetManager, HoverPreview, IconSize};
 use super::commands::ActionToPerform;
+use crate::app::archive;
+use crate::app::batch_rename_dialog;
+use crate::app::checksums::{self, ChecksumKind};
+use crate::app::clipboard::{self, ClipboardMode};
 use crate::app::command_palette::build_for_path;
 use crate::app::commands::{ModalWindow, TabAction, TabTarget};
+use crate::app::compress_dialog;
+use crate::app::content_search::ContentHit;
+use crate::app::delete_dialog;
+use crate::app::dir_handling;
+use crate::app::dir_sizes;
 use crate::app::directory_view_settings::{DirectoryShowHidden, DirectoryViewSettings};
+use crate::app::drag_drop;
+use crate::app::extract_dialog;
+use crate::app::file_ops::{FileOperation, FileOperationKind};
+use crate::app::journal::{self, JournalOperation};
+use crate::app::new_entry;
+use crate::app::properties_dialog;
 use crate::app::top_bottom::TopDisplayPath;
+use crate::app::trash_bin_view;
 use crate::app::{DisplayType, LUA_INSTANCE, Search, Sort};
 use crate::data::files::{DirEntry, DirList, EntryType};
 use crate::data::time::ElapsedTime;
@@ -125,6 +141,10 @@ pub enum CurrentPath {
     None,
     One(PathBuf),
     Multiple(Vec<PathBuf>),
+    /// A folder inside an archive file, `inner` is empty for its root.
+    Archive { archive: PathBuf, inner: PathBuf },
+    /// The system trash, listed by [`trash_bin_view`].
+    Trash,
 }
 
 impl UserData for CurrentPath {
@@ -162,7 +182,7 @@ impl CurrentPath {
     }
     pub fn get_path(&self) -> Option<PathBuf> {
         match self {
-            Self::None => None,
+            Self::None | Self::Trash => None,
             Self::One(path) => Some(path.clone()),
             Self::Multiple(paths) => {
                 let mut common_path = paths.first()?.clone();
@@ -182,6 +202,7 @@ impl CurrentPath {
                 }
                 Some(common_path)
             }
+            Self::Archive { archive, inner } => Some(archive::virtual_path(archive, inner)),
         }
     }
 }
@@ -190,6 +211,8 @@ impl From<PathBuf> for CurrentPath {
     fn from(path: PathBuf) -> Self {
         if path.is_dir() {
             Self::One(path)
+        } else if let Some((archive, inner)) = archive::split(&path) {
+            Self::Archive { archive, inner }
         } else {
             Self::None
         }
@@ -224,11 +247,19 @@ impl CurrentPath {
                 }
             }
             Self::Multiple(_) => "Multiple".into(),
+            Self::Trash => "Trash".into(),
+            Self::Archive { archive, inner } => archive::virtual_path(archive, inner)
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_default(),
         }
     }
     pub fn parent(&self) -> Option<PathBuf> {
         match self {
             Self::One(path) => path.parent().map(PathBuf::from),
+            Self::Archive { archive, inner } => {
+                archive::virtual_path(archive, inner).parent().map(PathBuf::from)
+            }
             _ => None,
         }
     }
@@ -252,11 +283,16 @@ pub struct TabData {
     pub current_path: CurrentPath,
     pub show_hidden: bool,
     pub display_type: DisplayType,
+    pub checksum_column: Option<ChecksumKind>,
     pub search: Option<Search>,
+    /// Matching lines of the files found by a content search.
+    pub content_hits: HashMap<PathBuf, ContentHit>,
     pub loading: bool,
     pub loading_progress: Option<String>,
     pub(crate) refresh_generation: Arc<AtomicU64>,
     pub(crate) pending_refresh: bool,
+    /// Entry to select once the next listing arrives, and whether to rename it.
+    pub(crate) pending_reveal: Option<(PathBuf, bool)>,
     pub(crate) cancel_token: Arc<AtomicBool>,
     undoer: Undoer<CurrentPath>,
     pub id: u32,
@@ -279,6 +315,28 @@ pub struct Selected {
     pub just_changed: bool,
 }
 
+impl Selected {
+    pub fn paths(&self, tab: &TabData) -> Vec<PathBuf> {
+        self.selected_fields
+            .iter()
+            .filter_map(|row| tab.entry_at(*row))
+            .map(|entry| entry.get_path())
+            .collect()
+    }
+
+    /// The whole selection when `row_index` is part of it, otherwise only the
+    /// entry at `row_index`.
+    pub fn dragged_paths(&self, tab: &TabData, row_index: usize) -> Vec<PathBuf> {
+        if self.selected_fields.contains(&row_index) {
+            self.paths(tab)
+        } else {
+            tab.entry_at(row_index)
+                .map(|entry| vec![entry.get_path()])
+                .unwrap_or_default()
+        }
+    }
+}
+
 impl TabData {
     pub fn can_undo(&self) -> bool {
         self.undoer.has_undo(&self.current_path)
@@ -304,7 +362,7 @@ impl TabData {
     }
     pub fn toggle_search(&mut self, data_source: &impl DataHolder) {
         if let Some(search) = &self.search {
-            let was_deep = search.depth > 1;
+            let was_deep = search.depth > 1 || self.is_content_search();
             data_source.data_set_tab::<Search>(self.id, search.clone());
             self.search = None;
             if self.current_path.multiple_paths() {
@@ -327,7 +385,7 @@ impl TabData {
             let restored_depth = search.depth;
             let is_multiple = self.current_path.multiple_paths();
             self.search = Some(search);
-            if is_multiple || restored_depth > 1 {
+            if is_multiple || restored_depth > 1 || self.is_content_search() {
                 TabAction::RequestFilesRefresh.schedule_tab(self.id);
             }
         }
@@ -365,6 +423,11 @@ impl TabData {
                 term_type: _,
                 extra_dirs: _,
                 match_mode: _,
+                in_contents: _,
+                sort_by_relevance: _,
+                respect_ignore_files: _,
+                exclude_globs: _,
+                new_exclude_input: _,
                 new_dir_input: _,
                 save_name_input: _,
             }) => !value.is_empty() || !terms.is_empty(),
@@ -372,6 +435,12 @@ impl TabData {
         }
     }
 
+    /// Whether the search looks inside files, so the listing depends on what
+    /// was searched for.
+    pub fn is_content_search(&self) -> bool {
+        self.search.as_ref().is_some_and(Search::searches_contents)
+    }
+
     /// Materialise the entry visible at `visible_row` (0-based in visible order).
     ///
     /// For single-directory reads the backing store is a lazily-materialised
@@ -417,17 +486,24 @@ impl TabData {
             current_path: CurrentPath::None,
             show_hidden: false,
             display_type: DisplayType::default(),
+            checksum_column: None,
             search: None,
+            content_hits: HashMap::new(),
             loading: false,
             loading_progress: None,
             refresh_generation: Arc::new(AtomicU64::new(0)),
             pending_refresh: false,
+            pending_reveal: None,
             cancel_token: Arc::new(AtomicBool::new(false)),
             undoer: Undoer::default(),
             top_display_path,
             dir_list: None,
         };
-        TabAction::ChangePaths(CurrentPath::One(path.into())).schedule_tab(new.id);
+        let current_path = match CurrentPath::from(path.to_path_buf()) {
+            archive @ CurrentPath::Archive { .. } => archive,
+            _ => CurrentPath::One(path.into()),
+        };
+        TabAction::ChangePaths(current_path).schedule_tab(new.id);
         new
     }
 }
@@ -438,6 +514,9 @@ pub struct MyTabViewer<'a> {
     active_tab: u32,
     focused: bool,
     assets: &'a mut AssetManager,
+    /// Set when a folder inside the current tab is the drop target, so the
+    /// tab itself is not highlighted as well.
+    folder_drop_hovered: bool,
 }
 
 // Column dimension constants used in both the header and data rows of the file grid.
@@ -447,6 +526,7 @@ const COL_MODIFIED_MAX: f32 = 150.0;
 const COL_SIZE_W: f32 = 125.0;
 const COL_SIZE_MIN: f32 = 100.0;
 const COL_SIZE_MAX: f32 = 150.0;
+const COL_CHECKSUM_W: f32 = 160.0;
 const GRID_TILE_PADDING: f32 = 10.0;
 const GRID_VIEW_PADDING: f32 = 8.0;
 const GRID_TILE_TEXT_HEIGHT: f32 = 20.0;
@@ -669,6 +749,7 @@ impl MyTabViewer<'_> {
         // ── Unified virtual-grid: sticky header row + scrollable body ────────
         //
         // The grid has 3 columns:  [Name (flex) | Modified (fixed) | Size (fixed)]
+        // plus a fixed Checksum column when one is picked in the view settings.
         // Row 1 is the sticky header.  Data rows follow via VirtualGridRowHelper.
         // Row interactions are collected into a Vec and processed after the tui
         // closure, because we cannot borrow `tab` inside the closure.
@@ -677,6 +758,13 @@ impl MyTabViewer<'_> {
         // We need to collect row responses outside the tui closure because
         // borrowing `tab` inside it is not possible.
         let mut row_results: Vec<RowResult> = Vec::with_capacity(entries_len.min(64));
+        let checksum_column = tab
+            .checksum_column
+            .filter(|_| !matches!(tab.current_path, CurrentPath::Archive { .. }));
+        let mut grid_template_columns = vec![fr(1.), length(COL_MODIFIED_W), length(COL_SIZE_W)];
+        if checksum_column.is_some() {
+            grid_template_columns.push(length(COL_CHECKSUM_W));
+        }
 
         tui(ui, Id::new("file_grid").with(tab.id))
         .reserve_available_space()
@@ -718,8 +806,8 @@ impl MyTabViewer<'_> {
                     top: LengthPercentage::ZERO,
                     bottom: LengthPercentage::ZERO,
                 },
-                // 3 columns: Name (1fr, fills remaining), Modified (fixed), Size (fixed)
-                grid_template_columns: vec![fr(1.), length(COL_MODIFIED_W), length(COL_SIZE_W)],
+                // Name (1fr, fills remaining), Modified (fixed), Size (fixed), Checksum (fixed)
+                grid_template_columns,
                 size: taffy::Size {
                     width: percent(1.),
                     height: auto(),
@@ -813,7 +901,7 @@ impl MyTabViewer<'_> {
 
                                           let (dir, file) = val.get_splitted_path();
                                           let render_sz = self.assets.render_size_for(val);
-                                          ui.with_layout(
+                                          let response = ui.with_layout(
                                               Layout::left_to_right(egui::Align::Center),
                                               |ui| {
                                                   if let Some(texture) = self.assets.request_entry_texture(val) {
@@ -859,7 +947,15 @@ impl MyTabViewer<'_> {
                                                     );
                                                 }
 
-                                                let file_name_response = FILE_NAME_POOL.with_borrow_mut(|pool| {
+                                                let highlights = if is_searching {
+                                                    tab.search.as_ref().map_or_else(Vec::new, |search| {
+                                                        dir_handling::fuzzy_highlights(search, file)
+                                                    })
+                                                } else {
+                                                    Vec::new()
+                                                };
+                                                let file_name_response = if highlights.is_empty() {
+                                                    FILE_NAME_POOL.with_borrow_mut(|pool| {
                                                     pool.get(&(file.to_string(), is_dir)).cloned()
                                                 }).map(|galley| {
                                                     let available_width = ui.available_width();
@@ -889,7 +985,20 @@ impl MyTabViewer<'_> {
                                                         .selectable(false)
                                                         .sense(Sense::empty()),
                                                     )
-                                                });
+                                                })
+                                                } else {
+                                                    ui.add(
+                                                        egui::Label::new(Self::highlighted_name(
+                                                            ui,
+                                                            file,
+                                                            &highlights,
+                                                            color,
+                                                        ))
+                                                        .wrap_mode(egui::TextWrapMode::Truncate)
+                                                        .selectable(false)
+                                                        .sense(Sense::empty()),
+                                                    )
+                                                };
 
                                                 file_name_response.on_hover_ui(|ui| {
                                                     Self::show_entry_hover_preview(
@@ -900,7 +1009,11 @@ impl MyTabViewer<'_> {
                                                 });
                                             },
                                         )
-                                        .response
+                                        .response;
+                                        if let Some(hit) = tab.content_hits.get(&val.get_path()) {
+                                            Self::show_content_hit(ui, hit);
+                                        }
+                                        response
                                     })
                                 },
                             )
@@ -1009,10 +1122,37 @@ impl MyTabViewer<'_> {
                                         );
 
                                         if is_dir {
-                                            ui.allocate_response(
-                                                egui::Vec2::ZERO,
-                                                Sense::empty(),
+                                            let size = (!matches!(
+                                                tab.current_path,
+                                                CurrentPath::Archive { .. }
+                                            ))
+                                            .then(|| dir_sizes::get(&val.get_path(), ui.ctx()))
+                                            .flatten();
+                                            ui.with_layout(
+                                                Layout::right_to_left(egui::Align::Center),
+                                                |ui| {
+                                                    let Some(size) = size else {
+                                                        return;
+                                                    };
+                                                    populate_sizes_pool(
+                                                        std::iter::once(size.size),
+                                                        ui.ctx(),
+                                                    );
+                                                    draw_size(ui, size.size);
+                                                    let items = match size.items {
+                                                        1 => "1 item".to_string(),
+                                                        items => format!("{items} items"),
+                                                    };
+                                                    ui.add(
+                                                        egui::Label::new(
+                                                            egui::RichText::new(items).weak(),
+                                                        )
+                                                        .truncate()
+                                                        .selectable(false),
+                                                    );
+                                                },
                                             )
+                                            .response
                                         } else {
                                             ui.with_layout(
                                                 Layout::right_to_left(egui::Align::Center),
@@ -1030,6 +1170,70 @@ impl MyTabViewer<'_> {
                                 },
                             );
 
+                        // ── Checksum column ───────────────────────────
+                        if let Some(kind) = checksum_column {
+                            tui.id(idgen())
+                                .mut_style(&grid_row_param)
+                                .mut_style(|style| {
+                                    style.padding = Rect {
+                                        left: LengthPercentage::length(10.0),
+                                        right: LengthPercentage::length(10.0),
+                                        top: LengthPercentage::ZERO,
+                                        bottom: LengthPercentage::ZERO,
+                                    };
+                                    style.size = taffy::Size {
+                                        width: length(COL_CHECKSUM_W),
+                                        height: auto(),
+                                    };
+                                    style.min_size = taffy::Size {
+                                        width: length(COL_CHECKSUM_W),
+                                        height,
+                                    };
+                                    style.overflow.x = taffy::Overflow::Hidden;
+                                    style.align_items = Some(taffy::AlignItems::Stretch);
+                                })
+                                .add_with_background_ui(
+                                    |ui, container| {
+                                        ui.painter().rect_filled(
+                                            container.full_container(),
+                                            0.0,
+                                            bg_color,
+                                        );
+                                    },
+                                    |tui, ()| {
+                                        tui.mut_style(|style| {
+                                            style.size.width = percent(1.);
+                                        })
+                                        .ui(|ui: &mut Ui| {
+                                            let digest = (!is_dir)
+                                                .then(|| {
+                                                    checksums::get(&val.get_path(), kind, ui.ctx())
+                                                })
+                                                .flatten();
+                                            ui.with_layout(
+                                                Layout::left_to_right(egui::Align::Center),
+                                                |ui| {
+                                                    let Some(digest) = digest else {
+                                                        return;
+                                                    };
+                                                    ui.add(
+                                                        egui::Label::new(
+                                                            egui::RichText::new(&digest)
+                                                                .monospace()
+                                                                .weak(),
+                                                        )
+                                                        .truncate()
+                                                        .selectable(false),
+                                                    )
+                                                    .on_hover_text(digest);
+                                                },
+                                            )
+                                            .response
+                                        })
+                                    },
+                                );
+                        }
+
                         // ── Row interaction sense (full row) ──────────
                         // Build a rect that spans the full available width at the
                         // same vertical position as the name cell.
@@ -1045,7 +1249,7 @@ impl MyTabViewer<'_> {
                         let row_response = tui.egui_ui_mut().interact(
                             full_row_rect,
                             Id::new("row_sense_full").with(tab_id).with(row_index),
-                            Sense::click(),
+                            Sense::click_and_drag(),
                         );
 
                         row_results.push(RowResult {
@@ -1172,6 +1376,34 @@ impl MyTabViewer<'_> {
                             });
                         });
                     });
+
+                // Checksum header
+                if let Some(kind) = checksum_column {
+                    tui.sticky([false, true].into())
+                        .id(tid(("header_checksum", tab_id)))
+                        .mut_style(|style| {
+                            style.grid_row = style_helpers::line(1);
+                            style.grid_column = line(4);
+                            style.padding = length(4.);
+                            style.align_items = Some(taffy::AlignItems::Center);
+                            style.size = taffy::Size {
+                                width: length(COL_CHECKSUM_W),
+                                height: auto(),
+                            };
+                        })
+                        .add_with_background_color(|tui| {
+                            tui.mut_style(|style| {
+                                style.size.width = percent(1.);
+                            })
+                            .ui(|ui: &mut Ui| {
+                                ui.add(
+                                    egui::Label::new(kind.to_string())
+                                        .wrap_mode(egui::TextWrapMode::Extend)
+                                        .selectable(false),
+                                );
+                            });
+                        });
+                }
             });
         });
 
@@ -1204,6 +1436,12 @@ impl MyTabViewer<'_> {
                     .data_set_path(&tab.current_path, selected_tabs.clone());
             }
 
+            drag_drop::drag_source(row_response, || selected_tabs.dragged_paths(tab, row_index));
+            if !val.is_file() {
+                self.folder_drop_hovered |=
+                    drag_drop::folder_drop_target(row_response, &val.get_path(), tab_id);
+            }
+
             self.show_entry_context_menu(row_response, tab, val, row_index, tab_popup_id, &favorites);
 
             // Scroll selected row into view
@@ -1292,7 +1530,7 @@ impl MyTabViewer<'_> {
         selected_inline_budget: &mut u32,
     ) -> egui::Response {
         let (rect, response) =
-            ui.allocate_exact_size(Vec2::new(tile_width, tile_height), Sense::click());
+            ui.allocate_exact_size(Vec2::new(tile_width, tile_height), Sense::click_and_drag());
         let visuals = &ui.style().visuals;
         let hovered = response.hovered() || is_popup_open;
         let bg_fill = if is_selected {
@@ -1451,6 +1689,12 @@ impl MyTabViewer<'_> {
                     .data_set_path(&tab.current_path, selected_tabs.clone());
             }
 
+            drag_drop::drag_source(row_response, || selected_tabs.dragged_paths(tab, row_index));
+            if !val.is_file() {
+                self.folder_drop_hovered |=
+                    drag_drop::folder_drop_target(row_response, &val.get_path(), tab.id);
+            }
+
             self.show_entry_context_menu(
                 row_response,
                 tab,
@@ -1485,6 +1729,18 @@ impl MyTabViewer<'_> {
             .id(popup_id)
             .show(|ui| {
                 ui.data_set_tab::<PopupOpened>(tab.id, PopupOpened(popup_id, row_index));
+                // Archive contents are read-only.
+                if matches!(tab.current_path, CurrentPath::Archive { .. }) {
+                    if ui.button("Open").clicked() {
+                        Self::activate_entry(val, tab.id, false);
+                        ui.close();
+                    }
+                    if is_dir && ui.button("Open in new tab").clicked() {
+                        Self::activate_entry(val, tab.id, true);
+                        ui.close();
+                    }
+                    return;
+                }
                 let options = build_for_path(&tab.current_path, &val.get_path(), favorites);
                 for option in options {
                     if ui.button(option.name.as_str()).clicked() {
@@ -1507,60 +1763,42 @@ impl MyTabViewer<'_> {
                         );
                         ui.close();
                     }
-                    let val_dir = PathBuf::from(val.get_splitted_path().0);
-                    let matching_dirs = self.tab_paths.iter().filter(|path| !val_dir.eq(*path));
-                    #[allow(clippy::collapsible_else_if)]
-                    if matching_dirs.clone().count() > 0 {
-                        ui.separator();
-                        ui.menu_button("Move to", |ui| {
-                            for other in matching_dirs {
+                }
+                let source = val.get_path();
+                let val_dir = PathBuf::from(val.get_splitted_path().0);
+                let matching_dirs = self
+                    .tab_paths
+                    .iter()
+                    .filter(|path| !val_dir.eq(*path) && !path.starts_with(&source))
+                    .collect::<Vec<_>>();
+                if !matching_dirs.is_empty() {
+                    ui.separator();
+                    for (label, kind) in [
+                        ("Move to", FileOperationKind::Move),
+                        ("Copy to", FileOperationKind::Copy),
+                    ] {
+                        ui.menu_button(label, |ui| {
+                            for other in &matching_dirs {
                                 let other = PathBuf::from(
                                     std::fs::canonicalize(other)
-                                        .unwrap_or_else(|_| val.get_path().to_path_buf())
+                                        .unwrap_or_else(|_| other.to_path_buf())
                                         .to_fixed_string(),
                                 );
-
+                                let name = other.file_name().map_or_else(
+                                    || other.to_string_lossy(),
+                                    |name| name.to_string_lossy(),
+                                );
                                 if ui
-                                    .button(format!(
-                                        "{}",
-                                        &other.file_name().expect("Failed").to_string_lossy()
-                                    ))
+                                    .button(name.as_ref())
                                     .on_hover_text(other.display().to_string())
                                     .clicked()
                                 {
-                                    let path = val.get_path();
-                                    let filename = path.file_name().expect("NO FILENAME").to_os_string();
-                                    let target_path = other.join(&filename);
-                                    println!("{}", &target_path.display());
-                                    let move_result = fs::rename(&path, &target_path);
-                                    let mut success = move_result.is_ok();
-                                    let _ = move_result.inspect_err(|e| {
-                                        e.raw_os_error().inspect(|nr| {
-                                            if *nr == 17 {
-                                                let copy_success =
-                                                    fs::copy(&path, target_path.clone()).is_ok();
-                                                if copy_success {
-                                                    success = fs::remove_file(&path).is_ok();
-                                                }
-                                            }
-                                        });
-                                    });
-                                    if success {
-                                        crate::app::database::invalidate_dir(&val_dir);
-                                        crate::app::database::invalidate_dir(&other);
-                                        crate::app::commands::COMMANDS_QUEUE.push(
-                                            ActionToPerform::TabAction(
-                                                TabTarget::AllTabs,
-                                                TabAction::RequestFilesRefresh,
-                                            ),
-                                        );
-                                    } else {
-                                        toast!(
-                                            Error,
-                                            "Failed to move file {}",
-                                            filename.to_string_lossy()
-                                        );
-                                    }
+                                    FileOperation::new(
+                                        kind,
+                                        vec![source.clone()],
+                                        Some(other.clone()),
+                                    )
+                                    .schedule();
                                     ui.close();
                                 }
                             }
@@ -1570,13 +1808,121 @@ impl MyTabViewer<'_> {
                 ui.separator();
                 if ui.button("Move to Trash").clicked() {
                     let parent = PathBuf::from(val.get_splitted_path().0);
-                    trash::delete(val.get_path()).unwrap_or_else(|_| {
-                        toast!(Error, "Could not move it to trash.");
-                    });
+                    match trash::delete(val.get_path()) {
+                        Ok(()) => journal::record(&JournalOperation::Trash(vec![val.get_path()])),
+                        Err(err) => {
+                            log::error!(
+                                "Could not move {} to trash: {err}",
+                                val.get_path().display()
+                            );
+                            delete_dialog::open(
+                                ui.ctx(),
+                                vec![val.get_path()],
+                                Some(err.to_string()),
+                            );
+                        }
+                    }
                     crate::app::database::invalidate_dir(&parent);
                     ui.close();
                     TabAction::RequestFilesRefresh.schedule_active_tab();
                 }
+                // The selection when the clicked row is part of it.
+                let selected_paths = |ui: &egui::Ui| {
+                    ui.data_get_path::<Selected>(&tab.current_path).map_or_else(
+                        || vec![val.get_path()],
+                        |selected| selected.dragged_paths(tab, row_index),
+                    )
+                };
+                if ui
+                    .button("Delete permanently")
+                    .on_hover_text("Shift+Delete")
+                    .clicked()
+                {
+                    delete_dialog::open(ui.ctx(), selected_paths(ui), None);
+                    ui.close();
+                }
+                if ui.button("Cut").clicked() {
+                    clipboard::set_files(selected_paths(ui), ClipboardMode::Cut);
+                    ui.close();
+                }
+                if ui.button("Copy").clicked() {
+                    clipboard::set_files(selected_paths(ui), ClipboardMode::Copy);
+                    ui.close();
+                }
+                if is_dir && ui.button("Paste into folder").clicked() {
+                    clipboard::paste_into(&val.get_path(), None);
+                    ui.close();
+                }
+                if is_dir && ui.button("Analyze disk usage…").clicked() {
+                    ActionToPerform::AnalyzeDiskUsage(val.get_path()).schedule();
+                    ui.close();
+                }
+                if ui.button("Compress…").clicked() {
+                    compress_dialog::open(ui.ctx(), selected_paths(ui));
+                    ui.close();
+                }
+                if archive::is_archive(&source) {
+                    let archives = ui
+                        .data_get_path::<Selected>(&tab.current_path)
+                        .map_or_else(
+                            || vec![source.clone()],
+                            |selected| selected.dragged_paths(tab, row_index),
+                        )
+                        .into_iter()
+                        .filter(|path| archive::is_archive(path))
+                        .collect::<Vec<_>>();
+                    if ui.button("Extract here").clicked() {
+                        FileOperation::extract(archives.clone(), val_dir.clone()).schedule();
+                        ui.close();
+                    }
+                    let label = match archives.as_slice() {
+                        [archive] => format!(
+                            "Extract to {}/",
+                            archive::stem(archive).unwrap_or_default()
+                        ),
+                        _ => "Extract each to its own folder".to_string(),
+                    };
+                    if ui.button(label).clicked() {
+                        for archive in &archives {
+                            if let Some(stem) = archive::stem(archive) {
+                                FileOperation::extract(vec![archive.clone()], val_dir.join(stem))
+                                    .schedule();
+                            }
+                        }
+                        ui.close();
+                    }
+                    if ui.button("Extract to…").clicked() {
+                        let suggestions = self
+                            .tab_paths
+                            .iter()
+                            .filter(|path| !val_dir.eq(*path))
+                            .cloned()
+                            .collect();
+                        extract_dialog::open(ui.ctx(), archives, suggestions);
+                        ui.close();
+                    }
+                }
+                if !is_dir {
+                    if ChecksumKind::of_manifest(&source).is_some()
+                        && ui.button("Verify checksums").clicked()
+                    {
+                        ActionToPerform::VerifyChecksums(source.clone()).schedule();
+                        ui.close();
+                    }
+                    if ui.button("Checksums…").clicked() {
+                        let paths = ui
+                            .data_get_path::<Selected>(&tab.current_path)
+                            .map_or_else(
+                                || vec![source.clone()],
+                                |selected| selected.dragged_paths(tab, row_index),
+                            )
+                            .into_iter()
+                            .filter(|path| path.is_file())
+                            .collect();
+                        ActionToPerform::ComputeChecksums(paths).schedule();
+                        ui.close();
+                    }
+                }
                 if ui.button("Copy path to clipboard").clicked() {
                     let Ok(mut clipboard) = arboard::Clipboard::new() else {
                         toast!(Error, "Failed to read the clipboard.");
@@ -1587,21 +1933,39 @@ impl MyTabViewer<'_> {
                     });
                     ui.close();
                 }
-                if ui.button("Rename").clicked() {
+                let selected = ui
+                    .data_get_path::<Selected>(&tab.current_path)
+                    .filter(|selected| {
+                        selected.selected_fields.len() > 1
+                            && selected.selected_fields.contains(&row_index)
+                    });
+                if let Some(selected) = selected {
+                    let paths = selected.paths(tab);
+                    if ui.button(format!("Rename {} items", paths.len())).clicked() {
+                        batch_rename_dialog::open(ui.ctx(), &paths);
+                        ui.close();
+                    }
+                } else if ui.button("Rename").clicked() {
                     ui.data_mut(|w| {
                         w.insert_temp(egui::Id::new(ModalWindow::Rename), val.clone());
                     });
                     ActionToPerform::ToggleModalWindow(ModalWindow::Rename).schedule();
                     ui.close();
                 }
+                if tab.current_path.single_path().is_some() {
+                    ui.separator();
+                    new_entry::menu_ui(ui);
+                }
 
+                ui.separator();
+                if ui.button("Properties").clicked() {
+                    properties_dialog::open(ui.ctx(), &val.get_path());
+                    ui.close();
+                }
                 #[cfg(windows)]
-                {
-                    ui.separator();
-                    if ui.button("Properties").clicked() {
-                        crate::windows_tools::open_properties(val.get_path());
-                        ui.close();
-                    }
+                if ui.button("System properties").clicked() {
+                    crate::windows_tools::open_properties(val.get_path());
+                    ui.close();
                 }
             });
     }
@@ -1674,9 +2038,15 @@ impl MyTabViewer<'_> {
     }
 
     fn activate_entry(entry: &DirEntry, tab_id: u32, open_in_new_tab: bool) {
-        if entry.is_file() {
+        let path = entry.get_path();
+        if entry.is_file() && ChecksumKind::of_manifest(&path).is_some() && path.exists() {
+            ActionToPerform::VerifyChecksums(path).schedule();
+        } else if entry.is_file() && !archive::is_archive(&path) {
             ActionToPerform::SystemOpen(entry.full_path_string().into()).schedule();
-        } else if let Ok(path) = std::fs::canonicalize(entry.get_path()) {
+        } else if let Some(path) = std::fs::canonicalize(&path)
+            .ok()
+            .or_else(|| archive::split(&path).map(|_| path))
+        {
             if open_in_new_tab {
                 ActionToPerform::NewTab(path).schedule();
             } else {
@@ -1685,6 +2055,56 @@ impl MyTabViewer<'_> {
         }
     }
 
+    /// `name` with the characters at the char indices in `highlights` drawn
+    /// emphasized, for showing what a fuzzy search matched.
+    fn highlighted_name(ui: &Ui, name: &str, highlights: &[usize], color: Color32) -> LayoutJob {
+        let plain = TextFormat::simple(FontId::default(), color);
+        let matched = TextFormat {
+            color: ui.visuals().strong_text_color(),
+            underline: egui::Stroke::new(1.0, ui.visuals().selection.stroke.color),
+            ..plain.clone()
+        };
+        let mut job = LayoutJob::default();
+        let mut buf = [0; 4];
+        for (i, c) in name.chars().enumerate() {
+            let format = if highlights.binary_search(&i).is_ok() {
+                matched.clone()
+            } else {
+                plain.clone()
+            };
+            job.append(c.encode_utf8(&mut buf), 0.0, format);
+        }
+        job
+    }
+
+    /// The first matching line of a content search hit, with the rest on hover.
+    fn show_content_hit(ui: &mut Ui, hit: &ContentHit) {
+        let Some(first) = hit.lines.first() else {
+            return;
+        };
+        let more = match hit.total {
+            1 => String::new(),
+            total => format!("  (+{} more)", total - 1),
+        };
+        ui.add(
+            egui::Label::new(
+                egui::RichText::new(format!("{}: {}{more}", first.number, first.text))
+                    .monospace()
+                    .weak(),
+            )
+            .truncate()
+            .selectable(false),
+        )
+        .on_hover_ui(|ui| {
+            for line in &hit.lines {
+                ui.monospace(format!("{:>5}: {}", line.number, line.text));
+            }
+            if hit.total > hit.lines.len() {
+                ui.weak(format!("…and {} more", hit.total - hit.lines.len()));
+            }
+        });
+    }
+
     fn show_entry_hover_preview(assets: &mut AssetManager, ui: &mut Ui, entry: &DirEntry) {
         match assets.request_hover_preview(entry) {
             HoverPreview::ImageUri(path) => {
@@ -1751,10 +2171,75 @@ impl TabViewer for MyTabViewer<'_> {
     fn ui(&mut self, ui: &mut Ui, tab: &mut Self::Tab) {
         #[cfg(feature = "profiling")]
         puffin::profile_scope!("lwa_fm::MyTabViewer::ui");
+        self.folder_drop_hovered = false;
+        if matches!(tab.current_path, CurrentPath::Trash) {
+            trash_bin_view::ui(ui, tab.id);
+            return;
+        }
+        // Registered before the entries, so it only catches clicks on empty space.
+        let background = ui.interact(
+            ui.max_rect(),
+            Id::new("tab_background").with(tab.id),
+            Sense::click(),
+        );
         match tab.display_type {
             DisplayType::List => self.list_view(ui, tab),
             DisplayType::Icons => self.grid_view(ui, tab),
         }
+        if !matches!(
+            tab.current_path,
+            CurrentPath::None | CurrentPath::Archive { .. }
+        ) {
+            background.context_menu(|ui| {
+                if let Some(path) = tab.current_path.single_path() {
+                    if ui.button("Paste").clicked() {
+                        clipboard::paste_into(&path, None);
+                        ui.close();
+                    }
+                    ui.separator();
+                    new_entry::menu_ui(ui);
+                    ui.separator();
+                }
+                if ui.button("Find duplicates…").clicked() {
+                    ActionToPerform::FindDuplicates.schedule();
+                    ui.close();
+                }
+                if let Some(path) = tab.current_path.single_path()
+                    && ui.button("Analyze disk usage…").clicked()
+                {
+                    ActionToPerform::AnalyzeDiskUsage(path).schedule();
+                    ui.close();
+                }
+                if tab.current_path.single_path().is_some()
+                    && ui.button("Compare with…").clicked()
+                {
+                    ActionToPerform::CompareFolders.schedule();
+                    ui.close();
+                }
+                if ui.button("Sync jobs…").clicked() {
+                    ActionToPerform::ManageSyncJobs.schedule();
+                    ui.close();
+                }
+            });
+        }
+        // Drops that did not land on a folder go into the tab's directory.
+        if drag_drop::is_dragging(ui.ctx())
+            && !self.folder_drop_hovered
+            && let Some(path) = tab.current_path.single_path()
+        {
+            let response = ui.interact(
+                ui.max_rect(),
+                Id::new("tab_drop_zone").with(tab.id),
+                Sense::hover(),
+            );
+            drag_drop::drop_target(&response, &path);
+        }
+    }
+
+    fn on_tab_button(&mut self, tab: &mut Self::Tab, response: &egui::Response) {
+        if let Some(path) = tab.current_path.single_path() {
+            drag_drop::drop_target(response, &path);
+        }
     }
 }
 
@@ -1853,6 +2338,7 @@ impl MyTabs {
             active_tab,
             focused: self.focused,
             assets,
+            folder_drop_hovered: false,
         };
         ui.spacing_mut().item_spacing = [0.0, 0.0].into();
         DockArea::new(&mut self.dock_state)
@@ -1864,13 +2350,15 @@ impl MyTabs {
             .show_inside(ui, &mut my_tab_viewer);
     }
 
-    pub fn open_in_new_tab(&mut self, path: &Path) {
+    /// Opens `path` in a new tab and returns the tab's id.
+    pub fn open_in_new_tab(&mut self, path: &Path) -> u32 {
         let is_not_focused = self.dock_state.focused_leaf().is_none();
         if is_not_focused {
             self.dock_state
                 .set_focused_node_and_surface(egui_dock::NodePath::MAIN_ROOT);
         }
         let new_window = TabData::from_path(path);
+        let id = new_window.id;
         let root_node = self
             .dock_state
             .main_surface_mut()
@@ -1881,9 +2369,10 @@ impl MyTabs {
         } else {
             self.dock_state.push_to_focused_leaf(new_window);
         }
+        id
     }
 
-    fn get_tabs_paths(&self) -> Vec<PathBuf> {
+    pub fn get_tabs_paths(&self) -> Vec<PathBuf> {
         self.dock_state
             .iter_all_tabs()
             .filter_map(|(_, tab)| tab.current_path.single_path())
@@ -0,0 +1,207 @@
+//! Drag and drop of entries between tabs, folders and the sidebar.
+//!
+//! Dragged rows carry a [`DraggedEntries`] payload. Dropping it onto a folder,
+//! a tab or a sidebar location moves the entries there, holding the command
+//! (or alt) modifier copies them instead. Files dropped from other applications
+//! are copied into the active tab's directory.
+
+use std::path::{Path, PathBuf};
+
+use egui::{Context, DragAndDrop, Id, Response};
+
+use crate::app::commands::TabAction;
+use crate::app::file_ops::{FileOperation, FileOperationKind};
+use crate::toast;
+
+/// How long a folder has to be hovered during a drag before it opens.
+const SPRING_LOAD_DELAY: f64 = 0.7;
+
+/// Payload of an entry drag started in a tab.
+#[derive(Debug, Clone)]
+pub struct DraggedEntries(pub Vec<PathBuf>);
+
+#[derive(Debug, Clone)]
+struct SpringLoad {
+    path: PathBuf,
+    since: f64,
+    last_pass: u64,
+    opened: bool,
+}
+
+/// Starts a drag of `paths` when `response` begins being dragged.
+pub fn drag_source(response: &Response, paths: impl FnOnce() -> Vec<PathBuf>) {
+    if response.drag_started() {
+        let paths = paths();
+        if !paths.is_empty() {
+            DragAndDrop::set_payload(&response.ctx, DraggedEntries(paths));
+        }
+    }
+}
+
+pub fn is_dragging(ctx: &Context) -> bool {
+    DragAndDrop::has_payload_of_type::<DraggedEntries>(ctx)
+}
+
+/// Move unless the command or alt modifier is held.
+fn drop_kind(ctx: &Context) -> FileOperationKind {
+    if ctx.input(|i| i.modifiers.command || i.modifiers.alt) {
+        FileOperationKind::Copy
+    } else {
+        FileOperationKind::Move
+    }
+}
+
+/// Entries that can be dropped into `target`. Drops of a directory into itself
+/// or its own subtree are filtered out, as are moves that would not change
+/// anything.
+fn drop_sources(paths: &[PathBuf], target: &Path, kind: FileOperationKind) -> Vec<PathBuf> {
+    paths
+        .iter()
+        .filter(|source| !target.starts_with(source))
+        .filter(|source| kind != FileOperationKind::Move || source.parent() != Some(target))
+        .cloned()
+        .collect()
+}
+
+/// Makes `response` a drop target for `target`. Highlights it while a valid
+/// drag hovers it and schedules the move or copy once dropped. Returns `true`
+/// while a drag that can be dropped here hovers it.
+pub fn drop_target(response: &Response, target: &Path) -> bool {
+    let Some(payload) = response.dnd_hover_payload::<DraggedEntries>() else {
+        return false;
+    };
+    let ctx = &response.ctx;
+    let kind = drop_kind(ctx);
+    let sources = drop_sources(&payload.0, target, kind);
+    if sources.is_empty() {
+        return false;
+    }
+    let painter = ctx
+        .layer_painter(response.layer_id)
+        .with_clip_rect(response.interact_rect);
+    painter.rect_stroke(
+        response.interact_rect.shrink(1.0),
+        4.0,
+        ctx.style().visuals.selection.stroke,
+        egui::StrokeKind::Inside,
+    );
+    if response.dnd_release_payload::<DraggedEntries>().is_some() {
+        FileOperation::new(kind, sources, Some(target.to_path_buf())).schedule();
+        return false;
+    }
+    true
+}
+
+/// Returns `true` once a drag has hovered the folder `path` for
+/// [`SPRING_LOAD_DELAY`] seconds. Call it every frame the folder is hovered.
+pub fn spring_loaded(ctx: &Context, path: &Path) -> bool {
+    let id = Id::new("spring_loaded_folder");
+    let now = ctx.input(|i| i.time);
+    let pass = ctx.cumulative_pass_nr();
+    let mut state = ctx
+        .data(|d| d.get_temp::<SpringLoad>(id))
+        .filter(|state| state.path == path && state.last_pass + 1 >= pass)
+        .unwrap_or_else(|| SpringLoad {
+            path: path.to_path_buf(),
+            since: now,
+            last_pass: pass,
+            opened: false,
+        });
+    state.last_pass = pass;
+    let open = !state.opened && now - state.since >= SPRING_LOAD_DELAY;
+    state.opened |= open;
+    if !state.opened {
+        ctx.request_repaint_after(std::time::Duration::from_secs_f64(
+            (SPRING_LOAD_DELAY - (now - state.since)).max(0.0),
+        ));
+    }
+    ctx.data_mut(|d| d.insert_temp(id, state));
+    open
+}
+
+/// Drop target for a folder shown in `tab_id`, which also opens the folder
+/// in that tab when hovered long enough. Returns `true` while hovered.
+pub fn folder_drop_target(response: &Response, folder: &Path, tab_id: u32) -> bool {
+    let hovered = drop_target(response, folder);
+    if hovered && spring_loaded(&response.ctx, folder) {
+        TabAction::ChangePaths(folder.to_path_buf().into()).schedule_tab(tab_id);
+    }
+    hovered
+}
+
+/// Shows what will happen to the dragged entries next to the pointer.
+pub fn drag_preview_ui(ctx: &Context) {
+    let Some(payload) = DragAndDrop::payload::<DraggedEntries>(ctx) else {
+        return;
+    };
+    let Some(pos) = ctx.pointer_interact_pos() else {
+        return;
+    };
+    let count = payload.0.len();
+    let text = match (drop_kind(ctx), count) {
+        (FileOperationKind::Copy, 1) => "Copy 1 item".to_string(),
+        (FileOperationKind::Copy, _) => format!("Copy {count} items"),
+        (_, 1) => "Move 1 item".to_string(),
+        (_, _) => format!("Move {count} items"),
+    };
+    egui::Area::new(Id::new("drag_preview"))
+        .order(egui::Order::Tooltip)
+        .fixed_pos(pos + egui::vec2(16.0, 16.0))
+        .interactable(false)
+        .show(ctx, |ui| {
+            egui::Frame::popup(ui.style()).show(ui, |ui| {
+                ui.label(text);
+            });
+        });
+}
+
+/// Copies files dropped from other applications into `target`.
+pub fn handle_external_drop(ctx: &Context, target: Option<PathBuf>) {
+    let paths: Vec<PathBuf> = ctx.input(|i| {
+        i.raw
+            .dropped_files
+            .iter()
+            .filter_map(|file| file.path.clone())
+            .collect()
+    });
+    if paths.is_empty() {
+        return;
+    }
+    let Some(target) = target else {
+        toast!(Error, "Can't drop files while showing multiple directories");
+        return;
+    };
+    let sources = drop_sources(&paths, &target, FileOperationKind::Copy);
+    if !sources.is_empty() {
+        FileOperation::copy(sources, target).schedule();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::{Path, PathBuf};
+
+    use super::drop_sources;
+    use crate::app::file_ops::FileOperationKind;
+
+    #[test]
+    fn drop_sources_skip_self_nesting_and_noop_moves() {
+        let paths = vec![
+            PathBuf::from("/a/b"),
+            PathBuf::from("/a/c.txt"),
+            PathBuf::from("/d/e.txt"),
+        ];
+        assert_eq!(
+            drop_sources(&paths, Path::new("/a"), FileOperationKind::Move),
+            vec![PathBuf::from("/d/e.txt")]
+        );
+        assert_eq!(
+            drop_sources(&paths, Path::new("/a"), FileOperationKind::Copy),
+            paths
+        );
+        assert_eq!(
+            drop_sources(&paths, Path::new("/a/b/f"), FileOperationKind::Copy),
+            vec![PathBuf::from("/a/c.txt"), PathBuf::from("/d/e.txt")]
+        );
+    }
+}
@@ -0,0 +1,347 @@
+//! Finding files with identical contents.
+//!
+//! Candidates are narrowed down in stages, each cheaper than the next: files
+//! of the same size, then the same hash of their first block, then the same
+//! hash of their whole contents. Paths that are hard links of a file already
+//! seen are left out, they take no extra space.
+
+use std::{
+    collections::{HashMap, HashSet},
+    fs::{self, File},
+    io::{self, Read},
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicBool, Ordering},
+    time::SystemTime,
+};
+
+use anyhow::{Context, Result, bail};
+
+use super::file_ops::staging_path;
+
+/// How much of every file the partial hash covers.
+const PARTIAL_HASH_LEN: u64 = 16 * 1024;
+/// How many hashed files pass between progress updates.
+const PROGRESS_INTERVAL: usize = 64;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DuplicateFile {
+    pub path: PathBuf,
+    pub modified: Option<SystemTime>,
+}
+
+/// Files with the same contents, oldest first.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DuplicateGroup {
+    /// Size of each of the files.
+    pub size: u64,
+    /// Hash of the contents of each of the files.
+    pub hash: blake3::Hash,
+    pub files: Vec<DuplicateFile>,
+}
+
+impl DuplicateGroup {
+    /// Space freed by keeping only one of the files.
+    pub fn wasted(&self) -> u64 {
+        self.size * (self.files.len() as u64).saturating_sub(1)
+    }
+
+    /// The most recently modified file.
+    pub fn newest(&self) -> Option<&DuplicateFile> {
+        self.files.iter().max_by_key(|file| file.modified)
+    }
+}
+
+/// Whether `name` is hidden, by the same rule as the directory listings.
+fn is_hidden(name: &str) -> bool {
+    name.starts_with('.') || name.starts_with('$')
+}
+
+/// Every regular file below `roots` with its size, each one once. Empty
+/// files are left out.
+fn collect_files(
+    roots: &[PathBuf],
+    show_hidden: bool,
+    cancel: &AtomicBool,
+) -> Vec<(PathBuf, fs::Metadata)> {
+    let mut seen_paths = HashSet::new();
+    #[cfg(unix)]
+    let mut seen_inodes = HashSet::new();
+    let mut files = Vec::new();
+    for root in roots {
+        let walker = walkdir::WalkDir::new(root)
+            .follow_links(false)
+            .into_iter()
+            .filter_entry(|entry| {
+                entry.depth() == 0
+                    || show_hidden
+                    || !is_hidden(&entry.file_name().to_string_lossy())
+            });
+        for entry in walker.filter_map(std::result::Result::ok) {
+            if cancel.load(Ordering::Relaxed) {
+                return Vec::new();
+            }
+            if !entry.file_type().is_file() || !seen_paths.insert(entry.path().to_path_buf()) {
+                continue;
+            }
+            let Ok(meta) = entry.metadata() else {
+                continue;
+            };
+            if meta.len() == 0 {
+                continue;
+            }
+            #[cfg(unix)]
+            {
+                use std::os::unix::fs::MetadataExt;
+                if !seen_inodes.insert((meta.dev(), meta.ino())) {
+                    continue;
+                }
+            }
+            files.push((entry.into_path(), meta));
+        }
+    }
+    files
+}
+
+/// Hash of the first `limit` bytes of `path`, all of it without a limit.
+pub fn hash_file(path: &Path, limit: Option<u64>) -> io::Result<blake3::Hash> {
+    let file = File::open(path)?;
+    let mut hasher = blake3::Hasher::new();
+    match limit {
+        Some(limit) => io::copy(&mut file.take(limit), &mut hasher)?,
+        None => io::copy(&mut io::BufReader::new(file), &mut hasher)?,
+    };
+    Ok(hasher.finalize())
+}
+
+type Candidates = Vec<(PathBuf, fs::Metadata)>;
+
+/// Splits every group by the hash of its files, keeping the parts with more
+/// than one file. Files that can't be read are dropped.
+fn split_by_hash(
+    groups: Vec<Candidates>,
+    limit: Option<u64>,
+    stage: &str,
+    cancel: &AtomicBool,
+    progress: &dyn Fn(String),
+) -> Vec<(blake3::Hash, Candidates)> {
+    let total = groups.iter().map(Vec::len).sum::<usize>();
+    let mut hashed = 0;
+    let mut split = Vec::new();
+    for group in groups {
+        let mut by_hash: HashMap<blake3::Hash, Vec<_>> = HashMap::new();
+        for (path, meta) in group {
+            if cancel.load(Ordering::Relaxed) {
+                return Vec::new();
+            }
+            if hashed % PROGRESS_INTERVAL == 0 {
+                progress(format!("{stage} {hashed} of {total} files…"));
+            }
+            hashed += 1;
+            match hash_file(&path, limit) {
+                Ok(hash) => by_hash.entry(hash).or_default().push((path, meta)),
+                Err(err) => log::warn!("Failed to read {}: {err}", path.display()),
+            }
+        }
+        split.extend(by_hash.into_iter().filter(|(_, group)| group.len() > 1));
+    }
+    split
+}
+
+/// Groups of files below `roots` with identical contents, the most space
+/// wasted first. Gives no groups once `cancel` is set.
+pub fn find(
+    roots: &[PathBuf],
+    show_hidden: bool,
+    cancel: &AtomicBool,
+    progress: &dyn Fn(String),
+) -> Vec<DuplicateGroup> {
+    progress("Listing files…".to_string());
+    let mut by_size: HashMap<u64, Vec<_>> = HashMap::new();
+    for (path, meta) in collect_files(roots, show_hidden, cancel) {
+        by_size.entry(meta.len()).or_default().push((path, meta));
+    }
+    let same_size = by_size
+        .into_values()
+        .filter(|group| group.len() > 1)
+        .collect();
+    // Files no longer than the partial hash are already fully compared.
+    let (small, large): (Vec<_>, Vec<_>) = split_by_hash(
+        same_size,
+        Some(PARTIAL_HASH_LEN),
+        "Comparing the start of",
+        cancel,
+        progress,
+    )
+    .into_iter()
+    .partition(|(_, group)| group[0].1.len() <= PARTIAL_HASH_LEN);
+    let large = large.into_iter().map(|(_, group)| group).collect();
+    let mut groups = small;
+    groups.extend(split_by_hash(large, None, "Comparing", cancel, progress));
+    if cancel.load(Ordering::Relaxed) {
+        return Vec::new();
+    }
+
+    let mut groups = groups
+        .into_iter()
+        .map(|(hash, group)| {
+            let size = group[0].1.len();
+            let mut files = group
+                .into_iter()
+                .map(|(path, meta)| DuplicateFile {
+                    path,
+                    modified: meta.modified().ok(),
+                })
+                .collect::<Vec<_>>();
+            files.sort_by(|a, b| {
+                a.modified
+                    .cmp(&b.modified)
+                    .then_with(|| a.path.cmp(&b.path))
+            });
+            DuplicateGroup { size, hash, files }
+        })
+        .collect::<Vec<_>>();
+    groups.sort_by(|a, b| {
+        b.wasted()
+            .cmp(&a.wasted())
+            .then_with(|| a.files[0].path.cmp(&b.files[0].path))
+    });
+    groups
+}
+
+/// Fails when `file` changed since `group` was found.
+fn check_unchanged(group: &DuplicateGroup, file: &DuplicateFile) -> Result<()> {
+    let meta = fs::symlink_metadata(&file.path)
+        .with_context(|| format!("Failed to read {}", file.path.display()))?;
+    let unchanged = meta.is_file()
+        && meta.len() == group.size
+        && meta.modified().ok() == file.modified
+        && hash_file(&file.path, None).is_ok_and(|hash| hash == group.hash);
+    if !unchanged {
+        bail!("{} changed since the scan", file.path.display());
+    }
+    Ok(())
+}
+
+/// Replaces the `duplicate` of `group` with a hard link to its `original`,
+/// once both are checked to be as found. The link is made next to the
+/// duplicate first and renamed over it, so a failure leaves it untouched.
+pub fn replace_with_hard_link(
+    group: &DuplicateGroup,
+    original: &DuplicateFile,
+    duplicate: &DuplicateFile,
+) -> Result<()> {
+    check_unchanged(group, original)?;
+    check_unchanged(group, duplicate)?;
+    let (original, duplicate) = (&original.path, &duplicate.path);
+    let temporary = staging_path(duplicate);
+    fs::hard_link(original, &temporary).with_context(|| {
+        format!(
+            "Failed to link {} to {}",
+            duplicate.display(),
+            original.display()
+        )
+    })?;
+    if let Err(err) = fs::rename(&temporary, duplicate) {
+        let _ = fs::remove_file(&temporary);
+        return Err(err).with_context(|| format!("Failed to replace {}", duplicate.display()));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, sync::atomic::AtomicBool};
+
+    use super::{PARTIAL_HASH_LEN, find, replace_with_hard_link};
+    use crate::app::test_support::unique_test_dir;
+
+    #[test]
+    fn files_are_grouped_by_contents() {
+        let dir = unique_test_dir("duplicates_find");
+        fs::create_dir_all(dir.join("a/b")).expect("create dirs");
+        fs::create_dir_all(dir.join("other")).expect("create dirs");
+        fs::write(dir.join("a/one.txt"), b"same").expect("write");
+        fs::write(dir.join("a/b/two.txt"), b"same").expect("write");
+        fs::write(dir.join("other/three.txt"), b"same").expect("write");
+        fs::write(dir.join("a/size.txt"), b"diff").expect("write");
+        fs::write(dir.join("a/.hidden"), b"same").expect("write");
+        fs::write(dir.join("a/empty1"), b"").expect("write");
+        fs::write(dir.join("a/empty2"), b"").expect("write");
+        // Same start and size, different ending.
+        let mut long = vec![7; PARTIAL_HASH_LEN as usize + 10];
+        fs::write(dir.join("a/long1"), &long).expect("write");
+        long[PARTIAL_HASH_LEN as usize + 5] = 8;
+        fs::write(dir.join("a/long2"), &long).expect("write");
+
+        let roots = [dir.join("a"), dir.join("other")];
+        let groups = find(&roots, false, &AtomicBool::new(false), &|_| {});
+        assert_eq!(groups.len(), 1);
+        let mut paths = groups[0]
+            .files
+            .iter()
+            .map(|file| file.path.clone())
+            .collect::<Vec<_>>();
+        paths.sort();
+        assert_eq!(
+            paths,
+            [
+                dir.join("a/b/two.txt"),
+                dir.join("a/one.txt"),
+                dir.join("other/three.txt")
+            ]
+        );
+        assert_eq!(groups[0].wasted(), 8);
+
+        let with_hidden = find(&roots, true, &AtomicBool::new(false), &|_| {});
+        assert_eq!(with_hidden[0].files.len(), 4);
+        assert!(find(&roots, false, &AtomicBool::new(true), &|_| {}).is_empty());
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn duplicates_are_replaced_with_hard_links() {
+        let dir = unique_test_dir("duplicates_link");
+        fs::write(dir.join("original"), b"same").expect("write");
+        fs::write(dir.join("copy"), b"same").expect("write");
+
+        let roots = std::slice::from_ref(&dir);
+        let groups = find(roots, false, &AtomicBool::new(false), &|_| {});
+        let [original, copy] = groups[0].files.as_slice() else {
+            panic!("expected two files, got {groups:?}");
+        };
+        let (original, copy) = if original.path.ends_with("original") {
+            (original, copy)
+        } else {
+            (copy, original)
+        };
+
+        // The copy was edited after the scan, so it is kept.
+        fs::write(dir.join("copy"), b"edit").expect("write");
+        assert!(replace_with_hard_link(&groups[0], original, copy).is_err());
+        assert_eq!(fs::read(dir.join("copy")).expect("read"), b"edit");
+        fs::write(dir.join("copy"), b"same").expect("write");
+        let copy = &super::DuplicateFile {
+            modified: fs::metadata(dir.join("copy"))
+                .and_then(|meta| meta.modified())
+                .ok(),
+            ..copy.clone()
+        };
+
+        // Whatever is at the first staging name isn't ours to remove.
+        fs::write(dir.join(".copy.lwa_fm-0"), b"mine").expect("write");
+
+        replace_with_hard_link(&groups[0], original, copy).expect("link");
+        assert_eq!(fs::read(dir.join("copy")).expect("read"), b"same");
+        assert_eq!(fs::read(dir.join(".copy.lwa_fm-0")).expect("read"), b"mine");
+        assert_eq!(fs::read_dir(&dir).expect("list").count(), 3);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::MetadataExt;
+            let ino = |name: &str| fs::metadata(dir.join(name)).expect("metadata").ino();
+            assert_eq!(ino("original"), ino("copy"));
+            // Linked files count once.
+            assert!(find(roots, false, &AtomicBool::new(false), &|_| {}).is_empty());
+        }
+        let _ = fs::remove_dir_all(dir);
+    }
+}
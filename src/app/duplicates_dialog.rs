@@ -0,0 +1,396 @@
+use std::{
+    collections::HashSet,
+    path::{Path, PathBuf},
+    sync::{
+        Arc, Mutex, OnceLock,
+        atomic::{AtomicBool, Ordering},
+    },
+};
+
+use egui::{Context, Id, Ui};
+
+use super::App;
+use super::commands::{ActionToPerform, ModalWindow, TabAction};
+use super::duplicates::{self, DuplicateGroup};
+use super::file_ops;
+use super::journal::{self, JournalOperation};
+use crate::data::time::TimestampSeconds;
+use crate::helper::format_bytes_simple;
+use crate::toast;
+
+/// What the user asked to do with the marked files.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Cleanup {
+    Trash,
+    HardLink,
+}
+
+/// Outcome of a cleanup, once it has run in the background.
+#[derive(Debug, Clone, Default)]
+struct CleanupReport {
+    /// Files that no longer take space of their own.
+    done: Vec<PathBuf>,
+    errors: Vec<String>,
+}
+
+#[derive(Debug, Clone)]
+struct DuplicatesState {
+    roots: Vec<PathBuf>,
+    /// Latest progress message of the scan.
+    status: Arc<Mutex<String>>,
+    cancel: Arc<AtomicBool>,
+    /// Filled in once the scan is done.
+    scan: Arc<OnceLock<Vec<DuplicateGroup>>>,
+    /// The scan result, minus files cleaned up since.
+    groups: Option<Vec<DuplicateGroup>>,
+    /// Files to trash or replace with hard links.
+    marked: HashSet<PathBuf>,
+    cleanup: Option<(Cleanup, Arc<OnceLock<CleanupReport>>)>,
+}
+
+impl DuplicatesState {
+    /// Groups in which every file is marked, which would leave no copy.
+    fn fully_marked(&self) -> usize {
+        self.groups.as_ref().map_or(0, |groups| {
+            groups
+                .iter()
+                .filter(|group| {
+                    group
+                        .files
+                        .iter()
+                        .all(|file| self.marked.contains(&file.path))
+                })
+                .count()
+        })
+    }
+
+    fn keep_newest(&mut self) {
+        self.marked.clear();
+        for group in self.groups.iter().flatten() {
+            let newest = group.newest().map(|file| file.path.clone());
+            self.marked.extend(
+                group
+                    .files
+                    .iter()
+                    .map(|file| file.path.clone())
+                    .filter(|path| Some(path) != newest.as_ref()),
+            );
+        }
+    }
+
+    /// Starts `cleanup` of the marked files in the background.
+    fn start(&mut self, ctx: &Context, cleanup: Cleanup) {
+        let report = Arc::new(OnceLock::new());
+        self.cleanup = Some((cleanup, Arc::clone(&report)));
+        let groups = self.groups.clone().unwrap_or_default();
+        let marked = self.marked.clone();
+        let ctx = ctx.clone();
+        file_ops::spawn_task(move || {
+            let _ = report.set(match cleanup {
+                Cleanup::Trash => trash_marked(&groups, &marked),
+                Cleanup::HardLink => link_marked(&groups, &marked),
+            });
+            ctx.request_repaint();
+        });
+    }
+
+    /// Takes the finished cleanup's report, dropping the files it handled.
+    fn finish_cleanup(&mut self) -> Option<(Cleanup, CleanupReport)> {
+        let (cleanup, report) = self.cleanup.as_ref()?;
+        let report = report.get()?.clone();
+        let cleanup = *cleanup;
+        self.cleanup = None;
+        let done = report.done.iter().collect::<HashSet<_>>();
+        if let Some(groups) = &mut self.groups {
+            for group in groups.iter_mut() {
+                group.files.retain(|file| !done.contains(&file.path));
+            }
+            groups.retain(|group| group.files.len() > 1);
+        }
+        self.marked.clear();
+        Some((cleanup, report))
+    }
+}
+
+fn trash_marked(groups: &[DuplicateGroup], marked: &HashSet<PathBuf>) -> CleanupReport {
+    let paths = groups
+        .iter()
+        .flat_map(|group| &group.files)
+        .map(|file| file.path.clone())
+        .filter(|path| marked.contains(path))
+        .collect::<Vec<_>>();
+    match trash::delete_all(&paths) {
+        Ok(()) => CleanupReport {
+            done: paths,
+            errors: Vec::new(),
+        },
+        Err(err) => CleanupReport {
+            done: Vec::new(),
+            errors: vec![err.to_string()],
+        },
+    }
+}
+
+fn link_marked(groups: &[DuplicateGroup], marked: &HashSet<PathBuf>) -> CleanupReport {
+    let mut report = CleanupReport::default();
+    for group in groups {
+        let Some(original) = group.files.iter().find(|file| !marked.contains(&file.path)) else {
+            continue;
+        };
+        for file in group
+            .files
+            .iter()
+            .filter(|file| marked.contains(&file.path))
+        {
+            match duplicates::replace_with_hard_link(group, original, file) {
+                Ok(()) => report.done.push(file.path.clone()),
+                Err(err) => report.errors.push(format!("{err:#}")),
+            }
+        }
+    }
+    report
+}
+
+fn state_id() -> Id {
+    Id::new(ModalWindow::Duplicates)
+}
+
+/// Looks for duplicate files below `roots` in the background and shows them
+/// once found.
+pub fn open(ctx: &Context, roots: Vec<PathBuf>, show_hidden: bool) {
+    let status = Arc::new(Mutex::new(String::new()));
+    let cancel = Arc::new(AtomicBool::new(false));
+    let scan = Arc::new(OnceLock::new());
+    {
+        let status = Arc::clone(&status);
+        let cancel = Arc::clone(&cancel);
+        let scan = Arc::clone(&scan);
+        let roots = roots.clone();
+        let ctx = ctx.clone();
+        file_ops::spawn_task(move || {
+            let progress = |message: String| {
+                if let Ok(mut status) = status.lock() {
+                    *status = message;
+                }
+                ctx.request_repaint();
+            };
+            let _ = scan.set(duplicates::find(&roots, show_hidden, &cancel, &progress));
+            ctx.request_repaint();
+        });
+    }
+    let state = DuplicatesState {
+        roots,
+        status,
+        cancel,
+        scan,
+        groups: None,
+        marked: HashSet::new(),
+        cleanup: None,
+    };
+    ctx.data_mut(|d| d.insert_temp(state_id(), state));
+    ActionToPerform::ToggleModalWindow(ModalWindow::Duplicates).schedule();
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name().map_or_else(
+        || path.display().to_string(),
+        |name| name.to_string_lossy().to_string(),
+    )
+}
+
+impl App {
+    /// Duplicate files grouped by contents, with ways to get rid of them.
+    pub(crate) fn duplicates_modal(&mut self, ctx: &Context) {
+        #[cfg(feature = "profiling")]
+        puffin::profile_scope!("lwa_fm::duplicates_modal");
+        let Some(mut state) = ctx.data(|d| d.get_temp::<DuplicatesState>(state_id())) else {
+            self.display_modal = None;
+            return;
+        };
+        if state.groups.is_none() {
+            state.groups = state.scan.get().cloned();
+        }
+        if let Some((cleanup, report)) = state.finish_cleanup() {
+            let dirs = report
+                .done
+                .iter()
+                .filter_map(|path| path.parent().map(Path::to_path_buf))
+                .collect::<HashSet<_>>();
+            self.refresh_changed_dirs(dirs.into_iter().collect());
+            if cleanup == Cleanup::Trash && !report.done.is_empty() {
+                journal::record(&JournalOperation::Trash(report.done.clone()));
+            }
+            if let Some(error) = report.errors.first() {
+                for error in &report.errors {
+                    log::error!("Duplicate cleanup: {error}");
+                }
+                toast!(Error, "{} item(s) failed: {error}", report.errors.len());
+            } else {
+                match cleanup {
+                    Cleanup::Trash => {
+                        toast!(Success, "Moved {} file(s) to Trash", report.done.len());
+                    }
+                    Cleanup::HardLink => {
+                        toast!(
+                            Success,
+                            "Replaced {} file(s) with hard links",
+                            report.done.len()
+                        );
+                    }
+                }
+            }
+        }
+
+        let mut close = false;
+        let modal = egui::Modal::new(state_id()).show(ctx, |ui| {
+            ui.set_width(720.0);
+            ui.heading("Duplicates");
+            ui.weak(
+                state
+                    .roots
+                    .iter()
+                    .map(|root| root.display().to_string())
+                    .collect::<Vec<_>>()
+                    .join(", "),
+            );
+            ui.separator();
+            if state.groups.is_some() {
+                groups_ui(ui, &mut state, &mut close);
+                ui.separator();
+                buttons_ui(ui, &mut state);
+            } else {
+                ui.horizontal(|ui| {
+                    ui.spinner();
+                    ui.label(state.status.lock().map(|s| s.clone()).unwrap_or_default());
+                });
+                if ui.button("Cancel").clicked() {
+                    ui.close();
+                }
+            }
+        });
+        if close || modal.should_close() {
+            state.cancel.store(true, Ordering::Relaxed);
+            ctx.data_mut(|d| d.remove::<DuplicatesState>(state_id()));
+            self.display_modal = None;
+        } else {
+            ctx.data_mut(|d| d.insert_temp(state_id(), state));
+        }
+    }
+}
+
+fn groups_ui(ui: &mut Ui, state: &mut DuplicatesState, close: &mut bool) {
+    let Some(groups) = &state.groups else {
+        return;
+    };
+    if groups.is_empty() {
+        ui.weak("No duplicate files found");
+        return;
+    }
+    ui.label(format!(
+        "{} sets of duplicates, {} can be freed",
+        groups.len(),
+        format_bytes_simple(groups.iter().map(DuplicateGroup::wasted).sum())
+    ));
+    egui::ScrollArea::vertical()
+        .max_height(420.0)
+        .auto_shrink([false, true])
+        .show(ui, |ui| {
+            for (index, group) in groups.iter().enumerate() {
+                let title = format!(
+                    "{} × {} ({})",
+                    group.files.len(),
+                    file_name(&group.files[0].path),
+                    format_bytes_simple(group.size)
+                );
+                egui::CollapsingHeader::new(title)
+                    .id_salt(("duplicate_group", index))
+                    .default_open(true)
+                    .show(ui, |ui| {
+                        for file in &group.files {
+                            ui.horizontal(|ui| {
+                                let mut marked = state.marked.contains(&file.path);
+                                if ui.checkbox(&mut marked, "").changed() {
+                                    if marked {
+                                        state.marked.insert(file.path.clone());
+                                    } else {
+                                        state.marked.remove(&file.path);
+                                    }
+                                }
+                                if let Some(modified) = file.modified {
+                                    ui.weak(TimestampSeconds::from(modified).format_utc());
+                                }
+                                if ui.small_button("Show").clicked() {
+                                    if let Some(parent) = file.path.parent() {
+                                        TabAction::ChangePaths(parent.to_path_buf().into())
+                                            .schedule_active_tab();
+                                        TabAction::RevealEntry {
+                                            path: file.path.clone(),
+                                            rename: false,
+                                        }
+                                        .schedule_active_tab();
+                                    }
+                                    *close = true;
+                                }
+                                ui.label(file.path.display().to_string());
+                            });
+                        }
+                    });
+            }
+        });
+}
+
+fn buttons_ui(ui: &mut Ui, state: &mut DuplicatesState) {
+    let fully_marked = state.fully_marked();
+    if fully_marked > 0 {
+        ui.colored_label(
+            ui.visuals().warn_fg_color,
+            format!("Every file is marked in {fully_marked} set(s), keep at least one"),
+        );
+    }
+    ui.horizontal(|ui| {
+        let idle = state.cleanup.is_none();
+        let has_groups = state
+            .groups
+            .as_ref()
+            .is_some_and(|groups| !groups.is_empty());
+        if ui
+            .add_enabled(idle && has_groups, egui::Button::new("Keep newest"))
+            .on_hover_text("Mark every file but the newest of each set")
+            .clicked()
+        {
+            state.keep_newest();
+        }
+        if ui
+            .add_enabled(
+                idle && !state.marked.is_empty(),
+                egui::Button::new("Clear marks"),
+            )
+            .clicked()
+        {
+            state.marked.clear();
+        }
+        ui.separator();
+        let can_clean = idle && !state.marked.is_empty() && fully_marked == 0;
+        let label = format!("{} marked", state.marked.len());
+        ui.label(label);
+        if ui
+            .add_enabled(can_clean, egui::Button::new("Move to Trash"))
+            .clicked()
+        {
+            state.start(ui.ctx(), Cleanup::Trash);
+        }
+        if ui
+            .add_enabled(can_clean, egui::Button::new("Replace with hard links"))
+            .on_hover_text("Keep one copy of each set and link the marked files to it")
+            .clicked()
+        {
+            state.start(ui.ctx(), Cleanup::HardLink);
+        }
+        if !idle {
+            ui.spinner();
+        }
+        if ui.button("Close").clicked() {
+            ui.close();
+        }
+    });
+}
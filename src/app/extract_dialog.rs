@@ -0,0 +1,122 @@
+use std::path::{Path, PathBuf};
+
+use egui::{Context, Id};
+
+use super::App;
+use super::commands::{ActionToPerform, ModalWindow};
+use super::file_ops::FileOperation;
+
+#[derive(Debug, Clone)]
+struct ExtractState {
+    archives: Vec<PathBuf>,
+    destination: String,
+    /// Folders open in other tabs, offered as destinations.
+    suggestions: Vec<PathBuf>,
+}
+
+impl ExtractState {
+    /// The folder to extract to, or why it can't be used.
+    fn destination(&self) -> Result<PathBuf, String> {
+        let destination = PathBuf::from(self.destination.trim());
+        if !destination.is_absolute() {
+            return Err("Enter the full path of a folder".to_string());
+        }
+        if destination.exists() && !destination.is_dir() {
+            return Err(format!("{} is not a folder", destination.display()));
+        }
+        Ok(destination)
+    }
+}
+
+fn state_id() -> Id {
+    Id::new(ModalWindow::Extract)
+}
+
+/// Asks for the folder to extract `archives` to, suggesting `suggestions`.
+pub fn open(ctx: &Context, archives: Vec<PathBuf>, suggestions: Vec<PathBuf>) {
+    let Some(dir) = archives.first().and_then(|archive| archive.parent()) else {
+        return;
+    };
+    let state = ExtractState {
+        destination: dir.display().to_string(),
+        archives,
+        suggestions,
+    };
+    ctx.data_mut(|d| d.insert_temp(state_id(), state));
+    ActionToPerform::ToggleModalWindow(ModalWindow::Extract).schedule();
+}
+
+fn folder_name(path: &Path) -> String {
+    path.file_name().map_or_else(
+        || path.display().to_string(),
+        |name| name.to_string_lossy().to_string(),
+    )
+}
+
+impl App {
+    /// Extracts archives to a chosen folder as a background file job.
+    pub(crate) fn extract_modal(&mut self, ctx: &Context) {
+        #[cfg(feature = "profiling")]
+        puffin::profile_scope!("lwa_fm::extract_modal");
+        let Some(mut state) = ctx.data(|d| d.get_temp::<ExtractState>(state_id())) else {
+            self.display_modal = None;
+            return;
+        };
+        let mut confirmed = false;
+        let modal = egui::Modal::new(state_id()).show(ctx, |ui| {
+            ui.set_width(420.0);
+            ui.heading(match state.archives.as_slice() {
+                [archive] => format!("Extract {}", folder_name(archive)),
+                archives => format!("Extract {} archives", archives.len()),
+            });
+            ui.separator();
+            ui.label("Destination folder");
+            let response = ui.add(
+                egui::TextEdit::singleline(&mut state.destination).desired_width(f32::INFINITY),
+            );
+            if response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter)) {
+                confirmed = true;
+            }
+            if !state.suggestions.is_empty() {
+                ui.horizontal_wrapped(|ui| {
+                    ui.weak("Open tabs:");
+                    for suggestion in &state.suggestions {
+                        if ui
+                            .small_button(folder_name(suggestion))
+                            .on_hover_text(suggestion.display().to_string())
+                            .clicked()
+                        {
+                            state.destination = suggestion.display().to_string();
+                        }
+                    }
+                });
+            }
+            ui.separator();
+            let destination = state.destination();
+            if let Err(error) = &destination {
+                ui.colored_label(ui.visuals().error_fg_color, error);
+            }
+            ui.horizontal(|ui| {
+                if ui
+                    .add_enabled(destination.is_ok(), egui::Button::new("Extract"))
+                    .clicked()
+                {
+                    confirmed = true;
+                }
+                if ui.button("Cancel").clicked() {
+                    ui.close();
+                }
+            });
+            confirmed &= destination.is_ok();
+        });
+        if confirmed && let Ok(destination) = state.destination() {
+            FileOperation::extract(state.archives.clone(), destination).schedule();
+        }
+        if confirmed || modal.should_close() {
+            ctx.data_mut(|d| d.remove::<ExtractState>(state_id()));
+            self.display_modal = None;
+        } else {
+            ctx.data_mut(|d| d.insert_temp(state_id(), state));
+        }
+    }
+}
@@ -0,0 +1,1558 @@
+//! Background file operations (copy, move, delete, compress, extract, sync).
+//!
+//! Jobs run on a dedicated worker pool so large transfers never block the UI.
+//! Every job exposes a [`JobProgress`] that the bottom panel polls each frame,
+//! and posts a [`JobReport`] back through `COMMANDS_QUEUE` once it is done.
+
+use std::{
+    collections::VecDeque,
+    fmt::Display,
+    fs::{self, File, OpenOptions},
+    io::{ErrorKind, Read, Write},
+    path::{Path, PathBuf},
+    sync::{
+        Arc, LazyLock, Mutex,
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        mpsc,
+    },
+    thread,
+    time::{Duration, Instant, SystemTime},
+};
+
+use anyhow::{Context, Result, bail};
+use rayon::ThreadPoolBuilder;
+
+use crate::app::archive;
+use crate::app::commands::ActionToPerform;
+use crate::app::sync::{self, SyncPlan, SyncStep};
+
+const COPY_BUFFER_SIZE: usize = 1024 * 1024;
+const PAUSE_POLL_INTERVAL: Duration = Duration::from_millis(100);
+/// How long watcher events below an extraction's destination keep being
+/// ignored once it finished, for events that arrive late.
+const EXTRACTION_QUIET_GRACE: Duration = Duration::from_millis(500);
+
+/// Dedicated pool for file operations. Kept apart from `BG_POOL` so a long
+/// copy never stalls directory listings.
+static FILE_OPS_POOL: LazyLock<rayon::ThreadPool> = LazyLock::new(|| {
+    ThreadPoolBuilder::new()
+        .num_threads(2)
+        .thread_name(|i| format!("lwa_fm_ops_{i}"))
+        .build()
+        .expect("Failed to create file operations thread pool")
+});
+
+static NEXT_JOB_ID: AtomicU64 = AtomicU64::new(1);
+
+/// Jobs that are queued or running. Finished jobs are removed before their
+/// report is posted.
+static ACTIVE_JOBS: LazyLock<Mutex<Vec<FileJob>>> = LazyLock::new(|| Mutex::new(Vec::new()));
+
+/// Destinations of finished extractions, with when their grace period ends.
+static FINISHED_EXTRACTIONS: LazyLock<Mutex<Vec<(PathBuf, Instant)>>> =
+    LazyLock::new(|| Mutex::new(Vec::new()));
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FileOperationKind {
+    Copy,
+    Move,
+    Delete,
+    /// Write the sources into a new archive, in the format the target's
+    /// extension names.
+    Compress { level: u32 },
+    /// Unpack the source archives into the target directory.
+    Extract,
+    /// Apply the steps of a sync plan.
+    Sync,
+}
+
+impl Display for FileOperationKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Copy => write!(f, "Copy"),
+            Self::Move => write!(f, "Move"),
+            Self::Delete => write!(f, "Delete"),
+            Self::Compress { .. } => write!(f, "Compress"),
+            Self::Extract => write!(f, "Extract"),
+            Self::Sync => write!(f, "Sync"),
+        }
+    }
+}
+
+/// What to do when an item already exists at the destination.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ConflictResolution {
+    Skip,
+    Overwrite,
+    /// Overwrite only when the incoming item was modified later.
+    OverwriteIfNewer,
+    /// Keep the existing item and add the new one as `name (2).ext`.
+    KeepBoth,
+}
+
+impl ConflictResolution {
+    pub const ALL: [Self; 4] = [
+        Self::Skip,
+        Self::Overwrite,
+        Self::OverwriteIfNewer,
+        Self::KeepBoth,
+    ];
+}
+
+impl Display for ConflictResolution {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Skip => write!(f, "Skip"),
+            Self::Overwrite => write!(f, "Overwrite"),
+            Self::OverwriteIfNewer => write!(f, "Overwrite if newer"),
+            Self::KeepBoth => write!(f, "Keep both"),
+        }
+    }
+}
+
+/// A destination that already exists, waiting for the user to decide.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileConflict {
+    pub job_id: u64,
+    pub source: PathBuf,
+    pub destination: PathBuf,
+}
+
+#[derive(Debug)]
+struct PendingConflict {
+    conflict: FileConflict,
+    reply: mpsc::Sender<(ConflictResolution, bool)>,
+}
+
+/// Conflicts of all running jobs, oldest first.
+static PENDING_CONFLICTS: LazyLock<Mutex<VecDeque<PendingConflict>>> =
+    LazyLock::new(|| Mutex::new(VecDeque::new()));
+
+/// The conflict the UI should ask about next.
+pub fn next_conflict() -> Option<FileConflict> {
+    PENDING_CONFLICTS
+        .lock()
+        .ok()?
+        .front()
+        .map(|pending| pending.conflict.clone())
+}
+
+/// Answers the oldest conflict of `job_id`. With `apply_to_all` the job stops
+/// asking and uses `resolution` for every following conflict.
+pub fn resolve_conflict(job_id: u64, resolution: ConflictResolution, apply_to_all: bool) {
+    let Ok(mut pending) = PENDING_CONFLICTS.lock() else {
+        return;
+    };
+    if let Some(index) = pending
+        .iter()
+        .position(|pending| pending.conflict.job_id == job_id)
+        && let Some(pending) = pending.remove(index)
+    {
+        let _ = pending.reply.send((resolution, apply_to_all));
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileOperation {
+    pub kind: FileOperationKind,
+    pub sources: Vec<PathBuf>,
+    /// Destination directory, or the archive file when compressing. `None`
+    /// for deletes.
+    pub target: Option<PathBuf>,
+    /// Resolution for existing destinations. `None` asks the user.
+    pub on_conflict: Option<ConflictResolution>,
+    /// What a sync changes, the sources and target only name the folders.
+    pub sync_plan: Option<Arc<SyncPlan>>,
+}
+
+impl FileOperation {
+    pub const fn new(
+        kind: FileOperationKind,
+        sources: Vec<PathBuf>,
+        target: Option<PathBuf>,
+    ) -> Self {
+        Self {
+            kind,
+            sources,
+            target,
+            on_conflict: None,
+            sync_plan: None,
+        }
+    }
+
+    pub fn copy(sources: Vec<PathBuf>, target: impl Into<PathBuf>) -> Self {
+        Self::new(FileOperationKind::Copy, sources, Some(target.into()))
+    }
+
+    pub fn move_to(sources: Vec<PathBuf>, target: impl Into<PathBuf>) -> Self {
+        Self::new(FileOperationKind::Move, sources, Some(target.into()))
+    }
+
+    pub const fn delete(sources: Vec<PathBuf>) -> Self {
+        Self::new(FileOperationKind::Delete, sources, None)
+    }
+
+    pub fn compress(sources: Vec<PathBuf>, archive: impl Into<PathBuf>, level: u32) -> Self {
+        Self::new(
+            FileOperationKind::Compress { level },
+            sources,
+            Some(archive.into()),
+        )
+    }
+
+    pub fn extract(archives: Vec<PathBuf>, target: impl Into<PathBuf>) -> Self {
+        Self::new(FileOperationKind::Extract, archives, Some(target.into()))
+    }
+
+    pub fn sync(plan: SyncPlan) -> Self {
+        let mut operation = Self::new(
+            FileOperationKind::Sync,
+            vec![plan.job.source.clone()],
+            Some(plan.job.target.clone()),
+        );
+        operation.sync_plan = Some(Arc::new(plan));
+        operation
+    }
+
+    #[must_use]
+    pub const fn with_conflict_resolution(mut self, resolution: ConflictResolution) -> Self {
+        self.on_conflict = Some(resolution);
+        self
+    }
+
+    /// Directories whose listing changes once the operation has run.
+    pub fn affected_dirs(&self) -> Vec<PathBuf> {
+        if let Some(plan) = &self.sync_plan {
+            return plan.affected_dirs();
+        }
+        if let FileOperationKind::Compress { .. } = self.kind {
+            return self
+                .target
+                .as_deref()
+                .and_then(Path::parent)
+                .map(Path::to_path_buf)
+                .into_iter()
+                .collect();
+        }
+        let mut dirs = Vec::new();
+        if matches!(
+            self.kind,
+            FileOperationKind::Move | FileOperationKind::Delete
+        ) {
+            dirs.extend(
+                self.sources
+                    .iter()
+                    .filter_map(|source| source.parent().map(Path::to_path_buf)),
+            );
+        }
+        dirs.extend(self.target.iter().cloned());
+        if self.kind == FileOperationKind::Extract {
+            // The destination folder may be new.
+            dirs.extend(
+                self.target
+                    .as_deref()
+                    .and_then(Path::parent)
+                    .map(Path::to_path_buf),
+            );
+        }
+        dirs.sort();
+        dirs.dedup();
+        dirs
+    }
+
+    /// Short human readable description, e.g. `Copy 3 items to Documents`.
+    pub fn label(&self) -> String {
+        let items = match self.sources.as_slice() {
+            [single] => single.file_name().map_or_else(
+                || single.display().to_string(),
+                |name| name.to_string_lossy().to_string(),
+            ),
+            sources => format!("{} items", sources.len()),
+        };
+        self.target.as_ref().map_or_else(
+            || format!("{} {items}", self.kind),
+            |target| {
+                let target_name = target.file_name().map_or_else(
+                    || target.display().to_string(),
+                    |name| name.to_string_lossy().to_string(),
+                );
+                format!("{} {items} to {target_name}", self.kind)
+            },
+        )
+    }
+
+    /// Queues the operation on the file operations pool and returns the job id.
+    pub fn schedule(self) -> u64 {
+        let job = FileJob::new(NEXT_JOB_ID.fetch_add(1, Ordering::Relaxed), self);
+        let id = job.id;
+        ACTIVE_JOBS
+            .lock()
+            .expect("file jobs mutex poisoned")
+            .push(job.clone());
+        FILE_OPS_POOL.spawn(move || {
+            let report = job.run();
+            if report.operation.kind == FileOperationKind::Extract
+                && let Some(target) = &report.operation.target
+                && let Ok(mut finished) = FINISHED_EXTRACTIONS.lock()
+            {
+                let now = Instant::now();
+                finished.retain(|(_, until)| *until > now);
+                finished.push((target.clone(), now + EXTRACTION_QUIET_GRACE));
+            }
+            ACTIVE_JOBS
+                .lock()
+                .expect("file jobs mutex poisoned")
+                .retain(|active| active.id != report.id);
+            ActionToPerform::FileJobFinished(report).schedule();
+        });
+        id
+    }
+}
+
+/// Live counters of a running job, shared between the worker and the UI.
+#[derive(Debug, Default)]
+pub struct JobProgress {
+    bytes_done: AtomicU64,
+    bytes_total: AtomicU64,
+    files_done: AtomicU64,
+    files_total: AtomicU64,
+    cancelled: AtomicBool,
+    paused: AtomicBool,
+    current: Mutex<Option<PathBuf>>,
+    /// Set once the user picked "Apply to all" (or the operation preset one).
+    conflict_policy: Mutex<Option<ConflictResolution>>,
+}
+
+impl JobProgress {
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+
+    pub fn set_paused(&self, paused: bool) {
+        self.paused.store(paused, Ordering::Relaxed);
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused.load(Ordering::Relaxed)
+    }
+
+    /// `(done, total)` bytes.
+    pub fn bytes(&self) -> (u64, u64) {
+        (
+            self.bytes_done.load(Ordering::Relaxed),
+            self.bytes_total.load(Ordering::Relaxed),
+        )
+    }
+
+    /// `(done, total)` files.
+    pub fn files(&self) -> (u64, u64) {
+        (
+            self.files_done.load(Ordering::Relaxed),
+            self.files_total.load(Ordering::Relaxed),
+        )
+    }
+
+    /// Progress in `0.0..=1.0`, by bytes when there are any, by files otherwise.
+    pub fn fraction(&self) -> f32 {
+        let (bytes_done, bytes_total) = self.bytes();
+        if bytes_total > 0 {
+            return (bytes_done as f64 / bytes_total as f64).min(1.0) as f32;
+        }
+        let (files_done, files_total) = self.files();
+        if files_total > 0 {
+            return (files_done as f64 / files_total as f64).min(1.0) as f32;
+        }
+        0.0
+    }
+
+    pub fn current_file(&self) -> Option<PathBuf> {
+        self.current.lock().ok().and_then(|current| current.clone())
+    }
+
+    pub fn set_current(&self, path: &Path) {
+        if let Ok(mut current) = self.current.lock() {
+            *current = Some(path.to_path_buf());
+        }
+    }
+
+    fn conflict_policy(&self) -> Option<ConflictResolution> {
+        self.conflict_policy.lock().ok().and_then(|policy| *policy)
+    }
+
+    pub fn add_bytes(&self, bytes: u64) {
+        self.bytes_done.fetch_add(bytes, Ordering::Relaxed);
+    }
+
+    pub fn add_files(&self, files: u64) {
+        self.files_done.fetch_add(files, Ordering::Relaxed);
+    }
+
+    /// Blocks while the job is paused and fails once it has been cancelled.
+    pub fn checkpoint(&self) -> Result<()> {
+        while self.is_paused() && !self.is_cancelled() {
+            thread::sleep(PAUSE_POLL_INTERVAL);
+        }
+        if self.is_cancelled() {
+            return Err(Cancelled.into());
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+pub struct Cancelled;
+
+impl Display for Cancelled {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Operation cancelled")
+    }
+}
+
+impl std::error::Error for Cancelled {}
+
+#[derive(Debug, Clone)]
+pub struct FileJob {
+    pub id: u64,
+    pub operation: FileOperation,
+    pub progress: Arc<JobProgress>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum JobOutcome {
+    Completed,
+    Cancelled,
+    /// Finished, but some items failed. Holds one message per failed item.
+    Failed(Vec<String>),
+}
+
+#[derive(Debug, Clone)]
+pub struct JobReport {
+    pub id: u64,
+    pub operation: FileOperation,
+    pub outcome: JobOutcome,
+    pub files_done: u64,
+    pub bytes_done: u64,
+    /// `(source, destination)` of every item that was fully copied or moved.
+    pub transferred: Vec<(PathBuf, PathBuf)>,
+    /// Destinations among `transferred` that took the place of an existing
+    /// item, which is gone for good.
+    pub replaced: Vec<PathBuf>,
+}
+
+/// Snapshot of the jobs that are currently queued or running.
+pub fn active_jobs() -> Vec<FileJob> {
+    ACTIVE_JOBS
+        .lock()
+        .map(|jobs| jobs.clone())
+        .unwrap_or_default()
+}
+
+pub fn has_active_jobs() -> bool {
+    ACTIVE_JOBS.lock().is_ok_and(|jobs| !jobs.is_empty())
+}
+
+/// Whether watcher events for `dir` come from an extraction. Those write many
+/// files, so instead of refreshing for each the finished job refreshes its
+/// destination once. Only the destination and what's below it are quiet,
+/// changes next to it still show up.
+pub fn is_quiet(dir: &Path) -> bool {
+    let quiet = |target: &Path| dir.starts_with(target);
+    let now = Instant::now();
+    ACTIVE_JOBS.lock().is_ok_and(|jobs| {
+        jobs.iter().any(|job| {
+            job.operation.kind == FileOperationKind::Extract
+                && job.operation.target.as_deref().is_some_and(quiet)
+        })
+    }) || FINISHED_EXTRACTIONS.lock().is_ok_and(|finished| {
+        finished
+            .iter()
+            .any(|(target, until)| *until > now && quiet(target))
+    })
+}
+
+impl FileJob {
+    fn new(id: u64, operation: FileOperation) -> Self {
+        let progress = JobProgress {
+            conflict_policy: Mutex::new(operation.on_conflict),
+            ..JobProgress::default()
+        };
+        Self {
+            id,
+            operation,
+            progress: Arc::new(progress),
+        }
+    }
+
+    /// `(files, bytes)` below the sources.
+    fn measure_sources(&self) -> (u64, u64) {
+        self.operation
+            .sources
+            .iter()
+            .map(|source| {
+                if self.operation.kind == FileOperationKind::Extract {
+                    archive::measure(source)
+                } else {
+                    measure(source)
+                }
+            })
+            .fold((0, 0), |(files, bytes), (source_files, source_bytes)| {
+                (files + source_files, bytes + source_bytes)
+            })
+    }
+
+    fn run(&self) -> JobReport {
+        #[cfg(feature = "profiling")]
+        puffin::profile_scope!("lwa_fm::file_ops::run");
+        let progress = &self.progress;
+        let mut errors = Vec::new();
+        let mut transferred = Vec::new();
+        let mut replaced = Vec::new();
+        let mut cancelled = false;
+
+        let (files, bytes) = self
+            .operation
+            .sync_plan
+            .as_ref()
+            .map_or_else(|| self.measure_sources(), |plan| plan.totals());
+        progress.files_total.fetch_add(files, Ordering::Relaxed);
+        progress.bytes_total.fetch_add(bytes, Ordering::Relaxed);
+
+        if let Some(plan) = &self.operation.sync_plan {
+            match run_sync(plan, progress) {
+                Ok(failed) => errors = failed,
+                Err(err) if err.is::<Cancelled>() => cancelled = true,
+                Err(err) => errors.push(format!("{err:#}")),
+            }
+        } else if let (FileOperationKind::Compress { level }, Some(target)) =
+            (self.operation.kind, &self.operation.target)
+        {
+            match archive::compress(&self.operation.sources, target, level, progress) {
+                Ok(()) => {}
+                Err(err) if err.is::<Cancelled>() => cancelled = true,
+                Err(err) => errors.push(format!("{}: {err:#}", target.display())),
+            }
+        } else {
+            for source in &self.operation.sources {
+                let result = match (self.operation.kind, &self.operation.target) {
+                    (FileOperationKind::Copy, Some(target)) => {
+                        self.resolve_destination(source, target).and_then(|dst| {
+                            dst.map(|dst| {
+                                let replaces = dst.replaces();
+                                place(dst, |path| copy_tree(source, path, progress))
+                                    .map(|dst| (dst, replaces))
+                            })
+                            .transpose()
+                        })
+                    }
+                    (FileOperationKind::Move, Some(target)) => {
+                        self.resolve_destination(source, target).and_then(|dst| {
+                            dst.map(|dst| {
+                                let replaces = dst.replaces();
+                                let mut copied = false;
+                                let dst = place(dst, |path| {
+                                    copied = rename_or_copy(source, path, progress)?;
+                                    Ok(())
+                                })?;
+                                if copied {
+                                    remove_tree(source, None)?;
+                                }
+                                Ok((dst, replaces))
+                            })
+                            .transpose()
+                        })
+                    }
+                    (FileOperationKind::Extract, Some(target)) => {
+                        self.extract(source, target).map(|()| None)
+                    }
+                    (FileOperationKind::Delete, _) => {
+                        remove_tree(source, Some(progress)).map(|()| None)
+                    }
+                    (FileOperationKind::Compress { .. }, Some(_)) => Err(anyhow::anyhow!(
+                        "Archives are written from all sources at once"
+                    )),
+                    (FileOperationKind::Sync, _) => Err(anyhow::anyhow!("Sync without a plan")),
+                    (_, None) => Err(anyhow::anyhow!("No destination directory")),
+                };
+                match result {
+                    Ok(Some((destination, replaces))) => {
+                        if replaces {
+                            replaced.push(destination.clone());
+                        }
+                        transferred.push((source.clone(), destination));
+                    }
+                    Ok(None) => {}
+                    Err(err) if err.is::<Cancelled>() => {
+                        cancelled = true;
+                        break;
+                    }
+                    Err(err) => errors.push(format!("{}: {err:#}", source.display())),
+                }
+            }
+        }
+
+        let outcome = if cancelled {
+            JobOutcome::Cancelled
+        } else if errors.is_empty() {
+            JobOutcome::Completed
+        } else {
+            JobOutcome::Failed(errors)
+        };
+        let (files_done, _) = progress.files();
+        let (bytes_done, _) = progress.bytes();
+        JobReport {
+            id: self.id,
+            operation: self.operation.clone(),
+            outcome,
+            files_done,
+            bytes_done,
+            transferred,
+            replaced,
+        }
+    }
+}
+
+/// Runs `task` on the file operations pool.
+pub fn spawn_task(task: impl FnOnce() + Send + 'static) {
+    FILE_OPS_POOL.spawn(task);
+}
+
+/// Moves `src` to exactly `dst` on the calling thread.
+pub fn move_path(src: &Path, dst: &Path) -> Result<()> {
+    if dst.symlink_metadata().is_ok() {
+        bail!("{} already exists", dst.display());
+    }
+    move_tree(src, dst, &JobProgress::default())
+}
+
+/// Where an item is written to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Destination {
+    /// Nothing is there yet.
+    Free(PathBuf),
+    /// Something is there that the item replaces.
+    Replace(PathBuf),
+}
+
+impl Destination {
+    const fn replaces(&self) -> bool {
+        matches!(self, Self::Replace(_))
+    }
+}
+
+/// Writes an item to `destination` with `write` and returns where it ended
+/// up. A replacement is written next to the item it replaces, which is only
+/// removed once the replacement is complete, so a failed write keeps it.
+fn place(destination: Destination, write: impl FnOnce(&Path) -> Result<()>) -> Result<PathBuf> {
+    let path = match destination {
+        Destination::Free(path) => {
+            write(&path)?;
+            return Ok(path);
+        }
+        Destination::Replace(path) => path,
+    };
+    let staging = staging_path(&path);
+    if let Err(err) = write(&staging) {
+        if staging.symlink_metadata().is_ok() {
+            let _ = remove_tree(&staging, None);
+        }
+        return Err(err);
+    }
+    replace(&staging, &path)?;
+    Ok(path)
+}
+
+/// Swaps the complete `staged` item in for `destination`. The old item is set
+/// aside first and put back when the swap fails.
+fn replace(staged: &Path, destination: &Path) -> Result<()> {
+    let old = staging_path(destination);
+    let swapped = fs::rename(destination, &old).and_then(|()| {
+        fs::rename(staged, destination).inspect_err(|_| {
+            let _ = fs::rename(&old, destination);
+        })
+    });
+    if let Err(err) = swapped {
+        let _ = remove_tree(staged, None);
+        return Err(err).context(format!("Failed to replace {}", destination.display()));
+    }
+    remove_tree(&old, None)
+}
+
+/// A free hidden sibling of `path` to write its replacement to.
+pub fn staging_path(path: &Path) -> PathBuf {
+    let parent = path.parent().unwrap_or_else(|| Path::new(""));
+    let name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let mut counter = 0_u32;
+    loop {
+        let candidate = parent.join(format!(".{name}.lwa_fm-{counter}"));
+        if candidate.symlink_metadata().is_err() {
+            return candidate;
+        }
+        counter += 1;
+    }
+}
+
+/// Copies `src` to exactly `dst` on the calling thread.
+pub fn copy_path(src: &Path, dst: &Path) -> Result<()> {
+    if dst.symlink_metadata().is_ok() {
+        bail!("{} already exists", dst.display());
+    }
+    copy_tree(src, dst, &JobProgress::default())
+}
+
+/// Counts files (anything that is not a directory) and their total size.
+fn measure(path: &Path) -> (u64, u64) {
+    walkdir::WalkDir::new(path)
+        .follow_links(false)
+        .into_iter()
+        .filter_map(std::result::Result::ok)
+        .filter(|entry| !entry.file_type().is_dir())
+        .fold((0, 0), |(files, bytes), entry| {
+            let size = entry.metadata().map(|meta| meta.len()).unwrap_or_default();
+            (files + 1, bytes + size)
+        })
+}
+
+/// What lies under some paths: how many files and folders, and the total size
+/// of the files.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TreeSummary {
+    pub files: u64,
+    pub dirs: u64,
+    pub bytes: u64,
+    /// Space the files occupy on disk, folders included.
+    pub disk_bytes: u64,
+}
+
+/// Space `meta`'s entry occupies on disk. Only known on unix, elsewhere this is
+/// the logical size.
+pub fn size_on_disk(meta: &fs::Metadata) -> u64 {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+        meta.blocks() * 512
+    }
+    #[cfg(not(unix))]
+    {
+        meta.len()
+    }
+}
+
+/// Walks `paths` without following links. The paths themselves are counted
+/// too. Returns `None` once `cancel` is set.
+pub fn summarize(paths: &[PathBuf], cancel: &AtomicBool) -> Option<TreeSummary> {
+    let mut summary = TreeSummary::default();
+    let entries = paths
+        .iter()
+        .flat_map(|path| walkdir::WalkDir::new(path).follow_links(false))
+        .filter_map(std::result::Result::ok);
+    for entry in entries {
+        if cancel.load(Ordering::Relaxed) {
+            return None;
+        }
+        let Ok(meta) = entry.metadata() else {
+            continue;
+        };
+        summary.disk_bytes += size_on_disk(&meta);
+        if meta.is_dir() {
+            summary.dirs += 1;
+        } else {
+            summary.files += 1;
+            summary.bytes += meta.len();
+        }
+    }
+    Some(summary)
+}
+
+impl FileJob {
+    /// Resolves where `source` ends up inside `target`. Returns `None` when
+    /// the item should be skipped. Existing destinations are resolved by the
+    /// job's conflict policy, asking the user when there is none.
+    fn resolve_destination(&self, source: &Path, target: &Path) -> Result<Option<Destination>> {
+        let Some(name) = source.file_name() else {
+            bail!("Source has no file name");
+        };
+        if target.starts_with(source) {
+            bail!("Cannot place a folder inside itself");
+        }
+        let destination = target.join(name);
+        if destination.symlink_metadata().is_err() {
+            return Ok(Some(Destination::Free(destination)));
+        }
+        if destination == source {
+            // Copying next to the original always keeps both, moving onto
+            // itself is a no-op.
+            return Ok((self.operation.kind == FileOperationKind::Copy)
+                .then(|| Destination::Free(unique_name(&destination))));
+        }
+        let destination = self.resolve_conflict(source, destination, modified(source))?;
+        if destination.is_none() {
+            let (files, bytes) = measure(source);
+            self.progress.add_files(files);
+            self.progress.add_bytes(bytes);
+        }
+        Ok(destination)
+    }
+
+    /// Where the top-level item `name` of `archive` is extracted to.
+    fn resolve_extracted(
+        &self,
+        archive: &Path,
+        target: &Path,
+        name: &str,
+        modified: Option<SystemTime>,
+    ) -> Result<Option<Destination>> {
+        let destination = target.join(name);
+        if destination.symlink_metadata().is_err() {
+            return Ok(Some(Destination::Free(destination)));
+        }
+        self.resolve_conflict(
+            &archive::virtual_path(archive, Path::new(name)),
+            destination,
+            modified,
+        )
+    }
+
+    /// Settles `destination` already existing by the job's policy, or by
+    /// asking. `None` means `source` is skipped.
+    fn resolve_conflict(
+        &self,
+        source: &Path,
+        destination: PathBuf,
+        source_modified: Option<SystemTime>,
+    ) -> Result<Option<Destination>> {
+        if source.starts_with(&destination) {
+            bail!("Cannot replace a folder with something inside it");
+        }
+        let resolution = match self.progress.conflict_policy() {
+            Some(resolution) => resolution,
+            None => self.ask(source, &destination)?,
+        };
+        let overwrite = match resolution {
+            ConflictResolution::Skip => false,
+            ConflictResolution::KeepBoth => {
+                return Ok(Some(Destination::Free(unique_name(&destination))));
+            }
+            ConflictResolution::Overwrite => true,
+            ConflictResolution::OverwriteIfNewer => {
+                is_newer(source_modified, modified(&destination))
+            }
+        };
+        Ok(overwrite.then_some(Destination::Replace(destination)))
+    }
+
+    /// Extracts `archive` into `target`. Items replacing existing ones are
+    /// written next to them and swapped in once everything is extracted.
+    fn extract(&self, archive: &Path, target: &Path) -> Result<()> {
+        let mut staged = Vec::new();
+        let result = archive::extract(archive, target, &self.progress, &mut |name, modified| {
+            let destination = self.resolve_extracted(archive, target, name, modified)?;
+            Ok(destination.map(|destination| match destination {
+                Destination::Free(path) => path,
+                Destination::Replace(path) => {
+                    let staging = staging_path(&path);
+                    staged.push((staging.clone(), path));
+                    staging
+                }
+            }))
+        });
+        if let Err(err) = result {
+            for (staging, _) in &staged {
+                if staging.symlink_metadata().is_ok() {
+                    let _ = remove_tree(staging, None);
+                }
+            }
+            return Err(err);
+        }
+        staged
+            .iter()
+            .filter(|(staging, _)| staging.symlink_metadata().is_ok())
+            .try_for_each(|(staging, destination)| replace(staging, destination))
+    }
+
+    /// Queues a conflict for the UI and blocks until it is answered.
+    fn ask(&self, source: &Path, destination: &Path) -> Result<ConflictResolution> {
+        let (reply, answer) = mpsc::channel();
+        PENDING_CONFLICTS
+            .lock()
+            .map_err(|_| anyhow::anyhow!("Conflict queue is poisoned"))?
+            .push_back(PendingConflict {
+                conflict: FileConflict {
+                    job_id: self.id,
+                    source: source.to_path_buf(),
+                    destination: destination.to_path_buf(),
+                },
+                reply,
+            });
+        loop {
+            match answer.recv_timeout(PAUSE_POLL_INTERVAL) {
+                Ok((resolution, apply_to_all)) => {
+                    if apply_to_all && let Ok(mut policy) = self.progress.conflict_policy.lock() {
+                        *policy = Some(resolution);
+                    }
+                    return Ok(resolution);
+                }
+                Err(mpsc::RecvTimeoutError::Timeout) if !self.progress.is_cancelled() => {}
+                Err(_) => {
+                    if let Ok(mut pending) = PENDING_CONFLICTS.lock() {
+                        pending.retain(|pending| pending.conflict.job_id != self.id);
+                    }
+                    return Err(Cancelled.into());
+                }
+            }
+        }
+    }
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    path.symlink_metadata()
+        .and_then(|meta| meta.modified())
+        .ok()
+}
+
+fn is_newer(source: Option<SystemTime>, destination: Option<SystemTime>) -> bool {
+    match (source, destination) {
+        (Some(source), Some(destination)) => source > destination,
+        _ => false,
+    }
+}
+
+/// First free `name (n).ext` sibling of `path`, counting on from an existing
+/// `(n)` suffix.
+pub fn unique_name(path: &Path) -> PathBuf {
+    let parent = path.parent().unwrap_or_else(|| Path::new(""));
+    let stem = path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let extension = path
+        .extension()
+        .map(|ext| format!(".{}", ext.to_string_lossy()))
+        .unwrap_or_default();
+    let (base, mut counter) = stem
+        .strip_suffix(')')
+        .and_then(|rest| rest.rsplit_once(" ("))
+        .and_then(|(base, number)| Some((base.to_string(), number.parse::<u32>().ok()?)))
+        .unwrap_or((stem, 1));
+    loop {
+        counter += 1;
+        let candidate = parent.join(format!("{base} ({counter}){extension}"));
+        if candidate.symlink_metadata().is_err() {
+            return candidate;
+        }
+    }
+}
+
+fn copy_tree(src: &Path, dst: &Path, progress: &JobProgress) -> Result<()> {
+    progress.checkpoint()?;
+    let meta = src
+        .symlink_metadata()
+        .with_context(|| format!("Failed to read metadata of {}", src.display()))?;
+    if meta.is_symlink() {
+        return copy_symlink(src, dst, progress);
+    }
+    if meta.is_dir() {
+        fs::create_dir(dst).with_context(|| format!("Failed to create {}", dst.display()))?;
+        let entries =
+            fs::read_dir(src).with_context(|| format!("Failed to read {}", src.display()))?;
+        for entry in entries {
+            let entry = entry.with_context(|| format!("Failed to read {}", src.display()))?;
+            copy_tree(&entry.path(), &dst.join(entry.file_name()), progress)?;
+        }
+        let _ = fs::set_permissions(dst, meta.permissions());
+        return Ok(());
+    }
+    copy_file(src, dst, &meta, progress)
+}
+
+#[cfg(unix)]
+fn copy_symlink(src: &Path, dst: &Path, progress: &JobProgress) -> Result<()> {
+    let link =
+        fs::read_link(src).with_context(|| format!("Failed to read link {}", src.display()))?;
+    std::os::unix::fs::symlink(&link, dst)
+        .with_context(|| format!("Failed to create link {}", dst.display()))?;
+    progress.add_files(1);
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn copy_symlink(src: &Path, dst: &Path, progress: &JobProgress) -> Result<()> {
+    // Creating links needs extra privileges on Windows, copy what it points to.
+    let meta = fs::metadata(src).with_context(|| format!("Broken link {}", src.display()))?;
+    if meta.is_dir() {
+        bail!("Linked folders can't be copied");
+    }
+    copy_file(src, dst, &meta, progress)
+}
+
+/// Copies a single file in chunks so the job can be paused or cancelled midway.
+/// A partially written destination is removed on failure.
+fn copy_file(src: &Path, dst: &Path, meta: &fs::Metadata, progress: &JobProgress) -> Result<()> {
+    progress.set_current(src);
+    let mut reader =
+        File::open(src).with_context(|| format!("Failed to open {}", src.display()))?;
+    let mut writer = OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(dst)
+        .with_context(|| format!("Failed to create {}", dst.display()))?;
+
+    let result = (|| -> Result<()> {
+        let mut buffer = vec![0; COPY_BUFFER_SIZE];
+        loop {
+            progress.checkpoint()?;
+            let read = match reader.read(&mut buffer) {
+                Ok(0) => break,
+                Ok(read) => read,
+                Err(err) if err.kind() == ErrorKind::Interrupted => continue,
+                Err(err) => return Err(err).context(format!("Failed to read {}", src.display())),
+            };
+            writer
+                .write_all(&buffer[..read])
+                .with_context(|| format!("Failed to write {}", dst.display()))?;
+            progress.add_bytes(read as u64);
+        }
+        writer.flush()?;
+        Ok(())
+    })();
+
+    if let Err(err) = result {
+        drop(writer);
+        let _ = fs::remove_file(dst);
+        return Err(err);
+    }
+    let _ = writer.set_permissions(meta.permissions());
+    if let Ok(modified) = meta.modified() {
+        let _ = writer.set_modified(modified);
+    }
+    progress.add_files(1);
+    Ok(())
+}
+
+/// Applies the steps of `plan` in order, logging each under the job's name.
+/// Steps whose items changed since the plan was made are skipped. A failed
+/// step doesn't stop the others, its error is given back. Fails only once
+/// cancelled or when the job can't be planned again.
+fn run_sync(plan: &SyncPlan, progress: &JobProgress) -> Result<Vec<String>> {
+    let name = &plan.job.name;
+    sync::start_log(name);
+    sync::log(
+        name,
+        format!(
+            "Syncing {} to {}",
+            plan.job.source.display(),
+            plan.job.target.display()
+        ),
+    );
+    let (steps, changed) = match plan.recheck(&progress.cancelled) {
+        Ok(steps) => steps,
+        Err(err) if err.is::<Cancelled>() => {
+            sync::log(name, "Cancelled".to_string());
+            return Err(err);
+        }
+        Err(err) => {
+            sync::log(name, format!("Failed: {err:#}"));
+            return Err(err);
+        }
+    };
+    for step in &changed {
+        sync::log(name, format!("Skipped, changed since the dry run: {step}"));
+    }
+    let mut errors = Vec::new();
+    for step in &steps {
+        let result = progress.checkpoint().and_then(|()| match step {
+            SyncStep::Copy { from, to, .. } => {
+                if let Some(parent) = to.parent() {
+                    fs::create_dir_all(parent)
+                        .with_context(|| format!("Failed to create {}", parent.display()))?;
+                }
+                copy_tree(from, to, progress)
+            }
+            SyncStep::Replace { from, to, .. } => place(Destination::Replace(to.clone()), |path| {
+                copy_tree(from, path, progress)
+            })
+            .map(drop),
+            SyncStep::Delete { path, stamp } => {
+                progress.set_current(path);
+                remove_tree(path, None).map(|()| progress.add_files(stamp.files))
+            }
+        });
+        match result {
+            Ok(()) => sync::log(name, step.to_string()),
+            Err(err) if err.is::<Cancelled>() => {
+                sync::log(name, "Cancelled".to_string());
+                return Err(err);
+            }
+            Err(err) => {
+                let error = format!("{step}: {err:#}");
+                sync::log(name, format!("Failed: {error}"));
+                errors.push(error);
+            }
+        }
+    }
+    sync::log(
+        name,
+        format!(
+            "Finished with {} error(s), {} step(s) skipped",
+            errors.len(),
+            changed.len()
+        ),
+    );
+    Ok(errors)
+}
+
+/// Renames when possible and falls back to copy + delete across devices.
+fn move_tree(src: &Path, dst: &Path, progress: &JobProgress) -> Result<()> {
+    if rename_or_copy(src, dst, progress)? {
+        remove_tree(src, None)?;
+    }
+    Ok(())
+}
+
+/// Renames `src` to `dst`, or copies it there across devices. Returns whether
+/// it was copied, which leaves `src` for the caller to remove.
+fn rename_or_copy(src: &Path, dst: &Path, progress: &JobProgress) -> Result<bool> {
+    progress.checkpoint()?;
+    progress.set_current(src);
+    match fs::rename(src, dst) {
+        Ok(()) => {
+            let (files, bytes) = measure(dst);
+            progress.add_files(files);
+            progress.add_bytes(bytes);
+            Ok(false)
+        }
+        Err(err) if err.kind() == ErrorKind::CrossesDevices => {
+            copy_tree(src, dst, progress)?;
+            Ok(true)
+        }
+        Err(err) => Err(err).context(format!("Failed to move to {}", dst.display())),
+    }
+}
+
+/// Permanently removes `path`. Files are counted in `progress` when given.
+fn remove_tree(path: &Path, progress: Option<&JobProgress>) -> Result<()> {
+    if let Some(progress) = progress {
+        progress.checkpoint()?;
+        progress.set_current(path);
+    }
+    let meta = path
+        .symlink_metadata()
+        .with_context(|| format!("Failed to read metadata of {}", path.display()))?;
+    if meta.is_dir() {
+        let entries =
+            fs::read_dir(path).with_context(|| format!("Failed to read {}", path.display()))?;
+        for entry in entries {
+            let entry = entry.with_context(|| format!("Failed to read {}", path.display()))?;
+            remove_tree(&entry.path(), progress)?;
+        }
+        fs::remove_dir(path).with_context(|| format!("Failed to remove {}", path.display()))?;
+        return Ok(());
+    }
+    fs::remove_file(path).with_context(|| format!("Failed to remove {}", path.display()))?;
+    if let Some(progress) = progress {
+        progress.add_files(1);
+        progress.add_bytes(meta.len());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        ConflictResolution, EXTRACTION_QUIET_GRACE, FINISHED_EXTRACTIONS, FileJob, FileOperation,
+        JobOutcome, JobProgress, is_quiet, next_conflict, resolve_conflict, summarize, unique_name,
+    };
+    use crate::app::sync::{self, SyncJob};
+    use crate::app::test_support::unique_test_dir;
+    use std::{
+        fs,
+        path::Path,
+        sync::{Arc, atomic::AtomicBool},
+    };
+
+    fn run(operation: FileOperation) -> (super::JobReport, Arc<JobProgress>) {
+        let job = FileJob::new(0, operation);
+        (job.run(), job.progress)
+    }
+
+    fn write_tree(root: &Path) {
+        fs::create_dir_all(root.join("nested/deeper")).expect("create tree");
+        fs::write(root.join("a.txt"), b"hello").expect("write a");
+        fs::write(root.join("nested/b.txt"), b"world!").expect("write b");
+        fs::write(root.join("nested/deeper/c.bin"), vec![7; 3000]).expect("write c");
+    }
+
+    #[test]
+    fn copy_directory_tree_recursively() {
+        let dir = unique_test_dir("ops_copy");
+        let source = dir.join("src");
+        let target = dir.join("dst");
+        write_tree(&source);
+        fs::create_dir(&target).expect("create target");
+
+        let (report, progress) = run(FileOperation::copy(vec![source.clone()], &target));
+
+        assert_eq!(report.outcome, JobOutcome::Completed);
+        assert_eq!(progress.files(), (3, 3));
+        assert_eq!(progress.bytes(), (3011, 3011));
+        assert_eq!(
+            fs::read(target.join("src/nested/deeper/c.bin")).expect("read copy"),
+            vec![7; 3000]
+        );
+        assert!(source.join("a.txt").exists(), "copy keeps the source");
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn move_directory_removes_source() {
+        let dir = unique_test_dir("ops_move");
+        let source = dir.join("src");
+        let target = dir.join("dst");
+        write_tree(&source);
+        fs::create_dir(&target).expect("create target");
+
+        let (report, progress) = run(FileOperation::move_to(vec![source.clone()], &target));
+
+        assert_eq!(report.outcome, JobOutcome::Completed);
+        assert_eq!(
+            report.transferred,
+            vec![(source.clone(), target.join("src"))]
+        );
+        assert_eq!(progress.files().0, 3);
+        assert!(!source.exists());
+        assert_eq!(
+            fs::read(target.join("src/nested/b.txt")).expect("read moved"),
+            b"world!"
+        );
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn delete_removes_files_and_directories() {
+        let dir = unique_test_dir("ops_delete");
+        let tree = dir.join("tree");
+        let file = dir.join("single.txt");
+        write_tree(&tree);
+        fs::write(&file, b"x").expect("write file");
+
+        let (report, progress) = run(FileOperation::delete(vec![tree.clone(), file.clone()]));
+
+        assert_eq!(report.outcome, JobOutcome::Completed);
+        assert_eq!(progress.files(), (4, 4));
+        assert!(!tree.exists());
+        assert!(!file.exists());
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn self_nesting_fails_per_item() {
+        let dir = unique_test_dir("ops_nesting");
+        let source = dir.join("src");
+        write_tree(&source);
+        let other = dir.join("other.txt");
+        fs::write(&other, b"new").expect("write other");
+
+        let (report, _) = run(FileOperation::copy(vec![source.clone(), other], &source));
+
+        let JobOutcome::Failed(errors) = report.outcome else {
+            panic!("expected failures, got {:?}", report.outcome);
+        };
+        assert_eq!(errors.len(), 1);
+        assert_eq!(
+            fs::read(source.join("other.txt")).expect("read copy"),
+            b"new",
+            "the other item is still copied"
+        );
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn conflict_policies_resolve_existing_destinations() {
+        let dir = unique_test_dir("ops_conflict");
+        let target = dir.join("dst");
+        fs::create_dir(&target).expect("create target");
+        let source = dir.join("note.txt");
+        fs::write(&source, b"new").expect("write source");
+        let existing = target.join("note.txt");
+
+        let cases = [
+            (ConflictResolution::Skip, b"old".as_slice(), false),
+            (ConflictResolution::Overwrite, b"new".as_slice(), false),
+            (ConflictResolution::KeepBoth, b"old".as_slice(), true),
+        ];
+        for (resolution, expected, kept_both) in cases {
+            fs::write(&existing, b"old").expect("write existing");
+            let _ = fs::remove_file(target.join("note (2).txt"));
+            let (report, progress) = run(FileOperation::copy(vec![source.clone()], &target)
+                .with_conflict_resolution(resolution));
+            assert_eq!(report.outcome, JobOutcome::Completed, "{resolution}");
+            assert_eq!(fs::read(&existing).expect("read existing"), expected);
+            assert_eq!(
+                target.join("note (2).txt").exists(),
+                kept_both,
+                "{resolution}"
+            );
+            assert_eq!(progress.files(), (1, 1), "{resolution}");
+            assert_eq!(
+                report.replaced == [existing.clone()],
+                resolution == ConflictResolution::Overwrite,
+                "{resolution}"
+            );
+        }
+
+        // The source is older than the destination now.
+        fs::write(&existing, b"old").expect("write existing");
+        let (report, _) = run(FileOperation::copy(vec![source], &target)
+            .with_conflict_resolution(ConflictResolution::OverwriteIfNewer));
+        assert!(report.transferred.is_empty());
+        assert_eq!(fs::read(&existing).expect("read existing"), b"old");
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn overwrite_keeps_the_destination_until_the_replacement_is_complete() {
+        let dir = unique_test_dir("ops_replace");
+        let target = dir.join("dst");
+        fs::create_dir_all(target.join("note.txt/old")).expect("create existing");
+        let source = dir.join("note.txt");
+        fs::write(&source, b"new").expect("write source");
+        let copy = || {
+            FileOperation::copy(vec![source.clone()], &target)
+                .with_conflict_resolution(ConflictResolution::Overwrite)
+        };
+
+        let job = FileJob::new(0, copy());
+        job.progress.cancel();
+        assert_eq!(job.run().outcome, JobOutcome::Cancelled);
+        assert!(target.join("note.txt/old").is_dir(), "kept when cancelled");
+
+        let (report, _) = run(copy());
+        assert_eq!(report.outcome, JobOutcome::Completed);
+        assert_eq!(fs::read(target.join("note.txt")).expect("read"), b"new");
+        assert_eq!(
+            fs::read_dir(&target).expect("read dir").count(),
+            1,
+            "nothing staged is left behind"
+        );
+
+        // Replacing x with x/x would remove the source before it is moved.
+        let nested = dir.join("x/x");
+        fs::create_dir_all(&nested).expect("create nested");
+        fs::write(nested.join("inner.txt"), b"inner").expect("write inner");
+        let (report, _) = run(FileOperation::move_to(vec![nested.clone()], &dir)
+            .with_conflict_resolution(ConflictResolution::Overwrite));
+        assert!(matches!(report.outcome, JobOutcome::Failed(_)));
+        assert_eq!(fs::read(nested.join("inner.txt")).expect("read"), b"inner");
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn only_the_extraction_target_and_below_are_quiet() {
+        let dir = unique_test_dir("ops_quiet");
+        let target = dir.join("archive");
+        FINISHED_EXTRACTIONS.lock().expect("lock").push((
+            target.clone(),
+            std::time::Instant::now() + EXTRACTION_QUIET_GRACE,
+        ));
+
+        assert!(is_quiet(&target));
+        assert!(is_quiet(&target.join("nested")));
+        assert!(!is_quiet(&dir));
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn copy_into_same_directory_keeps_both() {
+        let dir = unique_test_dir("ops_duplicate");
+        let source = dir.join("photo.jpg");
+        fs::write(&source, b"jpg").expect("write source");
+
+        let (report, _) = run(FileOperation::copy(vec![source.clone()], &dir));
+
+        assert_eq!(
+            report.transferred,
+            vec![(source, dir.join("photo (2).jpg"))]
+        );
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn unanswered_conflict_blocks_until_resolved() {
+        let dir = unique_test_dir("ops_ask");
+        let target = dir.join("dst");
+        fs::create_dir(&target).expect("create target");
+        let sources = ["a.txt", "b.txt"].map(|name| {
+            fs::write(target.join(name), b"old").expect("write existing");
+            let source = dir.join(name);
+            fs::write(&source, b"new").expect("write source");
+            source
+        });
+
+        let job = FileJob::new(4242, FileOperation::copy(sources.to_vec(), &target));
+        let worker = std::thread::spawn(move || job.run());
+        let conflict = loop {
+            if let Some(conflict) = next_conflict().filter(|conflict| conflict.job_id == 4242) {
+                break conflict;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(10));
+        };
+        assert_eq!(conflict.destination, target.join("a.txt"));
+        resolve_conflict(4242, ConflictResolution::Overwrite, true);
+
+        let report = worker.join().expect("job thread");
+        assert_eq!(report.outcome, JobOutcome::Completed);
+        assert_eq!(report.transferred.len(), 2, "apply to all answers both");
+        assert_eq!(fs::read(target.join("b.txt")).expect("read b"), b"new");
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn unique_name_counts_on_from_existing_suffix() {
+        let dir = unique_test_dir("ops_unique");
+        fs::write(dir.join("report.pdf"), b"").expect("write file");
+        assert_eq!(
+            unique_name(&dir.join("report.pdf")),
+            dir.join("report (2).pdf")
+        );
+        fs::write(dir.join("report (2).pdf"), b"").expect("write file");
+        assert_eq!(
+            unique_name(&dir.join("report.pdf")),
+            dir.join("report (3).pdf")
+        );
+        assert_eq!(
+            unique_name(&dir.join("report (2).pdf")),
+            dir.join("report (3).pdf")
+        );
+        assert_eq!(unique_name(&dir.join(".bashrc")), dir.join(".bashrc (2)"));
+        assert_eq!(unique_name(&dir.join("folder")), dir.join("folder (2)"));
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn cancelled_job_stops_before_copying() {
+        let dir = unique_test_dir("ops_cancel");
+        let source = dir.join("src");
+        let target = dir.join("dst");
+        write_tree(&source);
+        fs::create_dir(&target).expect("create target");
+
+        let job = FileJob::new(0, FileOperation::copy(vec![source], &target));
+        job.progress.cancel();
+        let report = job.run();
+
+        assert_eq!(report.outcome, JobOutcome::Cancelled);
+        assert!(!target.join("src").exists());
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn cancelled_delete_keeps_the_tree() {
+        let dir = unique_test_dir("ops_delete_cancel");
+        let source = dir.join("src");
+        write_tree(&source);
+        let not_cancelled = AtomicBool::new(false);
+        let before = summarize(std::slice::from_ref(&source), &not_cancelled).expect("summarize");
+        assert_eq!((before.files, before.dirs, before.bytes), (3, 3, 3011));
+
+        let job = FileJob::new(0, FileOperation::delete(vec![source.clone()]));
+        job.progress.cancel();
+        let report = job.run();
+
+        assert_eq!(report.outcome, JobOutcome::Cancelled);
+        assert_eq!(report.files_done, 0);
+        assert_eq!(
+            summarize(std::slice::from_ref(&source), &not_cancelled),
+            Some(before)
+        );
+        assert_eq!(summarize(&[source], &AtomicBool::new(true)), None);
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn affected_dirs_skip_copy_sources() {
+        let copy = FileOperation::copy(vec!["/a/one.txt".into(), "/b/two.txt".into()], "/c");
+        assert_eq!(copy.affected_dirs(), vec![std::path::PathBuf::from("/c")]);
+
+        let moved = FileOperation::move_to(vec!["/a/one.txt".into(), "/a/two.txt".into()], "/c");
+        assert_eq!(
+            moved.affected_dirs(),
+            vec![
+                std::path::PathBuf::from("/a"),
+                std::path::PathBuf::from("/c")
+            ]
+        );
+        assert_eq!(moved.label(), "Move 2 items to c");
+    }
+
+    #[test]
+    fn sync_plan_copies_replaces_and_deletes() {
+        let dir = unique_test_dir("ops_sync");
+        let (source, target) = (dir.join("src"), dir.join("dst"));
+        write_tree(&source);
+        fs::create_dir_all(target.join("old")).expect("create old");
+        fs::write(target.join("a.txt"), b"stale!").expect("write stale");
+        let job = SyncJob {
+            name: "ops_sync".to_string(),
+            source,
+            target: target.clone(),
+            ..SyncJob::default()
+        };
+        let plan = sync::plan(&job, &AtomicBool::new(false), &|_| {}).expect("plan");
+        assert_eq!(plan.steps.len(), 3, "{:?}", plan.steps);
+
+        let (report, progress) = run(FileOperation::sync(plan));
+
+        assert_eq!(report.outcome, JobOutcome::Completed);
+        assert_eq!(progress.files(), (3, 3));
+        assert_eq!(fs::read(target.join("a.txt")).expect("read a"), b"hello");
+        assert!(target.join("nested/deeper/c.bin").exists());
+        assert!(!target.join("old").exists());
+        let log = sync::run_log("ops_sync").expect("run log");
+        assert_eq!(log.len(), 5, "{log:?}");
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn sync_skips_steps_changed_since_the_plan() {
+        let dir = unique_test_dir("ops_sync_changed");
+        let (source, target) = (dir.join("src"), dir.join("dst"));
+        write_tree(&source);
+        fs::create_dir_all(target.join("old")).expect("create old");
+        fs::write(target.join("a.txt"), b"stale!").expect("write stale");
+        let job = SyncJob {
+            name: "ops_sync_changed".to_string(),
+            source,
+            target: target.clone(),
+            ..SyncJob::default()
+        };
+        let plan = sync::plan(&job, &AtomicBool::new(false), &|_| {}).expect("plan");
+        assert_eq!(plan.steps.len(), 3, "{:?}", plan.steps);
+        fs::write(target.join("a.txt"), b"edited since").expect("edit a");
+        fs::write(target.join("old/new.txt"), b"new").expect("write new");
+
+        let (report, _) = run(FileOperation::sync(plan));
+
+        assert_eq!(report.outcome, JobOutcome::Completed);
+        assert_eq!(
+            fs::read(target.join("a.txt")).expect("read a"),
+            b"edited since"
+        );
+        assert!(target.join("old/new.txt").exists());
+        assert!(target.join("nested/deeper/c.bin").exists());
+        let log = sync::run_log("ops_sync_changed").expect("run log");
+        assert_eq!(
+            log.iter()
+                .filter(|line| line.starts_with("Skipped"))
+                .count(),
+            2,
+            "{log:?}"
+        );
+        let _ = fs::remove_dir_all(dir);
+    }
+}
@@ -0,0 +1,566 @@
+//! Persistent journal of file operations, so they can be undone and redone.
+//!
+//! Entries live in two sled trees next to the directory cache in
+//! [`SLED_DIRS`]: an undo stack and a redo stack. Keys are big-endian sequence
+//! numbers, so the newest entry of a stack is always its last key.
+
+use std::{
+    fs::{self, File},
+    path::{Path, PathBuf},
+    sync::{LazyLock, Mutex},
+    time::{Duration, Instant},
+};
+
+use anyhow::{Context, Result, anyhow};
+use bincode::{Decode, Encode, config};
+
+use crate::app::{
+    batch_rename::rename_all,
+    commands::ActionToPerform,
+    database::SLED_DIRS,
+    file_ops::{self, copy_path, move_path},
+    trash_bin::{self, SystemTrash},
+};
+
+const UNDO_TREE: &[u8] = b"operation_journal_v1";
+const REDO_TREE: &[u8] = b"operation_journal_redo_v1";
+const MAX_ENTRIES: usize = 200;
+const NOTICE_DURATION: Duration = Duration::from_secs(8);
+
+static JOURNAL: LazyLock<Journal> = LazyLock::new(|| Journal::open(&SLED_DIRS));
+
+/// The last recorded operation, shown with an "Undo" button for a while.
+static NOTICE: LazyLock<Mutex<Option<(String, Instant)>>> = LazyLock::new(|| Mutex::new(None));
+
+#[derive(Debug, Clone, PartialEq, Eq, Encode, Decode)]
+pub enum JournalOperation {
+    /// A single item was renamed in place.
+    Rename { from: PathBuf, to: PathBuf },
+    /// Items were moved, as `(from, to)` pairs.
+    Move(Vec<(PathBuf, PathBuf)>),
+    /// Items were copied, as `(source, copy)` pairs.
+    Copy(Vec<(PathBuf, PathBuf)>),
+    /// Items were moved to the system trash.
+    Trash(Vec<PathBuf>),
+    /// A new file or directory was created.
+    Create { path: PathBuf, is_dir: bool },
+    /// Several items were renamed at once, as `(from, to)` pairs.
+    BatchRename(Vec<(PathBuf, PathBuf)>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JournalDirection {
+    Undo,
+    Redo,
+}
+
+#[derive(Debug, Clone)]
+pub struct JournalReport {
+    pub direction: JournalDirection,
+    pub label: String,
+    pub affected_dirs: Vec<PathBuf>,
+    pub error: Option<String>,
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name().map_or_else(
+        || path.display().to_string(),
+        |name| name.to_string_lossy().to_string(),
+    )
+}
+
+fn describe<'a>(mut paths: impl ExactSizeIterator<Item = &'a PathBuf>) -> String {
+    match paths.len() {
+        1 => paths.next().map(|path| file_name(path)).unwrap_or_default(),
+        count => format!("{count} items"),
+    }
+}
+
+impl JournalOperation {
+    pub fn label(&self) -> String {
+        match self {
+            Self::Rename { from, to } => {
+                format!("Rename {} to {}", file_name(from), file_name(to))
+            }
+            Self::Move(items) => format!("Move {}", describe(items.iter().map(|(from, _)| from))),
+            Self::Copy(items) => format!("Copy {}", describe(items.iter().map(|(from, _)| from))),
+            Self::Trash(paths) => format!("Move {} to Trash", describe(paths.iter())),
+            Self::Create { path, .. } => format!("Create {}", file_name(path)),
+            Self::BatchRename(items) => {
+                format!("Rename {}", describe(items.iter().map(|(from, _)| from)))
+            }
+        }
+    }
+
+    /// Parent directories of every path touched by the operation.
+    pub fn affected_dirs(&self) -> Vec<PathBuf> {
+        let paths: Vec<&PathBuf> = match self {
+            Self::Rename { from, to } => vec![from, to],
+            Self::Move(items) | Self::Copy(items) | Self::BatchRename(items) => {
+                items.iter().flat_map(|(from, to)| [from, to]).collect()
+            }
+            Self::Trash(paths) => paths.iter().collect(),
+            Self::Create { path, .. } => vec![path],
+        };
+        let mut dirs: Vec<PathBuf> = paths
+            .into_iter()
+            .filter_map(|path| path.parent().map(Path::to_path_buf))
+            .collect();
+        dirs.sort();
+        dirs.dedup();
+        dirs
+    }
+
+    /// Splits a move or copy into the items not in `failed` and those in
+    /// it. `None` when the operation isn't made of separate items or none of
+    /// them went through.
+    fn split(&self, failed: &[(PathBuf, PathBuf)]) -> Option<(Self, Self)> {
+        let (Self::Move(items) | Self::Copy(items)) = self else {
+            return None;
+        };
+        let (left, done): (Vec<_>, Vec<_>) = items
+            .iter()
+            .cloned()
+            .partition(|item| failed.contains(item));
+        if done.is_empty() {
+            return None;
+        }
+        Some(if matches!(self, Self::Move(_)) {
+            (Self::Move(done), Self::Move(left))
+        } else {
+            (Self::Copy(done), Self::Copy(left))
+        })
+    }
+
+    fn undo(&self) -> Result<(), Failure> {
+        match self {
+            Self::Rename { from, to } => Ok(move_path(to, from)?),
+            Self::Move(items) => {
+                for_each_item(items.iter().rev(), |(from, to)| move_path(to, from))
+            }
+            Self::Copy(items) => Ok(trash::delete_all(items.iter().map(|(_, copy)| copy))
+                .map_err(|err| anyhow!("Failed to move copies to trash: {err}"))?),
+            Self::Trash(paths) => Ok(trash_bin::restore_paths(&SystemTrash, paths)?),
+            Self::Create { path, .. } => Ok(trash::delete(path)
+                .map_err(|err| anyhow!("Failed to move {} to trash: {err}", path.display()))?),
+            Self::BatchRename(items) => {
+                let reversed: Vec<(PathBuf, PathBuf)> = items
+                    .iter()
+                    .map(|(from, to)| (to.clone(), from.clone()))
+                    .collect();
+                Ok(rename_all(&reversed)?)
+            }
+        }
+    }
+
+    fn redo(&self) -> Result<(), Failure> {
+        match self {
+            Self::Rename { from, to } => Ok(move_path(from, to)?),
+            Self::Move(items) => for_each_item(items.iter(), |(from, to)| move_path(from, to)),
+            Self::Copy(items) => for_each_item(items.iter(), |(from, to)| copy_path(from, to)),
+            Self::Trash(paths) => Ok(trash::delete_all(paths)
+                .map_err(|err| anyhow!("Failed to move to trash: {err}"))?),
+            Self::Create { path, is_dir } => Ok(if *is_dir {
+                fs::create_dir(path)
+            } else {
+                File::create_new(path).map(drop)
+            }
+            .with_context(|| format!("Failed to create {}", path.display()))?),
+            Self::BatchRename(items) => Ok(rename_all(items)?),
+        }
+    }
+}
+
+/// An undo or redo that didn't go through.
+struct Failure {
+    error: anyhow::Error,
+    /// The items of a move or copy that failed, the others were applied.
+    /// `None` when nothing was.
+    failed_items: Option<Vec<(PathBuf, PathBuf)>>,
+}
+
+impl From<anyhow::Error> for Failure {
+    fn from(error: anyhow::Error) -> Self {
+        Self {
+            error,
+            failed_items: None,
+        }
+    }
+}
+
+/// Applies `apply` to every item, continuing past failures.
+fn for_each_item<'a>(
+    items: impl Iterator<Item = &'a (PathBuf, PathBuf)>,
+    mut apply: impl FnMut(&'a (PathBuf, PathBuf)) -> Result<()>,
+) -> Result<(), Failure> {
+    let mut errors = Vec::new();
+    let mut failed = Vec::new();
+    for item in items {
+        if let Err(err) = apply(item) {
+            errors.push(format!("{err:#}"));
+            failed.push(item.clone());
+        }
+    }
+    if errors.is_empty() {
+        return Ok(());
+    }
+    Err(Failure {
+        error: anyhow!(errors.join("; ")),
+        failed_items: Some(failed),
+    })
+}
+
+struct Journal {
+    undo: sled::Tree,
+    redo: sled::Tree,
+    /// Held while an entry is applied, so quick undos revert one operation
+    /// after the other, newest first.
+    applying: Mutex<()>,
+}
+
+impl Journal {
+    fn open(db: &sled::Db) -> Self {
+        Self {
+            undo: db
+                .open_tree(UNDO_TREE)
+                .expect("operation journal tree should open"),
+            redo: db
+                .open_tree(REDO_TREE)
+                .expect("operation journal redo tree should open"),
+            applying: Mutex::new(()),
+        }
+    }
+
+    fn record(&self, operation: &JournalOperation) {
+        if let Err(err) = self.redo.clear() {
+            log::warn!("failed to clear redo journal: {err}");
+        }
+        Self::push(&self.undo, operation);
+        while self.undo.len() > MAX_ENTRIES {
+            if self.undo.pop_min().is_err() {
+                break;
+            }
+        }
+    }
+
+    fn push(tree: &sled::Tree, operation: &JournalOperation) {
+        let next = tree
+            .last()
+            .ok()
+            .flatten()
+            .and_then(|(key, _)| <[u8; 8]>::try_from(key.as_ref()).ok())
+            .map_or(0, u64::from_be_bytes)
+            + 1;
+        let Ok(data) = bincode::encode_to_vec(operation, config::standard()) else {
+            return;
+        };
+        if let Err(err) = tree.insert(next.to_be_bytes(), data) {
+            log::warn!("failed to write operation journal: {err}");
+        }
+    }
+
+    fn pop(tree: &sled::Tree) -> Option<JournalOperation> {
+        let (_, data) = tree.pop_max().ok()??;
+        bincode::decode_from_slice(&data, config::standard())
+            .ok()
+            .map(|(operation, _)| operation)
+    }
+
+    fn peek(tree: &sled::Tree) -> Option<JournalOperation> {
+        let (_, data) = tree.last().ok()??;
+        bincode::decode_from_slice(&data, config::standard())
+            .ok()
+            .map(|(operation, _)| operation)
+    }
+
+    /// Pops the newest entry of one stack, applies it and moves it over to
+    /// the other stack. Failed entries go back where they came from, so they
+    /// stay in the history and can be tried again. When only some items of a
+    /// move or copy failed, just those go back and the rest move over.
+    fn apply(&self, direction: JournalDirection) -> Option<JournalReport> {
+        let _applying = self
+            .applying
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner);
+        let (from, to) = match direction {
+            JournalDirection::Undo => (&self.undo, &self.redo),
+            JournalDirection::Redo => (&self.redo, &self.undo),
+        };
+        let operation = Self::pop(from)?;
+        let result = match direction {
+            JournalDirection::Undo => operation.undo(),
+            JournalDirection::Redo => operation.redo(),
+        };
+        let error = match result {
+            Ok(()) => {
+                Self::push(to, &operation);
+                None
+            }
+            Err(failure) => {
+                let split = failure
+                    .failed_items
+                    .and_then(|failed| operation.split(&failed));
+                if let Some((done, left)) = split {
+                    Self::push(to, &done);
+                    Self::push(from, &left);
+                } else {
+                    Self::push(from, &operation);
+                }
+                Some(format!("{:#}", failure.error))
+            }
+        };
+        Some(JournalReport {
+            direction,
+            label: operation.label(),
+            affected_dirs: operation.affected_dirs(),
+            error,
+        })
+    }
+}
+
+/// Records a finished operation and shows it with an "Undo" button.
+pub fn record(operation: &JournalOperation) {
+    if let Ok(mut notice) = NOTICE.lock() {
+        *notice = Some((operation.label(), Instant::now()));
+    }
+    JOURNAL.record(operation);
+}
+
+/// Label of the operation the next undo would revert.
+pub fn next_undo() -> Option<String> {
+    Journal::peek(&JOURNAL.undo).map(|operation| operation.label())
+}
+
+/// Label of the operation the next redo would apply again.
+pub fn next_redo() -> Option<String> {
+    Journal::peek(&JOURNAL.redo).map(|operation| operation.label())
+}
+
+/// Reverts (or re-applies) the newest journal entry in the background. The
+/// result is posted back as [`ActionToPerform::JournalApplied`].
+pub fn schedule(direction: JournalDirection) {
+    if let Ok(mut notice) = NOTICE.lock() {
+        *notice = None;
+    }
+    file_ops::spawn_task(move || {
+        if let Some(report) = JOURNAL.apply(direction) {
+            ActionToPerform::JournalApplied(report).schedule();
+        }
+    });
+}
+
+/// Draws the "Undo" notice for the last recorded operation.
+pub fn notice_ui(ctx: &egui::Context) {
+    let Some((label, recorded_at)) = NOTICE.lock().ok().and_then(|notice| notice.clone()) else {
+        return;
+    };
+    let elapsed = recorded_at.elapsed();
+    if elapsed >= NOTICE_DURATION {
+        if let Ok(mut notice) = NOTICE.lock() {
+            *notice = None;
+        }
+        return;
+    }
+    egui::Area::new(egui::Id::new("journal_undo_notice"))
+        .anchor(egui::Align2::RIGHT_BOTTOM, egui::vec2(-12.0, -40.0))
+        .order(egui::Order::Foreground)
+        .show(ctx, |ui| {
+            egui::Frame::popup(ui.style()).show(ui, |ui| {
+                ui.horizontal(|ui| {
+                    ui.label(format!("✔ {label}"));
+                    if ui.button("Undo").clicked() {
+                        ActionToPerform::UndoFileOperation.schedule();
+                    }
+                    if ui.small_button("✖").clicked()
+                        && let Ok(mut notice) = NOTICE.lock()
+                    {
+                        *notice = None;
+                    }
+                });
+            });
+        });
+    ctx.request_repaint_after(NOTICE_DURATION.saturating_sub(elapsed));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Journal, JournalDirection, JournalOperation, MAX_ENTRIES};
+    use crate::app::test_support::unique_test_dir;
+    use std::{fs, path::PathBuf};
+
+    fn temporary_journal() -> Journal {
+        let db = sled::Config::new()
+            .temporary(true)
+            .open()
+            .expect("temporary sled database should open");
+        Journal::open(&db)
+    }
+
+    #[test]
+    fn entries_pop_newest_first_and_survive_encoding() {
+        let journal = temporary_journal();
+        let first = JournalOperation::Trash(vec!["/tmp/a".into()]);
+        let second = JournalOperation::Move(vec![("/tmp/b".into(), "/other/b".into())]);
+        journal.record(&first);
+        journal.record(&second);
+
+        assert_eq!(Journal::peek(&journal.undo), Some(second.clone()));
+        assert_eq!(Journal::pop(&journal.undo), Some(second));
+        assert_eq!(Journal::pop(&journal.undo), Some(first));
+        assert_eq!(Journal::pop(&journal.undo), None);
+    }
+
+    #[test]
+    fn journal_is_capped() {
+        let journal = temporary_journal();
+        for i in 0..MAX_ENTRIES + 5 {
+            journal.record(&JournalOperation::Trash(vec![PathBuf::from(format!(
+                "/tmp/{i}"
+            ))]));
+        }
+        assert_eq!(journal.undo.len(), MAX_ENTRIES);
+        assert_eq!(
+            Journal::peek(&journal.undo),
+            Some(JournalOperation::Trash(vec![PathBuf::from(format!(
+                "/tmp/{}",
+                MAX_ENTRIES + 4
+            ))]))
+        );
+    }
+
+    #[test]
+    fn rename_can_be_undone_and_redone() {
+        let dir = unique_test_dir("journal_rename");
+        let from = dir.join("old.txt");
+        let to = dir.join("new.txt");
+        fs::write(&to, b"data").expect("write file");
+
+        let journal = temporary_journal();
+        journal.record(&JournalOperation::Rename {
+            from: from.clone(),
+            to: to.clone(),
+        });
+
+        let report = journal
+            .apply(JournalDirection::Undo)
+            .expect("an entry to undo");
+        assert_eq!(report.error, None);
+        assert_eq!(report.affected_dirs, vec![dir.clone()]);
+        assert!(from.exists() && !to.exists());
+        assert!(journal.undo.is_empty());
+
+        let report = journal
+            .apply(JournalDirection::Redo)
+            .expect("an entry to redo");
+        assert_eq!(report.error, None);
+        assert!(to.exists() && !from.exists());
+
+        // A new operation invalidates the redo stack.
+        journal.record(&JournalOperation::Trash(vec![dir.join("x")]));
+        assert!(journal.redo.is_empty());
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn quick_undos_run_newest_first() {
+        let dir = unique_test_dir("journal_quick_undo");
+        let (a, b, c) = (dir.join("a.txt"), dir.join("b.txt"), dir.join("c.txt"));
+        fs::write(&c, b"data").expect("write file");
+        let journal = temporary_journal();
+        journal.record(&JournalOperation::Rename {
+            from: a.clone(),
+            to: b.clone(),
+        });
+        journal.record(&JournalOperation::Rename { from: b, to: c });
+
+        let reports = std::thread::scope(|scope| {
+            let undos = [0, 1].map(|_| scope.spawn(|| journal.apply(JournalDirection::Undo)));
+            undos.map(|undo| undo.join().expect("undo thread"))
+        });
+        for report in reports {
+            assert_eq!(report.expect("an entry to undo").error, None);
+        }
+        assert_eq!(fs::read(&a).expect("read file"), b"data");
+        assert_eq!(journal.redo.len(), 2);
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn only_the_failed_items_are_kept_for_a_retry() {
+        let dir = unique_test_dir("journal_partial");
+        let moved = (dir.join("a.txt"), dir.join("out/a.txt"));
+        let missing = (dir.join("b.txt"), dir.join("out/b.txt"));
+        fs::create_dir(dir.join("out")).expect("create dir");
+        fs::write(&moved.1, b"a").expect("write file");
+        let journal = temporary_journal();
+        journal.record(&JournalOperation::Move(vec![
+            moved.clone(),
+            missing.clone(),
+        ]));
+
+        let report = journal
+            .apply(JournalDirection::Undo)
+            .expect("an entry to undo");
+        assert!(report.error.is_some());
+        assert!(moved.0.exists());
+        assert_eq!(
+            Journal::peek(&journal.undo),
+            Some(JournalOperation::Move(vec![missing.clone()]))
+        );
+        assert_eq!(
+            Journal::peek(&journal.redo),
+            Some(JournalOperation::Move(vec![moved]))
+        );
+
+        fs::write(&missing.1, b"b").expect("write file");
+        let report = journal
+            .apply(JournalDirection::Undo)
+            .expect("an entry to undo");
+        assert_eq!(report.error, None);
+        assert!(missing.0.exists());
+        assert!(journal.undo.is_empty());
+        assert_eq!(journal.redo.len(), 2);
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn batch_rename_is_undone_as_one_entry() {
+        let dir = unique_test_dir("journal_batch_rename");
+        fs::write(dir.join("a.txt"), b"a").expect("write file");
+        fs::write(dir.join("b.txt"), b"b").expect("write file");
+        // The names were swapped.
+        let operation = JournalOperation::BatchRename(vec![
+            (dir.join("b.txt"), dir.join("a.txt")),
+            (dir.join("a.txt"), dir.join("b.txt")),
+        ]);
+        assert_eq!(operation.label(), "Rename 2 items");
+
+        let journal = temporary_journal();
+        journal.record(&operation);
+        let report = journal
+            .apply(JournalDirection::Undo)
+            .expect("an entry to undo");
+        assert_eq!(report.error, None);
+        assert_eq!(fs::read(dir.join("a.txt")).expect("read file"), b"b");
+        assert_eq!(fs::read(dir.join("b.txt")).expect("read file"), b"a");
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn failed_undo_is_reported_and_kept() {
+        let dir = unique_test_dir("journal_failed");
+        let journal = temporary_journal();
+        journal.record(&JournalOperation::Move(vec![(
+            dir.join("gone.txt"),
+            dir.join("missing.txt"),
+        )]));
+
+        let report = journal
+            .apply(JournalDirection::Undo)
+            .expect("an entry to undo");
+        assert!(report.error.is_some());
+        assert_eq!(journal.undo.len(), 1);
+        assert!(journal.redo.is_empty());
+        let _ = fs::remove_dir_all(dir);
+    }
+}
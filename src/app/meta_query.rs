@@ -0,0 +1,413 @@
+//! Search terms that filter by metadata instead of by name, such as
+//! `size>10MB modified<7d` or `created:2024-01..2024-06 ext:png,jpg`.
+//!
+//! A term holds one or more predicates separated by whitespace, all of which
+//! have to match. Every predicate is checked against the [`DirEntryMetaData`]
+//! that is already read for listing, so filtering never touches the disk.
+
+use std::path::Path;
+
+use anyhow::{Context, Result, anyhow, bail};
+
+use crate::data::{
+    files::{DirEntryMetaData, EntryType},
+    time::TimestampSeconds,
+};
+
+/// Shown next to the search field when a metadata term is being typed.
+pub const SYNTAX_HELP: &str = "Predicates separated by spaces, all of which must match:\n\
+    size>10MB, size<=500KB, size:1GB..4GB\n\
+    modified<7d (newer than a week), modified>1y, modified:2024-03\n\
+    created:2024-01..2024-06, created>=2023-12-24\n\
+    type:dir, type:file\n\
+    ext:png,jpg\n\
+    hidden:yes, hidden:no\n\
+    Ages use s, min, h, d, w, mo and y. Dates are UTC.";
+
+const SECONDS_PER_DAY: u64 = 86_400;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Op {
+    Less,
+    LessOrEqual,
+    Greater,
+    GreaterOrEqual,
+    Equal,
+}
+
+impl Op {
+    /// Values satisfying `value <op> [start, end)`, where the operand covers
+    /// everything in `start..end` (a whole month for `2024-03`, for example).
+    const fn range(self, start: u64, end: u64) -> (u64, u64) {
+        match self {
+            Self::Less => (0, start),
+            Self::LessOrEqual => (0, end),
+            Self::Greater => (end, u64::MAX),
+            Self::GreaterOrEqual => (start, u64::MAX),
+            Self::Equal => (start, end),
+        }
+    }
+
+    /// The same comparison seen from the other side, for ages: a file
+    /// younger than a week was modified after a week ago.
+    const fn flipped(self) -> Self {
+        match self {
+            Self::Less => Self::Greater,
+            Self::LessOrEqual => Self::GreaterOrEqual,
+            Self::Greater => Self::Less,
+            Self::GreaterOrEqual => Self::LessOrEqual,
+            Self::Equal => Self::Equal,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TimeField {
+    Modified,
+    Created,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Predicate {
+    /// Files whose size is in `start..end`. Folders never match.
+    Size {
+        start: u64,
+        end: u64,
+    },
+    /// Entries whose timestamp, in seconds, is in `start..end`.
+    Time {
+        field: TimeField,
+        start: u64,
+        end: u64,
+    },
+    Type(EntryType),
+    /// Lowercase extensions without the dot.
+    Extensions(Vec<String>),
+    Hidden(bool),
+}
+
+impl Predicate {
+    fn matches(&self, name: &str, meta: &DirEntryMetaData) -> bool {
+        match self {
+            Self::Size { start, end } => {
+                meta.entry_type == EntryType::File && (*start..*end).contains(&meta.size)
+            }
+            Self::Time { field, start, end } => {
+                let timestamp = match field {
+                    TimeField::Modified => meta.modified_at,
+                    TimeField::Created => meta.created_at,
+                };
+                (*start..*end).contains(&u64::from(*timestamp))
+            }
+            Self::Type(entry_type) => meta.entry_type == *entry_type,
+            Self::Extensions(extensions) => Path::new(name)
+                .extension()
+                .map(|extension| extension.to_string_lossy().to_lowercase())
+                .is_some_and(|extension| extensions.contains(&extension)),
+            Self::Hidden(hidden) => (name.starts_with('.') || name.starts_with('$')) == *hidden,
+        }
+    }
+}
+
+/// A parsed metadata search term.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MetaQuery {
+    predicates: Vec<Predicate>,
+}
+
+impl MetaQuery {
+    /// Parses `text`, resolving ages such as `7d` against `now`.
+    pub fn parse(text: &str, now: TimestampSeconds) -> Result<Self> {
+        let predicates = text
+            .split_whitespace()
+            .map(|predicate| {
+                parse_predicate(predicate, u64::from(*now))
+                    .with_context(|| format!("Invalid predicate \"{predicate}\""))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        if predicates.is_empty() {
+            bail!("Empty metadata query");
+        }
+        Ok(Self { predicates })
+    }
+
+    pub fn matches(&self, name: &str, meta: &DirEntryMetaData) -> bool {
+        self.predicates
+            .iter()
+            .all(|predicate| predicate.matches(name, meta))
+    }
+}
+
+fn split_op(text: &str) -> Option<(&str, Op, &str)> {
+    let index = text.find([':', '=', '<', '>'])?;
+    let (key, rest) = text.split_at(index);
+    let (op, value) = [
+        (">=", Op::GreaterOrEqual),
+        ("<=", Op::LessOrEqual),
+        (">", Op::Greater),
+        ("<", Op::Less),
+        ("=", Op::Equal),
+        (":", Op::Equal),
+    ]
+    .into_iter()
+    .find_map(|(symbol, op)| rest.strip_prefix(symbol).map(|value| (op, value)))?;
+    Some((key, op, value))
+}
+
+fn parse_predicate(text: &str, now: u64) -> Result<Predicate> {
+    let (key, op, value) =
+        split_op(text).ok_or_else(|| anyhow!("expected a key such as size: or type:"))?;
+    let key = key.to_lowercase();
+    if value.is_empty() {
+        bail!("missing a value");
+    }
+    let only_equal = || {
+        if op == Op::Equal {
+            Ok(())
+        } else {
+            Err(anyhow!("{key} can only be compared with :"))
+        }
+    };
+    match key.as_str() {
+        "size" => {
+            let (start, end) = parse_range(op, value, |bound| {
+                let size = parse_size(bound)?;
+                Ok((size, size.saturating_add(1)))
+            })?;
+            Ok(Predicate::Size { start, end })
+        }
+        "modified" | "created" => {
+            let field = if key == "modified" {
+                TimeField::Modified
+            } else {
+                TimeField::Created
+            };
+            // A lone age reads from the entry's side: `<7d` is younger than a
+            // week, and `:7d` means within the last week.
+            let op = match op {
+                _ if value.contains("..") || parse_age(value).is_none() => op,
+                Op::Equal => Op::GreaterOrEqual,
+                op => op.flipped(),
+            };
+            let (start, end) = parse_range(op, value, |bound| {
+                parse_age(bound).map_or_else(
+                    || parse_date(bound),
+                    |age| {
+                        let point = now.saturating_sub(age);
+                        Ok((point, point + 1))
+                    },
+                )
+            })?;
+            Ok(Predicate::Time { field, start, end })
+        }
+        "type" => {
+            only_equal()?;
+            match value.to_lowercase().as_str() {
+                "file" | "f" => Ok(Predicate::Type(EntryType::File)),
+                "dir" | "directory" | "folder" | "d" => Ok(Predicate::Type(EntryType::Directory)),
+                _ => bail!("type is file or dir"),
+            }
+        }
+        "ext" => {
+            only_equal()?;
+            let extensions = value
+                .split(',')
+                .map(|extension| extension.trim().trim_start_matches('.').to_lowercase())
+                .filter(|extension| !extension.is_empty())
+                .collect::<Vec<_>>();
+            if extensions.is_empty() {
+                bail!("missing an extension");
+            }
+            Ok(Predicate::Extensions(extensions))
+        }
+        "hidden" => {
+            only_equal()?;
+            match value.to_lowercase().as_str() {
+                "yes" | "true" | "y" => Ok(Predicate::Hidden(true)),
+                "no" | "false" | "n" => Ok(Predicate::Hidden(false)),
+                _ => bail!("hidden is yes or no"),
+            }
+        }
+        _ => bail!("unknown key {key}"),
+    }
+}
+
+/// Resolves `value`, either a single bound compared with `op` or a
+/// `from..to` range where either side may be left out.
+fn parse_range(
+    op: Op,
+    value: &str,
+    bound: impl Fn(&str) -> Result<(u64, u64)>,
+) -> Result<(u64, u64)> {
+    let Some((from, to)) = value.split_once("..") else {
+        let (start, end) = bound(value)?;
+        return Ok(op.range(start, end));
+    };
+    if op != Op::Equal {
+        bail!("ranges are written with :");
+    }
+    let from = if from.is_empty() {
+        None
+    } else {
+        Some(bound(from)?)
+    };
+    let to = if to.is_empty() {
+        None
+    } else {
+        Some(bound(to)?)
+    };
+    let (start, end) = match (from, to) {
+        (None, None) => bail!("a range needs at least one end"),
+        (Some((start, _)), None) => (start, u64::MAX),
+        (None, Some((_, end))) => (0, end),
+        // Ages run backwards, so whichever end is earlier starts the range.
+        (Some(from), Some(to)) => (from.0.min(to.0), from.1.max(to.1)),
+    };
+    Ok((start, end))
+}
+
+fn parse_size(text: &str) -> Result<u64> {
+    let split = text
+        .find(|c: char| !c.is_ascii_digit() && c != '.')
+        .unwrap_or(text.len());
+    let (number, unit) = text.split_at(split);
+    let number: f64 = number
+        .parse()
+        .map_err(|_| anyhow!("{text} is not a size"))?;
+    let multiplier: u64 = match unit.to_lowercase().as_str() {
+        "" | "b" => 1,
+        "k" | "kb" | "kib" => 1 << 10,
+        "m" | "mb" | "mib" => 1 << 20,
+        "g" | "gb" | "gib" => 1 << 30,
+        "t" | "tb" | "tib" => 1 << 40,
+        _ => bail!("unknown size unit {unit}"),
+    };
+    Ok((number * multiplier as f64) as u64)
+}
+
+/// Seconds in an age such as `7d` or `3mo`.
+fn parse_age(text: &str) -> Option<u64> {
+    let split = text.find(|c: char| !c.is_ascii_digit())?;
+    let (number, unit) = text.split_at(split);
+    let number: u64 = number.parse().ok()?;
+    let unit = match unit.to_lowercase().as_str() {
+        "s" => 1,
+        "min" => 60,
+        "h" => 3600,
+        "d" => SECONDS_PER_DAY,
+        "w" => 7 * SECONDS_PER_DAY,
+        "mo" => 30 * SECONDS_PER_DAY,
+        "y" => 365 * SECONDS_PER_DAY,
+        _ => return None,
+    };
+    Some(number.saturating_mul(unit))
+}
+
+/// The seconds covered by `YYYY`, `YYYY-MM` or `YYYY-MM-DD`, in UTC.
+fn parse_date(text: &str) -> Result<(u64, u64)> {
+    let invalid = || anyhow!("{text} is neither an age nor a date");
+    let parts = text
+        .split('-')
+        .map(str::parse::<i64>)
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|_| invalid())?;
+    let timestamp = |year, month, day| {
+        TimestampSeconds::from_utc_parts(year, month, day, 0, 0, 0)
+            .map(|timestamp| u64::from(*timestamp))
+            .ok_or_else(invalid)
+    };
+    match parts[..] {
+        [year] => Ok((timestamp(year, 1, 1)?, timestamp(year + 1, 1, 1)?)),
+        [year, month] => {
+            let (next_year, next_month) = if month == 12 {
+                (year + 1, 1)
+            } else {
+                (year, month + 1)
+            };
+            Ok((
+                timestamp(year, month, 1)?,
+                timestamp(next_year, next_month, 1)?,
+            ))
+        }
+        [year, month, day] => {
+            let start = timestamp(year, month, day)?;
+            Ok((start, start + SECONDS_PER_DAY))
+        }
+        _ => Err(invalid()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(date: &str) -> TimestampSeconds {
+        TimestampSeconds::parse_utc(&format!("{date} 12:00")).expect("valid date")
+    }
+
+    fn meta(entry_type: EntryType, size: u64, modified: &str, created: &str) -> DirEntryMetaData {
+        DirEntryMetaData {
+            size,
+            modified_at: at(modified),
+            created_at: at(created),
+            ..DirEntryMetaData::from(entry_type)
+        }
+    }
+
+    #[test]
+    fn predicates_match_against_metadata() {
+        let now = at("2024-07-01");
+        let photo = meta(EntryType::File, 12 << 20, "2024-06-28", "2024-02-10");
+        let folder = meta(EntryType::Directory, 0, "2023-05-01", "2023-05-01");
+        let query = |text: &str| MetaQuery::parse(text, now).expect(text);
+
+        assert!(query("size>10MB").matches("a.png", &photo));
+        assert!(!query("size<10MB").matches("a.png", &photo));
+        assert!(query("size:10M..1GB").matches("a.png", &photo));
+        assert!(
+            !query("size<1KB").matches("dir", &folder),
+            "folders have no size"
+        );
+
+        assert!(query("modified<7d").matches("a.png", &photo));
+        assert!(!query("modified<7d").matches("dir", &folder));
+        assert!(query("modified>1y").matches("dir", &folder));
+        assert!(query("modified:1d..1w").matches("a.png", &photo));
+        assert!(query("modified:1w").matches("a.png", &photo));
+        assert!(query("created:2024-01..2024-06").matches("a.png", &photo));
+        assert!(query("created:2024-02").matches("a.png", &photo));
+        assert!(!query("created>2024-02").matches("a.png", &photo));
+        assert!(query("created>=2024-02-10").matches("a.png", &photo));
+        assert!(query("created<2024").matches("dir", &folder));
+
+        assert!(query("type:dir").matches("dir", &folder));
+        assert!(!query("type:dir").matches("a.png", &photo));
+        assert!(query("ext:PNG,jpg").matches("a.png", &photo));
+        assert!(!query("ext:jpg").matches("a.png", &photo));
+        assert!(query("hidden:yes").matches(".cache", &folder));
+        assert!(query("hidden:no").matches("a.png", &photo));
+
+        assert!(query("type:file size>1MB ext:png").matches("a.png", &photo));
+        assert!(!query("type:file size>1MB ext:jpg").matches("a.png", &photo));
+    }
+
+    #[test]
+    fn rejects_malformed_queries() {
+        let now = at("2024-07-01");
+        for text in [
+            "",
+            "size",
+            "size>",
+            "size>10XB",
+            "weight>10",
+            "type:link",
+            "type>dir",
+            "modified<7fortnights",
+            "created:2024-13",
+            "created<2024-01..2024-02",
+            "modified:..",
+        ] {
+            assert!(MetaQuery::parse(text, now).is_err(), "{text}");
+        }
+    }
+}
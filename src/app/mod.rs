@@ -2,7 +2,8 @@ use crate::app::assets::AssetManager;
 use crate::app::commands::{COMMANDS_QUEUE, TabAction, TabTarget};
 use crate::app::directory_path_info::DirectoryPathInfo;
 use crate::app::directory_view_settings::DirectoryViewSettings;
-use crate::app::dock::CurrentPath;
+use crate::app::dock::{CurrentPath, Selected};
+use crate::app::journal::{JournalDirection, JournalOperation};
 use crate::data::files::{DirEntry, DirList};
 use crate::helper::{DataHolder, KeyWithCommandPressed};
 use crate::locations::Locations;
@@ -24,18 +25,51 @@ use std::sync::LazyLock;
 use std::time::{Duration, Instant};
 use std::fs;
 
+pub mod archive;
 pub mod assets;
+pub mod batch_rename;
+mod batch_rename_dialog;
 mod central_panel;
+pub mod checksums;
+mod checksums_dialog;
+pub mod clipboard;
 pub mod command_palette;
 pub mod commands;
+pub mod compare;
+mod compare_dialog;
+mod compress_dialog;
+mod conflict_dialog;
+pub mod content_search;
 pub mod database;
+mod delete_dialog;
 pub mod dir_handling;
+pub mod dir_sizes;
 pub mod directory_path_info;
 mod directory_view_settings;
+pub mod disk_usage;
+mod disk_usage_dialog;
 pub mod dock;
+pub mod drag_drop;
+pub mod duplicates;
+mod duplicates_dialog;
+mod extract_dialog;
+pub mod file_ops;
+pub mod journal;
+pub mod meta_query;
+pub mod name_index;
+pub mod new_entry;
+pub mod properties;
+mod properties_dialog;
 mod settings;
 mod side_panel;
+pub mod smart_folders;
+pub mod sync;
+mod sync_dialog;
+#[cfg(test)]
+mod test_support;
 mod top_bottom;
+pub mod trash_bin;
+mod trash_bin_view;
 
 /// Dedicated thread pool for filesystem reads. Limited to 2 threads to bound
 /// concurrent disk I/O while keeping the UI responsive.
@@ -160,7 +194,12 @@ impl App {
         self.watchers.check_for_new_watchers();
         const MODIFIED_FILE_COALESCE: Duration = Duration::from_millis(150);
 
-        let changes = self.watchers.check_for_file_system_events();
+        let mut changes = self.watchers.check_for_file_system_events();
+        // Extractions refresh their destination once they are done.
+        changes.structural_dirs.retain(|dir| !file_ops::is_quiet(dir));
+        changes
+            .modified_files
+            .retain(|file| !file.parent().is_some_and(file_ops::is_quiet));
         let now = Instant::now();
         for file in changes.modified_files {
             self.pending_modified_files.entry(file).or_insert(now);
@@ -187,6 +226,13 @@ impl App {
         self.assets
             .invalidate_directories(changes.structural_dirs.iter().cloned());
         crate::app::database::invalidate_dirs(changes.structural_dirs.iter().cloned());
+        dir_sizes::invalidate(
+            changes.structural_dirs.iter().cloned().chain(
+                ready_modified_files
+                    .iter()
+                    .filter_map(|file| file.parent().map(Path::to_path_buf)),
+            ),
+        );
 
         let tab_ids = self.tabs.get_tab_ids();
         let affected_tabs = tab_ids
@@ -223,6 +269,64 @@ impl App {
         }
     }
 
+    /// Sorts tabs ordered by size again once folder sizes come in.
+    fn sort_by_new_dir_sizes(&mut self, ctx: &egui::Context) {
+        if !dir_sizes::take_changed() {
+            return;
+        }
+        for tab_id in self.tabs.get_tab_ids() {
+            let Some(tab) = self.tabs.get_tab_by_id(tab_id) else {
+                continue;
+            };
+            let settings =
+                ctx.data_get_path_or_persisted::<DirectoryViewSettings>(&tab.current_path);
+            if settings.data.sorting == Sort::Size {
+                TabAction::FilesSort.schedule_tab(tab_id);
+            }
+        }
+    }
+
+    /// Drops cached listings and thumbnails of `dirs` after a file operation
+    /// changed them, then refreshes every tab.
+    fn refresh_changed_dirs(&mut self, dirs: Vec<PathBuf>) {
+        for dir in &dirs {
+            crate::app::database::invalidate_dir(dir);
+        }
+        dir_sizes::invalidate(dirs.iter().cloned());
+        self.assets.invalidate_directories(dirs);
+        ActionToPerform::TabAction(TabTarget::AllTabs, TabAction::RequestFilesRefresh).schedule();
+    }
+
+    /// Selects the entry a [`TabAction::RevealEntry`] waits for, once listed.
+    fn reveal_pending_entry(&mut self, ctx: &egui::Context, tab_id: u32) {
+        let Some(tab) = self.tabs.get_tab_by_id(tab_id) else {
+            return;
+        };
+        let Some((path, rename)) = tab.pending_reveal.take() else {
+            return;
+        };
+        let Some((row, entry)) = (0..tab.visible_entries.len())
+            .filter_map(|row| tab.entry_at(row).map(|entry| (row, entry)))
+            .find(|(_, entry)| entry.get_path() == path)
+        else {
+            return;
+        };
+        ctx.data_set_path(
+            &tab.current_path,
+            Selected {
+                selected_fields: vec![row],
+                just_changed: true,
+            },
+        );
+        if rename {
+            ctx.data_mut(|d| {
+                d.insert_temp(egui::Id::new(ModalWindow::Rename), entry);
+                d.remove_temp::<String>(egui::Id::new(ModalWindow::Rename).with("new"));
+            });
+            self.display_modal = Some(ModalWindow::Rename);
+        }
+    }
+
     fn drain_command_queue(&mut self, ctx: &egui::Context) {
         while let Some(action) = COMMANDS_QUEUE.pop() {
             self.handle_action(ctx, action);
@@ -260,6 +364,11 @@ pub enum SearchTermType {
     Plain,
     Glob,
     Regex,
+    /// Predicates on size, dates, type, extension and visibility, see
+    /// [`meta_query`].
+    Metadata,
+    /// Skim-style subsequence matching, scored so results can be ranked.
+    Fuzzy,
 }
 
 #[derive(Deserialize, Serialize, Default, Debug, Clone, Copy, PartialEq, Eq)]
@@ -269,7 +378,7 @@ pub enum MatchMode {
     Any,
 }
 
-#[derive(Deserialize, Serialize, Debug, Clone)]
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq)]
 pub struct SearchTerm {
     pub pattern: String,
     pub term_type: SearchTermType,
@@ -279,6 +388,12 @@ pub struct SearchTerm {
 pub struct SavedSearch {
     pub name: String,
     pub search: Search,
+    /// Folders the search was saved in, where it opens as a smart folder.
+    #[serde(default)]
+    pub roots: Vec<PathBuf>,
+    /// Shown in the sidebar as a smart folder.
+    #[serde(default)]
+    pub pinned: bool,
 }
 
 #[derive(Deserialize, Serialize, Default, Debug, Clone)]
@@ -286,7 +401,7 @@ pub struct SavedSearches {
     pub searches: Vec<SavedSearch>,
 }
 
-#[derive(Deserialize, Serialize, Default, Debug, Clone)]
+#[derive(Deserialize, Serialize, Default, Debug, Clone, PartialEq, Eq)]
 pub struct Search {
     pub value: String,
     pub depth: usize,
@@ -299,12 +414,35 @@ pub struct Search {
     pub terms: Vec<SearchTerm>,
     #[serde(default)]
     pub match_mode: MatchMode,
+    /// Look for `value` inside files; the terms still filter by name.
+    #[serde(default)]
+    pub in_contents: bool,
+    /// Order results by fuzzy score instead of the directory's `Sort`.
+    #[serde(default)]
+    pub sort_by_relevance: bool,
+    /// Skip what `.gitignore`, `.ignore` and the global git excludes ignore.
+    #[serde(default)]
+    pub respect_ignore_files: bool,
+    /// Globs for entries to leave out, tried on names and on paths relative
+    /// to the searched folder. Excluded folders are not descended into.
+    #[serde(default)]
+    pub exclude_globs: Vec<String>,
+    #[serde(skip)]
+    pub new_exclude_input: String,
     #[serde(skip)]
     pub new_dir_input: String,
     #[serde(skip)]
     pub save_name_input: String,
 }
 
+impl Search {
+    /// Whether `value` is looked for inside files instead of filtering the
+    /// entries. Metadata queries always filter the entries.
+    pub fn searches_contents(&self) -> bool {
+        self.in_contents && self.term_type != SearchTermType::Metadata
+    }
+}
+
 #[derive(Deserialize, Serialize, Default, Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
 pub enum DataSource {
     Settings,
@@ -416,6 +554,7 @@ impl App {
             value.tabs = crate::app::dock::MyTabs::new(&get_starting_path());
             value.assets = AssetManager::default();
             value.assets.set_icon_size(value.settings.icon_size);
+            name_index::sync_roots(&value.settings.index_roots);
             #[cfg(feature = "profiling")]
             {
                 value.profiler_visible = true;
@@ -494,6 +633,12 @@ impl App {
                             return;
                         };
                         tab.update_settings(ctx);
+                        // A content search only holds the files that matched, so
+                        // any change to it means reading the files again.
+                        if tab.is_content_search() {
+                            TabAction::RequestFilesRefresh.schedule_tab(tab_id);
+                            return;
+                        }
                         tab.update_visible_entries();
                     }
                     commands::TabAction::ForceRefresh => {
@@ -507,7 +652,9 @@ impl App {
                                 return;
                             };
                             let mut dirs: Vec<PathBuf> = match &tab.current_path {
-                                CurrentPath::None => vec![],
+                                CurrentPath::None
+                                | CurrentPath::Archive { .. }
+                                | CurrentPath::Trash => vec![],
                                 CurrentPath::One(p) => vec![p.clone()],
                                 CurrentPath::Multiple(ps) => ps.clone(),
                             };
@@ -518,6 +665,7 @@ impl App {
                             }
                             dirs
                         };
+                        dir_sizes::invalidate_below(force_dirs.iter().cloned());
                         crate::app::database::invalidate_dirs(force_dirs.into_iter());
                         // Fall through to normal refresh
                         self.handle_action(
@@ -539,6 +687,13 @@ impl App {
                             .fetch_add(1, std::sync::atomic::Ordering::SeqCst);
                         tab.cancel_token
                             .store(true, std::sync::atomic::Ordering::SeqCst);
+                        if matches!(tab.current_path, CurrentPath::Trash) {
+                            tab.list.clear();
+                            tab.dir_list = None;
+                            tab.visible_entries.clear();
+                            trash_bin_view::load(ctx, tab_id);
+                            return;
+                        }
                         if tab.loading {
                             tab.pending_refresh = true;
                             return;
@@ -546,7 +701,9 @@ impl App {
                         tab.update_settings(ctx);
 
                         let mut directories: Vec<PathBuf> = match &tab.current_path {
-                            CurrentPath::None => vec![],
+                            CurrentPath::None
+                            | CurrentPath::Archive { .. }
+                            | CurrentPath::Trash => vec![],
                             CurrentPath::One(path_buf) => vec![path_buf.clone()],
                             CurrentPath::Multiple(path_bufs) => path_bufs.clone(),
                         };
@@ -559,6 +716,19 @@ impl App {
                         let show_hidden = tab.show_hidden;
                         let current_path = tab.current_path.clone();
                         let search = tab.search.clone();
+                        let content_matcher =
+                            search.as_ref().and_then(content_search::ContentMatcher::new);
+                        let walk_filter =
+                            crate::app::dir_handling::WalkFilter::new(search.as_ref()).with_index();
+                        let max_file_size =
+                            self.settings.content_search_max_mb.saturating_mul(1024 * 1024);
+                        tab.content_hits.clear();
+                        if content_matcher.is_some() {
+                            // Hits stream in as they are found.
+                            tab.list.clear();
+                            tab.dir_list = None;
+                            tab.visible_entries.clear();
+                        }
 
                         let settings =
                             ctx.data_get_path_or_persisted::<DirectoryViewSettings>(&current_path);
@@ -574,10 +744,25 @@ impl App {
                         BG_POOL.spawn(move || {
                             #[cfg(feature = "profiling")]
                             puffin::profile_scope!("lwa_fm::handle_action::RefreshFiles::bg_thread");
+                            // A cancelled load still reports back, so the tab leaves the
+                            // loading state and runs the refresh that cancelled it.
+                            let cancelled = || {
+                                COMMANDS_QUEUE.push(ActionToPerform::TabAction(
+                                    TabTarget::TabWithId(tab_id),
+                                    TabAction::FilesLoaded {
+                                        list: Vec::new(),
+                                        generation,
+                                        visible: Vec::new(),
+                                        dir_list: None,
+                                    },
+                                ));
+                            };
                             if refresh_gen.load(std::sync::atomic::Ordering::SeqCst) != generation {
+                                cancelled();
                                 return;
                             }
                             if cancel.load(std::sync::atomic::Ordering::SeqCst) {
+                                cancelled();
                                 return;
                             }
 
@@ -597,15 +782,55 @@ impl App {
                                 if directories.len() == 1 { "y" } else { "ies" }
                             ));
 
-                            let mut list = crate::app::dir_handling::read_directory(
-                                &directories,
-                                depth,
-                                show_hidden,
-                                &cancel,
-                            );
+                            let mut list = if let CurrentPath::Archive { archive, inner } =
+                                &current_path
+                            {
+                                archive::read_dir(archive, inner).unwrap_or_else(|err| {
+                                    log::error!("{err:#}");
+                                    toast!(Error, "{err:#}");
+                                    Vec::new()
+                                })
+                            } else {
+                                crate::app::dir_handling::read_directory(
+                                    &directories,
+                                    depth,
+                                    show_hidden,
+                                    &walk_filter,
+                                    &cancel,
+                                )
+                            };
                             if cancel.load(std::sync::atomic::Ordering::SeqCst) {
+                                cancelled();
                                 return;
                             }
+                            if let Some(matcher) = &content_matcher {
+                                let candidates = crate::app::dir_handling::filter_visible_entries(
+                                    &list,
+                                    show_hidden,
+                                    search.as_ref(),
+                                )
+                                .into_iter()
+                                .filter_map(|index| list.get(index).cloned())
+                                .collect::<Vec<_>>();
+                                let found = |hits| {
+                                    COMMANDS_QUEUE.push(ActionToPerform::TabAction(
+                                        TabTarget::TabWithId(tab_id),
+                                        TabAction::ContentMatches { hits, generation },
+                                    ));
+                                };
+                                let Some(matched) = content_search::scan(
+                                    &candidates,
+                                    matcher,
+                                    max_file_size,
+                                    &cancel,
+                                    &|message| progress(&message),
+                                    &found,
+                                ) else {
+                                    cancelled();
+                                    return;
+                                };
+                                list = matched;
+                            }
 
                             progress(&format!("Sorting {} entries…", list.len()));
 
@@ -621,7 +846,10 @@ impl App {
                             // DirList so the tab's `list` can stay empty (full DirEntry values
                             // are then built only for visible rows, roughly halving memory for
                             // large folders). Multi-dir / search reads keep `list` populated.
-                            let (list, dir_list) = if depth <= 1 && directories.len() == 1 {
+                            let (list, dir_list) = if depth <= 1
+                                && directories.len() == 1
+                                && content_matcher.is_none()
+                            {
                                 (Vec::new(), crate::data::files::DirList::from_owned_list(list))
                             } else {
                                 (list, None)
@@ -646,6 +874,9 @@ impl App {
 
                         let settings = ctx
                             .data_get_path_or_persisted::<DirectoryViewSettings>(&tab.current_path);
+                        if settings.data.sorting == Sort::Size {
+                            tab.measure_dir_sizes(ctx);
+                        }
                         tab.sort_entries(&settings.data);
                         tab.update_visible_entries();
                     }
@@ -687,11 +918,31 @@ impl App {
                         tab.dir_list = dir_list;
                         tab.loading = false;
                         tab.loading_progress = None;
+                        let settings = ctx
+                            .data_get_path_or_persisted::<DirectoryViewSettings>(&tab.current_path);
+                        if settings.data.sorting == Sort::Size {
+                            tab.measure_dir_sizes(ctx);
+                        }
                         if tab.pending_refresh {
                             tab.pending_refresh = false;
                             TabAction::RequestFilesRefresh.schedule_tab(tab_id);
+                        } else {
+                            self.reveal_pending_entry(ctx, tab_id);
                         }
                     }
+                    commands::TabAction::RevealEntry { path, rename } => {
+                        let Some(tab) = self.tabs.get_tab_by_id(tab_id) else {
+                            return;
+                        };
+                        tab.pending_reveal = Some((path, rename));
+                        self.handle_action(
+                            ctx,
+                            ActionToPerform::TabAction(
+                                TabTarget::TabWithId(tab_id),
+                                TabAction::RequestFilesRefresh,
+                            ),
+                        );
+                    }
                     commands::TabAction::FilesProgress {
                         progress,
                         generation: progress_gen,
@@ -708,6 +959,23 @@ impl App {
                         }
                         tab.loading_progress = Some(progress);
                     }
+                    commands::TabAction::ContentMatches { hits, generation } => {
+                        let Some(tab) = self.tabs.get_tab_by_id(tab_id) else {
+                            return;
+                        };
+                        if tab
+                            .refresh_generation
+                            .load(std::sync::atomic::Ordering::SeqCst)
+                            != generation
+                        {
+                            return;
+                        }
+                        for (entry, hit) in hits {
+                            tab.content_hits.insert(entry.get_path(), hit);
+                            tab.list.push(entry);
+                        }
+                        tab.update_visible_entries();
+                    }
                     commands::TabAction::SearchInFavorites(start) => {
                         let Some(tab) = self.tabs.get_tab_by_id(tab_id) else {
                             return;
@@ -799,11 +1067,28 @@ impl App {
                             let mut saved = ctx
                                 .data_get_persisted::<SavedSearches>()
                                 .unwrap_or_default();
-                            saved.searches.retain(|s| s.name != name);
-                            saved.searches.push(SavedSearch {
+                            let roots = match &tab.current_path {
+                                CurrentPath::None
+                                | CurrentPath::Archive { .. }
+                                | CurrentPath::Trash => vec![],
+                                CurrentPath::One(p) => vec![p.clone()],
+                                CurrentPath::Multiple(ps) => ps.clone(),
+                            };
+                            let mut saved_search = SavedSearch {
                                 name: name.clone(),
                                 search: search.clone(),
-                            });
+                                roots,
+                                pinned: false,
+                            };
+                            // Saving over a search keeps its place and its pin.
+                            if let Some(existing) =
+                                saved.searches.iter_mut().find(|s| s.name == name)
+                            {
+                                saved_search.pinned = existing.pinned;
+                                *existing = saved_search;
+                            } else {
+                                saved.searches.push(saved_search);
+                            }
                             ctx.data_set_persisted(saved);
                             toast!(Success, "Saved search: {name}");
                         }
@@ -830,7 +1115,110 @@ impl App {
                     }
                 }
             }
-            ActionToPerform::NewTab(path) => self.tabs.open_in_new_tab(&path),
+            ActionToPerform::NewTab(path) => {
+                self.tabs.open_in_new_tab(&path);
+            }
+            ActionToPerform::OpenSmartFolder(name) => {
+                let saved = ctx
+                    .data_get_persisted::<SavedSearches>()
+                    .unwrap_or_default();
+                let Some(folder) = saved.searches.into_iter().find(|s| s.name == name) else {
+                    return;
+                };
+                let Some((root, others)) = folder.roots.split_first() else {
+                    toast!(Error, "{name} doesn't know which folder to search");
+                    return;
+                };
+                let tab_id = self.tabs.open_in_new_tab(root);
+                let Some(tab) = self.tabs.get_tab_by_id(tab_id) else {
+                    return;
+                };
+                let mut search = folder.search;
+                search.extra_dirs.extend(others.iter().cloned());
+                tab.search = Some(search);
+                TabAction::RequestFilesRefresh.schedule_tab(tab_id);
+            }
+            ActionToPerform::PinSavedSearch { name, pinned } => {
+                let mut saved = ctx
+                    .data_get_persisted::<SavedSearches>()
+                    .unwrap_or_default();
+                let roots = self
+                    .tabs
+                    .get_current_tab()
+                    .and_then(|tab| tab.current_path.single_path());
+                if let Some(search) = saved.searches.iter_mut().find(|s| s.name == name) {
+                    search.pinned = pinned;
+                    // Searches saved before smart folders existed don't know
+                    // their folder, the current one is the best guess.
+                    if search.roots.is_empty() {
+                        search.roots.extend(roots);
+                    }
+                }
+                ctx.data_set_persisted(saved);
+            }
+            ActionToPerform::RenameSmartFolder { from, to } => {
+                let mut saved = ctx
+                    .data_get_persisted::<SavedSearches>()
+                    .unwrap_or_default();
+                match smart_folders::rename(&mut saved, &from, &to) {
+                    Ok(()) => ctx.data_set_persisted(saved),
+                    Err(err) => {
+                        toast!(Error, "{err:#}");
+                    }
+                }
+            }
+            ActionToPerform::MoveSmartFolder { name, up } => {
+                let mut saved = ctx
+                    .data_get_persisted::<SavedSearches>()
+                    .unwrap_or_default();
+                smart_folders::move_folder(&mut saved, &name, up);
+                ctx.data_set_persisted(saved);
+            }
+            ActionToPerform::ExportSmartFolders => {
+                let Some(dir) = self
+                    .tabs
+                    .get_current_tab()
+                    .and_then(|tab| tab.current_path.single_path())
+                else {
+                    toast!(Error, "Open the folder to export to first");
+                    return;
+                };
+                let saved = ctx
+                    .data_get_persisted::<SavedSearches>()
+                    .unwrap_or_default();
+                match smart_folders::export(&saved, &dir) {
+                    Ok(path) => {
+                        toast!(Success, "Exported to {}", path.display());
+                        self.refresh_changed_dirs(vec![dir]);
+                    }
+                    Err(err) => {
+                        toast!(Error, "{err:#}");
+                    }
+                }
+            }
+            ActionToPerform::ImportSmartFolders => {
+                let Some(dir) = self
+                    .tabs
+                    .get_current_tab()
+                    .and_then(|tab| tab.current_path.single_path())
+                else {
+                    toast!(Error, "Open the folder to import from first");
+                    return;
+                };
+                let mut saved = ctx
+                    .data_get_persisted::<SavedSearches>()
+                    .unwrap_or_default();
+                let path = dir.join(smart_folders::EXPORT_FILE_NAME);
+                match smart_folders::import(&mut saved, &path) {
+                    Ok(read) => {
+                        ctx.data_set_persisted(saved);
+                        toast!(Success, "Imported {read} smart folders");
+                    }
+                    Err(err) => {
+                        toast!(Error, "{err:#}");
+                    }
+                }
+            }
             ActionToPerform::OpenInTerminal(path_buf) => {
                 match self.settings.open_in_terminal(&path_buf) {
                     Ok(_) => {
@@ -913,7 +1301,192 @@ impl App {
                 ctx.data_set_persisted(favorites);
             }
             ActionToPerform::SystemOpen(cow) => {
-                let _ = open::that_detached(cow.as_str());
+                let path = Path::new(cow.as_str());
+                if !path.exists()
+                    && let Some((archive, inner)) = archive::split(path)
+                {
+                    // Files inside archives are opened from a temporary copy.
+                    file_ops::spawn_task(move || match archive::extract_file(&archive, &inner) {
+                        Ok(extracted) => {
+                            let _ = open::that_detached(extracted);
+                        }
+                        Err(err) => {
+                            log::error!("{err:#}");
+                            toast!(Error, "{err:#}");
+                        }
+                    });
+                } else {
+                    let _ = open::that_detached(cow.as_str());
+                }
+            }
+            ActionToPerform::FileJobFinished(report) => {
+                self.refresh_changed_dirs(report.operation.affected_dirs());
+                clipboard::move_finished(&report);
+                let label = report.operation.label();
+                // What an item replaced is gone, undoing it can't bring that back.
+                let mut transferred = report.transferred;
+                transferred.retain(|(_, to)| !report.replaced.contains(to));
+                if !report.replaced.is_empty() {
+                    toast!(
+                        Info,
+                        "{label}: {} replaced item(s) can't be undone",
+                        report.replaced.len()
+                    );
+                }
+                let journal_entry = match report.operation.kind {
+                    _ if transferred.is_empty() => None,
+                    file_ops::FileOperationKind::Copy => Some(JournalOperation::Copy(transferred)),
+                    file_ops::FileOperationKind::Move => Some(JournalOperation::Move(transferred)),
+                    file_ops::FileOperationKind::Delete
+                    | file_ops::FileOperationKind::Compress { .. }
+                    | file_ops::FileOperationKind::Extract
+                    | file_ops::FileOperationKind::Sync => None,
+                };
+                match &report.outcome {
+                    // Journaled operations get the notice with an "Undo" button instead.
+                    file_ops::JobOutcome::Completed if journal_entry.is_some() => {}
+                    file_ops::JobOutcome::Completed => {
+                        toast!(Success, "{label} finished");
+                    }
+                    file_ops::JobOutcome::Cancelled => {
+                        toast!(Info, "{label} cancelled");
+                    }
+                    file_ops::JobOutcome::Failed(errors) => {
+                        for error in errors {
+                            log::error!("{label}: {error}");
+                        }
+                        toast!(
+                            Error,
+                            "{label} failed for {} item(s): {}",
+                            errors.len(),
+                            errors.first().map_or("", String::as_str)
+                        );
+                    }
+                }
+                if let Some(entry) = journal_entry {
+                    journal::record(&entry);
+                }
+            }
+            ActionToPerform::UndoFileOperation => {
+                if journal::next_undo().is_some() {
+                    journal::schedule(JournalDirection::Undo);
+                } else {
+                    toast!(Info, "Nothing to undo");
+                }
+            }
+            ActionToPerform::RedoFileOperation => {
+                if journal::next_redo().is_some() {
+                    journal::schedule(JournalDirection::Redo);
+                } else {
+                    toast!(Info, "Nothing to redo");
+                }
+            }
+            ActionToPerform::CreateEntry(kind) => {
+                let Some(dir) = self.tabs.get_current_path() else {
+                    toast!(Error, "Can't create items while showing multiple directories");
+                    return;
+                };
+                match new_entry::create(&dir, &kind) {
+                    Ok(path) => {
+                        journal::record(&JournalOperation::Create {
+                            path: path.clone(),
+                            is_dir: path.is_dir(),
+                        });
+                        self.refresh_changed_dirs(vec![dir]);
+                        TabAction::RevealEntry { path, rename: true }.schedule_active_tab();
+                    }
+                    Err(err) => {
+                        log::error!("{}: {err:#}", kind.label());
+                        toast!(Error, "{} failed: {err}", kind.label());
+                    }
+                }
+            }
+            ActionToPerform::JournalApplied(report) => {
+                self.refresh_changed_dirs(report.affected_dirs);
+                let verb = match report.direction {
+                    JournalDirection::Undo => "Undo",
+                    JournalDirection::Redo => "Redo",
+                };
+                match report.error {
+                    None => {
+                        toast!(Success, "{verb}: {}", report.label);
+                    }
+                    Some(error) => {
+                        log::error!("{verb} of {} failed: {error}", report.label);
+                        toast!(Error, "{verb} of {} failed: {error}", report.label);
+                    }
+                }
+            }
+            ActionToPerform::FindDuplicates => {
+                let Some(tab) = self.tabs.get_current_tab() else {
+                    return;
+                };
+                let mut roots = match &tab.current_path {
+                    CurrentPath::None
+                    | CurrentPath::Archive { .. }
+                    | CurrentPath::Trash => vec![],
+                    CurrentPath::One(path) => vec![path.clone()],
+                    CurrentPath::Multiple(paths) => paths.clone(),
+                };
+                if let Some(search) = &tab.search {
+                    roots.extend(search.extra_dirs.iter().cloned());
+                }
+                roots.sort();
+                roots.dedup();
+                if roots.is_empty() {
+                    toast!(Info, "Open a folder to look for duplicates in");
+                    return;
+                }
+                duplicates_dialog::open(ctx, roots, tab.show_hidden);
+            }
+            ActionToPerform::AnalyzeDiskUsage(path) => {
+                disk_usage_dialog::open(ctx, path);
+            }
+            ActionToPerform::CompareFolders => {
+                let other_tabs = self.tabs.get_tabs_paths();
+                let Some(tab) = self.tabs.get_current_tab() else {
+                    return;
+                };
+                let Some(left) = tab.current_path.single_path() else {
+                    toast!(Info, "Open a folder to compare");
+                    return;
+                };
+                let mut suggestions = other_tabs
+                    .into_iter()
+                    .filter(|path| *path != left)
+                    .collect::<Vec<_>>();
+                suggestions.sort();
+                suggestions.dedup();
+                compare_dialog::open(ctx, left, suggestions, tab.show_hidden);
+            }
+            ActionToPerform::ManageSyncJobs => {
+                let source = self.tabs.get_current_path();
+                sync_dialog::open(ctx, &self.settings.sync_jobs, source.as_deref());
+            }
+            ActionToPerform::ComputeChecksums(paths) => {
+                if paths.is_empty() {
+                    toast!(Info, "Select files to compute checksums");
+                    return;
+                }
+                checksums_dialog::open(ctx, paths);
+            }
+            ActionToPerform::VerifyChecksums(manifest) => {
+                checksums_dialog::open_verify(ctx, manifest);
+            }
+            ActionToPerform::TrashApplied(report) => {
+                for tab_id in self.tabs.get_tab_ids() {
+                    trash_bin_view::task_finished(ctx, tab_id);
+                }
+                self.refresh_changed_dirs(report.affected_dirs);
+                match report.error {
+                    None => {
+                        toast!(Success, "{}", report.label);
+                    }
+                    Some(error) => {
+                        log::error!("{} failed: {error}", report.label);
+                        toast!(Error, "{} failed: {error}", report.label);
+                    }
+                }
             }
         }
     }
@@ -939,6 +1512,7 @@ impl eframe::App for App {
         self.assets.poll_results(&ctx);
         self.drain_command_queue(&ctx);
         self.process_file_system_changes(&ctx);
+        self.sort_by_new_dir_sizes(&ctx);
         let active_directory = self.tabs.get_current_path();
         self.assets
             .set_active_directory(active_directory.as_deref());
@@ -965,12 +1539,43 @@ impl eframe::App for App {
                 );
             }
         }
+        if self.display_modal.is_none()
+            && let Some(tab) = self.tabs.get_current_tab()
+        {
+            clipboard::handle_shortcuts(&ctx, tab);
+            if !crate::helper::text_edit_focused(&ctx)
+                && ctx.input(crate::helper::shift_delete_pressed)
+            {
+                delete_dialog::open(&ctx, clipboard::selected_paths(&ctx, tab), None);
+            }
+        }
+        drag_drop::handle_external_drop(&ctx, self.tabs.get_current_path());
+        if self.display_modal.is_none() && !crate::helper::text_edit_focused(&ctx) {
+            let (undo, redo) = ctx.input(|i| {
+                let z = i.key_pressed(egui::Key::Z);
+                (
+                    i.modifiers.command_only() && z,
+                    (i.modifiers.command && i.modifiers.shift && z)
+                        || i.key_with_command_pressed(egui::Key::Y),
+                )
+            });
+            if undo {
+                ActionToPerform::UndoFileOperation.schedule();
+            } else if redo {
+                ActionToPerform::RedoFileOperation.schedule();
+            }
+        }
         // F5: Force refresh the active tab (bypass all caches)
         if ctx.input(|i| i.key_pressed(egui::Key::F5)) {
             toast!(Info, "Force refreshing...");
             TabAction::ForceRefresh.schedule_active_tab();
         }
 
+        // Running copy/move jobs wait for an answer on existing destinations.
+        if self.display_modal.is_none() && file_ops::next_conflict().is_some() {
+            self.display_modal = Some(ModalWindow::Conflict);
+        }
+
         if let Some(modal) = &self.display_modal {
             match modal {
                 ModalWindow::Settings => {
@@ -978,43 +1583,87 @@ impl eframe::App for App {
                 }
                 ModalWindow::Commands => {
                     self.command_palette.ui(&ctx);
-                } // ModalWindow::NewDirectory => todo!(),
+                }
+                ModalWindow::Conflict => {
+                    self.conflict_modal(&ctx);
+                }
+                ModalWindow::BatchRename => {
+                    self.batch_rename_modal(&ctx);
+                }
+                ModalWindow::ConfirmDelete => {
+                    self.delete_modal(&ctx);
+                }
+                ModalWindow::Properties => {
+                    self.properties_modal(&ctx);
+                }
+                ModalWindow::Compress => {
+                    self.compress_modal(&ctx);
+                }
+                ModalWindow::Extract => {
+                    self.extract_modal(&ctx);
+                }
+                ModalWindow::Duplicates => {
+                    self.duplicates_modal(&ctx);
+                }
+                ModalWindow::DiskUsage => {
+                    self.disk_usage_modal(&ctx);
+                }
+                ModalWindow::Compare => {
+                    self.compare_modal(&ctx);
+                }
+                ModalWindow::Sync => {
+                    self.sync_modal(&ctx);
+                }
+                ModalWindow::Checksums => {
+                    self.checksums_modal(&ctx);
+                }
                 ModalWindow::Rename => {
                     let modal_response =
                         egui::Modal::new(egui::Id::new(ModalWindow::Rename)).show(&ctx, |ui| {
                             ui.label("Old name");
-                            let (old, mut name) = ui.data_mut(|d| {
-                                let old =
-                                    d.get_temp::<DirEntry>(egui::Id::new(ModalWindow::Rename));
-                                let new = d
-                                    .get_temp::<String>(
+                            let (old, new) = ui.data_mut(|d| {
+                                (
+                                    d.get_temp::<DirEntry>(egui::Id::new(ModalWindow::Rename)),
+                                    d.get_temp::<String>(
                                         egui::Id::new(ModalWindow::Rename).with("new"),
-                                    )
-                                    .unwrap_or_else(|| {
-                                        old.as_ref()
-                                            .map(|d| d.get_splitted_path().1.to_string())
-                                            .unwrap_or_default()
-                                    });
-                                (old, new)
+                                    ),
+                                )
                             });
                             let Some(old) = old else {
                                 return;
                             };
+                            let just_opened = new.is_none();
+                            let mut name =
+                                new.unwrap_or_else(|| old.get_splitted_path().1.to_string());
                             let mut old_file_name = old.get_splitted_path().1.to_string();
                             ui.add_enabled(false, egui::TextEdit::singleline(&mut old_file_name));
                             ui.label("New name");
-                            ui.text_edit_singleline(&mut name);
-                            let valid = !Path::new(&name).try_exists().is_ok_and(|f| f);
-                            if ui.add_enabled(valid, egui::Button::new("Rename")).clicked() {
-                                if fs::rename(
-                                    old.get_path(),
-                                    Path::new(old.get_splitted_path().0).join(&name),
-                                )
-                                .is_ok()
-                                {
+                            let response = ui.text_edit_singleline(&mut name);
+                            if just_opened {
+                                response.request_focus();
+                            }
+                            let new_path = Path::new(old.get_splitted_path().0).join(&name);
+                            let problem = batch_rename::validate_name(&name).or_else(|| {
+                                (name != old_file_name && new_path.symlink_metadata().is_ok())
+                                    .then_some(batch_rename::RenameProblem::Exists)
+                            });
+                            if let Some(problem) = problem {
+                                ui.colored_label(ui.visuals().error_fg_color, problem.to_string());
+                            }
+                            let valid = problem.is_none() && name != old_file_name;
+                            let submitted = response.lost_focus()
+                                && ui.input(|i| i.key_pressed(egui::Key::Enter));
+                            if ui.add_enabled(valid, egui::Button::new("Rename")).clicked()
+                                || (valid && submitted)
+                            {
+                                if fs::rename(old.get_path(), &new_path).is_ok() {
                                     crate::app::database::invalidate_dir(Path::new(
                                         old.get_splitted_path().0,
                                     ));
+                                    journal::record(&JournalOperation::Rename {
+                                        from: old.get_path(),
+                                        to: new_path,
+                                    });
                                     TabAction::RequestFilesRefresh.schedule_active_tab();
                                 }
                                 ui.data_mut(|w| {
@@ -1049,10 +1698,15 @@ impl eframe::App for App {
         }
 
         TOASTS.write().show(&ctx);
+        journal::notice_ui(&ctx);
+        drag_drop::drag_preview_ui(&ctx);
         self.drain_command_queue(&ctx);
         if self.watchers.is_active() {
             ctx.request_repaint_after(Duration::from_millis(200));
         }
+        if file_ops::has_active_jobs() {
+            ctx.request_repaint_after(Duration::from_millis(100));
+        }
         // Defensive: keep the UI ticking while any tab is loading
         if self.tabs.has_loading() {
             #[cfg(feature = "profiling")]
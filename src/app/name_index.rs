@@ -0,0 +1,730 @@
+//! Persistent index of every entry below the roots picked in the settings,
+//! so deep searches there don't have to walk the disk again.
+//!
+//! Each root is rescanned in the background on every start, since changes
+//! made while the app was closed aren't seen by the watcher, then kept up to
+//! date by a recursive watcher. Searches in tabs inside an indexed root are
+//! answered from sled while that rescan runs; entries the rescan didn't see
+//! are dropped once it's done. Anything acting on what's on disk, like
+//! comparing or syncing folders, walks the disk instead. Links are indexed
+//! but not followed.
+
+use std::{
+    collections::{HashMap, HashSet},
+    path::{MAIN_SEPARATOR, Path, PathBuf},
+    sync::{
+        Arc, LazyLock, Mutex,
+        atomic::{AtomicBool, Ordering},
+    },
+    time::Duration,
+};
+
+use bincode::{Decode, Encode, config};
+use notify::RecursiveMode;
+use rayon::ThreadPoolBuilder;
+
+use super::database::{SLED_DIRS, cache_key};
+use crate::{
+    data::{
+        files::{DirEntry, DirEntryMetaData, EntryType, SortKey},
+        time::TimestampSeconds,
+    },
+    helper::normalize_path,
+    watcher::{DirectoryWatcher, FileSystemChanges},
+};
+
+const ENTRIES_TREE: &[u8] = b"name_index_v1";
+const ROOTS_TREE: &[u8] = b"name_index_roots_v1";
+const BATCH_SIZE: usize = 1024;
+
+/// Every change to the index goes through this single thread, so a rescan
+/// and the watcher events that come in meanwhile are applied in order.
+static INDEX_POOL: LazyLock<rayon::ThreadPool> = LazyLock::new(|| {
+    ThreadPoolBuilder::new()
+        .num_threads(1)
+        .thread_name(|_| "lwa_fm_index".to_string())
+        .build()
+        .expect("Failed to create name index thread pool")
+});
+
+static TREES: LazyLock<Option<(sled::Tree, sled::Tree)>> = LazyLock::new(|| {
+    let open = |name| {
+        SLED_DIRS
+            .open_tree(name)
+            .map_err(|err| log::warn!("failed to open name index tree: {err}"))
+            .ok()
+    };
+    Some((open(ENTRIES_TREE)?, open(ROOTS_TREE)?))
+});
+
+/// What is known about an indexed root.
+#[derive(Debug, Clone, PartialEq, Eq, Encode, Decode)]
+pub struct RootInfo {
+    pub root: String,
+    /// Number of the scan that last completed, entries it didn't touch are
+    /// stale.
+    scan: u64,
+    pub entries: u64,
+    /// Space taken by the root's keys and values.
+    pub bytes: u64,
+    pub last_rescan: TimestampSeconds,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum IndexState {
+    /// Waiting for another root to finish scanning.
+    Queued,
+    /// Entries seen so far by the running scan.
+    Scanning(u64),
+    Ready,
+    Failed(String),
+}
+
+/// What the settings show for a root.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RootStatus {
+    pub state: IndexState,
+    /// `None` until the first scan finished.
+    pub info: Option<RootInfo>,
+}
+
+/// State of the roots being indexed this session.
+static STATES: LazyLock<Mutex<HashMap<PathBuf, IndexState>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// Roots being watched, with the flag that stops their watcher and scans.
+static WATCHED: LazyLock<Mutex<HashMap<PathBuf, Arc<AtomicBool>>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+#[derive(Debug, Encode, Decode)]
+struct Indexed {
+    path: String,
+    meta: DirEntryMetaData,
+    scan: u64,
+}
+
+/// Change in size of the index made by an update.
+#[derive(Debug, Default, Clone, Copy)]
+struct Tally {
+    added: u64,
+    removed: u64,
+    bytes_added: u64,
+    bytes_removed: u64,
+}
+
+impl Tally {
+    fn apply(self, info: &mut RootInfo) {
+        info.entries = (info.entries + self.added).saturating_sub(self.removed);
+        info.bytes = (info.bytes + self.bytes_added).saturating_sub(self.bytes_removed);
+    }
+}
+
+impl std::ops::AddAssign for Tally {
+    fn add_assign(&mut self, other: Self) {
+        self.added += other.added;
+        self.removed += other.removed;
+        self.bytes_added += other.bytes_added;
+        self.bytes_removed += other.bytes_removed;
+    }
+}
+
+/// Entries are keyed by their folder and name, so the direct children of a
+/// folder share a prefix and everything below it shares a shorter one.
+fn entry_key(path: &Path) -> Option<Vec<u8>> {
+    let mut key = cache_key(path.parent()?);
+    key.push(0);
+    key.extend(cache_key(Path::new(path.file_name()?)));
+    Some(key)
+}
+
+fn children_prefix(dir: &Path) -> Vec<u8> {
+    let mut key = cache_key(dir);
+    key.push(0);
+    key
+}
+
+/// Keys of the entries anywhere below `dir`.
+fn keys_below(tree: &sled::Tree, dir: &Path) -> impl Iterator<Item = (sled::IVec, sled::IVec)> {
+    let prefix = cache_key(dir);
+    let len = prefix.len();
+    // A root such as `/` ends with the separator already.
+    let is_root = prefix.last() == Some(&(MAIN_SEPARATOR as u8));
+    tree.scan_prefix(prefix)
+        .filter_map(Result::ok)
+        .filter(move |(key, _)| {
+            is_root
+                || key
+                    .get(len)
+                    .is_some_and(|&b| b == 0 || b == MAIN_SEPARATOR as u8)
+        })
+}
+
+fn decode(value: &[u8]) -> Option<Indexed> {
+    bincode::decode_from_slice(value, config::standard())
+        .ok()
+        .map(|(indexed, _)| indexed)
+}
+
+fn insert(tree: &sled::Tree, path: &Path, meta: DirEntryMetaData, scan: u64) -> Tally {
+    let Some(key) = entry_key(path) else {
+        return Tally::default();
+    };
+    let indexed = Indexed {
+        path: path.to_string_lossy().into_owned(),
+        meta,
+        scan,
+    };
+    let Ok(value) = bincode::encode_to_vec(&indexed, config::standard()) else {
+        return Tally::default();
+    };
+    let (key_len, value_len) = (key.len() as u64, value.len() as u64);
+    match tree.insert(key, value) {
+        Ok(Some(old)) => Tally {
+            bytes_added: value_len,
+            bytes_removed: old.len() as u64,
+            ..Tally::default()
+        },
+        Ok(None) => Tally {
+            added: 1,
+            bytes_added: key_len + value_len,
+            ..Tally::default()
+        },
+        Err(err) => {
+            log::warn!("failed to index {}: {err}", path.display());
+            Tally::default()
+        }
+    }
+}
+
+/// Removes `path` and everything below it.
+fn remove(tree: &sled::Tree, path: &Path) -> Tally {
+    let mut tally = Tally::default();
+    let keys = entry_key(path)
+        .into_iter()
+        .map(sled::IVec::from)
+        .chain(keys_below(tree, path).map(|(key, _)| key))
+        .collect::<Vec<_>>();
+    for key in keys {
+        if let Ok(Some(old)) = tree.remove(&key) {
+            tally += Tally {
+                removed: 1,
+                bytes_removed: (key.len() + old.len()) as u64,
+                ..Tally::default()
+            };
+        }
+    }
+    tally
+}
+
+/// Indexes everything below `dir`, reporting the entries seen so far.
+fn walk(
+    tree: &sled::Tree,
+    dir: &Path,
+    scan: u64,
+    cancel: &AtomicBool,
+    progress: &dyn Fn(u64),
+) -> Tally {
+    let mut tally = Tally::default();
+    let mut seen = 0;
+    for entry in walkdir::WalkDir::new(dir)
+        .min_depth(1)
+        .into_iter()
+        .filter_map(Result::ok)
+    {
+        if cancel.load(Ordering::Relaxed) {
+            break;
+        }
+        let Ok(meta) = entry.metadata() else {
+            continue;
+        };
+        tally += insert(tree, entry.path(), meta.into(), scan);
+        seen += 1;
+        if seen % BATCH_SIZE as u64 == 0 {
+            progress(seen);
+        }
+    }
+    tally
+}
+
+/// Brings the direct children of `dir` in line with the disk, indexing the
+/// folders that appeared and dropping the ones that went away.
+fn refresh_dir(tree: &sled::Tree, dir: &Path, scan: u64) -> Tally {
+    let Ok(read) = std::fs::read_dir(dir) else {
+        // Gone, the event for its parent removes it.
+        return Tally::default();
+    };
+    let current = read
+        .filter_map(Result::ok)
+        .map(|entry| entry.path())
+        .collect::<HashSet<_>>();
+    let known = tree
+        .scan_prefix(children_prefix(dir))
+        .values()
+        .filter_map(Result::ok)
+        .filter_map(|value| decode(&value))
+        .map(|indexed| PathBuf::from(indexed.path))
+        .collect::<HashSet<_>>();
+    let mut tally = Tally::default();
+    for gone in known.difference(&current) {
+        tally += remove(tree, gone);
+    }
+    for path in &current {
+        let Ok(meta) = std::fs::symlink_metadata(path) else {
+            continue;
+        };
+        let meta = DirEntryMetaData::from(meta);
+        tally += insert(tree, path, meta, scan);
+        if meta.entry_type == EntryType::Directory && !known.contains(path) {
+            tally += walk(tree, path, scan, &AtomicBool::new(false), &|_| {});
+        }
+    }
+    tally
+}
+
+/// Applies watcher events to the index of `root`.
+fn apply_changes(trees: &(sled::Tree, sled::Tree), root: &Path, changes: &FileSystemChanges) {
+    let (entries, roots) = trees;
+    let Some(mut info) = stored_info(roots, root) else {
+        return;
+    };
+    let mut tally = Tally::default();
+    for dir in changes
+        .structural_dirs
+        .iter()
+        .filter(|dir| dir.starts_with(root))
+    {
+        tally += refresh_dir(entries, dir, info.scan);
+    }
+    for path in changes
+        .modified_files
+        .iter()
+        .filter(|path| path.starts_with(root) && path.as_path() != root)
+    {
+        if let Ok(meta) = std::fs::symlink_metadata(path) {
+            tally += insert(entries, path, meta.into(), info.scan);
+        }
+    }
+    tally.apply(&mut info);
+    store_info(roots, root, &info);
+}
+
+fn stored_info(roots: &sled::Tree, root: &Path) -> Option<RootInfo> {
+    let data = roots.get(cache_key(root)).ok()??;
+    bincode::decode_from_slice(&data, config::standard())
+        .ok()
+        .map(|(info, _)| info)
+}
+
+fn store_info(roots: &sled::Tree, root: &Path, info: &RootInfo) {
+    if let Ok(data) = bincode::encode_to_vec(info, config::standard())
+        && let Err(err) = roots.insert(cache_key(root), data)
+    {
+        log::warn!("failed to store index info of {}: {err}", root.display());
+    }
+}
+
+/// Walks all of `root`, then drops what the walk didn't see and counts what
+/// is left.
+fn rescan(
+    trees: &(sled::Tree, sled::Tree),
+    root: &Path,
+    cancel: &AtomicBool,
+    progress: &dyn Fn(u64),
+) -> Option<RootInfo> {
+    let (entries, roots) = trees;
+    let scan = stored_info(roots, root).map_or(1, |info| info.scan + 1);
+    walk(entries, root, scan, cancel, progress);
+    if cancel.load(Ordering::Relaxed) {
+        return None;
+    }
+    let mut info = RootInfo {
+        root: root.to_string_lossy().into_owned(),
+        scan,
+        entries: 0,
+        bytes: 0,
+        last_rescan: std::time::SystemTime::now().into(),
+    };
+    let mut stale = Vec::new();
+    for (key, value) in keys_below(entries, root) {
+        if decode(&value).is_some_and(|indexed| indexed.scan == scan) {
+            info.entries += 1;
+            info.bytes += (key.len() + value.len()) as u64;
+        } else {
+            stale.push(key);
+        }
+    }
+    for key in stale {
+        let _ = entries.remove(key);
+    }
+    store_info(roots, root, &info);
+    Some(info)
+}
+
+/// Roots dropped from the settings keep no state, even when a scan of
+/// theirs reports in late.
+fn set_state(root: &Path, state: IndexState) {
+    if let Ok(mut states) = STATES.lock()
+        && is_watched(root)
+    {
+        states.insert(root.to_path_buf(), state);
+    }
+}
+
+fn is_watched(root: &Path) -> bool {
+    WATCHED
+        .lock()
+        .is_ok_and(|watched| watched.contains_key(root))
+}
+
+fn queue_rescan(root: PathBuf) {
+    set_state(&root, IndexState::Queued);
+    INDEX_POOL.spawn(move || {
+        let Some(trees) = TREES.as_ref() else {
+            set_state(
+                &root,
+                IndexState::Failed("Database unavailable".to_string()),
+            );
+            return;
+        };
+        // Dropped from the settings while waiting.
+        if !is_watched(&root) {
+            return;
+        }
+        set_state(&root, IndexState::Scanning(0));
+        let stop = WATCHED
+            .lock()
+            .ok()
+            .and_then(|watched| watched.get(&root).map(Arc::clone))
+            .unwrap_or_default();
+        let progress = |seen| set_state(&root, IndexState::Scanning(seen));
+        if rescan(trees, &root, &stop, &progress).is_some() {
+            set_state(&root, IndexState::Ready);
+        }
+    });
+}
+
+fn watch(root: PathBuf, stop: Arc<AtomicBool>) {
+    std::thread::spawn(move || {
+        let mut watcher = match DirectoryWatcher::new().and_then(|mut watcher| {
+            watcher
+                .watch_directory(&root, RecursiveMode::Recursive)
+                .map(|()| watcher)
+        }) {
+            Ok(watcher) => watcher,
+            Err(err) => {
+                log::warn!("name index watcher failed for {}: {err:#}", root.display());
+                set_state(&root, IndexState::Failed(format!("Not watched: {err:#}")));
+                return;
+            }
+        };
+        while !stop.load(Ordering::Relaxed) {
+            if watcher.check_rescan() {
+                queue_rescan(root.clone());
+            }
+            let Some(mut changes) = watcher.recv_event_timeout(Duration::from_millis(500)) else {
+                continue;
+            };
+            while let Some(more) = watcher.try_recv_event() {
+                changes.modified_files.extend(more.modified_files);
+                changes.structural_dirs.extend(more.structural_dirs);
+            }
+            let root = root.clone();
+            INDEX_POOL.spawn(move || {
+                if let Some(trees) = TREES.as_ref() {
+                    apply_changes(trees, &root, &changes);
+                }
+            });
+        }
+        watcher.stop_watching();
+    });
+}
+
+/// Roots that aren't inside another one, the others are covered already.
+fn outermost(roots: &[PathBuf]) -> Vec<PathBuf> {
+    let roots = roots
+        .iter()
+        .map(|root| normalize_path(root))
+        .collect::<Vec<_>>();
+    let mut outermost = roots
+        .iter()
+        .filter(|root| {
+            !roots
+                .iter()
+                .any(|other| other != *root && root.starts_with(other))
+        })
+        .cloned()
+        .collect::<Vec<_>>();
+    outermost.sort();
+    outermost.dedup();
+    outermost
+}
+
+/// Starts indexing and watching the roots in `roots` that aren't yet, and
+/// stops and drops the index of the ones no longer listed.
+pub fn sync_roots(roots: &[PathBuf]) {
+    let Some((entries, stored)) = TREES.as_ref() else {
+        return;
+    };
+    let roots = outermost(roots);
+    let Ok(mut watched) = WATCHED.lock() else {
+        return;
+    };
+    let mut unwatched = Vec::new();
+    watched.retain(|root, stop| {
+        let keep = roots.contains(root);
+        if !keep {
+            stop.store(true, Ordering::Relaxed);
+            unwatched.push(root.clone());
+        }
+        keep
+    });
+    let mut added = Vec::new();
+    for root in &roots {
+        if watched.contains_key(root) {
+            continue;
+        }
+        let stop = Arc::new(AtomicBool::new(false));
+        watched.insert(root.clone(), Arc::clone(&stop));
+        watch(root.clone(), stop);
+        added.push(root.clone());
+    }
+    drop(watched);
+    if let Ok(mut states) = STATES.lock() {
+        for root in &unwatched {
+            states.remove(root);
+        }
+    }
+
+    let dropped = stored
+        .iter()
+        .values()
+        .filter_map(Result::ok)
+        .filter_map(|data| {
+            bincode::decode_from_slice::<RootInfo, _>(&data, config::standard()).ok()
+        })
+        .map(|(info, _)| PathBuf::from(info.root))
+        .filter(|root| !roots.contains(root))
+        .collect::<Vec<_>>();
+    for root in dropped {
+        if let Ok(mut states) = STATES.lock() {
+            states.remove(&root);
+        }
+        let (entries, stored) = (entries.clone(), stored.clone());
+        INDEX_POOL.spawn(move || {
+            remove(&entries, &root);
+            let _ = stored.remove(cache_key(&root));
+        });
+    }
+
+    for root in added {
+        queue_rescan(root);
+    }
+}
+
+/// The watched root holding `path`, which is `path` itself unless it's
+/// covered by an outer root.
+fn covering_root(path: &Path) -> PathBuf {
+    let path = normalize_path(path);
+    WATCHED
+        .lock()
+        .ok()
+        .and_then(|watched| watched.keys().find(|root| path.starts_with(root)).cloned())
+        .unwrap_or(path)
+}
+
+/// Scans `root` again from scratch, in the background.
+pub fn rebuild(root: &Path) {
+    queue_rescan(covering_root(root));
+}
+
+pub fn status(root: &Path) -> RootStatus {
+    let root = covering_root(root);
+    let state = STATES
+        .lock()
+        .ok()
+        .and_then(|states| states.get(&root).cloned())
+        .unwrap_or(IndexState::Queued);
+    let info = TREES
+        .as_ref()
+        .and_then(|(_, roots)| stored_info(roots, &root));
+    RootStatus { state, info }
+}
+
+/// Whether a scan is waiting or running, so the settings keep repainting.
+pub fn is_busy() -> bool {
+    STATES.lock().is_ok_and(|states| {
+        states
+            .values()
+            .any(|state| matches!(state, IndexState::Queued | IndexState::Scanning(_)))
+    })
+}
+
+fn is_hidden(name: &str) -> bool {
+    name.starts_with('.') || name.starts_with('$')
+}
+
+fn lookup_in(
+    entries: &sled::Tree,
+    dir: &Path,
+    max_depth: usize,
+    show_hidden: bool,
+    cancel: &AtomicBool,
+) -> Vec<DirEntry> {
+    let mut found = Vec::new();
+    for (index, (_, value)) in keys_below(entries, dir).enumerate() {
+        if index % BATCH_SIZE == 0 && cancel.load(Ordering::Relaxed) {
+            return Vec::new();
+        }
+        let Some(indexed) = decode(&value) else {
+            continue;
+        };
+        let path = Path::new(&indexed.path);
+        let Ok(relative) = path.strip_prefix(dir) else {
+            continue;
+        };
+        let mut depth = 0;
+        let mut hidden = false;
+        for component in relative.components() {
+            depth += 1;
+            hidden |= is_hidden(&component.as_os_str().to_string_lossy());
+        }
+        if depth == 0 || depth > max_depth || (hidden && !show_hidden) {
+            continue;
+        }
+        let (Some(parent), Some(file_name)) = (path.parent(), path.file_name()) else {
+            continue;
+        };
+        let file_name = file_name.to_string_lossy().into_owned();
+        found.push(DirEntry {
+            sort_key: SortKey::new_path(&file_name, indexed.meta.entry_type == EntryType::File),
+            meta: indexed.meta,
+            dir: Arc::from(parent.to_string_lossy().as_ref()),
+            file_name,
+        });
+    }
+    found
+}
+
+/// The entries below `dir` at most `max_depth` levels down, read from the
+/// index. While the startup rescan runs these may miss changes made while
+/// the app was closed. `None` when `dir` isn't inside an indexed root, so
+/// the caller walks the disk instead.
+pub fn lookup(
+    dir: &Path,
+    max_depth: usize,
+    show_hidden: bool,
+    cancel: &AtomicBool,
+) -> Option<Vec<DirEntry>> {
+    let root = WATCHED
+        .lock()
+        .ok()?
+        .keys()
+        .find(|root| dir.starts_with(root))?
+        .clone();
+    let (entries, roots) = TREES.as_ref()?;
+    stored_info(roots, &root)?;
+    Some(lookup_in(entries, dir, max_depth, show_hidden, cancel))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+    use crate::app::test_support::unique_test_dir;
+
+    fn temporary_trees() -> (sled::Tree, sled::Tree) {
+        let db = sled::Config::new()
+            .temporary(true)
+            .open()
+            .expect("open temporary db");
+        (
+            db.open_tree(ENTRIES_TREE).expect("entries tree"),
+            db.open_tree(ROOTS_TREE).expect("roots tree"),
+        )
+    }
+
+    fn names(entries: &[DirEntry]) -> Vec<String> {
+        let mut names = entries
+            .iter()
+            .map(|entry| entry.file_name.clone())
+            .collect::<Vec<_>>();
+        names.sort();
+        names
+    }
+
+    #[test]
+    fn indexes_a_tree_and_follows_changes() {
+        let root = unique_test_dir("name_index");
+        fs::create_dir_all(root.join("a/b")).expect("create dirs");
+        fs::create_dir_all(root.join(".git")).expect("create dirs");
+        fs::write(root.join("top.txt"), "x").expect("write");
+        fs::write(root.join("a/b/deep.txt"), "xy").expect("write");
+        fs::write(root.join(".git/config"), "").expect("write");
+        let root = normalize_path(&root);
+        let trees = temporary_trees();
+        let cancel = AtomicBool::new(false);
+
+        let info = rescan(&trees, &root, &cancel, &|_| {}).expect("not cancelled");
+        assert_eq!(info.entries, 6);
+        assert!(info.bytes > 0);
+        let all = lookup_in(&trees.0, &root, usize::MAX, true, &cancel);
+        assert_eq!(
+            names(&all),
+            [".git", "a", "b", "config", "deep.txt", "top.txt"]
+        );
+        let shallow = lookup_in(&trees.0, &root, 2, false, &cancel);
+        assert_eq!(names(&shallow), ["a", "b", "top.txt"]);
+        let below = lookup_in(&trees.0, &root.join("a"), usize::MAX, false, &cancel);
+        assert_eq!(names(&below), ["b", "deep.txt"]);
+        let deep = all
+            .iter()
+            .find(|entry| entry.file_name == "deep.txt")
+            .expect("indexed");
+        assert_eq!(deep.get_path(), root.join("a/b/deep.txt"));
+        assert_eq!(deep.meta.size, 2);
+
+        fs::rename(root.join("a/b"), root.join("a/c")).expect("rename");
+        fs::write(root.join("top.txt"), "longer").expect("write");
+        apply_changes(
+            &trees,
+            &root,
+            &FileSystemChanges {
+                modified_files: [root.join("top.txt")].into(),
+                structural_dirs: [root.join("a")].into(),
+            },
+        );
+        let all = lookup_in(&trees.0, &root, usize::MAX, false, &cancel);
+        assert_eq!(names(&all), ["a", "c", "deep.txt", "top.txt"]);
+        let top = all
+            .iter()
+            .find(|entry| entry.file_name == "top.txt")
+            .expect("indexed");
+        assert_eq!(top.meta.size, 6);
+        assert_eq!(
+            stored_info(&trees.1, &root).map(|info| info.entries),
+            Some(6)
+        );
+
+        // Changes the watcher missed are dropped by the next rescan.
+        fs::remove_file(root.join("a/c/deep.txt")).expect("remove");
+        let info = rescan(&trees, &root, &cancel, &|_| {}).expect("not cancelled");
+        assert_eq!(info.entries, 5);
+        assert_eq!(info.scan, 2);
+
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn nested_roots_are_covered_by_their_parent() {
+        let roots = [
+            PathBuf::from("/index/home/docs"),
+            PathBuf::from("/index/home"),
+            PathBuf::from("/index/other"),
+        ];
+        assert_eq!(
+            outermost(&roots),
+            [PathBuf::from("/index/home"), PathBuf::from("/index/other")]
+        );
+    }
+}
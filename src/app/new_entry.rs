@@ -0,0 +1,131 @@
+//! Creating new folders and files, either empty or copied from a template in
+//! the user's Templates directory.
+
+use std::{
+    fs::{self, File},
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result};
+use egui::Ui;
+
+use crate::app::commands::ActionToPerform;
+use crate::app::file_ops::{copy_path, unique_name};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NewEntryKind {
+    Directory,
+    File,
+    /// A copy of the given template.
+    Template(PathBuf),
+}
+
+impl NewEntryKind {
+    fn default_name(&self) -> String {
+        match self {
+            Self::Directory => "New Folder".to_string(),
+            Self::File => "New File".to_string(),
+            Self::Template(template) => template
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_default(),
+        }
+    }
+
+    pub fn label(&self) -> String {
+        match self {
+            Self::Directory => "New Folder".to_string(),
+            Self::File => "New File".to_string(),
+            Self::Template(_) => format!("New {}", self.default_name()),
+        }
+    }
+}
+
+/// Entries of the user's Templates directory, sorted by name. Empty when the
+/// platform has no such directory.
+pub fn templates() -> Vec<PathBuf> {
+    let Some(dir) =
+        directories::UserDirs::new().and_then(|dirs| dirs.template_dir().map(Path::to_path_buf))
+    else {
+        return Vec::new();
+    };
+    let Ok(read_dir) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut templates: Vec<PathBuf> = read_dir
+        .filter_map(Result::ok)
+        .filter(|entry| !entry.file_name().to_string_lossy().starts_with('.'))
+        .map(|entry| entry.path())
+        .collect();
+    templates.sort();
+    templates
+}
+
+/// Creates a new entry in `dir` under a free name and returns its path.
+pub fn create(dir: &Path, kind: &NewEntryKind) -> Result<PathBuf> {
+    let mut path = dir.join(kind.default_name());
+    if path.symlink_metadata().is_ok() {
+        path = unique_name(&path);
+    }
+    match kind {
+        NewEntryKind::Directory => {
+            fs::create_dir(&path)
+                .with_context(|| format!("Failed to create {}", path.display()))?;
+        }
+        NewEntryKind::File => File::create_new(&path)
+            .map(drop)
+            .with_context(|| format!("Failed to create {}", path.display()))?,
+        NewEntryKind::Template(template) => copy_path(template, &path)?,
+    }
+    Ok(path)
+}
+
+/// Buttons for creating entries in the current directory, for context menus.
+pub fn menu_ui(ui: &mut Ui) {
+    for kind in [NewEntryKind::Directory, NewEntryKind::File] {
+        if ui.button(kind.label()).clicked() {
+            ActionToPerform::CreateEntry(kind).schedule();
+            ui.close();
+        }
+    }
+    let templates = templates();
+    if templates.is_empty() {
+        return;
+    }
+    ui.menu_button("New from template", |ui| {
+        for template in templates {
+            let kind = NewEntryKind::Template(template);
+            if ui.button(kind.default_name()).clicked() {
+                ActionToPerform::CreateEntry(kind).schedule();
+                ui.close();
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{NewEntryKind, create};
+    use crate::app::test_support::unique_test_dir;
+
+    #[test]
+    fn new_entries_get_free_names() {
+        let dir = unique_test_dir("new_entry");
+
+        let first = create(&dir, &NewEntryKind::Directory).expect("created");
+        let second = create(&dir, &NewEntryKind::Directory).expect("created");
+        assert_eq!(first, dir.join("New Folder"));
+        assert_eq!(second, dir.join("New Folder (2)"));
+        assert!(second.is_dir());
+
+        let template = dir.join("Report.odt");
+        std::fs::write(&template, b"template").expect("write template");
+        let copy = create(&dir, &NewEntryKind::Template(template)).expect("created");
+        assert_eq!(copy, dir.join("Report (2).odt"));
+        assert_eq!(std::fs::read(copy).expect("read copy"), b"template");
+
+        let file = create(&dir, &NewEntryKind::File).expect("created");
+        assert!(file.is_file());
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
@@ -0,0 +1,417 @@
+//! Details of a single entry for the Properties dialog, and editing of its
+//! permissions, ownership and timestamps.
+//!
+//! Unix platforms expose the full mode bits, ownership, inode and link count.
+//! Elsewhere the mode is derived from the read-only flag and the unix-only
+//! details are left empty.
+
+use std::{
+    fs::{self, File, FileTimes},
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use anyhow::{Context, Result};
+
+use crate::app::file_ops::size_on_disk;
+use crate::data::time::TimestampSeconds;
+
+/// A user or group, by id and name when it could be looked up.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Account {
+    pub id: u32,
+    pub name: Option<String>,
+}
+
+impl std::fmt::Display for Account {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.name {
+            Some(name) => write!(f, "{name} ({})", self.id),
+            None => write!(f, "{}", self.id),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Properties {
+    pub path: PathBuf,
+    pub is_dir: bool,
+    /// Where the entry points to, if it is a symbolic link.
+    pub link_target: Option<PathBuf>,
+    pub size: u64,
+    pub size_on_disk: u64,
+    pub modified: Option<TimestampSeconds>,
+    pub accessed: Option<TimestampSeconds>,
+    pub created: Option<TimestampSeconds>,
+    /// Last change of the metadata (unix `ctime`).
+    pub changed: Option<TimestampSeconds>,
+    pub inode: Option<u64>,
+    pub hard_links: Option<u64>,
+    pub owner: Option<Account>,
+    pub group: Option<Account>,
+    /// Permission bits, `0o777` at most.
+    pub mode: u32,
+}
+
+impl Properties {
+    pub fn kind(&self) -> &'static str {
+        if self.link_target.is_some() {
+            "Symbolic link"
+        } else if self.is_dir {
+            "Folder"
+        } else {
+            "File"
+        }
+    }
+}
+
+/// Reads the properties of `path` without following a final symbolic link.
+pub fn read(path: &Path) -> Result<Properties> {
+    let meta = fs::symlink_metadata(path)
+        .with_context(|| format!("Failed to read metadata of {}", path.display()))?;
+    let timestamp = |time: std::io::Result<SystemTime>| time.ok().map(TimestampSeconds::from);
+    #[cfg_attr(not(unix), allow(unused_mut))]
+    let mut properties = Properties {
+        path: path.to_path_buf(),
+        is_dir: meta.is_dir(),
+        link_target: meta
+            .file_type()
+            .is_symlink()
+            .then(|| fs::read_link(path).unwrap_or_default()),
+        size: meta.len(),
+        size_on_disk: size_on_disk(&meta),
+        modified: timestamp(meta.modified()),
+        accessed: timestamp(meta.accessed()),
+        created: timestamp(meta.created()),
+        changed: None,
+        inode: None,
+        hard_links: None,
+        owner: None,
+        group: None,
+        mode: if meta.permissions().readonly() {
+            0o444
+        } else {
+            0o666
+        },
+    };
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+        properties.changed = u64::try_from(meta.ctime()).ok().map(|seconds| {
+            (std::time::UNIX_EPOCH + std::time::Duration::from_secs(seconds)).into()
+        });
+        properties.inode = Some(meta.ino());
+        properties.hard_links = Some(meta.nlink());
+        properties.owner = Some(Account {
+            id: meta.uid(),
+            name: accounts::user_name(meta.uid()),
+        });
+        properties.group = Some(Account {
+            id: meta.gid(),
+            name: accounts::group_name(meta.gid()),
+        });
+        properties.mode = meta.mode() & 0o777;
+    }
+    Ok(properties)
+}
+
+/// `rwxr-xr-x` style rendering of the permission bits.
+pub fn format_mode(mode: u32) -> String {
+    (0..9)
+        .map(|bit| {
+            if mode & (0o400 >> bit) == 0 {
+                '-'
+            } else {
+                ['r', 'w', 'x'][bit % 3]
+            }
+        })
+        .collect()
+}
+
+/// Which entries get the execute bits of a mode, see [`set_mode`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Execute {
+    /// Files and folders alike.
+    #[default]
+    Everywhere,
+    /// Folders, and files that were executable already, like the capital `X`
+    /// of `chmod`.
+    FoldersAndExecutables,
+}
+
+/// Applies `mode` to `path`, and to everything below it when `recursive` is
+/// set. Folders are changed after their contents, so a mode without `x`
+/// doesn't lock the walk out halfway. The setuid, setgid and sticky bits are
+/// kept and symbolic links inside the tree are left alone. Without unix
+/// permissions only the write bits matter, they clear the read-only flag.
+pub fn set_mode(
+    path: &Path,
+    mode: u32,
+    recursive: bool,
+    #[cfg_attr(not(unix), allow(unused_variables))] execute: Execute,
+) -> Result<()> {
+    let apply = |path: &Path| {
+        let metadata = fs::metadata(path)
+            .with_context(|| format!("Failed to read metadata of {}", path.display()))?;
+        let mut permissions = metadata.permissions();
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let current = permissions.mode();
+            let executable =
+                execute == Execute::Everywhere || metadata.is_dir() || current & 0o111 != 0;
+            let bits = if executable {
+                mode & 0o777
+            } else {
+                mode & 0o666
+            };
+            permissions.set_mode((current & 0o7000) | bits);
+        }
+        #[cfg(not(unix))]
+        permissions.set_readonly((mode & 0o222) == 0);
+        fs::set_permissions(path, permissions)
+            .with_context(|| format!("Failed to change permissions of {}", path.display()))
+    };
+    if recursive && path.is_dir() {
+        let entries = walkdir::WalkDir::new(path)
+            .min_depth(1)
+            .follow_links(false)
+            .contents_first(true);
+        for entry in entries {
+            let entry = entry.with_context(|| format!("Failed to read {}", path.display()))?;
+            if !entry.path_is_symlink() {
+                apply(entry.path())?;
+            }
+        }
+    }
+    apply(path)
+}
+
+/// Changes the owner and/or group of `path`, given as names or numeric ids,
+/// and of everything below it when `recursive` is set.
+#[cfg(unix)]
+pub fn set_owner(
+    path: &Path,
+    owner: Option<&str>,
+    group: Option<&str>,
+    recursive: bool,
+) -> Result<()> {
+    let uid = owner
+        .map(|owner| accounts::user_id(owner).with_context(|| format!("Unknown user {owner}")))
+        .transpose()?;
+    let gid = group
+        .map(|group| accounts::group_id(group).with_context(|| format!("Unknown group {group}")))
+        .transpose()?;
+    let apply = |path: &Path| {
+        std::os::unix::fs::lchown(path, uid, gid)
+            .with_context(|| format!("Failed to change the owner of {}", path.display()))
+    };
+    apply(path)?;
+    if recursive && path.is_dir() {
+        for entry in walkdir::WalkDir::new(path).min_depth(1).follow_links(false) {
+            let entry = entry.with_context(|| format!("Failed to read {}", path.display()))?;
+            apply(entry.path())?;
+        }
+    }
+    Ok(())
+}
+
+/// Sets the modification and/or access time of `path`.
+pub fn set_times(
+    path: &Path,
+    modified: Option<SystemTime>,
+    accessed: Option<SystemTime>,
+) -> Result<()> {
+    let mut times = FileTimes::new();
+    if let Some(modified) = modified {
+        times = times.set_modified(modified);
+    }
+    if let Some(accessed) = accessed {
+        times = times.set_accessed(accessed);
+    }
+    open_for_times(path)
+        .and_then(|file| file.set_times(times))
+        .with_context(|| format!("Failed to change the timestamps of {}", path.display()))
+}
+
+fn open_for_times(path: &Path) -> std::io::Result<File> {
+    #[cfg(windows)]
+    {
+        use std::os::windows::fs::OpenOptionsExt;
+        // Needed to open directories.
+        const FILE_FLAG_BACKUP_SEMANTICS: u32 = 0x0200_0000;
+        fs::OpenOptions::new()
+            .write(true)
+            .custom_flags(FILE_FLAG_BACKUP_SEMANTICS)
+            .open(path)
+    }
+    #[cfg(not(windows))]
+    File::open(path)
+}
+
+/// User and group names from `/etc/passwd` and `/etc/group`.
+#[cfg(unix)]
+mod accounts {
+    const PASSWD: &str = "/etc/passwd";
+    const GROUP: &str = "/etc/group";
+
+    /// Finds the name for `id` in a passwd/group style database, where every
+    /// line starts with `name:password:id:`.
+    pub fn name_in(database: &str, id: u32) -> Option<String> {
+        database.lines().find_map(|line| {
+            let mut fields = line.split(':');
+            let name = fields.next()?;
+            (fields.nth(1)?.parse::<u32>().ok()? == id).then(|| name.to_string())
+        })
+    }
+
+    /// The id of `name` in a passwd/group style database. Numeric names are
+    /// taken as ids.
+    pub fn id_in(database: &str, name: &str) -> Option<u32> {
+        let name = name.trim();
+        name.parse().ok().or_else(|| {
+            database.lines().find_map(|line| {
+                let mut fields = line.split(':');
+                if fields.next()? != name {
+                    return None;
+                }
+                fields.nth(1)?.parse().ok()
+            })
+        })
+    }
+
+    fn read(path: &str) -> String {
+        std::fs::read_to_string(path).unwrap_or_default()
+    }
+
+    pub fn user_name(id: u32) -> Option<String> {
+        name_in(&read(PASSWD), id)
+    }
+
+    pub fn group_name(id: u32) -> Option<String> {
+        name_in(&read(GROUP), id)
+    }
+
+    pub fn user_id(name: &str) -> Option<u32> {
+        id_in(&read(PASSWD), name)
+    }
+
+    pub fn group_id(name: &str) -> Option<u32> {
+        id_in(&read(GROUP), name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, time::Duration};
+
+    use super::{Execute, format_mode, read, set_mode, set_times};
+    use crate::app::test_support::unique_test_dir;
+    use crate::data::time::TimestampSeconds;
+
+    #[test]
+    fn mode_is_rendered_like_ls() {
+        assert_eq!(format_mode(0o755), "rwxr-xr-x");
+        assert_eq!(format_mode(0o640), "rw-r-----");
+        assert_eq!(format_mode(0), "---------");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn account_names_and_ids_are_looked_up() {
+        use super::accounts::{id_in, name_in};
+        let database = "root:x:0:0:root:/root:/bin/sh\nalice:x:1000:1000::/home/alice:/bin/sh\n";
+        assert_eq!(name_in(database, 1000).as_deref(), Some("alice"));
+        assert_eq!(name_in(database, 7), None);
+        assert_eq!(id_in(database, "root"), Some(0));
+        assert_eq!(id_in(database, "42"), Some(42));
+        assert_eq!(id_in(database, "bob"), None);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn mode_is_applied_recursively() {
+        let dir = unique_test_dir("properties_mode");
+        fs::create_dir(dir.join("nested")).expect("create dir");
+        fs::write(dir.join("nested/file.txt"), b"x").expect("write file");
+
+        set_mode(&dir.join("nested"), 0o750, false, Execute::Everywhere).expect("chmod");
+        assert_eq!(read(&dir.join("nested")).expect("read").mode, 0o750);
+        assert_ne!(
+            read(&dir.join("nested/file.txt")).expect("read").mode,
+            0o750
+        );
+
+        set_mode(&dir.join("nested"), 0o700, true, Execute::Everywhere).expect("chmod -R");
+        assert_eq!(
+            read(&dir.join("nested/file.txt")).expect("read").mode,
+            0o700
+        );
+
+        // Without `x` on folders the contents still have to be reached.
+        fs::create_dir(dir.join("nested/deeper")).expect("create dir");
+        fs::write(dir.join("nested/deeper/file.txt"), b"x").expect("write file");
+        set_mode(&dir.join("nested"), 0o644, true, Execute::Everywhere).expect("chmod -R");
+        set_mode(&dir.join("nested"), 0o755, false, Execute::Everywhere).expect("chmod");
+        set_mode(
+            &dir.join("nested/deeper"),
+            0o755,
+            false,
+            Execute::Everywhere,
+        )
+        .expect("chmod");
+        assert_eq!(
+            read(&dir.join("nested/deeper/file.txt"))
+                .expect("read")
+                .mode,
+            0o644
+        );
+
+        // Files only keep execute bits when they had some.
+        let script = dir.join("nested/script.sh");
+        fs::write(&script, b"x").expect("write file");
+        set_mode(&script, 0o700, false, Execute::Everywhere).expect("chmod");
+        set_mode(
+            &dir.join("nested"),
+            0o755,
+            true,
+            Execute::FoldersAndExecutables,
+        )
+        .expect("chmod -R");
+        assert_eq!(read(&dir.join("nested")).expect("read").mode, 0o755);
+        assert_eq!(read(&dir.join("nested/deeper")).expect("read").mode, 0o755);
+        assert_eq!(read(&script).expect("read").mode, 0o755);
+        assert_eq!(
+            read(&dir.join("nested/deeper/file.txt"))
+                .expect("read")
+                .mode,
+            0o644
+        );
+
+        // Special bits survive a change of the permission bits.
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let file = dir.join("nested/file.txt");
+            fs::set_permissions(&file, fs::Permissions::from_mode(0o4755)).expect("setuid");
+            set_mode(&file, 0o700, false, Execute::Everywhere).expect("chmod");
+            let mode = fs::metadata(&file).expect("metadata").permissions().mode();
+            assert_eq!(mode & 0o7777, 0o4700);
+        }
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn timestamps_can_be_changed() {
+        let dir = unique_test_dir("properties_times");
+        let file = dir.join("file.txt");
+        fs::write(&file, b"x").expect("write file");
+        let time = std::time::UNIX_EPOCH + Duration::from_secs(951_827_696);
+
+        set_times(&file, Some(time), Some(time)).expect("set times");
+        let properties = read(&file).expect("read");
+        assert_eq!(properties.modified, Some(TimestampSeconds::from(time)));
+        assert_eq!(properties.accessed, Some(TimestampSeconds::from(time)));
+        assert_eq!(properties.kind(), "File");
+        let _ = fs::remove_dir_all(dir);
+    }
+}
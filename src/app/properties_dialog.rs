@@ -0,0 +1,418 @@
+use std::{
+    path::Path,
+    sync::{
+        Arc, OnceLock,
+        atomic::{AtomicBool, Ordering},
+    },
+    time::SystemTime,
+};
+
+use egui::{Context, Id, Ui};
+
+use super::App;
+use super::commands::{ActionToPerform, ModalWindow};
+use super::file_ops::{self, TreeSummary};
+use super::properties::{self, Execute, Properties};
+use crate::data::time::TimestampSeconds;
+use crate::helper::format_bytes_simple;
+use crate::toast;
+
+const TIMESTAMP_HINT: &str = "YYYY-MM-DD HH:MM:SS";
+
+/// Edits requested in the dialog, applied in the background.
+#[derive(Debug, Clone, Default)]
+struct Changes {
+    mode: Option<u32>,
+    recursive: bool,
+    execute: Execute,
+    owner: Option<String>,
+    group: Option<String>,
+    modified: Option<SystemTime>,
+    accessed: Option<SystemTime>,
+}
+
+impl Changes {
+    fn is_empty(&self) -> bool {
+        self.mode.is_none()
+            && self.owner.is_none()
+            && self.group.is_none()
+            && self.modified.is_none()
+            && self.accessed.is_none()
+    }
+
+    fn apply(&self, path: &Path) -> anyhow::Result<()> {
+        if let Some(mode) = self.mode {
+            properties::set_mode(path, mode, self.recursive, self.execute)?;
+        }
+        #[cfg(unix)]
+        if self.owner.is_some() || self.group.is_some() {
+            properties::set_owner(
+                path,
+                self.owner.as_deref(),
+                self.group.as_deref(),
+                self.recursive,
+            )?;
+        }
+        if self.modified.is_some() || self.accessed.is_some() {
+            properties::set_times(path, self.modified, self.accessed)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone)]
+struct PropertiesState {
+    properties: Properties,
+    /// Recursive size of a directory, filled in by a background walk.
+    summary: Option<Arc<OnceLock<TreeSummary>>>,
+    /// Stops the walk for `summary` once the dialog is closed.
+    cancel: Arc<AtomicBool>,
+    mode: u32,
+    recursive: bool,
+    /// Which enclosed entries get the execute bits when `recursive` is set.
+    execute: Execute,
+    owner: String,
+    group: String,
+    modified: String,
+    accessed: String,
+    /// Result of the running apply, set once it is done.
+    applying: Option<Arc<OnceLock<Result<(), String>>>>,
+    error: Option<String>,
+}
+
+fn account_name(account: Option<&properties::Account>) -> String {
+    account
+        .map(|account| {
+            account
+                .name
+                .clone()
+                .unwrap_or_else(|| account.id.to_string())
+        })
+        .unwrap_or_default()
+}
+
+fn timestamp_text(timestamp: Option<TimestampSeconds>) -> String {
+    timestamp
+        .map(TimestampSeconds::format_utc)
+        .unwrap_or_default()
+}
+
+impl PropertiesState {
+    fn new(ctx: &Context, properties: Properties) -> Self {
+        let cancel = Arc::new(AtomicBool::new(false));
+        let summary = properties.is_dir.then(|| {
+            let summary = Arc::new(OnceLock::new());
+            let result = Arc::clone(&summary);
+            let cancel = Arc::clone(&cancel);
+            let path = properties.path.clone();
+            let ctx = ctx.clone();
+            file_ops::spawn_task(move || {
+                if let Some(summary) = file_ops::summarize(&[path], &cancel) {
+                    let _ = result.set(summary);
+                    ctx.request_repaint();
+                }
+            });
+            summary
+        });
+        Self {
+            summary,
+            cancel,
+            mode: properties.mode,
+            recursive: false,
+            execute: Execute::FoldersAndExecutables,
+            owner: account_name(properties.owner.as_ref()),
+            group: account_name(properties.group.as_ref()),
+            modified: timestamp_text(properties.modified),
+            accessed: timestamp_text(properties.accessed),
+            applying: None,
+            error: None,
+            properties,
+        }
+    }
+
+    /// Only fields that were edited, so untouched timestamps keep their
+    /// seconds.
+    fn changes(&self) -> Result<Changes, String> {
+        let properties = &self.properties;
+        let timestamp = |text: &str, initial: Option<TimestampSeconds>| {
+            edited(text, &timestamp_text(initial))
+                .map(|text| {
+                    TimestampSeconds::parse_utc(text)
+                        .map(TimestampSeconds::system_time)
+                        .ok_or_else(|| {
+                            format!("\"{text}\" is not a valid date, use {TIMESTAMP_HINT} in UTC")
+                        })
+                })
+                .transpose()
+        };
+        Ok(Changes {
+            mode: (self.mode != properties.mode || self.recursive).then_some(self.mode),
+            recursive: self.recursive,
+            execute: if self.recursive {
+                self.execute
+            } else {
+                Execute::Everywhere
+            },
+            owner: edited(&self.owner, &account_name(properties.owner.as_ref()))
+                .map(str::to_string),
+            group: edited(&self.group, &account_name(properties.group.as_ref()))
+                .map(str::to_string),
+            modified: timestamp(&self.modified, properties.modified)?,
+            accessed: timestamp(&self.accessed, properties.accessed)?,
+        })
+    }
+}
+
+/// The trimmed `text`, if it differs from `initial`.
+fn edited<'a>(text: &'a str, initial: &str) -> Option<&'a str> {
+    let text = text.trim();
+    (text != initial).then_some(text)
+}
+
+fn state_id() -> Id {
+    Id::new(ModalWindow::Properties)
+}
+
+/// Shows the Properties dialog for `path`.
+pub fn open(ctx: &Context, path: &Path) {
+    match properties::read(path) {
+        Ok(properties) => {
+            let state = PropertiesState::new(ctx, properties);
+            ctx.data_mut(|d| d.insert_temp(state_id(), state));
+            ActionToPerform::ToggleModalWindow(ModalWindow::Properties).schedule();
+        }
+        Err(err) => {
+            log::error!("{err:#}");
+            toast!(Error, "{err}");
+        }
+    }
+}
+
+impl App {
+    /// Details of one entry, with editing of its permissions and timestamps.
+    pub(crate) fn properties_modal(&mut self, ctx: &Context) {
+        #[cfg(feature = "profiling")]
+        puffin::profile_scope!("lwa_fm::properties_modal");
+        let Some(mut state) = ctx.data(|d| d.get_temp::<PropertiesState>(state_id())) else {
+            self.display_modal = None;
+            return;
+        };
+        if let Some(result) = state.applying.as_ref().and_then(|applying| applying.get()) {
+            let path = state.properties.path.clone();
+            match result {
+                Ok(()) => {
+                    toast!(Success, "Properties updated");
+                    state.error = None;
+                }
+                Err(error) => state.error = Some(error.clone()),
+            }
+            if let Ok(properties) = properties::read(&path) {
+                state.cancel.store(true, Ordering::Relaxed);
+                state = PropertiesState {
+                    error: state.error,
+                    ..PropertiesState::new(ctx, properties)
+                };
+            }
+            state.applying = None;
+            self.refresh_changed_dirs(path.parent().map(Path::to_path_buf).into_iter().collect());
+        }
+
+        let mut apply = None;
+        let modal = egui::Modal::new(state_id()).show(ctx, |ui| {
+            ui.set_width(480.0);
+            let name = state.properties.path.file_name().map_or_else(
+                || state.properties.path.display().to_string(),
+                |name| name.to_string_lossy().to_string(),
+            );
+            ui.heading(name);
+            ui.separator();
+            details_ui(ui, &state);
+            ui.separator();
+            ui.strong("Permissions");
+            permissions_ui(ui, &mut state);
+            ui.separator();
+            ui.strong("Timestamps (UTC)");
+            egui::Grid::new("properties_times")
+                .num_columns(2)
+                .show(ui, |ui| {
+                    ui.label("Modified");
+                    ui.add(
+                        egui::TextEdit::singleline(&mut state.modified).hint_text(TIMESTAMP_HINT),
+                    );
+                    ui.end_row();
+                    ui.label("Accessed");
+                    ui.add(
+                        egui::TextEdit::singleline(&mut state.accessed).hint_text(TIMESTAMP_HINT),
+                    );
+                    ui.end_row();
+                });
+            ui.separator();
+            let changes = state.changes();
+            if let Some(error) = changes.as_ref().err().or(state.error.as_ref()) {
+                ui.colored_label(ui.visuals().error_fg_color, error);
+            }
+            ui.horizontal(|ui| {
+                let changes = changes.ok().filter(|changes| !changes.is_empty());
+                let enabled = changes.is_some() && state.applying.is_none();
+                if ui
+                    .add_enabled(enabled, egui::Button::new("Apply"))
+                    .clicked()
+                {
+                    apply = changes;
+                }
+                if state.applying.is_some() {
+                    ui.spinner();
+                }
+                if ui.button("Close").clicked() {
+                    ui.close();
+                }
+            });
+        });
+
+        if let Some(changes) = apply {
+            let result = Arc::new(OnceLock::new());
+            state.applying = Some(Arc::clone(&result));
+            let path = state.properties.path.clone();
+            let ctx = ctx.clone();
+            file_ops::spawn_task(move || {
+                let _ = result.set(changes.apply(&path).map_err(|err| format!("{err:#}")));
+                ctx.request_repaint();
+            });
+        }
+        if modal.should_close() {
+            state.cancel.store(true, Ordering::Relaxed);
+            ctx.data_mut(|d| d.remove::<PropertiesState>(state_id()));
+            self.display_modal = None;
+        } else {
+            ctx.data_mut(|d| d.insert_temp(state_id(), state));
+        }
+    }
+}
+
+fn details_ui(ui: &mut Ui, state: &PropertiesState) {
+    let properties = &state.properties;
+    let optional = |value: Option<String>| value.unwrap_or_else(|| "Unknown".to_string());
+    let timestamp = |timestamp: Option<TimestampSeconds>| {
+        optional(timestamp.map(|timestamp| format!("{} UTC", timestamp.format_utc())))
+    };
+    egui::Grid::new("properties_details")
+        .num_columns(2)
+        .striped(true)
+        .show(ui, |ui| {
+            let mut row = |label: &str, value: String| {
+                ui.label(label);
+                ui.add(egui::Label::new(value).wrap());
+                ui.end_row();
+            };
+            row("Location", properties.path.display().to_string());
+            row("Type", properties.kind().to_string());
+            if let Some(target) = &properties.link_target {
+                row("Points to", target.display().to_string());
+            }
+            if let Some(summary) = &state.summary {
+                row(
+                    "Size",
+                    summary.get().map_or_else(
+                        || "Counting…".to_string(),
+                        |summary| {
+                            format!(
+                                "{} ({} files, {} folders)",
+                                format_bytes_simple(summary.bytes),
+                                summary.files,
+                                summary.dirs.saturating_sub(1)
+                            )
+                        },
+                    ),
+                );
+                if let Some(summary) = summary.get() {
+                    row("Size on disk", format_bytes_simple(summary.disk_bytes));
+                }
+            } else {
+                row(
+                    "Size",
+                    format!(
+                        "{} ({} bytes)",
+                        format_bytes_simple(properties.size),
+                        properties.size
+                    ),
+                );
+                row("Size on disk", format_bytes_simple(properties.size_on_disk));
+            }
+            row("Modified", timestamp(properties.modified));
+            row("Accessed", timestamp(properties.accessed));
+            row("Created", timestamp(properties.created));
+            if cfg!(unix) {
+                row("Changed", timestamp(properties.changed));
+                row(
+                    "Inode",
+                    optional(properties.inode.map(|inode| inode.to_string())),
+                );
+                row(
+                    "Hard links",
+                    optional(properties.hard_links.map(|links| links.to_string())),
+                );
+            }
+        });
+}
+
+fn permissions_ui(ui: &mut Ui, state: &mut PropertiesState) {
+    if cfg!(unix) {
+        egui::Grid::new("properties_mode")
+            .num_columns(4)
+            .show(ui, |ui| {
+                ui.label("");
+                ui.label("Read");
+                ui.label("Write");
+                ui.label("Execute");
+                ui.end_row();
+                for (row, class) in ["Owner", "Group", "Others"].into_iter().enumerate() {
+                    ui.label(class);
+                    for column in 0..3 {
+                        let bit = 0o400 >> (row * 3 + column);
+                        let mut set = state.mode & bit != 0;
+                        if ui.checkbox(&mut set, "").changed() {
+                            state.mode ^= bit;
+                        }
+                    }
+                    ui.end_row();
+                }
+            });
+        ui.monospace(format!(
+            "{} ({:03o})",
+            properties::format_mode(state.mode),
+            state.mode
+        ));
+        egui::Grid::new("properties_owner")
+            .num_columns(2)
+            .show(ui, |ui| {
+                ui.label("Owner");
+                ui.text_edit_singleline(&mut state.owner);
+                ui.end_row();
+                ui.label("Group");
+                ui.text_edit_singleline(&mut state.group);
+                ui.end_row();
+            });
+    } else {
+        let mut read_only = state.mode & 0o222 == 0;
+        if ui.checkbox(&mut read_only, "Read-only").changed() {
+            state.mode = if read_only { 0o444 } else { 0o666 };
+        }
+    }
+    if state.properties.is_dir {
+        ui.checkbox(&mut state.recursive, "Apply to enclosed files and folders");
+        if cfg!(unix) && state.recursive {
+            let mut folders_only = state.execute == Execute::FoldersAndExecutables;
+            if ui
+                .checkbox(&mut folders_only, "Execute only on folders and executables")
+                .on_hover_text("Files that weren't executable stay that way, like chmod's X")
+                .changed()
+            {
+                state.execute = if folders_only {
+                    Execute::FoldersAndExecutables
+                } else {
+                    Execute::Everywhere
+                };
+            }
+        }
+    }
+}
@@ -1,4 +1,8 @@
-use std::{path::Path, process::Command};
+use std::{
+    path::{Path, PathBuf},
+    process::Command,
+    time::Duration,
+};
 
 use egui::Modal;
 use serde::{Deserialize, Serialize};
@@ -7,18 +11,29 @@ use crate::{
     app::{
         Sort,
         assets::IconSize,
+        content_search,
         directory_view_settings::{DirectoryShowHidden, DirectoryViewSettings},
+        name_index::{self, IndexState},
+        sync::SyncJob,
     },
-    helper::DataHolder,
+    helper::{DataHolder, format_bytes_simple, normalize_path},
 };
 
 use super::commands::ActionToPerform;
+use crate::toast;
 
 #[derive(Serialize, Deserialize)]
 #[serde(default)]
 pub struct ApplicationSettings {
     pub terminal_path: String,
     pub icon_size: IconSize,
+    pub sync_jobs: Vec<SyncJob>,
+    /// Files larger than this are skipped by content searches.
+    pub content_search_max_mb: u64,
+    /// Folders whose entries are kept in the search index.
+    pub index_roots: Vec<PathBuf>,
+    #[serde(skip)]
+    new_index_root: String,
 }
 
 impl Default for ApplicationSettings {
@@ -29,6 +44,10 @@ impl Default for ApplicationSettings {
             #[cfg(target_os = "macos")]
             terminal_path: "Terminal".into(),
             icon_size: IconSize::default(),
+            sync_jobs: Vec::new(),
+            content_search_max_mb: content_search::DEFAULT_MAX_FILE_MB,
+            index_roots: Vec::new(),
+            new_index_root: String::new(),
         }
     }
 }
@@ -142,6 +161,17 @@ impl ApplicationSettings {
                         ui.selectable_value(&mut self.icon_size, IconSize::ExtraLarge, "Extra Large");
                     });
                 ui.add_space(10.0);
+                ui.label("Content Search");
+                ui.add(
+                    egui::DragValue::new(&mut self.content_search_max_mb)
+                        .range(1..=4096)
+                        .prefix("skip files over ")
+                        .suffix(" MB"),
+                );
+                ui.add_space(10.0);
+                ui.label("Search Index");
+                self.index_ui(ui);
+                ui.add_space(10.0);
                 ui.separator();
                 close = ui.button("Close").clicked();
             });
@@ -151,4 +181,87 @@ impl ApplicationSettings {
             ActionToPerform::CloseActiveModalWindow.schedule();
         }
     }
+
+    /// The indexed folders with their state, and a field to add more.
+    fn index_ui(&mut self, ui: &mut egui::Ui) {
+        let mut changed = false;
+        let mut to_remove = None;
+        egui::Grid::new("name_index")
+            .spacing([8., 4.])
+            .num_columns(3)
+            .show(ui, |ui| {
+                for (index, root) in self.index_roots.iter().enumerate() {
+                    ui.label(root.to_string_lossy());
+                    let status = name_index::status(root);
+                    let busy = matches!(status.state, IndexState::Queued | IndexState::Scanning(_));
+                    ui.vertical(|ui| {
+                        match status.state {
+                            IndexState::Queued => ui.label("Waiting"),
+                            IndexState::Scanning(seen) => {
+                                ui.label(format!("Scanning, {seen} entries"))
+                            }
+                            IndexState::Ready => ui.label("Ready"),
+                            IndexState::Failed(err) => {
+                                ui.colored_label(ui.visuals().error_fg_color, err)
+                            }
+                        };
+                        if let Some(info) = status.info {
+                            ui.weak(format!(
+                                "{} entries, {}, rescanned {}",
+                                info.entries,
+                                format_bytes_simple(info.bytes),
+                                info.last_rescan.format_utc()
+                            ));
+                        }
+                    });
+                    ui.horizontal(|ui| {
+                        if ui
+                            .add_enabled(!busy, egui::Button::new("Rebuild"))
+                            .on_hover_text("Scan the whole folder again")
+                            .clicked()
+                        {
+                            name_index::rebuild(root);
+                        }
+                        if ui.button("✕").on_hover_text("Stop indexing").clicked() {
+                            to_remove = Some(index);
+                        }
+                    });
+                    ui.end_row();
+                }
+            });
+        if let Some(index) = to_remove {
+            self.index_roots.remove(index);
+            changed = true;
+        }
+        ui.horizontal(|ui| {
+            let edit = ui.add(
+                egui::TextEdit::singleline(&mut self.new_index_root)
+                    .hint_text("Add folder...")
+                    .desired_width(180.0),
+            );
+            let enter_pressed = edit.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+            if ui.button("+").on_hover_text("Index this folder").clicked() || enter_pressed {
+                let path = PathBuf::from(self.new_index_root.trim());
+                if path.is_dir() {
+                    let path = normalize_path(&path);
+                    if !self.index_roots.contains(&path) {
+                        self.index_roots.push(path);
+                        changed = true;
+                    }
+                    self.new_index_root.clear();
+                } else if !self.new_index_root.trim().is_empty() {
+                    toast!(Warning, "{} is not a folder", path.display());
+                }
+            }
+        });
+        if self.index_roots.is_empty() {
+            ui.weak("Deep searches in indexed folders don't have to read the disk.");
+        }
+        if changed {
+            name_index::sync_roots(&self.index_roots);
+        }
+        if name_index::is_busy() {
+            ui.ctx().request_repaint_after(Duration::from_millis(250));
+        }
+    }
 }
@@ -1,8 +1,19 @@
-use egui::Context;
+use egui::{Align, Context, Layout, RichText, Ui, Vec2};
 
-use crate::{consts::TOP_SIDE_MARGIN, helper::DataHolder, locations::Locations};
+use crate::{
+    consts::TOP_SIDE_MARGIN,
+    helper::DataHolder,
+    locations::{Locations, empty_icon},
+};
 
-use super::App;
+use super::{
+    App, SavedSearches,
+    commands::ActionToPerform,
+    directory_view_settings::DirectoryShowHidden,
+    dock::CurrentPath,
+    smart_folders::{self, Count},
+    trash_bin, trash_bin_view,
+};
 
 impl App {
     pub(crate) fn left_side_panel(&mut self, ctx: &Context) {
@@ -12,6 +23,23 @@ impl App {
             .tabs
             .get_current_tab()
             .is_some_and(|tab| !tab.is_searching());
+        let saved = ctx
+            .data_get_persisted::<SavedSearches>()
+            .unwrap_or_default();
+        smart_folders::sync(
+            ctx,
+            &saved,
+            |path| {
+                ctx.data_get_path_or_persisted::<DirectoryShowHidden>(&CurrentPath::One(
+                    path.to_path_buf(),
+                ))
+                .data
+                .0
+            },
+            self.settings
+                .content_search_max_mb
+                .saturating_mul(1024 * 1024),
+        );
         egui::SidePanel::left("leftPanel")
             .frame(egui::Frame::canvas(&ctx.style()).inner_margin(10.0))
             .show(ctx, |ui| {
@@ -21,13 +49,140 @@ impl App {
                         if let Some(data) = ui.data_get_persisted::<Locations>() {
                             data.draw_ui("Favorites", ui, true, &mut self.assets);
                         }
+                        smart_folders_ui(ui, &saved, self.assets.render_size());
                         self.user_locations
                             .draw_ui("User", ui, false, &mut self.assets);
                         #[cfg(not(target_os = "macos"))]
                         self.drives_locations
                             .draw_ui("Drives", ui, false, &mut self.assets);
+                        if trash_bin::SUPPORTED {
+                            trash_bin_view::sidebar_ui(ui, self.assets.render_size());
+                        }
                     });
                 });
             });
     }
 }
+
+/// The saved searches pinned to the sidebar, each with its live match count.
+fn smart_folders_ui(ui: &mut Ui, saved: &SavedSearches, icon_size: f32) {
+    let pinned = saved
+        .searches
+        .iter()
+        .filter(|search| search.pinned)
+        .collect::<Vec<_>>();
+    if pinned.is_empty() {
+        return;
+    }
+    egui::CollapsingHeader::new(RichText::new("Smart folders").weak().size(21.0))
+        .icon(empty_icon)
+        .default_open(true)
+        .show_unindented(ui, |ui| {
+            ui.with_layout(
+                Layout::top_down(Align::Min).with_cross_justify(true),
+                |ui| {
+                    for (i, folder) in pinned.iter().enumerate() {
+                        let row = ui.horizontal(|ui| {
+                            ui.allocate_space(Vec2::splat(icon_size));
+                            let button = ui.add(
+                                egui::Button::new(folder.name.as_str())
+                                    .frame(false)
+                                    .fill(egui::Color32::from_white_alpha(0)),
+                            );
+                            ui.with_layout(Layout::right_to_left(Align::Center), |ui| {
+                                match smart_folders::count(&folder.name) {
+                                    Some(Count::Ready(found)) => {
+                                        ui.weak(found.to_string());
+                                    }
+                                    Some(Count::Counting) | None => {
+                                        ui.add(egui::Spinner::new().size(10.0));
+                                    }
+                                }
+                            });
+                            button
+                        });
+                        let button = row.inner.on_hover_text(
+                            folder
+                                .roots
+                                .iter()
+                                .map(|root| root.display().to_string())
+                                .collect::<Vec<_>>()
+                                .join("\n"),
+                        );
+                        if button.clicked() {
+                            ActionToPerform::OpenSmartFolder(folder.name.clone()).schedule();
+                        }
+                        button.context_menu(|ui| {
+                            rename_ui(ui, &folder.name);
+                            ui.separator();
+                            if ui
+                                .add_enabled(i > 0, egui::Button::new("Move up"))
+                                .clicked()
+                            {
+                                ActionToPerform::MoveSmartFolder {
+                                    name: folder.name.clone(),
+                                    up: true,
+                                }
+                                .schedule();
+                                ui.close();
+                            }
+                            if ui
+                                .add_enabled(i + 1 < pinned.len(), egui::Button::new("Move down"))
+                                .clicked()
+                            {
+                                ActionToPerform::MoveSmartFolder {
+                                    name: folder.name.clone(),
+                                    up: false,
+                                }
+                                .schedule();
+                                ui.close();
+                            }
+                            if ui.button("Unpin").clicked() {
+                                ActionToPerform::PinSavedSearch {
+                                    name: folder.name.clone(),
+                                    pinned: false,
+                                }
+                                .schedule();
+                                ui.close();
+                            }
+                            ui.separator();
+                            if ui
+                                .button("Export to current folder")
+                                .on_hover_text(format!(
+                                    "Writes every smart folder to {}",
+                                    smart_folders::EXPORT_FILE_NAME
+                                ))
+                                .clicked()
+                            {
+                                ActionToPerform::ExportSmartFolders.schedule();
+                                ui.close();
+                            }
+                        });
+                    }
+                },
+            );
+        });
+}
+
+fn rename_ui(ui: &mut Ui, name: &str) {
+    let id = egui::Id::new("smart_folder_rename").with(name);
+    let mut new_name = ui.data_mut(|d| {
+        d.get_temp_mut_or_insert_with(id, || name.to_string())
+            .clone()
+    });
+    ui.horizontal(|ui| {
+        let edit = ui.add(egui::TextEdit::singleline(&mut new_name).desired_width(120.0));
+        let enter_pressed = edit.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+        if (ui.button("Rename").clicked() || enter_pressed) && new_name.trim() != name {
+            ActionToPerform::RenameSmartFolder {
+                from: name.to_string(),
+                to: new_name,
+            }
+            .schedule();
+            ui.data_mut(|d| d.remove::<String>(id));
+            ui.close();
+        } else {
+            ui.data_mut(|d| d.insert_temp(id, new_name));
+        }
+    });
+}
@@ -0,0 +1,371 @@
+//! Saved searches pinned to the sidebar as smart folders.
+//!
+//! Every pinned search is counted in the background and counted again once
+//! things below its folders stop changing for a moment. The counts are kept
+//! here by name, the definitions themselves live in [`SavedSearches`].
+
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::{
+        Arc, LazyLock, Mutex,
+        atomic::{AtomicBool, Ordering},
+    },
+    time::{Duration, Instant},
+};
+
+use anyhow::{Context as _, bail};
+use notify::RecursiveMode;
+
+use super::{
+    SavedSearch, SavedSearches, Search,
+    content_search::{self, ContentMatcher},
+    dir_handling::{self, WalkFilter},
+};
+use crate::watcher::DirectoryWatcher;
+
+/// Name of the file smart folders are exported to and imported from.
+pub const EXPORT_FILE_NAME: &str = "smart_folders.json";
+/// How long things have to stay unchanged before counting again.
+const SETTLE_TIME: Duration = Duration::from_secs(1);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Count {
+    Counting,
+    Ready(usize),
+}
+
+/// What a smart folder counts. A watcher is restarted when this changes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Definition {
+    dirs: Vec<PathBuf>,
+    search: Search,
+    show_hidden: bool,
+    max_file_size: u64,
+}
+
+impl Definition {
+    fn new(folder: &SavedSearch, show_hidden: bool, max_file_size: u64) -> Self {
+        let mut dirs = folder.roots.clone();
+        dirs.extend(folder.search.extra_dirs.iter().cloned());
+        dirs.sort();
+        dirs.dedup();
+        Self {
+            dirs,
+            search: folder.search.clone(),
+            show_hidden,
+            max_file_size,
+        }
+    }
+
+    /// Matches below the folders, the way a tab with the search lists them.
+    /// `None` when cancelled.
+    fn count(&self, cancel: &AtomicBool) -> Option<usize> {
+        let depth = self.search.depth.max(1);
+        let filter = WalkFilter::new(Some(&self.search));
+        let list =
+            dir_handling::read_directory(&self.dirs, depth, self.show_hidden, &filter, cancel);
+        if cancel.load(Ordering::Relaxed) {
+            return None;
+        }
+        let visible =
+            dir_handling::filter_visible_entries(&list, self.show_hidden, Some(&self.search));
+        let Some(matcher) = ContentMatcher::new(&self.search) else {
+            return Some(visible.len());
+        };
+        let candidates = visible
+            .into_iter()
+            .filter_map(|index| list.get(index).cloned())
+            .collect::<Vec<_>>();
+        content_search::scan(
+            &candidates,
+            &matcher,
+            self.max_file_size,
+            cancel,
+            &|_: String| {},
+            &|_: Vec<_>| {},
+        )
+        .map(|matched| matched.len())
+    }
+
+    fn mode(&self) -> RecursiveMode {
+        if self.search.depth > 1 {
+            RecursiveMode::Recursive
+        } else {
+            RecursiveMode::NonRecursive
+        }
+    }
+}
+
+static COUNTS: LazyLock<Mutex<HashMap<String, Count>>> = LazyLock::new(Default::default);
+static WATCHED: LazyLock<Mutex<HashMap<String, Watched>>> = LazyLock::new(Default::default);
+
+/// A smart folder being counted and watched.
+struct Watched {
+    definition: Definition,
+    stop: Arc<AtomicBool>,
+}
+
+/// The live count of the smart folder called `name`.
+pub fn count(name: &str) -> Option<Count> {
+    COUNTS.lock().ok()?.get(name).copied()
+}
+
+fn set_count(name: &str, count: Count) {
+    if let Ok(mut counts) = COUNTS.lock() {
+        counts.insert(name.to_string(), count);
+    }
+}
+
+/// Starts counting and watching the pinned searches in `searches` that
+/// aren't yet, or whose definition changed, and stops the ones no longer
+/// pinned. Cheap when nothing changed, so it can run every frame.
+pub fn sync(
+    ctx: &egui::Context,
+    searches: &SavedSearches,
+    show_hidden: impl Fn(&Path) -> bool,
+    max_file_size: u64,
+) {
+    let wanted = searches
+        .searches
+        .iter()
+        .filter(|folder| folder.pinned)
+        .map(|folder| {
+            let show_hidden = folder
+                .roots
+                .first()
+                .is_some_and(|root| show_hidden(root.as_path()));
+            (
+                folder.name.clone(),
+                Definition::new(folder, show_hidden, max_file_size),
+            )
+        })
+        .collect::<HashMap<_, _>>();
+    let Ok(mut watched) = WATCHED.lock() else {
+        return;
+    };
+    watched.retain(|name, watched| {
+        let keep = wanted.get(name) == Some(&watched.definition);
+        if !keep {
+            watched.stop.store(true, Ordering::Relaxed);
+            if let Ok(mut counts) = COUNTS.lock() {
+                counts.remove(name);
+            }
+        }
+        keep
+    });
+    for (name, definition) in wanted {
+        if watched.contains_key(&name) {
+            continue;
+        }
+        let stop = Arc::new(AtomicBool::new(false));
+        set_count(&name, Count::Counting);
+        watched.insert(
+            name.clone(),
+            Watched {
+                definition: definition.clone(),
+                stop: Arc::clone(&stop),
+            },
+        );
+        watch(ctx.clone(), name, definition, stop);
+    }
+}
+
+fn watch(ctx: egui::Context, name: String, definition: Definition, stop: Arc<AtomicBool>) {
+    std::thread::spawn(move || {
+        let recount = || {
+            set_count(&name, Count::Counting);
+            ctx.request_repaint();
+            if let Some(found) = definition.count(&stop) {
+                set_count(&name, Count::Ready(found));
+                ctx.request_repaint();
+            }
+        };
+        let watcher = DirectoryWatcher::new().and_then(|mut watcher| {
+            for dir in &definition.dirs {
+                watcher.watch_directory(dir, definition.mode())?;
+            }
+            Ok(watcher)
+        });
+        recount();
+        let mut watcher = match watcher {
+            Ok(watcher) => watcher,
+            Err(err) => {
+                log::warn!("smart folder {name} is not watched: {err:#}");
+                return;
+            }
+        };
+        let mut changed_at: Option<Instant> = None;
+        while !stop.load(Ordering::Relaxed) {
+            if watcher.check_rescan() {
+                changed_at = Some(Instant::now());
+            }
+            if watcher
+                .recv_event_timeout(Duration::from_millis(500))
+                .is_some()
+            {
+                while watcher.try_recv_event().is_some() {}
+                changed_at = Some(Instant::now());
+                continue;
+            }
+            if changed_at.is_some_and(|at| at.elapsed() >= SETTLE_TIME) {
+                changed_at = None;
+                recount();
+            }
+        }
+        watcher.stop_watching();
+    });
+}
+
+/// Moves the pinned search `name` above the previous pinned one, or below
+/// the next one. Unpinned searches in between keep their place.
+pub fn move_folder(searches: &mut SavedSearches, name: &str, up: bool) {
+    let pinned = searches
+        .searches
+        .iter()
+        .enumerate()
+        .filter(|(_, search)| search.pinned)
+        .map(|(index, _)| index)
+        .collect::<Vec<_>>();
+    let Some(position) = pinned
+        .iter()
+        .position(|&index| searches.searches[index].name == name)
+    else {
+        return;
+    };
+    let other = if up {
+        position.checked_sub(1)
+    } else {
+        Some(position + 1)
+    };
+    if let Some(&other) = other.and_then(|other| pinned.get(other)) {
+        searches.searches.swap(pinned[position], other);
+    }
+}
+
+/// Renames the saved search `from` to `to`, which has to be free.
+pub fn rename(searches: &mut SavedSearches, from: &str, to: &str) -> anyhow::Result<()> {
+    let to = to.trim();
+    if to.is_empty() {
+        bail!("The name can't be empty");
+    }
+    if from != to && searches.searches.iter().any(|search| search.name == to) {
+        bail!("A saved search called {to} exists already");
+    }
+    let Some(search) = searches
+        .searches
+        .iter_mut()
+        .find(|search| search.name == from)
+    else {
+        bail!("No saved search called {from}");
+    };
+    search.name = to.to_string();
+    Ok(())
+}
+
+/// Writes the pinned searches to [`EXPORT_FILE_NAME`] in `dir`.
+pub fn export(searches: &SavedSearches, dir: &Path) -> anyhow::Result<PathBuf> {
+    let pinned = searches
+        .searches
+        .iter()
+        .filter(|search| search.pinned)
+        .collect::<Vec<_>>();
+    if pinned.is_empty() {
+        bail!("There are no smart folders to export");
+    }
+    let path = dir.join(EXPORT_FILE_NAME);
+    let json = serde_json::to_string_pretty(&pinned)?;
+    std::fs::write(&path, json).with_context(|| format!("Failed to write {}", path.display()))?;
+    Ok(path)
+}
+
+/// Reads smart folders exported to `path` into `searches`, replacing saved
+/// searches of the same name. Returns how many were read.
+pub fn import(searches: &mut SavedSearches, path: &Path) -> anyhow::Result<usize> {
+    let json = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+    let imported: Vec<SavedSearch> = serde_json::from_str(&json)
+        .with_context(|| format!("{} is not a smart folder export", path.display()))?;
+    let read = imported.len();
+    for mut folder in imported {
+        folder.pinned = true;
+        if let Some(existing) = searches
+            .searches
+            .iter_mut()
+            .find(|search| search.name == folder.name)
+        {
+            *existing = folder;
+        } else {
+            searches.searches.push(folder);
+        }
+    }
+    Ok(read)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::test_support::unique_test_dir;
+
+    fn folder(name: &str, pinned: bool) -> SavedSearch {
+        SavedSearch {
+            name: name.to_string(),
+            search: Search {
+                value: name.to_string(),
+                ..Default::default()
+            },
+            roots: vec![PathBuf::from("/tmp")],
+            pinned,
+        }
+    }
+
+    fn names(searches: &SavedSearches) -> Vec<&str> {
+        searches
+            .searches
+            .iter()
+            .map(|search| search.name.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn moves_between_pinned_searches_and_renames() {
+        let mut searches = SavedSearches {
+            searches: vec![folder("a", true), folder("b", false), folder("c", true)],
+        };
+        move_folder(&mut searches, "c", true);
+        assert_eq!(names(&searches), ["c", "b", "a"], "unpinned b stays put");
+        move_folder(&mut searches, "c", true);
+        assert_eq!(names(&searches), ["c", "b", "a"], "already first");
+        move_folder(&mut searches, "c", false);
+        assert_eq!(names(&searches), ["a", "b", "c"]);
+
+        assert!(rename(&mut searches, "a", "b").is_err(), "name taken");
+        assert!(rename(&mut searches, "a", "  ").is_err());
+        rename(&mut searches, "a", " logs ").expect("rename");
+        assert_eq!(names(&searches), ["logs", "b", "c"]);
+    }
+
+    #[test]
+    fn exports_pinned_searches_and_imports_them_back() {
+        let dir = unique_test_dir("smart");
+        let searches = SavedSearches {
+            searches: vec![folder("a", true), folder("b", false)],
+        };
+        let path = export(&searches, &dir).expect("export");
+
+        let mut other = SavedSearches {
+            searches: vec![folder("a", false), folder("z", false)],
+        };
+        assert_eq!(import(&mut other, &path).expect("import"), 1);
+        assert_eq!(names(&other), ["a", "z"]);
+        assert!(other.searches[0].pinned);
+        assert_eq!(other.searches[0].roots, [PathBuf::from("/tmp")]);
+
+        other
+            .searches
+            .iter_mut()
+            .for_each(|search| search.pinned = false);
+        assert!(export(&other, &dir).is_err(), "nothing pinned");
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
@@ -0,0 +1,667 @@
+//! Sync jobs that keep a target folder in line with a source folder.
+//!
+//! A job is planned from a [`compare`](super::compare) of both folders. The
+//! plan is shown as a dry run first and applied as a file operation, which
+//! logs every step under the job's name. The log of each job's latest run is
+//! kept in [`SLED_DIRS`], so it can still be reviewed after a restart.
+
+use std::{
+    collections::{BTreeSet, HashSet},
+    fmt::Display,
+    path::{Component, Path, PathBuf},
+    sync::{LazyLock, atomic::AtomicBool},
+    time::UNIX_EPOCH,
+};
+
+use anyhow::{Context, Result, bail};
+use serde::{Deserialize, Serialize};
+
+use super::compare::{self, CompareBy, CompareStatus, ComparedEntry, Side};
+#[cfg(not(test))]
+use super::database::SLED_DIRS;
+use super::file_ops::Cancelled;
+use crate::data::time::TimestampSeconds;
+
+/// Modification times closer than this count as equal. FAT drives only
+/// keep them to two seconds.
+const MODIFIED_TOLERANCE_SECS: u64 = 2;
+/// Runs whose log is kept, oldest are dropped first.
+const MAX_LOGS: usize = 16;
+/// When each job was last run, by job name.
+const RUNS_TREE: &[u8] = b"sync_runs_v1";
+/// Lines of the logs, keyed by job name, a zero byte and the line number.
+const RUN_LOG_TREE: &[u8] = b"sync_run_log_v1";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum SyncMode {
+    /// Make the target an exact copy of the source, deleting what the source
+    /// doesn't have.
+    #[default]
+    Mirror,
+    /// Copy new files and files changed in the source, never delete.
+    UpdateOnly,
+    /// Copy new and changed files both ways, the newer file wins. Nothing is
+    /// deleted, as a missing file can't be told apart from a new one.
+    TwoWay,
+}
+
+impl SyncMode {
+    pub const ALL: [Self; 3] = [Self::Mirror, Self::UpdateOnly, Self::TwoWay];
+
+    pub const fn description(self) -> &'static str {
+        match self {
+            Self::Mirror => "Make the target an exact copy, deleting extra items",
+            Self::UpdateOnly => "Copy new and newer items, never delete",
+            Self::TwoWay => "Copy new and newer items both ways, never delete",
+        }
+    }
+}
+
+impl Display for SyncMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Mirror => write!(f, "Mirror"),
+            Self::UpdateOnly => write!(f, "Update only"),
+            Self::TwoWay => write!(f, "Two-way"),
+        }
+    }
+}
+
+/// A sync job as saved in the settings.
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SyncJob {
+    pub name: String,
+    pub source: PathBuf,
+    pub target: PathBuf,
+    pub mode: SyncMode,
+    /// Glob patterns matched against paths relative to either folder and
+    /// against every name in them, e.g. `*.tmp` or `node_modules`.
+    pub excludes: Vec<String>,
+}
+
+impl SyncJob {
+    fn exclude_patterns(&self) -> Result<Vec<glob::Pattern>> {
+        self.excludes
+            .iter()
+            .map(|exclude| exclude.trim())
+            .filter(|exclude| !exclude.is_empty())
+            .map(|exclude| {
+                glob::Pattern::new(exclude)
+                    .with_context(|| format!("Invalid exclude pattern {exclude}"))
+            })
+            .collect()
+    }
+}
+
+/// The files below an item as planned, to tell whether it changed since.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct TreeStamp {
+    pub files: u64,
+    pub bytes: u64,
+    /// Modification time of the newest file.
+    pub modified: TimestampSeconds,
+}
+
+/// One change a sync makes.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum SyncStep {
+    /// Copy `from` to `to`, which doesn't exist yet.
+    Copy {
+        from: PathBuf,
+        to: PathBuf,
+        stamp: TreeStamp,
+    },
+    /// Replace `to` with a copy of `from`.
+    Replace {
+        from: PathBuf,
+        to: PathBuf,
+        stamp: TreeStamp,
+        /// What `to` held.
+        replaced: TreeStamp,
+    },
+    /// Permanently remove `path`.
+    Delete { path: PathBuf, stamp: TreeStamp },
+}
+
+impl SyncStep {
+    /// The path the step changes.
+    pub fn destination(&self) -> &Path {
+        match self {
+            Self::Copy { to, .. } | Self::Replace { to, .. } => to,
+            Self::Delete { path, .. } => path,
+        }
+    }
+
+    /// `(files, bytes)` below the item.
+    pub const fn size(&self) -> (u64, u64) {
+        match self {
+            Self::Copy { stamp, .. } | Self::Replace { stamp, .. } | Self::Delete { stamp, .. } => {
+                (stamp.files, stamp.bytes)
+            }
+        }
+    }
+}
+
+impl Display for SyncStep {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Copy { from, to, .. } => {
+                write!(f, "Copy {} to {}", from.display(), to.display())
+            }
+            Self::Replace { from, to, .. } => {
+                write!(f, "Replace {} with {}", to.display(), from.display())
+            }
+            Self::Delete { path, .. } => write!(f, "Delete {}", path.display()),
+        }
+    }
+}
+
+/// Everything a sync job would change, in the order it is applied.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct SyncPlan {
+    /// The job planned, the run is logged under its name.
+    pub job: SyncJob,
+    pub steps: Vec<SyncStep>,
+    /// Paths relative to both folders left alone, with the reason why.
+    pub skipped: Vec<(PathBuf, String)>,
+}
+
+impl SyncPlan {
+    /// `(files, bytes)` the run goes through. Deletes count files only, as
+    /// they take no time compared to copies.
+    pub fn totals(&self) -> (u64, u64) {
+        self.steps.iter().fold((0, 0), |(files, bytes), step| {
+            let (step_files, step_bytes) = step.size();
+            match step {
+                SyncStep::Delete { .. } => (files + step_files, bytes),
+                _ => (files + step_files, bytes + step_bytes),
+            }
+        })
+    }
+
+    /// Directories whose listing changes once the plan has run.
+    pub fn affected_dirs(&self) -> Vec<PathBuf> {
+        self.steps
+            .iter()
+            .filter_map(|step| step.destination().parent().map(Path::to_path_buf))
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .collect()
+    }
+
+    /// Plans the job again and splits the steps into those that still come
+    /// out the same and those whose items changed since, which are better
+    /// left alone than applied to something that wasn't reviewed. Both keep
+    /// the order of the plan.
+    pub fn recheck(&self, cancel: &AtomicBool) -> Result<(Vec<SyncStep>, Vec<SyncStep>)> {
+        let current = plan(&self.job, cancel, &|_| {})?;
+        let current = current.steps.into_iter().collect::<HashSet<_>>();
+        Ok(self
+            .steps
+            .iter()
+            .cloned()
+            .partition(|step| current.contains(step)))
+    }
+}
+
+fn is_excluded(relative: &Path, patterns: &[glob::Pattern]) -> bool {
+    patterns.iter().any(|pattern| {
+        pattern.matches_path(relative)
+            || relative.components().any(|component| match component {
+                Component::Normal(name) => pattern.matches(&name.to_string_lossy()),
+                _ => false,
+            })
+    })
+}
+
+fn modified_secs(side: Side) -> u64 {
+    side.modified.to_duration().as_secs()
+}
+
+/// Which side holds the newer file, `None` when they were changed at about
+/// the same time.
+fn newer_side(left: Side, right: Side) -> Option<bool> {
+    let (left, right) = (modified_secs(left), modified_secs(right));
+    if left.abs_diff(right) <= MODIFIED_TOLERANCE_SECS {
+        None
+    } else {
+        Some(left > right)
+    }
+}
+
+/// The files of `entries[index]` and everything below it on one side.
+fn tree_stamp(entries: &[ComparedEntry], index: usize, left: bool) -> TreeStamp {
+    let root = &entries[index].relative;
+    let side = |entry: &ComparedEntry| if left { entry.left } else { entry.right };
+    // Entries are sorted by path, so the ones below `root` follow it.
+    std::iter::once(&entries[index])
+        .chain(
+            entries[index + 1..]
+                .iter()
+                .take_while(|entry| entry.relative.starts_with(root)),
+        )
+        .filter_map(side)
+        .filter(|side| !side.is_dir)
+        .fold(TreeStamp::default(), |stamp, side| TreeStamp {
+            files: stamp.files + 1,
+            bytes: stamp.bytes + side.size,
+            modified: stamp.modified.max(side.modified),
+        })
+}
+
+struct Planner<'a> {
+    plan: SyncPlan,
+    entries: &'a [ComparedEntry],
+    patterns: &'a [glob::Pattern],
+    /// Paths whose whole tree is taken care of.
+    covered: BTreeSet<PathBuf>,
+}
+
+impl Planner<'_> {
+    /// Whether anything below the item at `index` is excluded, in which case
+    /// it can't be copied or deleted as a whole.
+    fn holds_excluded(&self, index: usize) -> bool {
+        let root = &self.entries[index].relative;
+        // Entries are sorted by path, so the ones below `root` follow it.
+        self.entries[index + 1..]
+            .iter()
+            .take_while(|entry| entry.relative.starts_with(root))
+            .any(|entry| is_excluded(&entry.relative, self.patterns))
+    }
+
+    fn is_covered(&self, relative: &Path) -> bool {
+        relative
+            .ancestors()
+            .skip(1)
+            .any(|ancestor| self.covered.contains(ancestor))
+    }
+
+    /// Copies the item at `index` from the left to the right, or the other way
+    /// around with `to_left`.
+    fn copy(&mut self, index: usize, to_left: bool, replace: bool) {
+        let relative = &self.entries[index].relative;
+        let job = &self.plan.job;
+        let (from, to) = if to_left {
+            (&job.target, &job.source)
+        } else {
+            (&job.source, &job.target)
+        };
+        let (from, to) = (from.join(relative), to.join(relative));
+        let stamp = tree_stamp(self.entries, index, !to_left);
+        self.plan.steps.push(if replace {
+            SyncStep::Replace {
+                from,
+                to,
+                stamp,
+                replaced: tree_stamp(self.entries, index, to_left),
+            }
+        } else {
+            SyncStep::Copy { from, to, stamp }
+        });
+        self.covered.insert(relative.clone());
+    }
+
+    fn delete(&mut self, index: usize) {
+        let relative = &self.entries[index].relative;
+        self.plan.steps.push(SyncStep::Delete {
+            path: self.plan.job.target.join(relative),
+            stamp: tree_stamp(self.entries, index, false),
+        });
+        self.covered.insert(relative.clone());
+    }
+
+    fn skip(&mut self, index: usize, reason: &str) {
+        let relative = self.entries[index].relative.clone();
+        self.covered.insert(relative.clone());
+        self.plan.skipped.push((relative, reason.to_string()));
+    }
+
+    fn plan_entry(&mut self, index: usize, mode: SyncMode) {
+        let entries = self.entries;
+        let entry = &entries[index];
+        let holds_excluded = self.holds_excluded(index);
+        match (entry.status, entry.left, entry.right) {
+            // Planned item by item below, so the excluded ones are left alone.
+            (CompareStatus::OnlyLeft | CompareStatus::OnlyRight, ..) if holds_excluded => {}
+            (CompareStatus::OnlyLeft, ..) => self.copy(index, false, false),
+            (CompareStatus::OnlyRight, ..) => match mode {
+                SyncMode::Mirror => self.delete(index),
+                SyncMode::UpdateOnly => {
+                    self.covered.insert(entry.relative.clone());
+                }
+                SyncMode::TwoWay => self.copy(index, true, false),
+            },
+            (CompareStatus::Different, Some(left), Some(right)) if left.is_dir != right.is_dir => {
+                if holds_excluded {
+                    self.skip(index, "A folder holding excluded items on one side");
+                } else if mode == SyncMode::Mirror {
+                    self.copy(index, false, true);
+                } else {
+                    self.skip(index, "A folder on one side and a file on the other");
+                }
+            }
+            (CompareStatus::Different, Some(left), Some(right)) if !left.is_dir => {
+                match (mode, newer_side(left, right)) {
+                    // Only told apart by a rounded modification time.
+                    (_, None) if left.size == right.size => {}
+                    (SyncMode::Mirror, _) | (_, Some(true)) => self.copy(index, false, true),
+                    (SyncMode::UpdateOnly, Some(false)) => {
+                        self.skip(index, "Newer in the target");
+                    }
+                    (SyncMode::TwoWay, Some(false)) => self.copy(index, true, true),
+                    (SyncMode::UpdateOnly | SyncMode::TwoWay, None) => {
+                        self.skip(index, "Changed on both sides at about the same time");
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Works out what running `job` would change. Fails with [`Cancelled`] once
+/// `cancel` is set.
+pub fn plan(job: &SyncJob, cancel: &AtomicBool, progress: &dyn Fn(String)) -> Result<SyncPlan> {
+    let patterns = job.exclude_patterns()?;
+    if !job.source.is_dir() {
+        bail!("Source folder {} not found", job.source.display());
+    }
+    if job.target.starts_with(&job.source) || job.source.starts_with(&job.target) {
+        bail!("Source and target can't be inside one another");
+    }
+    let entries = compare::compare(
+        &job.source,
+        &job.target,
+        true,
+        CompareBy::SizeAndModified,
+        true,
+        cancel,
+        progress,
+    )
+    .ok_or(Cancelled)?;
+
+    let mut planner = Planner {
+        plan: SyncPlan {
+            job: job.clone(),
+            ..SyncPlan::default()
+        },
+        entries: &entries,
+        patterns: &patterns,
+        covered: BTreeSet::new(),
+    };
+    for (index, entry) in entries.iter().enumerate() {
+        if planner.is_covered(&entry.relative) {
+            continue;
+        }
+        if is_excluded(&entry.relative, planner.patterns) {
+            planner.covered.insert(entry.relative.clone());
+            continue;
+        }
+        planner.plan_entry(index, job.mode);
+    }
+    Ok(planner.plan)
+}
+
+static RUN_LOGS: LazyLock<RunLogs> = LazyLock::new(|| {
+    #[cfg(not(test))]
+    let db = &SLED_DIRS;
+    #[cfg(test)]
+    let db = &sled::Config::new()
+        .temporary(true)
+        .open()
+        .expect("temporary sled database should open");
+    RunLogs::open(db)
+});
+
+/// Logs of the latest run of each job.
+struct RunLogs {
+    runs: sled::Tree,
+    lines: sled::Tree,
+}
+
+impl RunLogs {
+    fn open(db: &sled::Db) -> Self {
+        Self {
+            runs: db.open_tree(RUNS_TREE).expect("sync runs tree should open"),
+            lines: db
+                .open_tree(RUN_LOG_TREE)
+                .expect("sync run log tree should open"),
+        }
+    }
+
+    fn prefix(name: &str) -> Vec<u8> {
+        let mut prefix = name.as_bytes().to_vec();
+        prefix.push(0);
+        prefix
+    }
+
+    fn remove(&self, name: &[u8]) -> sled::Result<()> {
+        let mut prefix = name.to_vec();
+        prefix.push(0);
+        for key in self.lines.scan_prefix(&prefix).keys() {
+            self.lines.remove(key?)?;
+        }
+        self.runs.remove(name).map(drop)
+    }
+
+    /// Drops the previous log of `name` and the oldest logs past
+    /// [`MAX_LOGS`].
+    fn start(&self, name: &str) -> sled::Result<()> {
+        self.remove(name.as_bytes())?;
+        let started = UNIX_EPOCH.elapsed().unwrap_or_default().as_secs();
+        self.runs.insert(name, &started.to_be_bytes())?;
+        while self.runs.len() > MAX_LOGS {
+            let oldest = self
+                .runs
+                .iter()
+                .filter_map(std::result::Result::ok)
+                .filter(|(job, _)| job != name.as_bytes())
+                .min_by_key(|(_, started)| started.clone());
+            let Some((oldest, _)) = oldest else {
+                break;
+            };
+            self.remove(&oldest)?;
+        }
+        Ok(())
+    }
+
+    fn push(&self, name: &str, line: &str) -> sled::Result<()> {
+        if !self.runs.contains_key(name)? {
+            return Ok(());
+        }
+        let prefix = Self::prefix(name);
+        let next = self
+            .lines
+            .scan_prefix(&prefix)
+            .keys()
+            .next_back()
+            .transpose()?
+            .and_then(|key| <[u8; 8]>::try_from(&key[prefix.len()..]).ok())
+            .map_or(0, |line| u64::from_be_bytes(line) + 1);
+        let mut key = prefix;
+        key.extend_from_slice(&next.to_be_bytes());
+        self.lines.insert(key, line.as_bytes()).map(drop)
+    }
+
+    fn lines(&self, name: &str) -> Option<Vec<String>> {
+        self.runs.contains_key(name).ok()?.then(|| {
+            self.lines
+                .scan_prefix(Self::prefix(name))
+                .values()
+                .filter_map(std::result::Result::ok)
+                .map(|line| String::from_utf8_lossy(&line).into_owned())
+                .collect()
+        })
+    }
+}
+
+/// Starts a new log for the job called `name`, replacing its previous one.
+pub fn start_log(name: &str) {
+    if let Err(err) = RUN_LOGS.start(name) {
+        log::warn!("failed to start the sync log of {name}: {err}");
+    }
+}
+
+/// Adds `line` to the log of the job called `name`.
+pub fn log(name: &str, line: String) {
+    log::info!("Sync {name}: {line}");
+    if let Err(err) = RUN_LOGS.push(name, &line) {
+        log::warn!("failed to write the sync log of {name}: {err}");
+    }
+}
+
+/// Log of the latest run of the job called `name`.
+pub fn run_log(name: &str) -> Option<Vec<String>> {
+    RUN_LOGS.lines(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        fs::{self, File},
+        path::Path,
+        sync::atomic::AtomicBool,
+        time::{Duration, SystemTime},
+    };
+
+    use super::{MAX_LOGS, RunLogs, SyncJob, SyncMode, SyncStep, plan};
+    use crate::app::test_support::unique_test_dir;
+
+    fn write(path: &Path, contents: &[u8], modified: u64) {
+        fs::create_dir_all(path.parent().expect("parent")).expect("create dirs");
+        fs::write(path, contents).expect("write");
+        File::options()
+            .write(true)
+            .open(path)
+            .expect("open")
+            .set_modified(SystemTime::UNIX_EPOCH + Duration::from_secs(modified))
+            .expect("set modified");
+    }
+
+    /// Steps as `action relative_path`, sorted.
+    fn steps(dir: &Path, mode: SyncMode) -> (Vec<String>, usize) {
+        let job = SyncJob {
+            name: "test".to_string(),
+            source: dir.join("source"),
+            target: dir.join("target"),
+            mode,
+            excludes: vec!["*.tmp".to_string(), "cache".to_string()],
+        };
+        let plan = plan(&job, &AtomicBool::new(false), &|_| {}).expect("plan");
+        let relative = |path: &Path| {
+            path.strip_prefix(&job.source)
+                .or_else(|_| path.strip_prefix(&job.target))
+                .expect("inside the job")
+                .to_string_lossy()
+                .replace('\\', "/")
+        };
+        let mut steps = plan
+            .steps
+            .iter()
+            .map(|step| match step {
+                SyncStep::Copy { to, .. } if to.starts_with(&job.source) => {
+                    format!("copy_back {}", relative(to))
+                }
+                SyncStep::Copy { to, .. } => format!("copy {}", relative(to)),
+                SyncStep::Replace { to, .. } if to.starts_with(&job.source) => {
+                    format!("replace_back {}", relative(to))
+                }
+                SyncStep::Replace { to, .. } => format!("replace {}", relative(to)),
+                SyncStep::Delete { path, .. } => format!("delete {}", relative(path)),
+            })
+            .collect::<Vec<_>>();
+        steps.sort();
+        (steps, plan.skipped.len())
+    }
+
+    #[test]
+    fn plans_follow_the_mode() {
+        let dir = unique_test_dir("sync_plan");
+        let (source, target) = (dir.join("source"), dir.join("target"));
+        write(&source.join("new/a"), b"a", 1_000);
+        write(&source.join("new/b"), b"b", 1_000);
+        write(&source.join("same"), b"same", 1_000);
+        // One second apart, as a FAT drive may round it.
+        write(&target.join("same"), b"same", 1_001);
+        write(&source.join("changed"), b"newer", 5_000);
+        write(&target.join("changed"), b"older", 1_000);
+        write(&source.join("stale"), b"older", 1_000);
+        write(&target.join("stale"), b"newer", 5_000);
+        write(&target.join("extra/file"), b"x", 1_000);
+        write(&target.join("extra/cache/kept"), b"x", 1_000);
+        write(&source.join("skip.tmp"), b"", 1_000);
+        write(&source.join("cache/data"), b"", 1_000);
+
+        let (mirror, skipped) = steps(&dir, SyncMode::Mirror);
+        assert_eq!(
+            mirror,
+            [
+                "copy new",
+                "delete extra/file",
+                "replace changed",
+                "replace stale"
+            ]
+        );
+        assert_eq!(skipped, 0);
+
+        let (update, skipped) = steps(&dir, SyncMode::UpdateOnly);
+        assert_eq!(update, ["copy new", "replace changed"]);
+        assert_eq!(skipped, 1);
+
+        let (two_way, _) = steps(&dir, SyncMode::TwoWay);
+        assert_eq!(
+            two_way,
+            [
+                "copy new",
+                "copy_back extra/file",
+                "replace changed",
+                "replace_back stale"
+            ]
+        );
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn run_logs_are_kept_for_the_latest_runs() {
+        let db = sled::Config::new()
+            .temporary(true)
+            .open()
+            .expect("temporary sled database should open");
+        let logs = RunLogs::open(&db);
+        logs.push("job", "before any run").expect("push");
+        assert_eq!(logs.lines("job"), None);
+
+        logs.start("job").expect("start");
+        logs.push("job", "first").expect("push");
+        logs.push("job", "second").expect("push");
+        assert_eq!(
+            logs.lines("job"),
+            Some(vec!["first".to_string(), "second".to_string()])
+        );
+        logs.start("job").expect("start again");
+        assert_eq!(logs.lines("job"), Some(Vec::new()));
+
+        for job in 0..=MAX_LOGS {
+            logs.start(&format!("job {job}")).expect("start");
+        }
+        assert_eq!(logs.runs.len(), MAX_LOGS);
+        assert_eq!(
+            RunLogs::open(&db).lines(&format!("job {MAX_LOGS}")),
+            Some(Vec::new()),
+            "read back from the database"
+        );
+    }
+
+    #[test]
+    fn nested_folders_are_refused() {
+        let dir = unique_test_dir("sync_nested");
+        let job = SyncJob {
+            source: dir.clone(),
+            target: dir.join("backup"),
+            ..SyncJob::default()
+        };
+        assert!(plan(&job, &AtomicBool::new(false), &|_| {}).is_err());
+        let _ = fs::remove_dir_all(dir);
+    }
+}
@@ -0,0 +1,439 @@
+use std::{
+    path::{Path, PathBuf},
+    sync::{
+        Arc, Mutex, OnceLock,
+        atomic::{AtomicBool, Ordering},
+    },
+};
+
+use egui::{Color32, Context, Id, Ui};
+
+use super::App;
+use super::commands::{ActionToPerform, ModalWindow};
+use super::file_ops::{self, Cancelled, FileOperation, FileOperationKind};
+use super::sync::{self, SyncJob, SyncMode, SyncPlan, SyncStep};
+use crate::helper::format_bytes_simple;
+use crate::toast;
+
+/// Height of a row in the dry run report.
+const ROW_HEIGHT: f32 = 18.0;
+
+/// A dry run of a job, running or done in the background.
+#[derive(Debug, Clone)]
+struct DryRun {
+    /// The job as it was planned.
+    job: SyncJob,
+    /// Latest progress message of the planning.
+    status: Arc<Mutex<String>>,
+    cancel: Arc<AtomicBool>,
+    /// Filled in once planned, with the error when it failed.
+    result: Arc<OnceLock<Result<Arc<SyncPlan>, String>>>,
+}
+
+impl DryRun {
+    fn start(ctx: &Context, job: SyncJob) -> Self {
+        let status = Arc::new(Mutex::new(String::new()));
+        let cancel = Arc::new(AtomicBool::new(false));
+        let result = Arc::new(OnceLock::new());
+        {
+            let status = Arc::clone(&status);
+            let cancel = Arc::clone(&cancel);
+            let result = Arc::clone(&result);
+            let job = job.clone();
+            let ctx = ctx.clone();
+            file_ops::spawn_task(move || {
+                let progress = |message: String| {
+                    if let Ok(mut status) = status.lock() {
+                        *status = message;
+                    }
+                    ctx.request_repaint();
+                };
+                match sync::plan(&job, &cancel, &progress) {
+                    Ok(plan) => {
+                        let _ = result.set(Ok(Arc::new(plan)));
+                    }
+                    Err(err) if err.is::<Cancelled>() => {}
+                    Err(err) => {
+                        let _ = result.set(Err(format!("{err:#}")));
+                    }
+                }
+                ctx.request_repaint();
+            });
+        }
+        Self {
+            job,
+            status,
+            cancel,
+            result,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct SyncState {
+    /// Index of the saved job being edited, `None` for a new one.
+    editing: Option<usize>,
+    name: String,
+    source: String,
+    target: String,
+    mode: SyncMode,
+    /// Exclude patterns, one per line.
+    excludes: String,
+    dry_run: Option<DryRun>,
+}
+
+impl SyncState {
+    fn new_job(source: Option<&Path>) -> Self {
+        Self {
+            editing: None,
+            name: String::new(),
+            source: source
+                .map(|path| path.display().to_string())
+                .unwrap_or_default(),
+            target: String::new(),
+            mode: SyncMode::default(),
+            excludes: String::new(),
+            dry_run: None,
+        }
+    }
+
+    fn edit(index: usize, job: &SyncJob) -> Self {
+        Self {
+            editing: Some(index),
+            name: job.name.clone(),
+            source: job.source.display().to_string(),
+            target: job.target.display().to_string(),
+            mode: job.mode,
+            excludes: job.excludes.join("\n"),
+            dry_run: None,
+        }
+    }
+
+    /// The job as filled in, named after its folders when left unnamed.
+    fn job(&self) -> SyncJob {
+        let (source, target) = (
+            PathBuf::from(self.source.trim()),
+            PathBuf::from(self.target.trim()),
+        );
+        let name = if self.name.trim().is_empty() {
+            format!("{} → {}", folder_name(&source), folder_name(&target))
+        } else {
+            self.name.trim().to_string()
+        };
+        SyncJob {
+            name,
+            source,
+            target,
+            mode: self.mode,
+            excludes: self
+                .excludes
+                .lines()
+                .map(str::trim)
+                .filter(|line| !line.is_empty())
+                .map(str::to_string)
+                .collect(),
+        }
+    }
+
+    fn cancel_dry_run(&mut self) {
+        if let Some(dry_run) = self.dry_run.take() {
+            dry_run.cancel.store(true, Ordering::Relaxed);
+        }
+    }
+}
+
+fn folder_name(path: &Path) -> String {
+    path.file_name().map_or_else(
+        || path.display().to_string(),
+        |name| name.to_string_lossy().to_string(),
+    )
+}
+
+fn state_id() -> Id {
+    Id::new(ModalWindow::Sync)
+}
+
+/// Shows the sync jobs, starting a new one from `source` when none are
+/// saved yet.
+pub fn open(ctx: &Context, jobs: &[SyncJob], source: Option<&Path>) {
+    let state = jobs
+        .first()
+        .map_or_else(|| SyncState::new_job(source), |job| SyncState::edit(0, job));
+    ctx.data_mut(|d| d.insert_temp(state_id(), state));
+    ActionToPerform::ToggleModalWindow(ModalWindow::Sync).schedule();
+}
+
+/// Whether a sync of the job called `name` is queued or running.
+fn is_running(name: &str) -> bool {
+    file_ops::active_jobs().iter().any(|job| {
+        job.operation.kind == FileOperationKind::Sync
+            && job
+                .operation
+                .sync_plan
+                .as_ref()
+                .is_some_and(|plan| plan.job.name == name)
+    })
+}
+
+impl App {
+    /// Saved sync jobs, their dry runs and the log of their latest run.
+    pub(crate) fn sync_modal(&mut self, ctx: &Context) {
+        #[cfg(feature = "profiling")]
+        puffin::profile_scope!("lwa_fm::sync_modal");
+        let Some(mut state) = ctx.data(|d| d.get_temp::<SyncState>(state_id())) else {
+            self.display_modal = None;
+            return;
+        };
+
+        let mut close = false;
+        let modal = egui::Modal::new(state_id()).show(ctx, |ui| {
+            ui.set_width(880.0);
+            ui.heading("Sync jobs");
+            ui.separator();
+            ui.horizontal_top(|ui| {
+                ui.vertical(|ui| {
+                    ui.set_width(200.0);
+                    jobs_ui(ui, &mut state, &self.settings.sync_jobs);
+                });
+                ui.separator();
+                ui.vertical(|ui| {
+                    editor_ui(ui, &mut state, &mut self.settings.sync_jobs);
+                });
+            });
+            ui.separator();
+            dry_run_ui(ui, &mut state);
+            log_ui(ui, &state.job().name);
+            ui.separator();
+            ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                if ui.button("Close").clicked() {
+                    close = true;
+                }
+            });
+        });
+        if close || modal.should_close() {
+            state.cancel_dry_run();
+            ctx.data_mut(|d| d.remove::<SyncState>(state_id()));
+            self.display_modal = None;
+        } else {
+            ctx.data_mut(|d| d.insert_temp(state_id(), state));
+        }
+    }
+}
+
+fn jobs_ui(ui: &mut Ui, state: &mut SyncState, jobs: &[SyncJob]) {
+    if jobs.is_empty() {
+        ui.weak("No saved jobs");
+    }
+    for (index, job) in jobs.iter().enumerate() {
+        if ui
+            .selectable_label(state.editing == Some(index), &job.name)
+            .on_hover_text(format!(
+                "{} → {}\n{}",
+                job.source.display(),
+                job.target.display(),
+                job.mode
+            ))
+            .clicked()
+        {
+            state.cancel_dry_run();
+            *state = SyncState::edit(index, job);
+        }
+    }
+    ui.add_space(4.0);
+    if ui.button("New job").clicked() {
+        state.cancel_dry_run();
+        *state = SyncState::new_job(None);
+    }
+}
+
+fn editor_ui(ui: &mut Ui, state: &mut SyncState, jobs: &mut Vec<SyncJob>) {
+    egui::Grid::new("sync_job").num_columns(2).show(ui, |ui| {
+        ui.label("Name");
+        ui.add(
+            egui::TextEdit::singleline(&mut state.name)
+                .hint_text("Named after the folders when empty")
+                .desired_width(480.0),
+        );
+        ui.end_row();
+        ui.label("Source");
+        ui.add(egui::TextEdit::singleline(&mut state.source).desired_width(480.0));
+        ui.end_row();
+        ui.label("Target");
+        ui.add(egui::TextEdit::singleline(&mut state.target).desired_width(480.0));
+        ui.end_row();
+        ui.label("Mode");
+        ui.horizontal(|ui| {
+            egui::ComboBox::from_id_salt("sync_mode")
+                .selected_text(state.mode.to_string())
+                .show_ui(ui, |ui| {
+                    for mode in SyncMode::ALL {
+                        ui.selectable_value(&mut state.mode, mode, mode.to_string())
+                            .on_hover_text(mode.description());
+                    }
+                });
+            ui.weak(state.mode.description());
+        });
+        ui.end_row();
+        ui.label("Exclude");
+        ui.add(
+            egui::TextEdit::multiline(&mut state.excludes)
+                .hint_text("One glob per line, e.g. *.tmp or node_modules")
+                .desired_rows(3)
+                .desired_width(480.0),
+        );
+        ui.end_row();
+    });
+    ui.horizontal(|ui| {
+        let job = state.job();
+        let filled = !state.source.trim().is_empty() && !state.target.trim().is_empty();
+        let saved = state.editing.and_then(|index| jobs.get(index));
+        if ui
+            .add_enabled(filled && saved != Some(&job), egui::Button::new("Save"))
+            .clicked()
+        {
+            save(state, jobs, job.clone());
+        }
+        if let Some(index) = state.editing
+            && ui.button("Delete job").clicked()
+        {
+            jobs.remove(index);
+            state.cancel_dry_run();
+            *state = SyncState::new_job(None);
+        }
+        let planning = state
+            .dry_run
+            .as_ref()
+            .is_some_and(|dry_run| dry_run.result.get().is_none());
+        if ui
+            .add_enabled(filled && !planning, egui::Button::new("Dry run"))
+            .on_hover_text("List what a run would change, without changing anything")
+            .clicked()
+        {
+            state.cancel_dry_run();
+            state.dry_run = Some(DryRun::start(ui.ctx(), job));
+        }
+    });
+}
+
+fn save(state: &mut SyncState, jobs: &mut Vec<SyncJob>, job: SyncJob) {
+    let taken = jobs
+        .iter()
+        .enumerate()
+        .any(|(index, other)| other.name == job.name && Some(index) != state.editing);
+    if taken {
+        toast!(Error, "A sync job called {} already exists", job.name);
+        return;
+    }
+    state.name.clone_from(&job.name);
+    if let Some(saved) = state.editing.and_then(|index| jobs.get_mut(index)) {
+        *saved = job;
+    } else {
+        jobs.push(job);
+        state.editing = Some(jobs.len() - 1);
+    }
+}
+
+fn step_color(ui: &Ui, step: &SyncStep) -> Color32 {
+    match step {
+        SyncStep::Copy { .. } => ui.visuals().text_color(),
+        SyncStep::Replace { .. } => ui.visuals().warn_fg_color,
+        SyncStep::Delete { .. } => ui.visuals().error_fg_color,
+    }
+}
+
+fn dry_run_ui(ui: &mut Ui, state: &mut SyncState) {
+    let Some(dry_run) = state.dry_run.clone() else {
+        ui.weak("Do a dry run to see what the job would change before running it");
+        return;
+    };
+    let plan = match dry_run.result.get() {
+        None => {
+            ui.horizontal(|ui| {
+                ui.spinner();
+                ui.label(dry_run.status.lock().map(|s| s.clone()).unwrap_or_default());
+                if ui.button("Cancel").clicked() {
+                    state.cancel_dry_run();
+                }
+            });
+            return;
+        }
+        Some(Err(err)) => {
+            ui.colored_label(ui.visuals().error_fg_color, err);
+            return;
+        }
+        Some(Ok(plan)) => Arc::clone(plan),
+    };
+    let count = |kind: fn(&SyncStep) -> bool| plan.steps.iter().filter(|step| kind(step)).count();
+    ui.label(format!(
+        "{} to copy, {} to replace, {} to delete, {} to transfer",
+        count(|step| matches!(step, SyncStep::Copy { .. })),
+        count(|step| matches!(step, SyncStep::Replace { .. })),
+        count(|step| matches!(step, SyncStep::Delete { .. })),
+        format_bytes_simple(plan.totals().1)
+    ));
+    egui::ScrollArea::vertical()
+        .id_salt("sync_steps")
+        .max_height(240.0)
+        .auto_shrink([false, true])
+        .show_rows(ui, ROW_HEIGHT, plan.steps.len(), |ui, range| {
+            for step in &plan.steps[range] {
+                ui.add(
+                    egui::Label::new(
+                        egui::RichText::new(step.to_string()).color(step_color(ui, step)),
+                    )
+                    .truncate(),
+                );
+            }
+        });
+    if !plan.skipped.is_empty() {
+        egui::CollapsingHeader::new(format!("{} item(s) left alone", plan.skipped.len()))
+            .id_salt("sync_skipped")
+            .show(ui, |ui| {
+                for (relative, reason) in &plan.skipped {
+                    ui.label(format!("{}: {reason}", relative.display()));
+                }
+            });
+    }
+    let current = dry_run.job == state.job();
+    if !current {
+        ui.weak("The job changed since the dry run, do it again to run it");
+    }
+    let running = is_running(&plan.job.name);
+    if ui
+        .add_enabled(
+            current && !running && !plan.steps.is_empty(),
+            egui::Button::new("Run"),
+        )
+        .clicked()
+    {
+        FileOperation::sync((*plan).clone()).schedule();
+        toast!(Info, "Sync {} started", plan.job.name);
+        state.dry_run = None;
+    }
+}
+
+fn log_ui(ui: &mut Ui, name: &str) {
+    let running = is_running(name);
+    let Some(lines) = sync::run_log(name) else {
+        return;
+    };
+    egui::CollapsingHeader::new(if running {
+        "Log of the running sync"
+    } else {
+        "Log of the last run"
+    })
+    .id_salt("sync_log")
+    .default_open(running)
+    .show(ui, |ui| {
+        egui::ScrollArea::vertical()
+            .id_salt("sync_log_lines")
+            .max_height(160.0)
+            .auto_shrink([false, true])
+            .stick_to_bottom(true)
+            .show_rows(ui, ROW_HEIGHT, lines.len(), |ui, range| {
+                for line in &lines[range] {
+                    ui.add(egui::Label::new(line).truncate());
+                }
+            });
+    });
+}
@@ -0,0 +1,24 @@
+//! Helpers shared by the test modules.
+
+use std::{
+    path::PathBuf,
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+static NEXT_DIR: AtomicU64 = AtomicU64::new(0);
+
+/// Creates an empty directory for one test. The name is unique across tests
+/// running in parallel and across test runs.
+pub fn unique_test_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!(
+        "lwa_fm_{name}_{}_{}_{}",
+        std::process::id(),
+        NEXT_DIR.fetch_add(1, Ordering::Relaxed),
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .expect("system time before unix epoch")
+            .as_nanos()
+    ));
+    std::fs::create_dir_all(&dir).expect("create temp test dir");
+    dir
+}
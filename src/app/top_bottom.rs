@@ -8,15 +8,19 @@ use super::{ActionToPerform, App, Sort};
 use crate::{
     app::{
         MatchMode, SearchTerm, SearchTermType,
+        checksums::ChecksumKind,
         commands::TabAction,
-        dir_handling::get_directories_recursive,
+        dir_handling::{self, get_directories_recursive},
         directory_path_info::DirectoryPathInfo,
         directory_view_settings::{DirectoryShowHidden, DirectoryViewSettings},
         dock::TabData,
+        file_ops,
+        meta_query::{self, MetaQuery},
     },
     consts::{GIT_HASH_INFO, HOMEPAGE, TOP_SIDE_MARGIN, VERSION},
-    helper::{DataHolder, KeyWithCommandPressed},
+    helper::{DataHolder, KeyWithCommandPressed, format_bytes_simple},
     locations::Locations,
+    toast,
     widgets::{ButtonGroupElement, UiBuilderExt},
 };
 use egui::{Button, Context, Frame, Layout, OpenUrl, Ui, Vec2, style::HandleShape};
@@ -305,6 +309,21 @@ impl App {
                                                             .schedule_tab(current_tab.id);
                                                             ui.close_menu();
                                                         }
+                                                        if ui
+                                                            .selectable_label(ss.pinned, "📌")
+                                                            .on_hover_text(if ss.pinned {
+                                                                "Unpin from the sidebar"
+                                                            } else {
+                                                                "Pin to the sidebar as a smart folder"
+                                                            })
+                                                            .clicked()
+                                                        {
+                                                            ActionToPerform::PinSavedSearch {
+                                                                name: ss.name.clone(),
+                                                                pinned: !ss.pinned,
+                                                            }
+                                                            .schedule();
+                                                        }
                                                         ui.label(&ss.name);
                                                     });
                                                 }
@@ -335,6 +354,10 @@ impl App {
                                             .toggle_value(&mut search.case_sensitive, "🇨")
                                             .on_hover_text("Case sensitive")
                                             .changed();
+                                        search_target_changed |= ui
+                                            .toggle_value(&mut search.in_contents, "📄")
+                                            .on_hover_text("Search inside files")
+                                            .changed();
                                         let previous_type = search.term_type;
                                         egui::ComboBox::from_id_salt("search_term_type")
                                             .width(80.0)
@@ -342,6 +365,8 @@ impl App {
                                                 SearchTermType::Plain => "Plain",
                                                 SearchTermType::Glob => "Glob",
                                                 SearchTermType::Regex => "Regex",
+                                                SearchTermType::Metadata => "Metadata",
+                                                SearchTermType::Fuzzy => "Fuzzy",
                                             })
                                             .show_ui(ui, |ui| {
                                                 ui.selectable_value(
@@ -359,13 +384,46 @@ impl App {
                                                     SearchTermType::Regex,
                                                     "Regex",
                                                 );
+                                                ui.selectable_value(
+                                                    &mut search.term_type,
+                                                    SearchTermType::Metadata,
+                                                    "Metadata",
+                                                );
+                                                ui.selectable_value(
+                                                    &mut search.term_type,
+                                                    SearchTermType::Fuzzy,
+                                                    "Fuzzy",
+                                                );
                                             });
                                         search_changed |= search.term_type != previous_type;
+                                        // Switching to or from a metadata query turns reading
+                                        // file contents off or on.
+                                        search_target_changed |=
+                                            search.in_contents && search.term_type != previous_type;
+                                        let is_metadata = search.term_type == SearchTermType::Metadata;
                                         let search_input = ui.add(
-                                            egui::TextEdit::singleline(&mut search.value)
-                                                .hint_text("Search"),
+                                            egui::TextEdit::singleline(&mut search.value).hint_text(
+                                                if is_metadata { "size>10MB modified<7d" } else { "Search" },
+                                            ),
                                         );
                                         search_changed |= search_input.changed();
+                                        if is_metadata {
+                                            if let Err(err) =
+                                                MetaQuery::parse(&search.value, std::time::SystemTime::now().into())
+                                                && !search.value.trim().is_empty()
+                                            {
+                                                ui.colored_label(egui::Color32::RED, "⚠")
+                                                    .on_hover_text(format!("{err:#}"));
+                                            } else {
+                                                ui.weak("ℹ").on_hover_text(meta_query::SYNTAX_HELP);
+                                            }
+                                        }
+                                        if dir_handling::uses_fuzzy(search) {
+                                            search_changed |= ui
+                                                .toggle_value(&mut search.sort_by_relevance, "⭐")
+                                                .on_hover_text("Sort by relevance instead of the view's sorting")
+                                                .changed();
+                                        }
                                         if ui
                                             .add_enabled(
                                                 !search.value.is_empty(),
@@ -413,7 +471,9 @@ impl App {
                                             }
                                         }
                                         let has_advanced = !search.terms.is_empty()
-                                            || !search.extra_dirs.is_empty();
+                                            || !search.extra_dirs.is_empty()
+                                            || !search.exclude_globs.is_empty()
+                                            || search.respect_ignore_files;
                                         ui.menu_button(
                                             egui::RichText::new(format!(
                                                 "⚙{}",
@@ -461,6 +521,12 @@ impl App {
                                                                 SearchTermType::Plain => {
                                                                     egui::Color32::WHITE
                                                                 }
+                                                                SearchTermType::Metadata => {
+                                                                    egui::Color32::GOLD
+                                                                }
+                                                                SearchTermType::Fuzzy => {
+                                                                    egui::Color32::ORANGE
+                                                                }
                                                             };
                                                             ui.label(
                                                                 egui::RichText::new(&term.pattern)
@@ -527,13 +593,74 @@ impl App {
                                                         search.new_dir_input.clear();
                                                     }
                                                 }
+                                                ui.separator();
+                                                search_target_changed |= ui
+                                                    .checkbox(
+                                                        &mut search.respect_ignore_files,
+                                                        "Respect ignore files",
+                                                    )
+                                                    .on_hover_text(
+                                                        "Skip what .gitignore, .ignore and the global git excludes ignore",
+                                                    )
+                                                    .changed();
+                                                ui.add_enabled_ui(false, |ui| {
+                                                    ui.label("Excluded:");
+                                                });
+                                                let mut exclude_to_remove: Option<usize> = None;
+                                                for (i, glob) in
+                                                    search.exclude_globs.iter().enumerate()
+                                                {
+                                                    ui.horizontal(|ui| {
+                                                        ui.label(glob);
+                                                        if ui.button("✕").clicked() {
+                                                            exclude_to_remove = Some(i);
+                                                        }
+                                                    });
+                                                }
+                                                if let Some(i) = exclude_to_remove {
+                                                    search.exclude_globs.remove(i);
+                                                    search_target_changed = true;
+                                                }
+                                                let exclude_edit = ui.add(
+                                                    egui::TextEdit::singleline(
+                                                        &mut search.new_exclude_input,
+                                                    )
+                                                    .hint_text("node_modules, *.log...")
+                                                    .desired_width(180.0),
+                                                );
+                                                if (exclude_edit.lost_focus()
+                                                    && ui
+                                                        .input(|i| i.key_pressed(egui::Key::Enter)))
+                                                    || ui
+                                                        .button("+")
+                                                        .on_hover_text("Add exclude glob")
+                                                        .clicked()
+                                                {
+                                                    let pattern = search.new_exclude_input.trim();
+                                                    if let Err(err) = glob::Pattern::new(pattern) {
+                                                        toast!(Error, "Invalid glob: {err}");
+                                                    } else if !pattern.is_empty()
+                                                        && !search
+                                                            .exclude_globs
+                                                            .iter()
+                                                            .any(|glob| glob == pattern)
+                                                    {
+                                                        search.exclude_globs.push(pattern.to_string());
+                                                        search.new_exclude_input.clear();
+                                                        search_target_changed = true;
+                                                    }
+                                                }
                                                 if !search.terms.is_empty()
                                                     || !search.extra_dirs.is_empty()
+                                                    || !search.exclude_globs.is_empty()
                                                 {
                                                     ui.separator();
                                                     if ui.button("✕ Clear all").clicked() {
                                                         search.terms.clear();
                                                         search.extra_dirs.clear();
+                                                        search_target_changed |=
+                                                            !search.exclude_globs.is_empty();
+                                                        search.exclude_globs.clear();
                                                         search_changed = true;
                                                         ui.close_menu();
                                                     }
@@ -703,6 +830,7 @@ impl App {
                             ui.data_get_path_or_persisted(&active_tab.current_path).data;
                         let old_value = settings.sorting;
                         let old_value_display = settings.display_type;
+                        let old_value_checksum = settings.checksum_column;
                         let mut display_hidden_changed: bool = false;
                         egui::ComboBox::from_label("Display")
                             .selected_text(format!("{:?}", settings.display_type))
@@ -718,6 +846,29 @@ impl App {
                                     "Icons",
                                 );
                             });
+                        if settings.display_type == crate::app::DisplayType::List {
+                            egui::ComboBox::from_id_salt("checksum_column")
+                                .selected_text(settings.checksum_column.map_or_else(
+                                    || "No checksum".to_string(),
+                                    |kind| kind.to_string(),
+                                ))
+                                .show_ui(ui, |ui| {
+                                    ui.selectable_value(
+                                        &mut settings.checksum_column,
+                                        None,
+                                        "No checksum",
+                                    );
+                                    for kind in ChecksumKind::ALL {
+                                        ui.selectable_value(
+                                            &mut settings.checksum_column,
+                                            Some(kind),
+                                            kind.to_string(),
+                                        );
+                                    }
+                                })
+                                .response
+                                .on_hover_text("Checksum column of the file list");
+                        }
                         egui::ComboBox::from_label("")
                             .selected_text(format!("↕ {:?}", settings.sorting))
                             .show_ui(ui, |ui| {
@@ -757,11 +908,13 @@ impl App {
                             });
                         setting_changed |= old_value != settings.sorting;
                         setting_changed |= old_value_display != settings.display_type;
+                        setting_changed |= old_value_checksum != settings.checksum_column;
                         if !display_hidden_changed && setting_changed {
                             ui.data_set_path(&active_tab.current_path, settings);
                         }
                         ui.spacing_mut().item_spacing = spacing;
                     });
+                    file_jobs_ui(ui);
                 });
             });
 
@@ -770,3 +923,38 @@ impl App {
         }
     }
 }
+
+/// Progress bars with pause/cancel buttons for running file operations.
+/// Drawn inside a right-to-left layout, so widgets are added in reverse.
+fn file_jobs_ui(ui: &mut Ui) {
+    for job in file_ops::active_jobs() {
+        let progress = &job.progress;
+        ui.add_space(8.0);
+        if ui.small_button("✖").on_hover_text("Cancel").clicked() {
+            progress.cancel();
+        }
+        let paused = progress.is_paused();
+        if ui
+            .small_button(if paused { "▶" } else { "⏸" })
+            .on_hover_text(if paused { "Resume" } else { "Pause" })
+            .clicked()
+        {
+            progress.set_paused(!paused);
+        }
+        let (files_done, files_total) = progress.files();
+        let (bytes_done, bytes_total) = progress.bytes();
+        let response = ui.add(
+            egui::ProgressBar::new(progress.fraction())
+                .desired_width(220.0)
+                .text(format!(
+                    "{} ({files_done}/{files_total}, {}/{})",
+                    job.operation.label(),
+                    format_bytes_simple(bytes_done),
+                    format_bytes_simple(bytes_total)
+                )),
+        );
+        if let Some(current) = progress.current_file() {
+            response.on_hover_text(current.display().to_string());
+        }
+    }
+}
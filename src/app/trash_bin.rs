@@ -0,0 +1,518 @@
+//! Browsing the system trash: listing, restoring and permanently deleting
+//! trashed items.
+//!
+//! The trash contents are only exposed on Windows and freedesktop platforms.
+//! Elsewhere [`SUPPORTED`] is `false` and every function returns an error.
+
+use std::{
+    collections::HashSet,
+    fs,
+    path::{Path, PathBuf},
+    time::{Duration, UNIX_EPOCH},
+};
+
+use anyhow::{Context, Result, anyhow, bail};
+pub use trash::TrashItem;
+
+use crate::app::commands::ActionToPerform;
+use crate::app::file_ops::{ConflictResolution, spawn_task, unique_name};
+use crate::data::time::TimestampSeconds;
+
+#[cfg(any(
+    target_os = "windows",
+    all(
+        unix,
+        not(target_os = "macos"),
+        not(target_os = "ios"),
+        not(target_os = "android")
+    )
+))]
+use trash::os_limited;
+
+/// Whether the trash contents can be browsed on this platform.
+pub const SUPPORTED: bool = cfg!(any(
+    target_os = "windows",
+    all(
+        unix,
+        not(target_os = "macos"),
+        not(target_os = "ios"),
+        not(target_os = "android")
+    )
+));
+
+#[cfg(not(any(
+    target_os = "windows",
+    all(
+        unix,
+        not(target_os = "macos"),
+        not(target_os = "ios"),
+        not(target_os = "android")
+    )
+)))]
+mod os_limited {
+    use trash::{Error, TrashItem};
+
+    fn unsupported() -> Error {
+        Error::Unknown {
+            description: "Browsing the trash isn't supported on this platform".to_string(),
+        }
+    }
+
+    pub fn list() -> Result<Vec<TrashItem>, Error> {
+        Err(unsupported())
+    }
+
+    pub fn purge_all<I: IntoIterator<Item = TrashItem>>(_items: I) -> Result<(), Error> {
+        Err(unsupported())
+    }
+
+    pub fn restore_all<I: IntoIterator<Item = TrashItem>>(_items: I) -> Result<(), Error> {
+        Err(unsupported())
+    }
+}
+
+/// The trash the functions below work on. The app uses [`SystemTrash`],
+/// tests pass a stand-in so they never touch the user's trash.
+pub trait Backend {
+    fn delete(&self, path: &Path) -> Result<(), trash::Error>;
+    fn list(&self) -> Result<Vec<TrashItem>, trash::Error>;
+    fn purge_all(&self, items: Vec<TrashItem>) -> Result<(), trash::Error>;
+    /// Fails without restoring anything when an original path is taken.
+    fn restore_all(&self, items: Vec<TrashItem>) -> Result<(), trash::Error>;
+}
+
+/// The trash of the platform.
+#[derive(Debug, Clone, Copy)]
+pub struct SystemTrash;
+
+impl Backend for SystemTrash {
+    fn delete(&self, path: &Path) -> Result<(), trash::Error> {
+        trash::delete(path)
+    }
+
+    fn list(&self) -> Result<Vec<TrashItem>, trash::Error> {
+        os_limited::list()
+    }
+
+    fn purge_all(&self, items: Vec<TrashItem>) -> Result<(), trash::Error> {
+        os_limited::purge_all(items)
+    }
+
+    fn restore_all(&self, items: Vec<TrashItem>) -> Result<(), trash::Error> {
+        os_limited::restore_all(items)
+    }
+}
+
+/// Trashed items, most recently deleted first.
+pub fn list(trash: &impl Backend) -> Result<Vec<TrashItem>> {
+    let mut items = trash
+        .list()
+        .map_err(|err| anyhow!("Failed to list the trash: {err}"))?;
+    items.sort_by_key(|item| std::cmp::Reverse(item.time_deleted));
+    Ok(items)
+}
+
+/// When `item` was moved to the trash, if the platform recorded it.
+pub fn deleted_at(item: &TrashItem) -> Option<TimestampSeconds> {
+    let seconds = u64::try_from(item.time_deleted).ok()?;
+    Some((UNIX_EPOCH + Duration::from_secs(seconds)).into())
+}
+
+/// Original locations of `items` that are taken, either by an existing entry
+/// or by another of the items deleted from the same path.
+pub fn conflicts(items: &[TrashItem]) -> Vec<PathBuf> {
+    let mut seen = HashSet::new();
+    items
+        .iter()
+        .map(TrashItem::original_path)
+        .filter(|path| path.symlink_metadata().is_ok() || !seen.insert(path.clone()))
+        .collect()
+}
+
+/// Restores `items` to their original locations one by one, resolving taken
+/// locations with `on_conflict`. Returns the paths the items ended up at.
+pub fn restore(
+    trash: &impl Backend,
+    items: Vec<TrashItem>,
+    on_conflict: ConflictResolution,
+) -> Result<Vec<PathBuf>> {
+    let mut restored = Vec::with_capacity(items.len());
+    for item in items {
+        if let Some(path) = restore_item(trash, item, on_conflict)? {
+            restored.push(path);
+        }
+    }
+    Ok(restored)
+}
+
+/// Restores the most recent deletion of each of `paths`.
+pub fn restore_paths(trash: &impl Backend, paths: &[PathBuf]) -> Result<()> {
+    let mut items = list(trash)?;
+    let mut to_restore = Vec::with_capacity(paths.len());
+    for path in paths {
+        let Some(index) = items.iter().position(|item| item.original_path() == *path) else {
+            bail!("{} is no longer in the trash", path.display());
+        };
+        to_restore.push(items.remove(index));
+    }
+    trash
+        .restore_all(to_restore)
+        .map_err(|err| anyhow!("Failed to restore: {err}"))
+}
+
+/// Deletes `items` from the trash for good.
+pub fn purge(trash: &impl Backend, items: Vec<TrashItem>) -> Result<()> {
+    trash
+        .purge_all(items)
+        .map_err(|err| anyhow!("Failed to delete from the trash: {err}"))
+}
+
+/// Deletes everything in the trash for good.
+pub fn empty(trash: &impl Backend) -> Result<()> {
+    purge(trash, list(trash)?)
+}
+
+fn restore_one(trash: &impl Backend, item: TrashItem) -> Result<()> {
+    let path = item.original_path();
+    trash
+        .restore_all(vec![item])
+        .map_err(|err| anyhow!("Failed to restore {}: {err}", path.display()))
+}
+
+fn restore_item(
+    trash: &impl Backend,
+    item: TrashItem,
+    on_conflict: ConflictResolution,
+) -> Result<Option<PathBuf>> {
+    let original = item.original_path();
+    if original.symlink_metadata().is_err() {
+        restore_one(trash, item)?;
+        return Ok(Some(original));
+    }
+    let overwrite = match on_conflict {
+        ConflictResolution::Skip => false,
+        ConflictResolution::KeepBoth => return keep_both(trash, item, &original).map(Some),
+        ConflictResolution::Overwrite => true,
+        ConflictResolution::OverwriteIfNewer => deleted_after_modified(&item, &original),
+    };
+    if !overwrite {
+        return Ok(None);
+    }
+    // The replaced entry goes to the trash as well, so nothing is lost.
+    trash
+        .delete(&original)
+        .map_err(|err| anyhow!("Failed to move {} to trash: {err}", original.display()))?;
+    restore_one(trash, item)?;
+    Ok(Some(original))
+}
+
+/// Whether the trashed item was deleted after the entry now at its original
+/// location was last modified.
+fn deleted_after_modified(item: &TrashItem, original: &Path) -> bool {
+    let Some(deleted_at) = deleted_at(item) else {
+        return false;
+    };
+    fs::symlink_metadata(original)
+        .and_then(|meta| meta.modified())
+        .is_ok_and(|modified| modified < deleted_at.system_time())
+}
+
+/// Restores `item` next to the entry taking its original location, under a
+/// free name. The trash can only restore to the original path, so the
+/// existing entry is parked under a hidden name in the meantime.
+fn keep_both(trash: &impl Backend, item: TrashItem, original: &Path) -> Result<PathBuf> {
+    let name = original
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let mut parked = original.with_file_name(format!(".{name}.restoring"));
+    if parked.symlink_metadata().is_ok() {
+        parked = unique_name(&parked);
+    }
+    fs::rename(original, &parked)
+        .with_context(|| format!("Failed to move {} aside", original.display()))?;
+    if let Err(err) = restore_one(trash, item) {
+        if let Err(rename_err) = fs::rename(&parked, original) {
+            log::error!(
+                "Failed to move {} back to {}: {rename_err}",
+                parked.display(),
+                original.display()
+            );
+        }
+        return Err(err);
+    }
+    let restored = unique_name(original);
+    fs::rename(original, &restored)
+        .with_context(|| format!("Failed to rename {}", original.display()))?;
+    fs::rename(&parked, original)
+        .with_context(|| format!("Failed to move {} back", original.display()))?;
+    Ok(restored)
+}
+
+/// A trash operation run in the background.
+#[derive(Debug, Clone)]
+pub enum TrashTask {
+    Restore(Vec<TrashItem>, ConflictResolution),
+    Purge(Vec<TrashItem>),
+    Empty,
+}
+
+#[derive(Debug, Clone)]
+pub struct TrashReport {
+    pub label: String,
+    pub affected_dirs: Vec<PathBuf>,
+    pub error: Option<String>,
+}
+
+impl TrashTask {
+    pub fn label(&self) -> String {
+        match self {
+            Self::Restore(items, _) if items.len() == 1 => "Restore 1 item".to_string(),
+            Self::Restore(items, _) => format!("Restore {} items", items.len()),
+            Self::Purge(items) if items.len() == 1 => "Delete 1 item permanently".to_string(),
+            Self::Purge(items) => format!("Delete {} items permanently", items.len()),
+            Self::Empty => "Empty Trash".to_string(),
+        }
+    }
+
+    /// Runs the task on the file operations pool and reports back with
+    /// [`ActionToPerform::TrashApplied`].
+    pub fn schedule(self) {
+        spawn_task(move || {
+            let report = self.run(&SystemTrash);
+            ActionToPerform::TrashApplied(report).schedule();
+        });
+    }
+
+    fn run(self, trash: &impl Backend) -> TrashReport {
+        let label = self.label();
+        let (affected_dirs, result) = match self {
+            Self::Restore(items, on_conflict) => {
+                let mut dirs: Vec<PathBuf> = items
+                    .iter()
+                    .map(|item| item.original_parent.clone())
+                    .collect();
+                dirs.sort();
+                dirs.dedup();
+                (dirs, restore(trash, items, on_conflict).map(drop))
+            }
+            Self::Purge(items) => (Vec::new(), purge(trash, items)),
+            Self::Empty => (Vec::new(), empty(trash)),
+        };
+        TrashReport {
+            label,
+            affected_dirs,
+            error: result.err().map(|err| format!("{err:#}")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        cell::{Cell, RefCell},
+        fs,
+        path::{Path, PathBuf},
+        time::UNIX_EPOCH,
+    };
+
+    use trash::Error;
+
+    use super::{Backend, SystemTrash, TrashItem, conflicts, list, purge, restore};
+    use crate::app::file_ops::ConflictResolution;
+    use crate::app::test_support::unique_test_dir;
+
+    /// Stands in for the system trash, keeping trashed entries in a folder of
+    /// its own.
+    struct FakeTrash {
+        dir: PathBuf,
+        next_id: Cell<u64>,
+        trashed: RefCell<Vec<TrashItem>>,
+    }
+
+    impl FakeTrash {
+        fn new() -> Self {
+            Self {
+                dir: unique_test_dir("trash"),
+                next_id: Cell::new(0),
+                trashed: RefCell::new(Vec::new()),
+            }
+        }
+
+        /// Takes `item` out of the trash, returning where its contents are kept.
+        fn take(&self, item: &TrashItem) -> Result<PathBuf, Error> {
+            let mut trashed = self.trashed.borrow_mut();
+            let index = trashed
+                .iter()
+                .position(|trashed| trashed.id == item.id)
+                .ok_or_else(|| error("not in the trash"))?;
+            Ok(PathBuf::from(trashed.remove(index).id))
+        }
+    }
+
+    fn error(err: impl std::fmt::Display) -> Error {
+        Error::Unknown {
+            description: err.to_string(),
+        }
+    }
+
+    impl Backend for FakeTrash {
+        fn delete(&self, path: &Path) -> Result<(), Error> {
+            let (Some(name), Some(parent)) = (path.file_name(), path.parent()) else {
+                return Err(error("no file name"));
+            };
+            let id = self.next_id.replace(self.next_id.get() + 1);
+            let kept = self.dir.join(id.to_string());
+            fs::rename(path, &kept).map_err(error)?;
+            let time_deleted = UNIX_EPOCH.elapsed().map_err(error)?.as_secs();
+            self.trashed.borrow_mut().push(TrashItem {
+                id: kept.into_os_string(),
+                name: name.to_os_string(),
+                original_parent: parent.to_path_buf(),
+                time_deleted: i64::try_from(time_deleted).unwrap_or_default(),
+            });
+            Ok(())
+        }
+
+        fn list(&self) -> Result<Vec<TrashItem>, Error> {
+            Ok(self.trashed.borrow().clone())
+        }
+
+        fn purge_all(&self, items: Vec<TrashItem>) -> Result<(), Error> {
+            for item in items {
+                let kept = self.take(&item)?;
+                if kept.is_dir() {
+                    fs::remove_dir_all(kept).map_err(error)?;
+                } else {
+                    fs::remove_file(kept).map_err(error)?;
+                }
+            }
+            Ok(())
+        }
+
+        fn restore_all(&self, items: Vec<TrashItem>) -> Result<(), Error> {
+            if items
+                .iter()
+                .any(|item| item.original_path().symlink_metadata().is_ok())
+            {
+                return Err(error("the original path is taken"));
+            }
+            for item in items {
+                fs::rename(self.take(&item)?, item.original_path()).map_err(error)?;
+            }
+            Ok(())
+        }
+    }
+
+    fn trashed_from(trash: &impl Backend, dir: &Path) -> Vec<TrashItem> {
+        let mut items = list(trash).expect("list trash");
+        items.retain(|item| item.original_parent == dir);
+        items
+    }
+
+    #[test]
+    fn restore_keeps_both_when_the_original_path_is_taken() {
+        let trash = FakeTrash::new();
+        let dir = unique_test_dir("restore");
+        let path = dir.join("notes.txt");
+        fs::write(&path, b"old").expect("write file");
+        trash.delete(&path).expect("move to trash");
+        fs::write(&path, b"new").expect("write file");
+
+        let items = trashed_from(&trash, &dir);
+        assert_eq!(items.len(), 1);
+        assert!(super::deleted_at(&items[0]).is_some());
+        assert_eq!(conflicts(&items), vec![path.clone()]);
+
+        let restored = restore(&trash, items, ConflictResolution::KeepBoth).expect("restore");
+        assert_eq!(restored, vec![dir.join("notes (2).txt")]);
+        assert_eq!(fs::read(&path).expect("read file"), b"new");
+        assert_eq!(fs::read(&restored[0]).expect("read file"), b"old");
+        assert!(trashed_from(&trash, &dir).is_empty());
+        assert_eq!(fs::read_dir(&dir).expect("read dir").count(), 2);
+    }
+
+    #[test]
+    fn restore_skips_or_overwrites_taken_paths() {
+        let trash = FakeTrash::new();
+        let dir = unique_test_dir("overwrite");
+        let path = dir.join("a.txt");
+        fs::write(&path, b"old").expect("write file");
+        trash.delete(&path).expect("move to trash");
+        fs::write(&path, b"new").expect("write file");
+
+        let items = trashed_from(&trash, &dir);
+        let restored = restore(&trash, items, ConflictResolution::Skip).expect("skip");
+        assert!(restored.is_empty());
+        assert_eq!(trashed_from(&trash, &dir).len(), 1);
+
+        let items = trashed_from(&trash, &dir);
+        let restored = restore(&trash, items, ConflictResolution::Overwrite).expect("overwrite");
+        assert_eq!(restored, vec![path.clone()]);
+        assert_eq!(fs::read(&path).expect("read file"), b"old");
+        // The overwritten entry went to the trash in turn.
+        let items = trashed_from(&trash, &dir);
+        assert_eq!(items.len(), 1);
+
+        purge(&trash, items).expect("purge");
+        assert!(trashed_from(&trash, &dir).is_empty());
+    }
+
+    /// Set for the copy of the test binary that talks to the real trash.
+    #[cfg(target_os = "linux")]
+    const REAL_TRASH_CHILD: &str = "LWA_FM_REAL_TRASH_TEST";
+
+    /// Runs the restore paths against the real freedesktop trash. The test
+    /// binary runs itself again with `XDG_DATA_HOME` set to a temporary
+    /// folder, so the user's trash is never touched and the environment of
+    /// the other tests stays as it is.
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn real_trash_lists_restores_and_purges() {
+        use std::process::Command;
+
+        if std::env::var_os(REAL_TRASH_CHILD).is_none() {
+            let module = module_path!()
+                .split_once("::")
+                .map_or(module_path!(), |(_, path)| path);
+            let filter = format!("{module}::real_trash_lists_restores_and_purges");
+            let output = Command::new(std::env::current_exe().expect("test binary path"))
+                .args([filter.as_str(), "--exact", "--test-threads=1"])
+                .env("XDG_DATA_HOME", unique_test_dir("xdg_data"))
+                .env(REAL_TRASH_CHILD, "1")
+                .output()
+                .expect("run the test binary");
+            let stdout = String::from_utf8_lossy(&output.stdout);
+            assert!(output.status.success(), "{stdout}");
+            assert!(stdout.contains("1 passed"), "{stdout}");
+            return;
+        }
+
+        let trash = SystemTrash;
+        let dir = fs::canonicalize(unique_test_dir("real_trash")).expect("resolve test dir");
+        let path = dir.join("notes.txt");
+        fs::write(&path, b"old").expect("write file");
+        trash.delete(&path).expect("move to trash");
+        let items = trashed_from(&trash, &dir);
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0].original_path(), path);
+        assert!(super::deleted_at(&items[0]).is_some());
+
+        // A taken original path is refused, which restoring relies on.
+        fs::write(&path, b"new").expect("write file");
+        assert!(trash.restore_all(items.clone()).is_err());
+        assert_eq!(fs::read(&path).expect("read file"), b"new");
+
+        let restored = restore(&trash, items, ConflictResolution::KeepBoth).expect("restore");
+        assert_eq!(restored, vec![dir.join("notes (2).txt")]);
+        assert_eq!(fs::read(&path).expect("read file"), b"new");
+        assert_eq!(fs::read(&restored[0]).expect("read file"), b"old");
+        assert!(trashed_from(&trash, &dir).is_empty());
+
+        trash.delete(&path).expect("move to trash");
+        purge(&trash, trashed_from(&trash, &dir)).expect("purge");
+        assert!(trashed_from(&trash, &dir).is_empty());
+        assert!(!path.exists());
+        let _ = fs::remove_dir_all(dir);
+    }
+}
@@ -0,0 +1,257 @@
+//! The "Trash" location: lists the trashed items in a tab and restores or
+//! permanently deletes them.
+
+use std::{
+    collections::HashSet,
+    ffi::OsString,
+    path::PathBuf,
+    sync::{Arc, OnceLock},
+};
+
+use egui::{Align, Context, Layout, Ui, Vec2};
+
+use super::BG_POOL;
+use super::commands::TabAction;
+use super::dock::CurrentPath;
+use super::file_ops::ConflictResolution;
+use super::trash_bin::{self, SystemTrash, TrashItem, TrashTask};
+use crate::helper::DataHolder;
+
+/// How many taken locations the restore prompt lists by name.
+const LISTED_CONFLICTS: usize = 5;
+
+#[derive(Debug, Clone, Default)]
+struct TrashState {
+    /// Filled in once listed, with the error when it failed.
+    listing: Arc<OnceLock<Result<Vec<TrashItem>, String>>>,
+    /// Ids of the selected items.
+    selected: HashSet<OsString>,
+    /// Items to restore over taken locations, waiting for a resolution.
+    pending_restore: Option<(Vec<TrashItem>, Vec<PathBuf>)>,
+    /// A permanent delete waiting for confirmation.
+    pending_purge: Option<TrashTask>,
+    /// A task is running, the listed items may be out of date.
+    busy: bool,
+}
+
+impl TrashState {
+    fn items(&self) -> &[TrashItem] {
+        match self.listing.get() {
+            Some(Ok(items)) => items,
+            _ => &[],
+        }
+    }
+
+    fn selected_items(&self) -> Vec<TrashItem> {
+        self.items()
+            .iter()
+            .filter(|item| self.selected.contains(&item.id))
+            .cloned()
+            .collect()
+    }
+
+    fn start(&mut self, task: TrashTask) {
+        self.busy = true;
+        self.selected.clear();
+        self.pending_restore = None;
+        self.pending_purge = None;
+        task.schedule();
+    }
+}
+
+/// Lists the trash for the tab `tab_id` in the background, keeping the
+/// selection and any open prompt.
+pub fn load(ctx: &Context, tab_id: u32) {
+    let listing = Arc::new(OnceLock::new());
+    let state = TrashState {
+        listing: Arc::clone(&listing),
+        ..ctx.data_get_tab::<TrashState>(tab_id).unwrap_or_default()
+    };
+    ctx.data_set_tab(tab_id, state);
+    let ctx = ctx.clone();
+    BG_POOL.spawn(move || {
+        let _ = listing.set(trash_bin::list(&SystemTrash).map_err(|err| format!("{err:#}")));
+        ctx.request_repaint();
+    });
+}
+
+/// Marks a trash task started from the tab `tab_id` as done.
+pub fn task_finished(ctx: &Context, tab_id: u32) {
+    if let Some(mut state) = ctx.data_get_tab::<TrashState>(tab_id) {
+        state.busy = false;
+        ctx.data_set_tab(tab_id, state);
+    }
+}
+
+/// The "Trash" entry of the sidebar.
+pub fn sidebar_ui(ui: &mut Ui, icon_size: f32) {
+    ui.with_layout(
+        Layout::top_down(Align::Min).with_cross_justify(true),
+        |ui| {
+            let row = ui.horizontal(|ui| {
+                ui.allocate_space(Vec2::splat(icon_size));
+                ui.add(
+                    egui::Button::new("Trash")
+                        .frame(false)
+                        .fill(egui::Color32::from_white_alpha(0)),
+                )
+            });
+            if row.inner.clicked() {
+                TabAction::ChangePaths(CurrentPath::Trash).schedule_active_tab();
+            }
+        },
+    );
+}
+
+/// The contents of a tab showing the trash.
+pub fn ui(ui: &mut Ui, tab_id: u32) {
+    #[cfg(feature = "profiling")]
+    puffin::profile_scope!("lwa_fm::trash_bin_view::ui");
+    let Some(mut state) = ui.data_get_tab::<TrashState>(tab_id) else {
+        ui.spinner();
+        return;
+    };
+    ui.horizontal(|ui| {
+        ui.heading("Trash");
+        if state.busy || state.listing.get().is_none() {
+            ui.spinner();
+        }
+    });
+    if let Some((items, taken)) = state.pending_restore.clone() {
+        restore_prompt_ui(ui, &mut state, items, &taken);
+    } else if let Some(task) = state.pending_purge.clone() {
+        purge_prompt_ui(ui, &mut state, task);
+    } else {
+        buttons_ui(ui, &mut state, tab_id);
+    }
+    ui.separator();
+    match state.listing.get() {
+        None => {}
+        Some(Err(error)) => {
+            ui.colored_label(ui.visuals().error_fg_color, error);
+        }
+        Some(Ok(items)) if items.is_empty() => {
+            ui.weak("The trash is empty");
+        }
+        Some(Ok(items)) => items_ui(ui, items, &mut state.selected),
+    }
+    ui.data_set_tab(tab_id, state);
+}
+
+fn items_ui(ui: &mut Ui, items: &[TrashItem], selected: &mut HashSet<OsString>) {
+    egui::ScrollArea::vertical()
+        .auto_shrink([false, false])
+        .show(ui, |ui| {
+            egui::Grid::new("trash_items")
+                .num_columns(3)
+                .striped(true)
+                .show(ui, |ui| {
+                    ui.strong("Name");
+                    ui.strong("Original location");
+                    ui.strong("Deleted (UTC)");
+                    ui.end_row();
+                    for item in items {
+                        let is_selected = selected.contains(&item.id);
+                        let name = item.name.to_string_lossy();
+                        if ui.selectable_label(is_selected, name).clicked() {
+                            if is_selected {
+                                selected.remove(&item.id);
+                            } else {
+                                selected.insert(item.id.clone());
+                            }
+                        }
+                        ui.label(item.original_parent.display().to_string());
+                        if let Some(deleted_at) = trash_bin::deleted_at(item) {
+                            ui.label(deleted_at.format_utc())
+                                .on_hover_text(deleted_at.elapsed().to_string());
+                        } else {
+                            ui.weak("Unknown");
+                        }
+                        ui.end_row();
+                    }
+                });
+        });
+}
+
+fn buttons_ui(ui: &mut Ui, state: &mut TrashState, tab_id: u32) {
+    let selected = state.selected_items();
+    ui.horizontal(|ui| {
+        ui.add_enabled_ui(!state.busy, |ui| {
+            let has_selection = !selected.is_empty();
+            if ui
+                .add_enabled(has_selection, egui::Button::new("Restore"))
+                .clicked()
+            {
+                let taken = trash_bin::conflicts(&selected);
+                if taken.is_empty() {
+                    state.start(TrashTask::Restore(
+                        selected.clone(),
+                        ConflictResolution::Skip,
+                    ));
+                } else {
+                    state.pending_restore = Some((selected.clone(), taken));
+                }
+            }
+            if ui
+                .add_enabled(has_selection, egui::Button::new("Delete permanently"))
+                .clicked()
+            {
+                state.pending_purge = Some(TrashTask::Purge(selected.clone()));
+            }
+            if ui
+                .add_enabled(!state.items().is_empty(), egui::Button::new("Empty Trash"))
+                .clicked()
+            {
+                state.pending_purge = Some(TrashTask::Empty);
+            }
+            if ui.button("Refresh").clicked() {
+                TabAction::RequestFilesRefresh.schedule_tab(tab_id);
+            }
+        });
+    });
+}
+
+fn restore_prompt_ui(
+    ui: &mut Ui,
+    state: &mut TrashState,
+    items: Vec<TrashItem>,
+    taken: &[PathBuf],
+) {
+    ui.label(if taken.len() == 1 {
+        "This location is taken:".to_string()
+    } else {
+        format!("{} locations are taken:", taken.len())
+    });
+    for path in taken.iter().take(LISTED_CONFLICTS) {
+        ui.monospace(path.display().to_string());
+    }
+    if taken.len() > LISTED_CONFLICTS {
+        ui.weak(format!("and {} more", taken.len() - LISTED_CONFLICTS));
+    }
+    ui.horizontal(|ui| {
+        let mut answer = None;
+        for resolution in ConflictResolution::ALL {
+            if ui.button(resolution.to_string()).clicked() {
+                answer = Some(resolution);
+            }
+        }
+        if let Some(resolution) = answer {
+            state.start(TrashTask::Restore(items, resolution));
+        } else if ui.button("Cancel").clicked() {
+            state.pending_restore = None;
+        }
+    });
+}
+
+fn purge_prompt_ui(ui: &mut Ui, state: &mut TrashState, task: TrashTask) {
+    ui.label(format!("{}? This can't be undone.", task.label()));
+    ui.horizontal(|ui| {
+        let confirm =
+            egui::Button::new(egui::RichText::new(task.label()).color(ui.visuals().error_fg_color));
+        if ui.add(confirm).clicked() {
+            state.start(task);
+        } else if ui.button("Cancel").clicked() {
+            state.pending_purge = None;
+        }
+    });
+}
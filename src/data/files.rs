@@ -180,6 +180,21 @@ impl DirEntry {
         self.get_path().to_full_path_string()
     }
 
+    /// Builds an entry with full metadata for a single path.
+    #[must_use]
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let meta: DirEntryMetaData = std::fs::metadata(path).ok()?.into();
+        let file_name = path.file_name()?.to_string_lossy().into_owned();
+        let dir = path.parent()?.to_full_path_string();
+        let sort_key = SortKey::new_path(&file_name, meta.entry_type == EntryType::File);
+        Some(Self {
+            meta,
+            sort_key,
+            dir: Arc::from(dir.as_str()),
+            file_name,
+        })
+    }
+
     #[cfg(test)]
     pub fn test_new(path: &str) -> Self {
         let sep = path.rfind(std::path::MAIN_SEPARATOR)
@@ -444,6 +459,29 @@ impl DirList {
     }
 }
 
+impl TryFrom<ignore::DirEntry> for DirEntry {
+    type Error = ();
+
+    #[inline]
+    fn try_from(value: ignore::DirEntry) -> Result<Self, Self::Error> {
+        #[cfg(feature = "profiling")]
+        puffin::profile_scope!("lwa_fm::dir_handling::conversion::from_ignore");
+        let meta = value.metadata().map_err(|_| ())?;
+        let meta: DirEntryMetaData = meta.into();
+        let path = value.path().to_full_path_string();
+        let file_name = value.file_name().to_string_lossy().into_owned();
+        let dir_len = path.len().saturating_sub(file_name.len() + 1);
+        let dir = Arc::from(&path[..dir_len]);
+        let sort_key = SortKey::new_path(&file_name, meta.entry_type.eq(&EntryType::File));
+        Ok(Self {
+            meta,
+            dir,
+            file_name,
+            sort_key,
+        })
+    }
+}
+
 impl TryFrom<walkdir::DirEntry> for DirEntry {
     type Error = ();
 
@@ -37,6 +37,75 @@ impl TimestampSeconds {
         std::time::UNIX_EPOCH + self.to_duration()
     }
 
+    /// Calendar date and time in UTC as `(year, month, day, hour, minute, second)`.
+    #[must_use]
+    pub const fn to_utc_parts(self) -> (i64, u32, u32, u32, u32, u32) {
+        let seconds = self.0 as i64;
+        let days = seconds.div_euclid(86_400);
+        let time = seconds.rem_euclid(86_400) as u32;
+        // Days to civil date, see http://howardhinnant.github.io/date_algorithms.html
+        let z = days + 719_468;
+        let era = z.div_euclid(146_097);
+        let doe = z.rem_euclid(146_097);
+        let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+        let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+        let mp = (5 * doy + 2) / 153;
+        let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+        let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+        let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+        (year, month, day, time / 3600, time % 3600 / 60, time % 60)
+    }
+
+    /// `YYYY-MM-DD HH:MM` in UTC.
+    #[must_use]
+    pub fn format_utc(self) -> String {
+        let (year, month, day, hour, minute, _) = self.to_utc_parts();
+        format!("{year:04}-{month:02}-{day:02} {hour:02}:{minute:02} UTC")
+    }
+
+    /// Parses `YYYY-MM-DD HH:MM[:SS]` in UTC, as written by [`Self::format_utc`].
+    #[must_use]
+    pub fn parse_utc(text: &str) -> Option<Self> {
+        let text = text.trim();
+        let text = text.strip_suffix("UTC").unwrap_or(text).trim_end();
+        let (date, time) = text.split_once([' ', 'T'])?;
+        let mut date = date.split('-').map(str::parse::<i64>);
+        let (year, month, day) = (date.next()?.ok()?, date.next()?.ok()?, date.next()?.ok()?);
+        let mut time = time.split(':').map(str::parse::<i64>);
+        let (hour, minute) = (time.next()?.ok()?, time.next()?.ok()?);
+        let second = time.next().transpose().ok()?.unwrap_or(0);
+        if date.next().is_some() || time.next().is_some() {
+            return None;
+        }
+        Self::from_utc_parts(year, month, day, hour, minute, second)
+    }
+
+    /// The timestamp of a calendar date and time in UTC, `None` when a field
+    /// is out of range or the date is before 1970.
+    #[must_use]
+    pub fn from_utc_parts(
+        year: i64,
+        month: i64,
+        day: i64,
+        hour: i64,
+        minute: i64,
+        second: i64,
+    ) -> Option<Self> {
+        // Civil date to days, see http://howardhinnant.github.io/date_algorithms.html
+        let shifted_year = if month <= 2 { year - 1 } else { year };
+        let era = shifted_year.div_euclid(400);
+        let yoe = shifted_year - era * 400;
+        let doy = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+        let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+        let days = era * 146_097 + doe - 719_468;
+        let seconds = days * 86_400 + hour * 3600 + minute * 60 + second;
+        let timestamp = Self(u32::try_from(seconds).ok()?);
+        // Out of range fields such as February 30th don't survive the round trip.
+        let parts = (year, month, day, hour, minute, second);
+        let (y, mo, d, h, mi, s) = timestamp.to_utc_parts();
+        (parts == (y, mo.into(), d.into(), h.into(), mi.into(), s.into())).then_some(timestamp)
+    }
+
     #[inline]
     #[must_use]
     pub fn elapsed(self) -> ElapsedTime {
@@ -134,3 +203,43 @@ impl Display for ElapsedTime {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::TimestampSeconds;
+
+    #[test]
+    fn utc_parts_match_known_dates() {
+        assert_eq!(TimestampSeconds(0).to_utc_parts(), (1970, 1, 1, 0, 0, 0));
+        // 2000-02-29 12:34:56
+        assert_eq!(
+            TimestampSeconds(951_827_696).to_utc_parts(),
+            (2000, 2, 29, 12, 34, 56)
+        );
+        // 2024-12-31 23:59:59
+        assert_eq!(
+            TimestampSeconds(1_735_689_599).to_utc_parts(),
+            (2024, 12, 31, 23, 59, 59)
+        );
+        assert_eq!(TimestampSeconds(951_827_696).format_utc(), "2000-02-29 12:34 UTC");
+    }
+
+    #[test]
+    fn parse_utc_reads_back_formatted_dates() {
+        assert_eq!(
+            TimestampSeconds::parse_utc("2000-02-29 12:34:56"),
+            Some(TimestampSeconds(951_827_696))
+        );
+        assert_eq!(
+            TimestampSeconds::parse_utc("2000-02-29 12:34 UTC"),
+            Some(TimestampSeconds(951_827_640))
+        );
+        assert_eq!(
+            TimestampSeconds::parse_utc("1970-01-01T00:00:00"),
+            Some(TimestampSeconds(0))
+        );
+        assert_eq!(TimestampSeconds::parse_utc("2001-02-29 00:00"), None);
+        assert_eq!(TimestampSeconds::parse_utc("2001-02-03"), None);
+        assert_eq!(TimestampSeconds::parse_utc("1969-12-31 23:59"), None);
+    }
+}
@@ -317,6 +317,25 @@ impl KeyWithCommandPressed for Ui {
     }
 }
 
+/// Whether a text field has keyboard focus, in which case it should receive
+/// shortcuts such as copy/paste and undo instead of the file views.
+pub fn text_edit_focused(ctx: &Context) -> bool {
+    ctx.memory(egui::Memory::focused)
+        .is_some_and(|id| egui::TextEdit::load_state(ctx, id).is_some())
+}
+
+/// Whether Shift+Delete was pressed. On Windows the backend turns it into
+/// `Event::Cut`, so a cut with only shift held counts as well.
+pub fn shift_delete_pressed(input: &InputState) -> bool {
+    (input.modifiers.shift_only() && input.key_pressed(egui::Key::Delete))
+        || (cfg!(target_os = "windows")
+            && input.modifiers.shift_only()
+            && input
+                .events
+                .iter()
+                .any(|event| matches!(event, egui::Event::Cut)))
+}
+
 /// Converts bytes (as usize) to a human-readable format
 /// Returns a tuple of (value, unit) for flexible formatting
 pub fn format_bytes_detailed(bytes: u64) -> (f64, &'static str) {
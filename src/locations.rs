@@ -3,7 +3,11 @@ use std::{borrow::Cow, path::PathBuf, str::FromStr};
 use egui::{Align, Layout, RichText, TextBuffer, Ui, Vec2};
 
 use crate::{
-    app::{assets::AssetManager, commands::ActionToPerform},
+    app::{
+        assets::AssetManager,
+        commands::{ActionToPerform, TabAction},
+        drag_drop,
+    },
     helper::KeyWithCommandPressed,
 };
 
@@ -22,7 +26,7 @@ impl Locations {
     }
 }
 
-const fn empty_icon(_ui: &mut egui::Ui, _openness: f32, _response: &egui::Response) {
+pub(crate) const fn empty_icon(_ui: &mut egui::Ui, _openness: f32, _response: &egui::Response) {
     // Empty icon function
 }
 
@@ -39,26 +43,28 @@ impl Locations {
                     Layout::top_down(Align::Min).with_cross_justify(true),
                     |ui| {
                         for location in &self.locations {
-                            let button = ui
-                                .horizontal(|ui| {
-                                    if let Some(texture) = assets.request_sidebar_texture(
-                                        &PathBuf::from_str(&location.path).unwrap_or_default(),
-                                    ) {
-                                        ui.add(
-                                            egui::Image::new(&texture).fit_to_exact_size(
-                                                Vec2::splat(assets.render_size()),
-                                            ),
-                                        );
-                                    } else {
-                                        ui.allocate_space(Vec2::splat(assets.render_size()));
-                                    }
+                            let path = PathBuf::from_str(&location.path).unwrap_or_default();
+                            let row = ui.horizontal(|ui| {
+                                if let Some(texture) = assets.request_sidebar_texture(&path) {
                                     ui.add(
-                                        egui::Button::new(location.name.as_str())
-                                            .frame(false)
-                                            .fill(egui::Color32::from_white_alpha(0)),
-                                    )
-                                })
-                                .inner;
+                                        egui::Image::new(&texture)
+                                            .fit_to_exact_size(Vec2::splat(assets.render_size())),
+                                    );
+                                } else {
+                                    ui.allocate_space(Vec2::splat(assets.render_size()));
+                                }
+                                ui.add(
+                                    egui::Button::new(location.name.as_str())
+                                        .frame(false)
+                                        .fill(egui::Color32::from_white_alpha(0)),
+                                )
+                            });
+                            let button = row.inner;
+                            if drag_drop::drop_target(&row.response.union(button.clone()), &path)
+                                && drag_drop::spring_loaded(ui.ctx(), &path)
+                            {
+                                TabAction::ChangePaths(path.into()).schedule_active_tab();
+                            }
                             if button.clicked() {
                                 if let Some(action) = ActionToPerform::path_from_str(
                                     &location.path,
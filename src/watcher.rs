@@ -361,7 +361,6 @@ impl DirectoryWatcher {
             .is_ok()
     }
 
-    #[allow(dead_code)]
     pub fn recv_event_timeout(&self, timeout: Duration) -> Option<FileSystemChanges> {
         self.receiver.recv_timeout(timeout).ok()
     }